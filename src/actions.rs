@@ -1,70 +1,1098 @@
-use std::fs;
-use std::path::PathBuf;
+use std::{
+    fs::{self, File},
+    io,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
 
 use crate::app::{App, Pane};
-use crate::fs_utils::copy_dir_recursively;
+use crate::archive::{extract_dir, Extraction, Format};
+use crate::clipboard;
+use crate::config::expand_home;
+use crate::exif;
+use crate::file_type;
+use crate::format::{absolute_time, human_size, relative_time, thousands};
+use crate::fs_utils::{
+    binary_diff, find_match, glob_match, is_separator, long_path, same_entry, shell_quote,
+    tree_size, validate_path, NameProblem,
+};
+use crate::keys::KeyCombo;
+use crate::line_edit::LineEditor;
+use crate::links::{
+    can_hard_link, file_id, link_chain, link_count, ChainEnd, LinkScan, LinkSearch,
+};
+use crate::media;
+use crate::mode::{Mode, TypeToConfirm};
+use crate::predicate::Predicate;
+use crate::rename;
+use crate::resume;
+use crate::scripting::{ScriptAction, ScriptContext};
+use crate::signature::signed_pair;
+use crate::sync::{compare_panes, plan, SyncPlan};
+use crate::tasks::JobKind;
+use crate::vfs::{copy_across, dir_size, remove_tree, Entry};
+use crate::xattr;
+
+/// Call the plugin function bound to `keys` and apply what it asked for.
+pub fn run_script(app: &mut App, keys: &[KeyCombo]) {
+    let pane = app.current_pane();
+    let cwd = pane.current_dir.clone();
+    let context = ScriptContext {
+        cwd: cwd.clone(),
+        other_cwd: app.other_pane().current_dir.clone(),
+        cursor: pane.items.get(pane.selected).map(|e| e.path()),
+        selection: pane.selection(),
+    };
+    let actions = match app.scripts.run(keys, context) {
+        Ok(actions) => actions,
+        Err(e) => {
+            app.mode = Mode::Message {
+                title: "Plugin error".into(),
+                lines: e.lines().map(String::from).collect(),
+            };
+            return;
+        }
+    };
+    for action in actions {
+        match action {
+            // relative paths are taken from the directory the function was called in
+            ScriptAction::Cd(dir) => {
+                app.change_dir(cwd.join(dir));
+            }
+            ScriptAction::Mark(path, mark) => {
+                let pane = app.current_pane_mut();
+                let path = pane.current_dir.join(path);
+                if let Some(i) = pane.items.iter().position(|e| e.path() == path) {
+                    pane.set_mark(i, mark);
+                }
+            }
+            ScriptAction::Message(title, text) => {
+                app.mode = Mode::Message {
+                    title,
+                    lines: text.lines().map(String::from).collect(),
+                };
+            }
+            ScriptAction::Refresh => app.refresh_panes(),
+        }
+    }
+}
 
 /// Toggle mark on the selected entry in the given pane.
 pub fn toggle_mark(pane: &mut Pane) {
-    if pane.marked.contains(&pane.selected) {
-        pane.marked.remove(&pane.selected);
-    } else {
-        pane.marked.insert(pane.selected);
+    pane.set_mark(pane.selected, !pane.is_marked(pane.selected));
+}
+
+/// Mark (or unmark) every entry whose name matches the glob `pattern`.
+pub fn mark_by_pattern(pane: &mut Pane, pattern: &str, mark: bool) {
+    for entry in &pane.items {
+        if glob_match(pattern, &entry.file_name().to_string_lossy()) {
+            if mark {
+                pane.marked.insert(entry.path());
+            } else {
+                pane.marked.remove(&entry.path());
+            }
+        }
+    }
+}
+
+/// Queue `template` to run for each of `paths` from the active pane's directory, with a report
+/// of the results in a temporary file; `name` is that of the share target it comes from.
+pub fn run_for_each(app: &mut App, paths: Vec<PathBuf>, template: String, name: Option<String>) {
+    let stamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis();
+    let report = std::env::temp_dir().join(format!("kura-each-{}-{stamp}.txt", std::process::id()));
+    let dir = app.current_pane().current_dir.clone();
+    app.tasks.enqueue(JobKind::ForEach {
+        name,
+        paths,
+        template,
+        dir,
+        report,
+    });
+}
+
+/// Mark (or unmark) the listed entries matching `predicate`, returning how many match.
+pub fn mark_where(pane: &mut Pane, predicate: &Predicate, mark: bool) -> usize {
+    let now = SystemTime::now();
+    let matching: Vec<PathBuf> = pane
+        .items
+        .iter()
+        .filter(|e| predicate.matches(e, now))
+        .map(Entry::path)
+        .collect();
+    for path in &matching {
+        if mark {
+            pane.marked.insert(path.clone());
+        } else {
+            pane.marked.remove(path);
+        }
+    }
+    matching.len()
+}
+
+/// Move the cursor to the next (or previous) entry matching a search, expanding its date group
+/// if it is collapsed.
+pub fn search(pane: &mut Pane, query: &str, regex: bool, backward: bool) {
+    if let Some(idx) = find_match(&pane.items, query, regex, pane.selected, backward) {
+        if pane.is_hidden(idx) {
+            pane.collapsed.remove(&pane.groups[idx]);
+        }
+        pane.selected = idx;
+    }
+}
+
+/// Mark every entry in the pane.
+pub fn mark_all(pane: &mut Pane) {
+    pane.marked = pane.items.iter().map(|e| e.path()).collect();
+}
+
+/// Mark every unmarked entry and unmark every marked one.
+pub fn invert_marks(pane: &mut Pane) {
+    pane.marked = pane
+        .items
+        .iter()
+        .map(|e| e.path())
+        .filter(|p| !pane.marked.contains(p))
+        .collect();
+}
+
+/// Yank marked entries or the current entry into `register` (only the yank history when
+/// `None`).
+pub fn copy_selection(app: &mut App, register: Option<char>) {
+    let pane = app.current_pane_mut();
+    let items = pane.selection();
+    pane.marked.clear();
+    app.registers.yank(register, items);
+}
+
+/// Put the selected paths on the system clipboard as shell-quoted, space-separated arguments.
+pub fn copy_as_arguments(app: &mut App) {
+    let pane = app.current_pane_mut();
+    let args: Vec<String> = pane
+        .selection()
+        .iter()
+        .map(|p| shell_quote(&p.to_string_lossy()))
+        .collect();
+    if args.is_empty() {
+        return;
+    }
+    pane.marked.clear();
+    if let Err(e) = clipboard::set_text(&args.join(" ")) {
+        app.notifications
+            .error(format!("cannot copy to the system clipboard: {e}"));
     }
 }
 
-/// Copy marked entries or the current entry into the clipboard.
-pub fn copy_selection(app: &mut App) {
-    let items = {
-        let pane = app.current_pane_mut();
-        let mut items = Vec::new();
-        if !pane.marked.is_empty() {
-            for &i in &pane.marked {
-                if let Some(entry) = pane.items.get(i) {
-                    items.push(entry.path());
+/// Compare the pair of selected files byte by byte and report the result in a popup.
+pub fn compare_binary(app: &mut App) {
+    let Some((a, b)) = app.pair_selection() else {
+        return;
+    };
+    let name = |p: &PathBuf| {
+        p.file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default()
+    };
+    let lines = match binary_diff(&a, &b) {
+        Ok(diff) => {
+            let mut lines = vec![
+                format!("{}: {} bytes", name(&a), diff.len_a),
+                format!("{}: {} bytes", name(&b), diff.len_b),
+                String::new(),
+            ];
+            match diff.first_difference {
+                None => lines.push("Files are identical".into()),
+                Some(offset) => {
+                    lines.push(format!(
+                        "First difference at offset {offset} (0x{offset:x})"
+                    ));
+                    let percent =
+                        diff.differing_blocks as f64 * 100.0 / diff.sampled_blocks.max(1) as f64;
+                    lines.push(format!(
+                        "{}/{} sampled blocks differ ({percent:.1}%)",
+                        diff.differing_blocks, diff.sampled_blocks
+                    ));
                 }
             }
-        } else if let Some(entry) = pane.items.get(pane.selected) {
-            items.push(entry.path());
+            lines
         }
-        pane.marked.clear();
-        items
+        Err(e) => vec![format!("Failed to compare: {e}")],
+    };
+    app.mode = Mode::Message {
+        title: "Binary comparison".into(),
+        lines,
     };
-    app.clipboard = items;
 }
 
-/// Paste clipboard entries into the current directory.
-pub fn paste(app: &mut App) {
-    let items = app.clipboard.clone();
-    let dst_dir = app.current_pane_mut().current_dir.clone();
-    for src in &items {
-        if let Some(file_name) = src.file_name() {
-            let dst = dst_dir.join(file_name);
-            if src.is_dir() {
-                if let Err(e) = copy_dir_recursively(src, &dst) {
-                    eprintln!("Failed to copy directory {:?}: {}", src, e);
-                }
-            } else if let Err(e) = fs::copy(src, &dst) {
-                eprintln!("Failed to copy file {:?}: {}", src, e);
+/// Show the details of the entry under the cursor.
+pub fn inspect(app: &mut App) {
+    let pane = app.current_pane();
+    let Some(entry) = pane.items.get(pane.selected) else {
+        return;
+    };
+    let path = entry.path();
+    let Ok(meta) = path.symlink_metadata() else {
+        return;
+    };
+    let kind = if meta.is_symlink() && path.metadata().is_err() {
+        "symlink (broken)".to_string()
+    } else if meta.is_symlink() {
+        "symlink".to_string()
+    } else if meta.is_dir() {
+        "directory".to_string()
+    } else {
+        match file_type::sniff(&path) {
+            Ok(Some(kind)) => format!("file ({}, {})", kind.description, kind.mime),
+            _ => "file".to_string(),
+        }
+    };
+    let mut lines = vec![
+        format!("Path:     {}", path.display()),
+        format!("Type:     {kind}"),
+        format!(
+            "Size:     {} bytes ({})",
+            thousands(meta.len()),
+            human_size(meta.len())
+        ),
+    ];
+    match link_count(&meta).filter(|_| meta.is_file()) {
+        Some(1) => lines.push("Hard links: 1".into()),
+        Some(count) => lines.push(format!("Hard links: {count} (gi: find the others)")),
+        None => {}
+    }
+    if let Ok(modified) = meta.modified() {
+        lines.push(format!(
+            "Modified: {} ({})",
+            absolute_time(modified),
+            relative_time(modified)
+        ));
+    }
+    if meta.is_symlink() {
+        let (targets, end) = link_chain(&path);
+        let mut chain = entry.file_name().to_string_lossy().into_owned();
+        for target in &targets {
+            chain.push_str(&format!(" → {}", target.display()));
+        }
+        match end {
+            ChainEnd::Resolved => {}
+            ChainEnd::Broken => chain.push_str(" (missing)"),
+            ChainEnd::Loop => chain.push_str(" (loop)"),
+        }
+        lines.push(format!("Links:    {chain}"));
+    }
+    let mut quarantined = false;
+    match xattr::list(&path) {
+        Ok(attributes) if !attributes.is_empty() => {
+            lines.push(String::new());
+            lines.push("Attributes:".into());
+            for (name, value) in &attributes {
+                quarantined |= name == xattr::QUARANTINE;
+                lines.push(format!("  {name} = {}", xattr::display_value(value)));
+            }
+        }
+        Ok(_) => {}
+        // not for this file system, or a broken link
+        Err(e)
+            if matches!(
+                e.kind(),
+                io::ErrorKind::Unsupported | io::ErrorKind::NotFound
+            ) => {}
+        Err(e) => lines.push(format!("Attributes: cannot read: {e}")),
+    }
+    if let Some(exif) = exif::read(&path).filter(|_| meta.is_file()) {
+        let exif = exif.lines();
+        if !exif.is_empty() {
+            lines.push(String::new());
+            lines.extend(exif);
+        }
+    }
+    if let Some((data, signature)) = signed_pair(&path).filter(|_| meta.is_file()) {
+        let name = signature.file_name().unwrap_or_default().to_string_lossy();
+        let checked = match app.signatures.get(&data) {
+            Some(verdict) => verdict.describe(),
+            None if app.is_verifying() => "checking…".into(),
+            None => "not checked (gV)".into(),
+        };
+        lines.push(String::new());
+        lines.push(format!("Signature: {name}, {checked}"));
+    }
+    let media = meta.is_file() && (media::is_audio(&path) || media::is_video(&path));
+    if media {
+        lines.push(String::new());
+        lines.extend(media::describe(&path));
+    }
+    app.mode = Mode::Inspect {
+        lines,
+        size: meta.len(),
+        video: media::is_video(&path)
+            .then(|| path.clone())
+            .filter(|_| media),
+        path,
+        quarantined,
+    };
+}
+
+/// Set an extended attribute of `path` from `input`, `name=value`, or remove one, `-name`,
+/// then show the details again.
+pub fn edit_xattr(app: &mut App, path: &Path, input: &str) {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let result = match (input.strip_prefix('-'), input.split_once('=')) {
+        (Some(attribute), _) if !attribute.trim().is_empty() => {
+            xattr::remove(path, attribute.trim())
+                .map(|_| format!("removed {} from {name}", attribute.trim()))
+        }
+        (None, Some((attribute, value))) if !attribute.trim().is_empty() => {
+            xattr::set(path, attribute.trim(), value.as_bytes())
+                .map(|_| format!("set {} on {name}", attribute.trim()))
+        }
+        _ => {
+            app.notifications
+                .warn("type name=value to set an attribute, or -name to remove one");
+            return;
+        }
+    };
+    match result {
+        Ok(message) => app.notifications.info(message),
+        Err(e) => app
+            .notifications
+            .error(format!("cannot change the attributes of {name}: {e}")),
+    }
+    inspect(app);
+}
+
+/// Let macOS open the downloaded file `path` without asking first.
+pub fn remove_quarantine(app: &mut App, path: &Path) {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    match xattr::remove(path, xattr::QUARANTINE) {
+        Ok(()) => app
+            .notifications
+            .info(format!("removed the quarantine flag from {name}")),
+        Err(e) => app.notifications.error(format!(
+            "cannot remove the quarantine flag from {name}: {e}"
+        )),
+    }
+    inspect(app);
+}
+
+/// Ask where to look for the other names of the file under the cursor, starting from the
+/// current directory.
+pub fn find_hard_links(app: &mut App) {
+    let pane = app.current_pane();
+    let Some(entry) = pane.items.get(pane.selected) else {
+        return;
+    };
+    let path = entry.path();
+    let name = entry.file_name().to_string_lossy().into_owned();
+    let Some(meta) = path.symlink_metadata().ok().filter(|m| m.is_file()) else {
+        app.notifications
+            .warn(format!("{name} is not a file: only files have hard links"));
+        return;
+    };
+    match link_count(&meta) {
+        None => app
+            .notifications
+            .warn("finding hard links is not supported on this platform"),
+        Some(1) => app.notifications.info(format!("{name} has no other names")),
+        Some(_) => {
+            let dir = pane.current_dir.to_string_lossy().into_owned();
+            app.mode = Mode::HardLinkScope {
+                path,
+                scope: LineEditor::new(dir.as_str()),
+            };
+        }
+    }
+}
+
+/// List the other names of the file `path` found under the directory `scope`.
+pub fn search_hard_links(app: &mut App, path: PathBuf, scope: &str) {
+    let dir = expand_home(scope.trim());
+    if !dir.is_dir() {
+        app.notifications
+            .warn(format!("{} is not a directory", dir.display()));
+        return;
+    }
+    let Ok(meta) = path.symlink_metadata() else {
+        return;
+    };
+    let (Some(id), Some(count)) = (file_id(&meta), link_count(&meta)) else {
+        return;
+    };
+    app.mode = Mode::Links(LinkScan::start(
+        dir,
+        LinkSearch::HardLinks {
+            of: path,
+            id,
+            count,
+        },
+    ));
+}
+
+/// Put the exact byte count shown in the inspector on the system clipboard.
+pub fn copy_size(app: &mut App, size: u64) {
+    if let Err(e) = clipboard::set_text(&size.to_string()) {
+        app.notifications
+            .error(format!("cannot copy to the system clipboard: {e}"));
+    }
+}
+
+/// Compare the two panes' directories and highlight the differences.
+pub fn compare_directories(app: &mut App) {
+    compare_panes(&mut app.left, &mut app.right);
+}
+
+/// Open a dry-run preview of the copies that would bring both panes in sync.
+pub fn preview_sync(app: &mut App) {
+    if app.left.comparison.is_empty() && app.right.comparison.is_empty() {
+        compare_directories(app);
+    }
+    let SyncPlan { to_right, to_left } = plan(&app.left, &app.right);
+    app.mode = Mode::Sync { to_right, to_left };
+}
+
+/// Queue the copies of a confirmed sync.
+pub fn apply_sync(app: &mut App, to_right: Vec<PathBuf>, to_left: Vec<PathBuf>) {
+    if !to_right.is_empty() {
+        let dest = app.right.current_dir.clone();
+        queue_copy(app, to_right, dest);
+    }
+    if !to_left.is_empty() {
+        let dest = app.left.current_dir.clone();
+        queue_copy(app, to_left, dest);
+    }
+}
+
+/// Queue a copy of `sources` into `dest`, first offering to resume it when the same copy was
+/// cancelled or interrupted before.
+fn queue_copy(app: &mut App, sources: Vec<PathBuf>, dest: PathBuf) {
+    let data_dir = app.config.data_dir.as_deref();
+    // the record of a copy still on the queue is its own, not left by an interrupted one
+    let interrupted = resume::interrupted(data_dir, &sources, &dest)
+        .filter(|_| !app.tasks.is_copying(&sources, &dest));
+    match interrupted {
+        Some(done) => {
+            app.mode = Mode::ConfirmResume {
+                sources,
+                dest,
+                done,
             }
         }
+        None => {
+            app.tasks.enqueue(JobKind::Copy { sources, dest });
+        }
     }
-    let _ = app.current_pane_mut().refresh();
 }
 
-/// Delete the given files or directories from disk and refresh the pane.
-pub fn delete_items(app: &mut App, items: &[PathBuf]) {
+/// Queue a copy offered to be resumed: skipping the files copied before, or with `resume`
+/// false copying everything again.
+pub fn resume_copy(app: &mut App, sources: Vec<PathBuf>, dest: PathBuf, resume: bool) {
+    if !resume {
+        resume::forget(app.config.data_dir.as_deref(), &sources, &dest);
+    }
+    app.tasks.enqueue(JobKind::Copy { sources, dest });
+}
+
+/// Queue a copy of the entries in `register` (the latest yank when `None`) into the current
+/// directory.
+pub fn paste(app: &mut App, register: Option<char>) {
+    let sources = app.registers.get(register).to_vec();
+    if sources.is_empty() {
+        return;
+    }
+    let dest = app.current_pane_mut().current_dir.clone();
+    if app.dry_run {
+        let conflicts = conflicts(&sources, &dest, app.current_pane());
+        report_dry_run(app, "copy", &sources, Some((&dest, conflicts)));
+        return;
+    }
+    if let Some(confirm) = paste_confirmation(app, sources.clone(), &dest, false) {
+        app.mode = confirm;
+        return;
+    }
+    queue_copy(app, sources, dest);
+}
+
+/// Queue links in the current directory to the entries in `register` (the latest yank when
+/// `None`): symbolic links, or hard links with `hard`.
+pub fn paste_links(app: &mut App, register: Option<char>, hard: bool) {
+    let sources = app.registers.get(register).to_vec();
+    if sources.is_empty() {
+        return;
+    }
+    let dest = app.current_pane().current_dir.clone();
+    if app.dry_run {
+        let conflicts = conflicts(&sources, &dest, app.current_pane());
+        let verb = if hard { "hard link" } else { "link" };
+        report_dry_run(app, verb, &sources, Some((&dest, conflicts)));
+        return;
+    }
+    if hard && app.current_pane().is_local() && can_hard_link(&sources, &dest) == Some(false) {
+        app.notifications.warn(format!(
+            "cannot hard link: only files on the file system of {} can be linked there",
+            dest.display()
+        ));
+        return;
+    }
+    app.tasks.enqueue(JobKind::Link {
+        sources,
+        dest,
+        hard,
+    });
+}
+
+/// Ask for the name to paste the entry in `register` under; it must hold a single entry.
+pub fn paste_as(app: &mut App, register: Option<char>) {
+    let [source] = app.registers.get(register) else {
+        app.notifications
+            .warn("paste as needs a single yanked entry");
+        return;
+    };
+    let name = source
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    app.mode = Mode::PasteAs {
+        source: source.clone(),
+        buffer: LineEditor::new(name.as_str()),
+        confirmed: false,
+    };
+}
+
+/// Queue the copy chosen in the paste-as prompt; `name` may be a path in directories that
+/// are created first.
+pub fn paste_as_name(app: &mut App, source: PathBuf, name: &str) {
+    let target = app.current_pane().current_dir.join(name);
+    if same_entry(&source, &target) {
+        app.notifications.warn("cannot paste an entry over itself");
+        return;
+    }
+    if app.dry_run {
+        let dest = app.current_pane().current_dir.clone();
+        let conflicts = if target.symlink_metadata().is_ok() {
+            vec![name.to_string()]
+        } else {
+            Vec::new()
+        };
+        report_dry_run(app, "copy", &[source], Some((&dest, conflicts)));
+        return;
+    }
+    app.tasks.enqueue(JobKind::CopyAs { source, target });
+}
+
+/// Check the path typed in the create prompt: a trailing separator asks for a directory, and
+/// an existing entry is never replaced.
+pub fn validate_new_entry(dir: &Path, path: &str) -> Option<NameProblem> {
+    match validate_path(dir, path.trim_end_matches(is_separator)) {
+        Some(NameProblem::Exists) => Some(NameProblem::Invalid("already exists".into())),
+        problem => problem,
+    }
+}
+
+/// Create the file, or directory if `path` ends with a separator, typed in the create prompt,
+/// along with the directories on the way to it, and put the cursor on it.
+pub fn create_entry(app: &mut App, path: &str) {
+    let dir = app.current_pane().current_dir.clone();
+    let target = dir.join(path.trim_end_matches(is_separator));
+    let long = long_path(&target);
+    let created = if path.ends_with(is_separator) {
+        fs::create_dir_all(&long)
+    } else {
+        long.parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|()| File::create_new(&long).map(drop))
+    };
+    if let Err(e) = created {
+        app.notifications
+            .error(format!("cannot create {}: {e}", target.display()));
+    }
     let pane = app.current_pane_mut();
-    for path in items {
-        if path.is_dir() {
-            if let Err(e) = fs::remove_dir_all(path) {
-                eprintln!("Failed to delete directory {:?}: {}", path, e);
+    if pane.refresh().is_ok() {
+        // the entry of this directory the new one is in, or is
+        let first = path.split(is_separator).next().unwrap_or_default();
+        if let Some(pos) = pane
+            .items
+            .iter()
+            .position(|e| e.file_name().to_string_lossy() == first)
+        {
+            pane.selected = pos;
+        }
+    }
+}
+
+/// Add the marked entries (or the current entry) to the basket, or take them out if all are in it.
+pub fn toggle_basket(app: &mut App) {
+    if !app.current_pane().is_local() {
+        app.notifications.warn("the basket only holds local files");
+        return;
+    }
+    let selection = app.current_pane().selection();
+    if selection.iter().all(|p| app.basket.contains(p)) {
+        app.basket.retain(|p| !selection.contains(p));
+    } else {
+        for path in selection {
+            if !app.basket.contains(&path) {
+                app.basket.push(path);
+            }
+        }
+    }
+    app.current_pane_mut().marked.clear();
+    app.notifications
+        .info(format!("{} in the basket", app.basket.len()));
+}
+
+/// Queue a copy (or move, emptying the basket) of the basket entries into the current directory.
+pub fn transfer_basket(app: &mut App, move_items: bool) {
+    if app.basket.is_empty() || !app.current_pane().is_local() {
+        return;
+    }
+    let dest = app.current_pane().current_dir.clone();
+    if app.dry_run {
+        let sources = app.basket.clone();
+        let conflicts = conflicts(&sources, &dest, app.current_pane());
+        let verb = if move_items { "move" } else { "copy" };
+        report_dry_run(app, verb, &sources, Some((&dest, conflicts)));
+        return;
+    }
+    if move_items {
+        app.tasks.enqueue(JobKind::Move {
+            sources: std::mem::take(&mut app.basket),
+            dest,
+        });
+    } else {
+        queue_copy(app, app.basket.clone(), dest);
+    }
+}
+
+/// Queue a move of the entries in `register` (the latest yank when `None`) into the current
+/// directory, emptying the register.
+pub fn move_clipboard(app: &mut App, register: Option<char>) {
+    let sources = app.registers.get(register).to_vec();
+    if sources.is_empty() {
+        return;
+    }
+    let dest = app.current_pane().current_dir.clone();
+    if app.dry_run {
+        let conflicts = conflicts(&sources, &dest, app.current_pane());
+        report_dry_run(app, "move", &sources, Some((&dest, conflicts)));
+        return;
+    }
+    if let Some(confirm) = paste_confirmation(app, sources, &dest, true) {
+        app.mode = confirm;
+        return;
+    }
+    let sources = app.registers.take(register);
+    app.tasks.enqueue(JobKind::Move { sources, dest });
+}
+
+/// Ask to copy (or move) the marked entries (or the current entry) into the other pane's directory.
+pub fn transfer_to_other_pane(app: &mut App, move_items: bool) {
+    let sources = app.current_pane().selection();
+    let dest = app.other_pane().current_dir.clone();
+    let remote = !app.current_pane().is_local() || !app.other_pane().is_local();
+    if sources.is_empty() || (!remote && app.current_pane().current_dir == dest) {
+        return;
+    }
+    if remote && move_items {
+        app.mode = Mode::Message {
+            title: "Move".into(),
+            lines: vec![
+                "Moving to or from a remote directory is not supported; copy with F5".into(),
+            ],
+        };
+        return;
+    }
+    let conflicts = conflicts(&sources, &dest, app.other_pane());
+    if app.dry_run {
+        let verb = if move_items { "move" } else { "copy" };
+        report_dry_run(app, verb, &sources, Some((&dest, conflicts)));
+        return;
+    }
+    let protected = if app.other_pane().is_local() {
+        let overwritten: Vec<PathBuf> = conflicts.iter().map(|name| dest.join(name)).collect();
+        protection(app, &overwritten)
+    } else {
+        None
+    };
+    let hard_links = !move_items
+        && conflicts.is_empty()
+        && app.current_pane().is_local()
+        && app.other_pane().is_local()
+        && can_hard_link(&sources, &dest) == Some(true);
+    app.mode = Mode::ConfirmTransfer {
+        sources,
+        dest,
+        move_items,
+        conflicts: conflicts.len(),
+        protected,
+        paste: false,
+        hard_links,
+    };
+}
+
+/// The popup to confirm pasting `sources` into `dest` in, when there are more of them than
+/// `confirm_over` or one would overwrite an entry in a protected directory.
+fn paste_confirmation(
+    app: &App,
+    sources: Vec<PathBuf>,
+    dest: &Path,
+    move_items: bool,
+) -> Option<Mode> {
+    let conflicts = conflicts(&sources, dest, app.current_pane());
+    let protected = if app.current_pane().is_local() {
+        let overwritten: Vec<PathBuf> = conflicts.iter().map(|name| dest.join(name)).collect();
+        protection(app, &overwritten)
+    } else {
+        None
+    };
+    if protected.is_none() && !app.config.is_many(sources.len()) {
+        return None;
+    }
+    let hard_links = !move_items
+        && conflicts.is_empty()
+        && app.current_pane().is_local()
+        && can_hard_link(&sources, dest) == Some(true);
+    Some(Mode::ConfirmTransfer {
+        sources,
+        dest: dest.to_path_buf(),
+        move_items,
+        conflicts: conflicts.len(),
+        protected,
+        paste: true,
+        hard_links,
+    })
+}
+
+/// Queue a paste confirmed in its popup; a move empties the registers it came from.
+pub fn apply_paste(app: &mut App, sources: Vec<PathBuf>, dest: PathBuf, move_items: bool) {
+    if move_items {
+        app.registers.forget(&sources);
+        app.tasks.enqueue(JobKind::Move { sources, dest });
+    } else {
+        queue_copy(app, sources, dest);
+    }
+}
+
+/// The name to type before deleting or overwriting `paths`, when one of them is a protected
+/// directory or in one.
+fn protection(app: &App, paths: &[PathBuf]) -> Option<TypeToConfirm> {
+    let dir = paths.iter().find_map(|p| app.config.protected_dir(p))?;
+    let name = dir.file_name().map_or_else(
+        || dir.display().to_string(),
+        |name| name.to_string_lossy().into_owned(),
+    );
+    Some(TypeToConfirm::new(name))
+}
+
+/// File names of `sources` that already exist in `dest`, the directory `listing` shows.
+/// Sources already in `dest` are left out: pasted, they get a new name.
+fn conflicts(sources: &[PathBuf], dest: &Path, listing: &Pane) -> Vec<String> {
+    sources
+        .iter()
+        .filter(|s| !(listing.is_local() && s.parent() == Some(dest)))
+        .filter_map(|s| s.file_name())
+        .filter(|&name| {
+            if listing.is_local() {
+                dest.join(name).symlink_metadata().is_ok()
+            } else {
+                listing.items.iter().any(|e| e.file_name() == name)
+            }
+        })
+        .map(|name| name.to_string_lossy().into_owned())
+        .collect()
+}
+
+/// Show what copying, moving or deleting `sources` would do instead of doing it.
+///
+/// `dest` is the target directory of a copy or move, with the names already present there.
+fn report_dry_run(
+    app: &mut App,
+    verb: &str,
+    sources: &[PathBuf],
+    dest: Option<(&Path, Vec<String>)>,
+) {
+    /// Conflicting names listed before the rest are summed up
+    const SHOWN: usize = 20;
+
+    let pane = app.current_pane();
+    let local = pane.is_local();
+    let sizes: Vec<(usize, u64)> = sources
+        .iter()
+        .map(|source| {
+            if local {
+                return tree_size(source);
+            }
+            match pane.items.iter().find(|e| e.path() == *source) {
+                Some(e) if e.is_dir() => dir_size(pane.vfs.as_ref(), source),
+                Some(e) => (1, e.len()),
+                None => (0, 0),
             }
+        })
+        .collect();
+    if local {
+        // the sizes are shown with the directory counts until the directories change
+        for (source, (_, bytes)) in sources.iter().zip(&sizes) {
+            if source.is_dir() {
+                app.left.record_size(source, *bytes);
+                app.right.record_size(source, *bytes);
+            }
+        }
+    }
+    let (files, bytes) = sizes
+        .iter()
+        .fold((0, 0), |(files, bytes), (f, b)| (files + f, bytes + b));
+    let items = match sources.len() {
+        1 => "1 item".to_string(),
+        n => format!("{} items", thousands(n as u64)),
+    };
+    let mut lines = vec![
+        match &dest {
+            Some((dest, _)) => format!("Would {verb} {items} into {}", dest.display()),
+            None => format!("Would {verb} {items}"),
+        },
+        format!(
+            "{} files, {} ({} bytes)",
+            thousands(files as u64),
+            human_size(bytes),
+            thousands(bytes)
+        ),
+    ];
+    if let Some((_, conflicts)) = dest {
+        if conflicts.is_empty() {
+            lines.push("No conflicts".into());
         } else {
-            if let Err(e) = fs::remove_file(path) {
-                eprintln!("Failed to delete file {:?}: {}", path, e);
+            lines.push(format!(
+                "{} conflicts would be overwritten:",
+                thousands(conflicts.len() as u64)
+            ));
+            lines.extend(conflicts.iter().take(SHOWN).map(|name| format!("  {name}")));
+            if conflicts.len() > SHOWN {
+                lines.push(format!("  ... and {} more", conflicts.len() - SHOWN));
             }
         }
     }
-    let _ = pane.refresh();
+    app.mode = Mode::Message {
+        title: "Dry run".into(),
+        lines,
+    };
+}
+
+/// Queue a confirmed copy or move between panes.
+///
+/// Transfers involving a remote directory run right away instead of in the task queue.
+pub fn apply_transfer(app: &mut App, sources: Vec<PathBuf>, dest: PathBuf, move_items: bool) {
+    app.current_pane_mut().marked.clear();
+    if !app.current_pane().is_local() || !app.other_pane().is_local() {
+        let (from, to) = (app.current_pane().vfs.clone(), app.other_pane().vfs.clone());
+        let failures: Vec<String> = sources
+            .iter()
+            .filter_map(|source| {
+                let is_dir = app
+                    .current_pane()
+                    .items
+                    .iter()
+                    .find(|e| e.path() == *source)
+                    .is_some_and(|e| e.is_dir());
+                let target = dest.join(source.file_name()?);
+                copy_across(from.as_ref(), source, is_dir, to.as_ref(), &target)
+                    .err()
+                    .map(|e| format!("{}: {e}", source.display()))
+            })
+            .collect();
+        app.refresh_panes();
+        if !failures.is_empty() {
+            app.mode = Mode::Message {
+                title: "Copy failed".into(),
+                lines: failures,
+            };
+        }
+        return;
+    }
+    if move_items {
+        app.tasks.enqueue(JobKind::Move { sources, dest });
+    } else {
+        queue_copy(app, sources, dest);
+    }
+}
+
+/// Queue a gzipped tarball of the marked entries (or the current entry) in the current directory.
+pub fn archive_selection(app: &mut App) {
+    let pane = app.current_pane_mut();
+    let sources = pane.selection();
+    let stem = match sources.as_slice() {
+        [] => return,
+        [single] => single
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "archive".into()),
+        _ => "archive".into(),
+    };
+    let dest = pane.current_dir.join(format!("{stem}.tar.gz"));
+    pane.marked.clear();
+    app.tasks.enqueue(JobKind::Archive { sources, dest });
+}
+
+/// Queue the extraction of each selected archive into a new directory beside it, named after
+/// the archive. Entries that are not archives are left out.
+pub fn extract_selection(app: &mut App) {
+    let pane = app.current_pane_mut();
+    let archives: Vec<(PathBuf, Format, String)> = pane
+        .selection()
+        .into_iter()
+        .filter_map(|path| Format::of(&path).map(|(format, stem)| (path, format, stem)))
+        .collect();
+    if archives.is_empty() {
+        app.notifications
+            .warn("nothing to extract: select a zip, tar, 7z or rar archive");
+        return;
+    }
+    pane.marked.clear();
+    let mut dests = Vec::new();
+    for (archive, format, stem) in archives {
+        let dest = extract_dir(&archive, &stem, &dests);
+        dests.push(dest.clone());
+        app.tasks.enqueue(JobKind::Extract(Extraction {
+            archive,
+            format,
+            dest,
+            password: None,
+        }));
+    }
+}
+
+/// Rename `sources` to `names` (in the same directory) as one batch, reporting a failure in a popup.
+pub fn bulk_rename(app: &mut App, sources: &[PathBuf], names: &[String]) {
+    let problem = if names.len() != sources.len() {
+        Some(format!(
+            "Expected {} names but got {}; nothing was renamed",
+            sources.len(),
+            names.len()
+        ))
+    } else {
+        names
+            .iter()
+            .find(|n| n.is_empty() || n.contains('/') || (cfg!(windows) && n.contains('\\')))
+            .map(|n| format!("Invalid name '{n}'; nothing was renamed"))
+    };
+    let result = match problem {
+        Some(problem) => Err(problem),
+        None => {
+            let renames: Vec<(PathBuf, PathBuf)> = sources
+                .iter()
+                .zip(names)
+                .map(|(from, name)| (from.clone(), from.with_file_name(name)))
+                .collect();
+            rename::apply(&renames).map_err(|e| e.to_string())
+        }
+    };
+    app.current_pane_mut().marked.clear();
+    app.refresh_panes();
+    tracing::info!(count = sources.len(), ok = result.is_ok(), "bulk rename");
+    if let Err(e) = result {
+        app.mode = Mode::Message {
+            title: "Bulk rename failed".into(),
+            lines: vec![e],
+        };
+    }
+}
+
+/// Queue moving the given files or directories to the trash.
+pub fn trash_items(app: &mut App, items: &[PathBuf]) {
+    if items.is_empty() {
+        return;
+    }
+    if app.dry_run {
+        report_dry_run(app, "move to the trash", items, None);
+        return;
+    }
+    app.current_pane_mut().marked.clear();
+    app.tasks.enqueue(JobKind::Trash {
+        paths: items.to_vec(),
+    });
+}
+
+/// Delete `items`, asking first with `ask`, when they are more than `confirm_over`, or with
+/// the name of the protected directory one of them is in to type.
+pub fn request_delete(app: &mut App, items: Vec<PathBuf>, ask: bool) {
+    if items.is_empty() {
+        return;
+    }
+    if app.dry_run {
+        delete_items(app, &items);
+        return;
+    }
+    let protected = if app.current_pane().is_local() {
+        protection(app, &items)
+    } else {
+        None
+    };
+    if ask || protected.is_some() || app.config.is_many(items.len()) {
+        app.mode = Mode::ConfirmDelete { items, protected };
+    } else {
+        delete_items(app, &items);
+    }
+}
+
+/// Ask before overwriting and deleting `items`, always: unlike a deletion, nothing can bring
+/// them back.
+pub fn request_shred(app: &mut App, items: Vec<PathBuf>) {
+    if items.is_empty() {
+        return;
+    }
+    if app.dry_run {
+        report_dry_run(app, "shred", &items, None);
+        return;
+    }
+    let protected = protection(app, &items);
+    app.mode = Mode::ConfirmShred { items, protected };
+}
+
+/// Queue overwriting and deleting the given files or directories.
+pub fn shred_items(app: &mut App, items: Vec<PathBuf>) {
+    app.current_pane_mut().marked.clear();
+    app.tasks.enqueue(JobKind::Shred {
+        paths: items,
+        passes: app.config.shred_passes,
+    });
+}
+
+/// Queue deletion of the given files or directories.
+///
+/// Remote entries are deleted right away instead of in the task queue.
+pub fn delete_items(app: &mut App, items: &[PathBuf]) {
+    if items.is_empty() {
+        return;
+    }
+    if app.dry_run {
+        report_dry_run(app, "delete", items, None);
+        return;
+    }
+    app.current_pane_mut().marked.clear();
+    if !app.current_pane().is_local() {
+        let pane = app.current_pane();
+        let failures: Vec<String> = items
+            .iter()
+            .filter_map(|path| {
+                let is_dir = pane
+                    .items
+                    .iter()
+                    .find(|e| e.path() == *path)
+                    .is_some_and(|e| e.is_dir() && !e.is_symlink());
+                remove_tree(pane.vfs.as_ref(), path, is_dir)
+                    .err()
+                    .map(|e| format!("{}: {e}", path.display()))
+            })
+            .collect();
+        app.refresh_panes();
+        if !failures.is_empty() {
+            app.mode = Mode::Message {
+                title: "Delete failed".into(),
+                lines: failures,
+            };
+        }
+        return;
+    }
+    app.tasks.enqueue(JobKind::Delete {
+        paths: items.to_vec(),
+    });
 }
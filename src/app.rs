@@ -1,34 +1,849 @@
-use std::{collections::HashSet, fs, io, path::PathBuf};
+use std::{
+    cell::{Cell, RefCell},
+    collections::{HashMap, HashSet},
+    ffi::OsString,
+    fs, io,
+    path::{Path, PathBuf},
+    rc::Rc,
+    sync::mpsc::{self, Receiver, TryRecvError},
+};
 
+use crate::fs_utils::{
+    contains_smart_case, group_by_date, sort_entries, DateGroup, SizeDisplay, SortSpec,
+};
+use crate::git::{spawn_last_commits, spawn_status, CommitInfo, GitInfo};
+use crate::heat::Heat;
+
+use crate::checksum::{self, ChecksumReport};
+use crate::columns::Columns;
+use crate::config::{expand_home, Config};
+use crate::diff::DiffView;
+use crate::dir_stats::{spawn_counts, DirStats};
+use crate::dir_views::{DirViews, ViewSettings};
+use crate::elevate::Privileged;
+use crate::file_index::{FileIndex, Finder};
+use crate::format;
+use crate::frecency::History;
+use crate::iso::{is_iso, IsoFs};
+use crate::jump_list::{Jump, JumpList};
+use crate::line_edit::LineEditor;
+use crate::log_view::{is_json_log, LogViewer};
+use crate::ls_colors::LsColors;
 use crate::mode::{Mode, PaneType};
+use crate::mounts::{self, Space};
+use crate::notification::Notifications;
+use crate::registers::Registers;
+use crate::runner::{open_default, reveal, Running};
+use crate::s3::S3Fs;
+use crate::scripting::Scripts;
+use crate::sftp::SftpFs;
+use crate::signature::{signed_pair, spawn_verify, Verdict};
+use crate::sync::CompareStatus;
+use crate::table_view::{is_table, TableView};
+use crate::tasks::{JobKind, JobStatus, TaskQueue};
+use crate::todo_scan::TodoList;
+use crate::tree_view::{is_structured, TreeView};
+use crate::vfs::{Entry, EntryChange, EntryStamp, LocalFs, VfsProvider};
+use crate::viewer::Viewer;
+use crate::watch::Watch;
+use crate::webdav::WebDavFs;
 
 pub struct Pane {
-    pub items: Vec<fs::DirEntry>,
+    pub items: Vec<Entry>,
     pub selected: usize,
     pub current_dir: PathBuf,
-    pub marked: HashSet<usize>,
+    /// File system `current_dir` belongs to
+    pub vfs: Rc<dyn VfsProvider>,
+    /// Paths of the marked entries, so marks stay on the same files when the listing changes
+    pub marked: HashSet<PathBuf>,
+    /// Order chosen from the sort popup; `None` lists entries by name
+    pub sort_by: Option<SortSpec>,
+    /// List entries whose names start with `.`
+    pub show_hidden: bool,
+    /// Only list entries whose names contain this; cleared when the directory changes
+    pub filter: String,
+    /// Section the listing by modification date
+    pub group_by_date: bool,
+    pub size_display: SizeDisplay,
+    /// What entry names are tinted by
+    pub heat: Heat,
+    /// Date bucket of each entry while grouping is on
+    pub groups: Vec<DateGroup>,
+    /// Groups whose entries are folded away behind their header
+    pub collapsed: HashSet<DateGroup>,
+    /// Result of the last directory comparison with the other pane, by file name
+    pub comparison: HashMap<OsString, CompareStatus>,
+    /// Branch and entry statuses when the directory is inside a git work tree
+    pub git: Option<GitInfo>,
+    git_pending: Option<Receiver<GitInfo>>,
+    /// Show the last commit touching each entry
+    pub show_commits: bool,
+    /// Last commit of each entry by name, once fetched
+    pub commits: HashMap<OsString, CommitInfo>,
+    commits_pending: Option<Receiver<HashMap<OsString, CommitInfo>>>,
+    /// Fetched last-commit tables by directory, reused when a directory is revisited
+    commit_cache: HashMap<PathBuf, HashMap<OsString, CommitInfo>>,
+    /// Show each directory's entry count, and size once known, after its name
+    pub show_counts: bool,
+    /// Counts and sizes of listed directories by path, kept while they are unchanged
+    dir_stats: HashMap<PathBuf, DirStats>,
+    counts_pending: Option<Receiver<(PathBuf, usize)>>,
+    /// Directories visited before and after the current one
+    jumps: JumpList,
+    /// Directory the pane is locked to; navigation never leaves it
+    pub root: Option<PathBuf>,
+    /// View settings remembered per directory, shared by both panes
+    pub views: Rc<RefCell<DirViews>>,
+    /// View settings of the last directory entered without remembered ones, to go back to
+    /// after leaving those that have them
+    outside: ViewSettings,
+    /// Page shown when the directory is too large to list at once
+    pub page: usize,
+    /// Number of entries in the directory when it is listed in pages
+    pub paged_total: Option<usize>,
+    /// Free and total space of the file system holding `current_dir`, when local
+    pub space: Option<Space>,
+    /// First listing row on screen, kept between frames so the view only moves when the
+    /// cursor would leave it
+    pub scroll: Cell<usize>,
+    /// Rows the listing had room for when it was last drawn
+    pub view_height: Cell<usize>,
+    /// Where the title with the breadcrumbs was last drawn, as column, row and width; `None`
+    /// while the pane is not on screen
+    pub title_area: Cell<Option<(u16, u16, u16)>>,
+}
+
+/// Where `Pane::align` puts the cursor row on screen.
+#[derive(Clone, Copy)]
+pub enum ViewAlign {
+    Top,
+    Center,
+    Bottom,
 }
 
+/// Directories with more entries than this are listed one page of this size at a time.
+pub const PAGE_SIZE: usize = 10_000;
+
 impl Pane {
     pub fn new(path: PathBuf) -> io::Result<Self> {
-        let mut entries = fs::read_dir(&path)?.collect::<Result<Vec<_>, _>>()?;
-        entries.sort_by_key(|e| e.file_name());
-        Ok(Self {
-            items: entries,
+        let mut pane = Self {
+            git_pending: None,
+            items: Vec::new(),
             selected: 0,
             current_dir: path,
+            vfs: Rc::new(LocalFs),
             marked: HashSet::new(),
-        })
+            sort_by: None,
+            show_hidden: true,
+            filter: String::new(),
+            group_by_date: false,
+            size_display: SizeDisplay::Hidden,
+            heat: Heat::Off,
+            groups: Vec::new(),
+            collapsed: HashSet::new(),
+            comparison: HashMap::new(),
+            git: None,
+            show_commits: false,
+            commits: HashMap::new(),
+            commits_pending: None,
+            commit_cache: HashMap::new(),
+            show_counts: false,
+            dir_stats: HashMap::new(),
+            counts_pending: None,
+            jumps: JumpList::default(),
+            root: None,
+            views: Rc::default(),
+            outside: ViewSettings {
+                sort_by: None,
+                show_hidden: true,
+                filter: String::new(),
+                size_display: SizeDisplay::Hidden,
+                group_by_date: false,
+            },
+            page: 0,
+            paged_total: None,
+            space: None,
+            scroll: Cell::new(0),
+            view_height: Cell::new(0),
+            title_area: Cell::new(None),
+        };
+        pane.refresh()?;
+        Ok(pane)
     }
 
     pub fn refresh(&mut self) -> io::Result<()> {
-        let mut entries = fs::read_dir(&self.current_dir)?.collect::<Result<Vec<_>, _>>()?;
-        entries.sort_by_key(|e| e.file_name());
+        let mut entries = self.read_entries()?;
+        match self.sort_by {
+            Some(by) => sort_entries(&mut entries, by),
+            None => entries.sort_by_key(|e| e.file_name()),
+        }
         self.items = entries;
+        self.groups = if self.group_by_date {
+            group_by_date(&mut self.items)
+        } else {
+            Vec::new()
+        };
         self.selected = 0;
-        self.marked.clear();
+        self.scroll.set(0);
+        if !self.marked.is_empty() {
+            let listed: HashSet<PathBuf> = self.items.iter().map(|e| e.path()).collect();
+            self.marked.retain(|p| listed.contains(p));
+        }
+        self.comparison.clear();
+        self.git = None;
+        self.git_pending = None;
+        self.space = None;
+        if self.is_local() {
+            self.git_pending = Some(spawn_status(self.current_dir.clone()));
+            self.space = mounts::space(&self.current_dir).ok();
+        }
+        self.load_commits();
+        self.load_counts();
+        Ok(())
+    }
+
+    /// Whether the pane shows the local file system.
+    pub fn is_local(&self) -> bool {
+        self.vfs.is_local()
+    }
+
+    /// Show `dir` on the file system of `vfs`, staying where the pane was if it cannot be read.
+    pub fn set_location(&mut self, vfs: Rc<dyn VfsProvider>, dir: PathBuf) -> io::Result<()> {
+        let from = self.here();
+        self.go(vfs, dir)?;
+        if !(Rc::ptr_eq(&from.vfs, &self.vfs) && from.dir == self.current_dir) {
+            self.jumps.record(from);
+        }
+        Ok(())
+    }
+
+    /// Where the pane is, for the jump list.
+    fn here(&self) -> Jump {
+        Jump {
+            vfs: self.vfs.clone(),
+            dir: self.current_dir.clone(),
+            selected: self.items.get(self.selected).map(|e| e.file_name()),
+        }
+    }
+
+    /// Go back to the previous place in the jump list, skipping those that are gone; returns
+    /// whether the pane moved.
+    pub fn jump_back(&mut self) -> bool {
+        let from = self.here();
+        while let Some(jump) = self.jumps.back() {
+            if self.restore(jump) {
+                self.jumps.went_back(from);
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Go forward again to a place left with `jump_back`; returns whether the pane moved.
+    pub fn jump_forward(&mut self) -> bool {
+        let from = self.here();
+        while let Some(jump) = self.jumps.forward() {
+            if self.restore(jump) {
+                self.jumps.went_forward(from);
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Show the place of `jump`, with the cursor on the same entry if it is still there.
+    fn restore(&mut self, jump: Jump) -> bool {
+        if self.go(jump.vfs, jump.dir).is_err() {
+            return false;
+        }
+        if let Some(pos) = jump
+            .selected
+            .and_then(|name| self.items.iter().position(|e| e.file_name() == name))
+        {
+            self.selected = pos;
+        }
+        true
+    }
+
+    /// Move to `dir` on `vfs` without recording it in the jump list.
+    fn go(&mut self, vfs: Rc<dyn VfsProvider>, dir: PathBuf) -> io::Result<()> {
+        if self.root.is_some() && !(vfs.is_local() && self.in_scope(&dir)) {
+            return Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                "the pane is locked to its root directory",
+            ));
+        }
+        self.store_view();
+        let previous_view = self.view();
+        let previous_outside = self.outside.clone();
+        let was_remembered = self.remembers_view();
+        let previous_vfs = std::mem::replace(&mut self.vfs, vfs);
+        let previous = std::mem::replace(&mut self.current_dir, dir);
+        let previous_page = std::mem::replace(&mut self.page, 0);
+        self.filter.clear();
+        self.switch_view(was_remembered);
+        if let Err(e) = self.refresh() {
+            self.vfs = previous_vfs;
+            self.current_dir = previous;
+            self.page = previous_page;
+            self.apply_view(previous_view);
+            self.outside = previous_outside;
+            let _ = self.refresh();
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    /// The settings the listing is shown with.
+    pub fn view(&self) -> ViewSettings {
+        ViewSettings {
+            sort_by: self.sort_by,
+            show_hidden: self.show_hidden,
+            filter: self.filter.clone(),
+            size_display: self.size_display,
+            group_by_date: self.group_by_date,
+        }
+    }
+
+    /// Show the listing with `view`, from the next refresh.
+    fn apply_view(&mut self, view: ViewSettings) {
+        if view.group_by_date != self.group_by_date {
+            self.collapsed.clear();
+        }
+        self.sort_by = view.sort_by;
+        self.show_hidden = view.show_hidden;
+        self.filter = view.filter;
+        self.size_display = view.size_display;
+        self.group_by_date = view.group_by_date;
+    }
+
+    /// Give the directory just entered its remembered view settings, or, coming from one with
+    /// remembered settings (`was_remembered`), those the pane had before.
+    fn switch_view(&mut self, was_remembered: bool) {
+        let remembered = self.remembered_view();
+        match remembered {
+            Some(view) => self.apply_view(view),
+            None => {
+                if was_remembered {
+                    self.apply_view(self.outside.clone());
+                }
+                self.outside = self.view();
+            }
+        }
+    }
+
+    fn remembered_view(&self) -> Option<ViewSettings> {
+        if !self.is_local() {
+            return None;
+        }
+        self.views.borrow().get(&self.current_dir).cloned()
+    }
+
+    /// Whether the view settings of the current directory are remembered.
+    pub fn remembers_view(&self) -> bool {
+        self.remembered_view().is_some()
+    }
+
+    /// Keep the view settings of the current directory up to date with the pane when they
+    /// are remembered.
+    pub fn store_view(&self) {
+        if self.remembers_view() {
+            self.views
+                .borrow_mut()
+                .remember(&self.current_dir, self.view());
+        }
+    }
+
+    /// Remember the view settings for the current directory, or forget them; returns whether
+    /// they are remembered now. Leaving the directory brings back the settings the pane had
+    /// when it entered it.
+    pub fn toggle_remembered_view(&mut self) -> bool {
+        if self.remembers_view() {
+            self.views.borrow_mut().forget(&self.current_dir);
+            self.outside = ViewSettings {
+                filter: String::new(),
+                ..self.view()
+            };
+            false
+        } else if self.is_local() {
+            self.views
+                .borrow_mut()
+                .remember(&self.current_dir, self.view());
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Share `views` with the other pane, and show the current directory with its remembered
+    /// settings.
+    pub fn set_views(&mut self, views: Rc<RefCell<DirViews>>) {
+        self.views = views;
+        self.outside = self.view();
+        if let Some(view) = self.remembered_view() {
+            self.apply_view(view);
+            let _ = self.refresh();
+        }
+    }
+
+    /// Read the directory, or only the current page of it if it holds more than `PAGE_SIZE`
+    /// entries, so memory stays bounded however large the directory is.
+    fn read_entries(&mut self) -> io::Result<Vec<Entry>> {
+        let vfs = self.vfs.clone();
+        let show_hidden = self.show_hidden;
+        let filter = self.filter.clone();
+        let shown = move |entry: &io::Result<Entry>| {
+            let Ok(e) = entry else {
+                return true;
+            };
+            let name = e.file_name();
+            let name = name.to_string_lossy();
+            (show_hidden || !name.starts_with('.')) && contains_smart_case(&name, &filter)
+        };
+        let mut iter = vfs.read_dir(&self.current_dir)?.filter(&shown);
+        let first = iter
+            .by_ref()
+            .take(PAGE_SIZE + 1)
+            .collect::<Result<Vec<_>, _>>()?;
+        if first.len() <= PAGE_SIZE {
+            self.page = 0;
+            self.paged_total = None;
+            return Ok(first);
+        }
+        let total = first.len() + iter.count();
+        drop(first);
+        self.paged_total = Some(total);
+        self.page = self.page.min((total - 1) / PAGE_SIZE);
+        let page = vfs
+            .read_dir(&self.current_dir)?
+            .filter(&shown)
+            .skip(self.page * PAGE_SIZE)
+            .take(PAGE_SIZE)
+            .collect();
+        page
+    }
+
+    /// Number of pages of a paged listing (1 when the directory is listed whole).
+    pub fn page_count(&self) -> usize {
+        self.paged_total
+            .map_or(1, |total| total.div_ceil(PAGE_SIZE))
+    }
+
+    /// Load the next (`forward`) or previous page of a paged listing.
+    pub fn turn_page(&mut self, forward: bool) {
+        let page = if forward {
+            self.page + 1
+        } else {
+            self.page.saturating_sub(1)
+        };
+        if page != self.page && page < self.page_count() {
+            self.page = page;
+            let _ = self.refresh();
+        }
+    }
+
+    /// Whether `dir` lies inside the pane's root lock (always true when unlocked).
+    ///
+    /// Symlinks are resolved, so a link pointing outside the root does not escape it.
+    pub fn in_scope(&self, dir: &Path) -> bool {
+        match &self.root {
+            None => true,
+            Some(root) => dir.canonicalize().is_ok_and(|d| d.starts_with(root)),
+        }
+    }
+
+    /// Move to `dir` if it is within scope; returns whether the pane changed directory.
+    pub fn change_dir(&mut self, dir: PathBuf) -> bool {
+        self.set_location(self.vfs.clone(), dir).is_ok()
+    }
+
+    /// Lock the pane to its current directory, or lift the lock.
+    pub fn toggle_root_lock(&mut self) {
+        self.root = match self.root {
+            Some(_) => None,
+            None => self.current_dir.canonicalize().ok(),
+        };
+    }
+
+    /// Show the cached last-commit table for the current directory, or start fetching it.
+    fn load_commits(&mut self) {
+        self.commits_pending = None;
+        if !self.show_commits || !self.is_local() {
+            self.commits.clear();
+            return;
+        }
+        if let Some(cached) = self.commit_cache.get(&self.current_dir) {
+            self.commits = cached.clone();
+            return;
+        }
+        self.commits.clear();
+        let names = self.items.iter().map(|e| e.file_name()).collect();
+        self.commits_pending = Some(spawn_last_commits(self.current_dir.clone(), names));
+    }
+
+    /// Turn the last-commit column on or off; turning it on fetches fresh data.
+    pub fn toggle_commits(&mut self) {
+        self.show_commits = !self.show_commits;
+        self.commit_cache.clear();
+        self.load_commits();
+    }
+
+    /// How the entry `name`, listed as `stamp`, changed since, looking it up again.
+    pub fn entry_change(&self, name: &str, stamp: EntryStamp) -> Option<EntryChange> {
+        let found = self
+            .vfs
+            .read_dir(&self.current_dir)
+            .ok()
+            .and_then(|entries| {
+                entries
+                    .flatten()
+                    .find(|e| e.file_name().to_string_lossy() == name)
+            });
+        match found {
+            None => Some(EntryChange::Removed),
+            Some(e) if e.stamp() != stamp => Some(EntryChange::Replaced),
+            Some(_) => None,
+        }
+    }
+
+    /// Count the entries of the listed directories that are not counted yet, or changed since.
+    fn load_counts(&mut self) {
+        self.counts_pending = None;
+        if !self.show_counts || !self.is_local() {
+            return;
+        }
+        let mut stale = Vec::new();
+        for entry in self.items.iter().filter(|e| e.is_dir()) {
+            let path = entry.path();
+            let modified = entry.modified();
+            match self.dir_stats.get(&path) {
+                Some(stats) if stats.modified == modified && stats.entries.is_some() => {}
+                Some(stats) if stats.modified == modified => stale.push(path),
+                _ => {
+                    self.dir_stats.insert(path.clone(), DirStats::new(modified));
+                    stale.push(path);
+                }
+            }
+        }
+        if !stale.is_empty() {
+            self.counts_pending = Some(spawn_counts(stale));
+        }
+    }
+
+    /// Turn the directory counts on or off.
+    pub fn toggle_counts(&mut self) {
+        self.show_counts = !self.show_counts;
+        self.load_counts();
+    }
+
+    /// Counts of the directory at `path`, if it was listed with counts on.
+    pub fn dir_stats(&self, path: &Path) -> Option<&DirStats> {
+        self.dir_stats.get(path)
+    }
+
+    /// Remember the total size of the directory at `path`, computed elsewhere, to show with its
+    /// count until it changes.
+    pub fn record_size(&mut self, path: &Path, size: u64) {
+        let modified = path.symlink_metadata().and_then(|m| m.modified()).ok();
+        let stats = self
+            .dir_stats
+            .entry(path.to_path_buf())
+            .or_insert_with(|| DirStats::new(modified));
+        if stats.modified != modified {
+            *stats = DirStats::new(modified);
+        }
+        stats.size = Some(size);
+    }
+
+    /// Pick up the directory counts finished in the background; returns true if any arrived.
+    pub fn poll_counts(&mut self) -> bool {
+        let Some(rx) = &self.counts_pending else {
+            return false;
+        };
+        let mut arrived = false;
+        loop {
+            match rx.try_recv() {
+                Ok((path, count)) => {
+                    if let Some(stats) = self.dir_stats.get_mut(&path) {
+                        stats.entries = Some(count);
+                    }
+                    arrived = true;
+                }
+                Err(TryRecvError::Disconnected) => {
+                    self.counts_pending = None;
+                    break;
+                }
+                Err(TryRecvError::Empty) => break,
+            }
+        }
+        arrived
+    }
+
+    /// Pick up the result of a background git status query; returns true once it arrives.
+    pub fn poll_git(&mut self) -> bool {
+        let Some(rx) = &self.git_pending else {
+            return false;
+        };
+        match rx.try_recv() {
+            Ok(info) => {
+                self.git = Some(info);
+                self.git_pending = None;
+                true
+            }
+            Err(TryRecvError::Disconnected) => {
+                self.git_pending = None;
+                false
+            }
+            Err(TryRecvError::Empty) => false,
+        }
+    }
+
+    /// Pick up the result of a background last-commit query; returns true once it arrives.
+    pub fn poll_commits(&mut self) -> bool {
+        let Some(rx) = &self.commits_pending else {
+            return false;
+        };
+        match rx.try_recv() {
+            Ok(commits) => {
+                self.commit_cache
+                    .insert(self.current_dir.clone(), commits.clone());
+                self.commits = commits;
+                self.commits_pending = None;
+                true
+            }
+            Err(TryRecvError::Disconnected) => {
+                self.commits_pending = None;
+                false
+            }
+            Err(TryRecvError::Empty) => false,
+        }
+    }
+
+    /// Show or hide entries whose names start with `.`.
+    pub fn toggle_hidden(&mut self) {
+        self.show_hidden = !self.show_hidden;
+        let _ = self.reload();
+    }
+
+    /// Narrow the listing to entries whose names contain `filter`; empty lists everything.
+    pub fn set_filter(&mut self, filter: &str) {
+        if self.filter != filter {
+            self.filter = filter.to_string();
+            self.page = 0;
+            let _ = self.reload();
+        }
+    }
+
+    /// Number of entries listed, across all pages.
+    pub fn total(&self) -> usize {
+        self.paged_total.unwrap_or(self.items.len())
+    }
+
+    /// Compact summary of the listing settings for the title, e.g. `[size↓ .:off]`.
+    pub fn state_label(&self) -> String {
+        let order = if self.group_by_date {
+            "date↓".to_string()
+        } else {
+            self.sort_by.unwrap_or_default().label()
+        };
+        let hidden = if self.show_hidden { "on" } else { "off" };
+        let mut label = format!("[{order} .:{hidden}");
+        if self.heat != Heat::Off {
+            label.push_str(&format!(" heat:{}", self.heat.label()));
+        }
+        if !self.filter.is_empty() {
+            label.push_str(&format!(" f:{}", self.filter));
+        }
+        label.push(']');
+        label
+    }
+
+    /// Turn the date-grouped listing on or off.
+    pub fn toggle_grouping(&mut self) {
+        self.group_by_date = !self.group_by_date;
+        self.collapsed.clear();
+        let _ = self.reload();
+    }
+
+    /// Fold or unfold the date group containing the cursor.
+    pub fn toggle_group_fold(&mut self) {
+        let Some(&group) = self.groups.get(self.selected) else {
+            return;
+        };
+        if !self.collapsed.remove(&group) {
+            self.collapsed.insert(group);
+            // park the cursor on the group's header row
+            while self.selected > 0 && self.groups[self.selected - 1] == group {
+                self.selected -= 1;
+            }
+        }
+    }
+
+    /// Whether entry `i` is the first of its date group.
+    pub fn starts_group(&self, i: usize) -> bool {
+        i == 0 || self.groups.get(i - 1) != self.groups.get(i)
+    }
+
+    /// Whether entry `i` is folded away; a folded group stays reachable through its first entry.
+    pub fn is_hidden(&self, i: usize) -> bool {
+        self.groups
+            .get(i)
+            .is_some_and(|g| self.collapsed.contains(g) && !self.starts_group(i))
+    }
+
+    /// Listing row of entry `i`, counting date group headers and leaving out folded entries.
+    pub fn row(&self, i: usize) -> usize {
+        if self.groups.is_empty() {
+            return i;
+        }
+        let headers = (0..=i).filter(|&j| self.starts_group(j)).count();
+        let shown = (0..i)
+            .filter(|&j| !self.collapsed.contains(&self.groups[j]))
+            .count();
+        // a folded group is shown as its header row alone
+        let folded = self
+            .groups
+            .get(i)
+            .is_some_and(|g| self.collapsed.contains(g));
+        headers + shown - usize::from(folded)
+    }
+
+    /// Scroll the view so the cursor row is at its top, middle or bottom, like vim's `zt`,
+    /// `zz` and `zb`.
+    pub fn align(&self, at: ViewAlign) {
+        let row = self.row(self.selected);
+        let height = self.view_height.get().max(1);
+        self.scroll.set(match at {
+            ViewAlign::Top => row,
+            ViewAlign::Center => row.saturating_sub(height / 2),
+            ViewAlign::Bottom => row.saturating_sub(height - 1),
+        });
+    }
+
+    /// Move the cursor and the view by `rows` rows, down when positive, like vim's `Ctrl-d`
+    /// and `Ctrl-f`.
+    pub fn scroll_rows(&mut self, rows: isize) {
+        let steps = rows.unsigned_abs();
+        let target = if rows > 0 {
+            (self.selected + 1..self.items.len())
+                .filter(|&i| !self.is_hidden(i))
+                .take(steps)
+                .last()
+        } else {
+            (0..self.selected)
+                .rev()
+                .filter(|&i| !self.is_hidden(i))
+                .take(steps)
+                .last()
+        };
+        if let Some(i) = target {
+            self.selected = i;
+        }
+        let scroll = self.scroll.get();
+        self.scroll.set(if rows > 0 {
+            scroll + steps
+        } else {
+            scroll.saturating_sub(steps)
+        });
+    }
+
+    /// Re-read the directory, keeping the cursor on the same entry if it still exists.
+    pub fn reload(&mut self) -> io::Result<()> {
+        let current = self.items.get(self.selected).map(|e| e.file_name());
+        let scroll = self.scroll.get();
+        self.refresh()?;
+        self.scroll.set(scroll);
+        if let Some(name) = current {
+            if let Some(pos) = self.items.iter().position(|e| e.file_name() == name) {
+                self.selected = pos;
+            }
+        }
         Ok(())
     }
+
+    /// Whether entry `i` is marked.
+    pub fn is_marked(&self, i: usize) -> bool {
+        self.items
+            .get(i)
+            .is_some_and(|e| self.marked.contains(&e.path()))
+    }
+
+    /// Mark or unmark entry `i`.
+    pub fn set_mark(&mut self, i: usize, mark: bool) {
+        let Some(path) = self.items.get(i).map(|e| e.path()) else {
+            return;
+        };
+        if mark {
+            self.marked.insert(path);
+        } else {
+            self.marked.remove(&path);
+        }
+    }
+
+    /// Paths of the marked entries in listing order, or of the entry under the cursor if
+    /// nothing is marked.
+    pub fn selection(&self) -> Vec<PathBuf> {
+        if !self.marked.is_empty() {
+            self.items
+                .iter()
+                .map(|e| e.path())
+                .filter(|p| self.marked.contains(p))
+                .collect()
+        } else {
+            self.items
+                .get(self.selected)
+                .map(|e| e.path())
+                .into_iter()
+                .collect()
+        }
+    }
+}
+
+/// What kura was started to pick with `--choose-files` or `--choose-dir`.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Picker {
+    Files,
+    Dir,
+}
+
+/// How the two panes share the screen.
+#[derive(Clone, Copy)]
+pub struct PaneLayout {
+    /// Show only the active pane, full size
+    pub single: bool,
+    /// Stack the panes top and bottom instead of side by side
+    pub stacked: bool,
+    /// Share of the left (or top) pane, in percent
+    pub ratio: u16,
+    /// Show the active pane between its parent directory and a preview of the entry under
+    /// the cursor, instead of both panes
+    pub columns: bool,
+}
+
+impl PaneLayout {
+    const STEP: u16 = 5;
+
+    /// Give the pane on `side` a bigger (`grow`) or smaller share of the screen.
+    pub fn resize(&mut self, side: &PaneType, grow: bool) {
+        let first = *side == PaneType::Left;
+        self.ratio = if first == grow {
+            self.ratio + Self::STEP
+        } else {
+            self.ratio.saturating_sub(Self::STEP)
+        }
+        .clamp(10, 90);
+    }
+}
+
+impl Default for PaneLayout {
+    fn default() -> Self {
+        Self {
+            single: false,
+            stacked: false,
+            ratio: 50,
+            columns: false,
+        }
+    }
 }
 
 pub struct App {
@@ -36,21 +851,130 @@ pub struct App {
     pub right: Pane,
     pub active: PaneType,
     pub mode: Mode,
-    pub clipboard: Vec<PathBuf>,
+    /// Paths yanked with `y`, pasted with `p`
+    pub registers: Registers,
+    pub tasks: TaskQueue,
+    pub config: Config,
+    /// Entry styles from `LS_COLORS`, if set
+    pub ls_colors: Option<LsColors>,
+    /// Last marker scan, kept while a match is open in the viewer
+    pub todos: Option<TodoList>,
+    /// Visited directories, for the `z` jump popup
+    pub history: History,
+    /// File name index of the `search_index` directory, for the finder
+    pub index: Option<FileIndex>,
+    /// Lua plugins and their key bindings
+    pub scripts: Scripts,
+    /// Set when running as a file picker
+    pub picker: Option<Picker>,
+    /// Paths picked before quitting, in picker mode
+    pub chosen: Option<Vec<PathBuf>>,
+    /// Last search query confirmed with Enter, repeated by `n`/`N`
+    pub search: String,
+    /// Whether searches are regular expressions; toggled with Ctrl-R in the search prompt
+    pub search_regex: bool,
+    pub layout: PaneLayout,
+    /// Parent directory and preview of the column layout
+    pub columns: Columns,
+    /// Program started with `!`, until it exits
+    pub running: Option<Running>,
+    /// Directory watched with `gW`, running a command on each change
+    pub watching: Option<Watch>,
+    /// Messages shown at the bottom for a while, and kept for the notification log
+    pub notifications: Notifications,
+    /// When set, paste, move and delete only report what they would do
+    pub dry_run: bool,
+    /// Local paths collected with `a`, kept across directory changes
+    pub basket: Vec<PathBuf>,
+    /// What checking the detached signature of each signed file found
+    pub signatures: HashMap<PathBuf, Verdict>,
+    /// The signature check started with `gV`, until it finishes
+    verifying: Option<Receiver<(PathBuf, Verdict)>>,
 }
 
 impl App {
+    /// Start in the current directory with the user's config, visit history, remembered view
+    /// settings and plugins.
     pub fn new() -> io::Result<Self> {
-        let cwd = std::env::current_dir()?;
+        Self::open(
+            std::env::current_dir()?,
+            Config::load(),
+            History::load(),
+            DirViews::load(),
+            Scripts::load(),
+        )
+    }
+
+    /// Start with both panes in `dir`.
+    pub fn open(
+        dir: PathBuf,
+        config: Config,
+        mut history: History,
+        views: DirViews,
+        scripts: Scripts,
+    ) -> io::Result<Self> {
+        format::set_si_units(config.si_units);
+        let mut left = Pane::new(dir.clone())?;
+        let mut right = Pane::new(dir)?;
+        if config.group_by_date {
+            left.toggle_grouping();
+            right.toggle_grouping();
+        }
+        if config.dir_counts {
+            left.toggle_counts();
+            right.toggle_counts();
+        }
+        left.heat = config.heat;
+        right.heat = config.heat;
+        let views = Rc::new(RefCell::new(views));
+        left.set_views(views.clone());
+        right.set_views(views);
+        history.record(&left.current_dir);
+        let mode = if scripts.errors.is_empty() {
+            Mode::Filer
+        } else {
+            Mode::Message {
+                title: "Plugin errors".into(),
+                lines: scripts.errors.clone(),
+            }
+        };
+        let index = config.search_index.clone().map(FileIndex::open);
         Ok(Self {
-            left: Pane::new(cwd.clone())?,
-            right: Pane::new(cwd)?,
+            left,
+            right,
             active: PaneType::Left,
-            mode: Mode::Filer,
-            clipboard: Vec::new(),
+            mode,
+            registers: Registers::default(),
+            tasks: TaskQueue::new(&config),
+            config,
+            ls_colors: LsColors::from_env(),
+            todos: None,
+            history,
+            index,
+            scripts,
+            picker: None,
+            chosen: None,
+            search: String::new(),
+            search_regex: false,
+            layout: PaneLayout::default(),
+            running: None,
+            watching: None,
+            notifications: Notifications::default(),
+            dry_run: false,
+            basket: Vec::new(),
+            columns: Columns::default(),
+            signatures: HashMap::new(),
+            verifying: None,
         })
     }
 
+    pub fn current_pane(&self) -> &Pane {
+        match self.active {
+            PaneType::Left => &self.left,
+            PaneType::Right => &self.right,
+        }
+    }
+
     pub fn current_pane_mut(&mut self) -> &mut Pane {
         match self.active {
             PaneType::Left => &mut self.left,
@@ -58,6 +982,203 @@ impl App {
         }
     }
 
+    pub fn other_pane(&self) -> &Pane {
+        match self.active {
+            PaneType::Left => &self.right,
+            PaneType::Right => &self.left,
+        }
+    }
+
+    pub fn other_pane_mut(&mut self) -> &mut Pane {
+        match self.active {
+            PaneType::Left => &mut self.right,
+            PaneType::Right => &mut self.left,
+        }
+    }
+
+    /// Show the marker scan of the current directory, scanning it unless a scan is kept.
+    pub fn open_todos(&mut self) {
+        let root = self.current_pane().current_dir.clone();
+        let list = match self.todos.take() {
+            Some(list) if list.root == root => list,
+            _ => TodoList::scan(root),
+        };
+        self.mode = Mode::Todos(list);
+    }
+
+    /// Show the entry under the cursor selected in the desktop's file manager, or the
+    /// directory itself when it is empty.
+    pub fn reveal_selected(&mut self) {
+        let pane = self.current_pane();
+        let path = match pane.items.get(pane.selected) {
+            Some(entry) => entry.path(),
+            None => pane.current_dir.clone(),
+        };
+        match reveal(&path) {
+            Ok(()) => self
+                .notifications
+                .info(format!("revealed {} in the file manager", path.display())),
+            Err(e) => self
+                .notifications
+                .error(format!("cannot reveal {}: {e}", path.display())),
+        }
+    }
+
+    /// Check the detached signature of the file under the cursor, or the file of the signature
+    /// under it, in the background. On a checksum list, check the files it names instead.
+    pub fn verify_selected(&mut self) {
+        let pane = self.current_pane();
+        let Some(entry) = pane.items.get(pane.selected) else {
+            return;
+        };
+        if pane.is_local() && checksum::is_list(&entry.path()) {
+            self.verify_checksums(entry.path());
+            return;
+        }
+        let Some((data, signature)) = signed_pair(&entry.path()) else {
+            self.notifications.warn(format!(
+                "{} has no .sig or .asc signature next to it",
+                entry.file_name().to_string_lossy()
+            ));
+            return;
+        };
+        self.notifications.info(format!(
+            "checking {} against {}…",
+            data.file_name().unwrap_or_default().to_string_lossy(),
+            signature.file_name().unwrap_or_default().to_string_lossy()
+        ));
+        let keyring = self.config.gnupg_home.clone();
+        self.verifying = Some(spawn_verify(data, signature, keyring));
+    }
+
+    /// Hash the files named in the checksum list `list` on the task queue, showing how each
+    /// compares as it is checked.
+    pub fn verify_checksums(&mut self, list: PathBuf) {
+        let entries = match fs::read_to_string(&list) {
+            Ok(text) => checksum::parse_list(&text),
+            Err(e) => {
+                self.notifications
+                    .error(format!("cannot read {}: {e}", list.display()));
+                return;
+            }
+        };
+        if entries.is_empty() {
+            self.notifications.warn(format!(
+                "{} lists no checksums",
+                list.file_name().unwrap_or_default().to_string_lossy()
+            ));
+            return;
+        }
+        let (results, rx) = mpsc::channel();
+        self.tasks.enqueue(JobKind::VerifySums {
+            list: list.clone(),
+            entries: entries.clone(),
+            results,
+        });
+        self.mode = Mode::Checksums(ChecksumReport::new(list, entries, rx));
+    }
+
+    /// Whether a signature check is still running.
+    pub fn is_verifying(&self) -> bool {
+        self.verifying.is_some()
+    }
+
+    /// Report the result of the signature check once it arrives.
+    pub fn poll_verification(&mut self) {
+        let Some(rx) = &self.verifying else {
+            return;
+        };
+        let (data, verdict) = match rx.try_recv() {
+            Ok(result) => result,
+            Err(TryRecvError::Empty) => return,
+            Err(TryRecvError::Disconnected) => {
+                self.verifying = None;
+                return;
+            }
+        };
+        self.verifying = None;
+        let message = format!(
+            "{}: {}",
+            data.file_name().unwrap_or_default().to_string_lossy(),
+            verdict.describe()
+        );
+        match verdict {
+            Verdict::Good { trusted: true, .. } => self.notifications.info(message),
+            Verdict::Bad { .. } => self.notifications.error(message),
+            _ => self.notifications.warn(message),
+        }
+        self.signatures.insert(data, verdict);
+    }
+
+    /// Open the finder on the active pane's directory, searching the index if it covers the
+    /// directory and scanning it otherwise.
+    pub fn open_finder(&mut self) {
+        let dir = self.current_pane().current_dir.clone();
+        let index = match &self.index {
+            Some(index) if index.covers(&dir) => index.clone(),
+            _ => FileIndex::scan(dir.clone()),
+        };
+        self.mode = Mode::Find(Finder::new(dir, index));
+    }
+
+    /// Apply job updates, reloading the panes when a job has finished and announcing how jobs
+    /// with a report ended.
+    pub fn poll_tasks(&mut self) {
+        if self.tasks.poll() {
+            self.refresh_panes();
+        }
+        for (description, status) in self.tasks.take_reported() {
+            match status {
+                JobStatus::Failed(e) => self.notifications.warn(format!("{description}: {e}")),
+                JobStatus::Cancelled => {
+                    self.notifications.warn(format!("{description}: cancelled"))
+                }
+                _ => self.notifications.info(format!("{description}: done")),
+            }
+        }
+        // one password prompt at a time, and none over another popup
+        if matches!(self.mode, Mode::Filer) {
+            if let Some(extraction) = self.tasks.next_locked() {
+                self.mode = Mode::ArchivePassword {
+                    extraction,
+                    password: LineEditor::default(),
+                };
+            } else if let Some((retry, reason)) = self.tasks.next_denied() {
+                self.offer_elevation(retry, reason);
+            }
+        }
+    }
+
+    /// Ask whether to run `retry` as root, refused for `reason`, unless `elevate_with` is
+    /// turned off.
+    pub fn offer_elevation(&mut self, retry: Privileged, reason: String) {
+        if self.config.elevate_with.trim().is_empty() {
+            self.notifications
+                .error(format!("cannot {}: {reason}", retry.describe()));
+        } else {
+            self.mode = Mode::ConfirmElevate { retry, reason };
+        }
+    }
+
+    /// Re-read both panes, e.g. after an external program may have changed the filesystem.
+    pub fn refresh_panes(&mut self) {
+        let _ = self.left.reload();
+        let _ = self.right.reload();
+        self.columns.clear();
+    }
+
+    /// Keep the parent directory and preview of the column layout in step with the active pane.
+    pub fn sync_columns(&mut self) {
+        if !self.layout.columns {
+            return;
+        }
+        let pane = match self.active {
+            PaneType::Left => &self.left,
+            PaneType::Right => &self.right,
+        };
+        self.columns.sync(pane);
+    }
+
     pub fn switch_pane(&mut self) {
         self.active = match self.active {
             PaneType::Left => PaneType::Right,
@@ -67,44 +1188,414 @@ impl App {
 
     pub fn on_up(&mut self) {
         let pane = self.current_pane_mut();
-        if pane.selected > 0 {
-            pane.selected -= 1;
+        if let Some(i) = (0..pane.selected).rev().find(|&i| !pane.is_hidden(i)) {
+            pane.selected = i;
         }
     }
 
     pub fn on_down(&mut self) {
         let pane = self.current_pane_mut();
-        if pane.selected + 1 < pane.items.len() {
-            pane.selected += 1;
+        if let Some(i) = (pane.selected + 1..pane.items.len()).find(|&i| !pane.is_hidden(i)) {
+            pane.selected = i;
         }
     }
 
-    pub fn on_left(&mut self) {
+    /// Move the active pane to `dir`, remembering the visit for the jump popup.
+    pub fn change_dir(&mut self, dir: PathBuf) -> bool {
+        let changed = self.current_pane_mut().change_dir(dir);
+        if changed && self.current_pane().is_local() {
+            let dir = self.current_pane().current_dir.clone();
+            self.history.record(&dir);
+        }
+        changed
+    }
+
+    /// Walk the active pane's jump list back or forward.
+    pub fn walk_jumps(&mut self, back: bool) {
         let pane = self.current_pane_mut();
+        let moved = if back {
+            pane.jump_back()
+        } else {
+            pane.jump_forward()
+        };
+        if moved && pane.is_local() {
+            let dir = pane.current_dir.clone();
+            self.history.record(&dir);
+        }
+    }
+
+    pub fn on_left(&mut self) {
+        let pane = self.current_pane();
         if let Some(parent) = pane.current_dir.parent() {
-            pane.current_dir = parent.to_path_buf();
-            let _ = pane.refresh();
+            self.change_dir(parent.to_path_buf());
+        } else if let Some(image) = pane.vfs.image() {
+            // up from the root of a disk image: back beside the image
+            let Some(dir) = image.parent() else {
+                return;
+            };
+            let pane = self.current_pane_mut();
+            if pane
+                .set_location(Rc::new(LocalFs), dir.to_path_buf())
+                .is_ok()
+            {
+                if let Some(i) = pane.items.iter().position(|e| e.path() == image) {
+                    pane.selected = i;
+                }
+            }
+        } else if cfg!(windows) && pane.is_local() && pane.root.is_none() {
+            // above a drive root: choose another drive
+            self.open_mounts();
         }
     }
 
-    pub fn on_enter(&mut self) {
+    /// List the mounted file systems, with the one holding the active pane's directory selected.
+    pub fn open_mounts(&mut self) {
+        let mounts = mounts::list(self.config.gvfs_root.as_deref());
+        let dir = &self.current_pane().current_dir;
+        let selected = mounts
+            .iter()
+            .enumerate()
+            .filter(|(_, m)| dir.starts_with(&m.path))
+            .max_by_key(|(_, m)| m.path.as_os_str().len())
+            .map_or(0, |(i, _)| i);
+        self.mode = Mode::Mounts { mounts, selected };
+    }
+
+    /// Open the basket popup, first dropping paths that no longer exist.
+    pub fn open_basket(&mut self) {
+        self.basket.retain(|p| p.symlink_metadata().is_ok());
+        self.mode = Mode::Basket { selected: 0 };
+    }
+
+    /// Move the active pane to the directory holding `path` and put the cursor on it.
+    pub fn go_to(&mut self, path: &Path) {
+        let Some(parent) = path.parent() else {
+            return;
+        };
+        self.change_dir(parent.to_path_buf());
         let pane = self.current_pane_mut();
+        if let Some(i) = pane.items.iter().position(|e| e.path() == path) {
+            pane.selected = i;
+        }
+    }
+
+    /// In picker mode, pick the marked entries, or the file under the cursor when picking
+    /// files; returns whether anything was picked.
+    pub fn pick(&mut self) -> bool {
+        let pane = self.current_pane();
+        let on_file = pane.items.get(pane.selected).is_some_and(|e| !e.is_dir());
+        if !pane.is_local()
+            || (pane.marked.is_empty() && !(on_file && self.picker == Some(Picker::Files)))
+        {
+            return false;
+        }
+        self.chosen = Some(pane.selection());
+        true
+    }
+
+    /// Point the active pane at `location`: a local directory (a leading `~` stands for the
+    /// home directory) or Windows share (`\\server\share`), `sftp://[user@]host[:port]/path`, `dav[s]://[user@]host[:port]/path`,
+    /// `s3://[bucket[/prefix]]`, or a phone or camera as `mtp://device/path` or
+    /// `gphoto2://device/path`, mounted through GVfs first.
+    pub fn open_location(&mut self, location: &str) {
+        let result = if location.starts_with("sftp://") {
+            SftpFs::connect(location)
+                .and_then(|(vfs, dir)| self.current_pane_mut().set_location(Rc::new(vfs), dir))
+        } else if location.starts_with("dav://") || location.starts_with("davs://") {
+            WebDavFs::connect(location)
+                .and_then(|(vfs, dir)| self.current_pane_mut().set_location(Rc::new(vfs), dir))
+        } else if location.starts_with("s3://") {
+            S3Fs::connect(location)
+                .and_then(|(vfs, dir)| self.current_pane_mut().set_location(Rc::new(vfs), dir))
+        } else {
+            let dir = if location.starts_with("mtp://") || location.starts_with("gphoto2://") {
+                match mounts::mount_device(self.config.gvfs_root.as_deref(), location) {
+                    Ok(dir) => dir,
+                    Err(e) => {
+                        self.mode = Mode::Message {
+                            title: "Cannot mount device".into(),
+                            lines: vec![format!("{location}: {e}")],
+                        };
+                        return;
+                    }
+                }
+            } else {
+                self.current_pane().current_dir.join(expand_home(location))
+            };
+            self.current_pane_mut()
+                .set_location(Rc::new(LocalFs), dir.clone())
+                .map(|()| self.history.record(&dir))
+        };
+        if let Err(e) = result {
+            self.mode = Mode::Message {
+                title: "Cannot open location".into(),
+                lines: vec![format!("{location}: {e}")],
+            };
+        }
+    }
+
+    /// Show the directory under the cursor in the other pane, keeping the active pane where it is.
+    pub fn open_in_other_pane(&mut self) {
+        let pane = self.current_pane();
+        let Some(entry) = pane.items.get(pane.selected).filter(|e| e.is_dir()) else {
+            return;
+        };
+        let (vfs, dir) = (pane.vfs.clone(), entry.path());
+        let local = vfs.is_local();
+        match self.other_pane_mut().set_location(vfs, dir.clone()) {
+            Ok(()) if local => self.history.record(&dir),
+            Ok(()) => {}
+            Err(e) => {
+                self.mode = Mode::Message {
+                    title: "Cannot open directory".into(),
+                    lines: vec![format!("{}: {e}", dir.display())],
+                };
+            }
+        }
+    }
+
+    /// Show the active pane's directory in the other pane too, with the cursor on the same entry.
+    pub fn equalize_panes(&mut self) {
+        let pane = self.current_pane();
+        let (vfs, dir) = (pane.vfs.clone(), pane.current_dir.clone());
+        let current = pane.items.get(pane.selected).map(|e| e.file_name());
+        let other = self.other_pane_mut();
+        match other.set_location(vfs, dir.clone()) {
+            Ok(()) => {
+                if let Some(pos) =
+                    current.and_then(|name| other.items.iter().position(|e| e.file_name() == name))
+                {
+                    other.selected = pos;
+                }
+            }
+            Err(e) => {
+                self.mode = Mode::Message {
+                    title: "Cannot open directory".into(),
+                    lines: vec![format!("{}: {e}", dir.display())],
+                };
+            }
+        }
+    }
+
+    /// Exchange the two panes, with their directories, cursors and marks; the active side stays.
+    pub fn swap_panes(&mut self) {
+        std::mem::swap(&mut self.left, &mut self.right);
+    }
+
+    /// A local copy of the remote file `path` in the active pane, for the viewers.
+    fn fetch(&self, path: &Path) -> io::Result<PathBuf> {
+        let dir = std::env::temp_dir().join(format!("kura-sftp-{}", std::process::id()));
+        fs::create_dir_all(&dir)?;
+        let local = dir.join(path.file_name().unwrap_or_default());
+        self.current_pane().vfs.download(path, &local)?;
+        Ok(local)
+    }
+
+    pub fn on_enter(&mut self) {
+        let pane = self.current_pane();
         if let Some(entry) = pane.items.get(pane.selected) {
-            let path = entry.path();
-            if path.is_dir() {
-                pane.current_dir = path;
-                let _ = pane.refresh();
-            } else if let Ok(content) = fs::read_to_string(&path) {
-                let title = path
-                    .file_name()
-                    .map(|os_str| os_str.to_string_lossy().into_owned())
-                    .unwrap_or_default();
-                self.mode = Mode::Viewer {
-                    content,
-                    title,
-                    offset: 0,
+            let mut path = entry.path();
+            if entry.is_dir() {
+                self.change_dir(path);
+                return;
+            }
+            if pane.is_local() && is_iso(&path) {
+                self.open_image(&path);
+                return;
+            }
+            if pane.is_local() && checksum::is_list(&path) {
+                self.verify_checksums(path);
+                return;
+            }
+            if !pane.is_local() {
+                match self.fetch(&path) {
+                    Ok(local) => path = local,
+                    Err(e) => {
+                        self.mode = Mode::Message {
+                            title: "Download failed".into(),
+                            lines: vec![e.to_string()],
+                        };
+                        return;
+                    }
+                }
+            }
+            if is_table(&path) {
+                if let Some(table) = TableView::open(&path) {
+                    self.mode = Mode::Table(table);
+                }
+            } else if let Some(tree) = is_structured(&path)
+                .then(|| TreeView::open(&path))
+                .flatten()
+            {
+                self.mode = Mode::Tree(tree);
+            } else if is_json_log(&path) {
+                if let Some(log) = LogViewer::open(&path) {
+                    self.mode = Mode::LogViewer(log);
+                }
+            } else if let Some(viewer) = Viewer::open(&path) {
+                self.mode = Mode::Viewer(viewer);
+            } else if let Err(e) = open_default(&path) {
+                self.notifications
+                    .error(format!("cannot open {}: {e}", path.display()));
+            }
+        }
+    }
+
+    /// Browse the file system in the disk image `image` in the active pane.
+    pub fn open_image(&mut self, image: &Path) {
+        let result = IsoFs::open(image).and_then(|iso| {
+            self.current_pane_mut()
+                .set_location(Rc::new(iso), PathBuf::from("/"))
+        });
+        if let Err(e) = result {
+            self.mode = Mode::Message {
+                title: "Cannot open disk image".into(),
+                lines: vec![format!("{}: {e}", image.display())],
+            };
+        }
+    }
+
+    /// Show a unified diff of the pair of selected files.
+    pub fn open_diff(&mut self) {
+        let Some((a, b)) = self.pair_selection() else {
+            return;
+        };
+        if let (Ok(old), Ok(new)) = (fs::read_to_string(&a), fs::read_to_string(&b)) {
+            self.mode = Mode::Diff(DiffView::new(&a, &b, &old, &new));
+        }
+    }
+
+    /// Two paths to compare: the two marked entries of the active pane,
+    /// or otherwise the entry under the cursor in each pane.
+    pub fn pair_selection(&mut self) -> Option<(PathBuf, PathBuf)> {
+        let pane = self.current_pane_mut();
+        if pane.marked.len() == 2 {
+            if let [a, b] = pane.selection().as_slice() {
+                return Some((a.clone(), b.clone()));
+            }
+        }
+        let selected = |pane: &Pane| pane.items.get(pane.selected).map(|e| e.path());
+        Some((selected(&self.left)?, selected(&self.right)?))
+    }
+
+    /// Run the executable `program` from the active pane's directory, following its output in
+    /// the text viewer.
+    pub fn start_program(&mut self, program: &Path, args: &str) {
+        if let Some(running) = &self.running {
+            self.notifications
+                .warn(format!("{} is still running", running.name));
+            return;
+        }
+        let dir = self.current_pane().current_dir.clone();
+        match Running::start(program, args, &dir) {
+            Ok(running) => {
+                if let Some(mut viewer) = Viewer::open(&running.output) {
+                    viewer.title = format!("{} [running]", running.name);
+                    viewer.toggle_follow();
+                    self.mode = Mode::Viewer(viewer);
+                }
+                self.running = Some(running);
+            }
+            Err(e) => {
+                self.mode = Mode::Message {
+                    title: "Cannot run program".into(),
+                    lines: vec![format!("{}: {e}", program.display())],
                 };
             }
         }
     }
+
+    /// Report the exit status of the running program once it has finished.
+    pub fn poll_program(&mut self) {
+        let Some(status) = self.running.as_mut().and_then(|r| r.try_wait()) else {
+            return;
+        };
+        let Some(running) = self.running.take() else {
+            return;
+        };
+        let result = match status.code() {
+            Some(code) => format!("exit code {code}"),
+            None => status.to_string(),
+        };
+        if let Mode::Viewer(viewer) = &mut self.mode {
+            if viewer.path() == running.output {
+                viewer.title = format!("{} ({result})", running.name);
+                viewer.follow_tick();
+            }
+        }
+        let message = format!("{} finished with {result}", running.name);
+        if status.success() {
+            self.notifications.info(message);
+        } else {
+            self.notifications.warn(message);
+        }
+        // the program may have changed the directories shown
+        self.refresh_panes();
+    }
+
+    /// Watch `dir` and run `template` whenever something in it changes, following the output
+    /// in the text viewer.
+    pub fn start_watch(&mut self, dir: PathBuf, template: String) {
+        if let Some(watch) = self.watching.take() {
+            watch.stop();
+        }
+        match Watch::start(dir.clone(), template) {
+            Ok(watch) => {
+                if let Some(mut viewer) = Viewer::open(&watch.log) {
+                    viewer.title = format!("watching {}: {}", dir.display(), watch.template);
+                    viewer.toggle_follow();
+                    self.mode = Mode::Viewer(viewer);
+                }
+                self.notifications
+                    .info(format!("watching {} (gW: stop watching)", dir.display()));
+                self.watching = Some(watch);
+            }
+            Err(e) => {
+                self.mode = Mode::Message {
+                    title: "Cannot watch directory".into(),
+                    lines: vec![format!("{}: {e}", dir.display())],
+                };
+            }
+        }
+    }
+
+    /// Stop the watch started with `gW`, if any.
+    pub fn stop_watch(&mut self) {
+        if let Some(watch) = self.watching.take() {
+            self.notifications
+                .info(format!("stopped watching {}", watch.dir.display()));
+            watch.stop();
+        }
+    }
+
+    /// Run the watched directory's command when it has changed.
+    pub fn poll_watch(&mut self) {
+        let Some(watch) = &mut self.watching else {
+            return;
+        };
+        if let Err(e) = watch.poll() {
+            self.notifications
+                .error(format!("stopped watching {}: {e}", watch.dir.display()));
+            if let Some(watch) = self.watching.take() {
+                watch.stop();
+            }
+        }
+    }
+
+    /// Open the pair of selected files side by side.
+    pub fn open_compare(&mut self) {
+        let Some((a, b)) = self.pair_selection() else {
+            return;
+        };
+        if a.is_dir() || b.is_dir() {
+            return;
+        }
+        if let (Some(left), Some(right)) = (Viewer::open(&a), Viewer::open(&b)) {
+            self.mode = Mode::Compare {
+                left: Box::new(left),
+                right: Box::new(right),
+                sync: false,
+            };
+        }
+    }
 }
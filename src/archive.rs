@@ -0,0 +1,230 @@
+use std::{
+    error::Error,
+    fmt, io,
+    path::{Path, PathBuf},
+    process::{Command, Output},
+};
+
+/// The kinds of archive kura can extract, each through its own backend.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Format {
+    /// Through `unzip`, or `tar` where that is bsdtar (Windows, macOS, the BSDs)
+    Zip,
+    /// Plain or compressed, through `tar`
+    Tar,
+    /// Read in process
+    SevenZip,
+    /// Through the external `unrar`
+    Rar,
+}
+
+/// Archive name endings, longest first so `.tar.gz` wins over a plain `.gz`.
+const SUFFIXES: [(&str, Format); 12] = [
+    (".tar.gz", Format::Tar),
+    (".tar.bz2", Format::Tar),
+    (".tar.xz", Format::Tar),
+    (".tar.zst", Format::Tar),
+    (".tgz", Format::Tar),
+    (".tbz2", Format::Tar),
+    (".txz", Format::Tar),
+    (".tar", Format::Tar),
+    (".zip", Format::Zip),
+    (".jar", Format::Zip),
+    (".7z", Format::SevenZip),
+    (".rar", Format::Rar),
+];
+
+impl Format {
+    /// The format of the archive `path` and its name without the archive suffix, judged by
+    /// the name.
+    pub fn of(path: &Path) -> Option<(Self, String)> {
+        let name = path.file_name()?.to_string_lossy();
+        let lower = name.to_lowercase();
+        SUFFIXES.iter().find_map(|(suffix, format)| {
+            let stem = &name[..name.len().checked_sub(suffix.len())?];
+            (lower.ends_with(suffix) && !stem.is_empty()).then(|| (*format, stem.to_string()))
+        })
+    }
+}
+
+/// An archive to unpack into the new directory `dest`, with the password to decrypt it with,
+/// once one has been asked for.
+#[derive(Clone, Debug)]
+pub struct Extraction {
+    pub archive: PathBuf,
+    pub format: Format,
+    pub dest: PathBuf,
+    pub password: Option<String>,
+}
+
+/// The error of an extraction stopped by the archive's encryption, to ask for a password (or
+/// another one, if `tried`) and try again.
+#[derive(Debug)]
+pub struct Locked {
+    pub tried: bool,
+}
+
+impl fmt::Display for Locked {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(if self.tried {
+            "wrong password"
+        } else {
+            "the archive is encrypted"
+        })
+    }
+}
+
+impl Error for Locked {}
+
+/// Whether `e` is a `Locked` error.
+pub fn is_locked(e: &io::Error) -> bool {
+    e.get_ref().is_some_and(|e| e.is::<Locked>())
+}
+
+/// Unpack the archive `archive` of `format` into the existing directory `dest`, decrypting it
+/// with `password` if given. An encrypted archive without the right password fails with
+/// `Locked` instead of asking on the terminal.
+pub fn extract(
+    archive: &Path,
+    format: Format,
+    dest: &Path,
+    password: Option<&str>,
+) -> io::Result<()> {
+    let locked = || {
+        io::Error::other(Locked {
+            tried: password.is_some(),
+        })
+    };
+    match format {
+        Format::Zip => {
+            // an empty password keeps unzip from asking for one
+            let unzip = output(
+                Command::new("unzip")
+                    .arg("-o")
+                    .arg("-P")
+                    .arg(password.unwrap_or_default())
+                    .arg(archive)
+                    .arg("-d")
+                    .arg(dest),
+            );
+            match unzip {
+                // bsdtar reads zip files too
+                Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                    let mut tar = Command::new("tar");
+                    tar.arg("-xf").arg(archive).arg("-C").arg(dest);
+                    if let Some(password) = password {
+                        tar.arg("--passphrase").arg(password);
+                    }
+                    check(output(&mut tar), "tar")
+                        .map_err(|_| missing("zip", "unzip (or a tar that reads zip files)"))
+                }
+                Ok(out)
+                    if !out.status.success()
+                        && String::from_utf8_lossy(&out.stderr).contains("incorrect password") =>
+                {
+                    Err(locked())
+                }
+                result => check(result, "unzip"),
+            }
+        }
+        Format::Tar => {
+            // tar picks the compression from the data
+            check(
+                output(
+                    Command::new("tar")
+                        .arg("-xf")
+                        .arg(archive)
+                        .arg("-C")
+                        .arg(dest),
+                ),
+                "tar",
+            )
+        }
+        Format::SevenZip => {
+            let result = match password {
+                Some(password) => {
+                    sevenz_rust::decompress_file_with_password(archive, dest, password.into())
+                }
+                None => sevenz_rust::decompress_file(archive, dest),
+            };
+            result.map_err(|e| match e {
+                sevenz_rust::Error::PasswordRequired | sevenz_rust::Error::MaybeBadPassword(_) => {
+                    locked()
+                }
+                // a wrong key decrypts to garbage
+                sevenz_rust::Error::ChecksumVerificationFailed if password.is_some() => locked(),
+                sevenz_rust::Error::Io(e, _) | sevenz_rust::Error::FileOpen(e, _) => e,
+                e => io::Error::new(io::ErrorKind::InvalidData, e.to_string()),
+            })
+        }
+        Format::Rar => {
+            // the trailing separator makes unrar take `dest` as the directory to extract into
+            let mut into = dest.as_os_str().to_os_string();
+            into.push(std::path::MAIN_SEPARATOR_STR);
+            // `-p-` answers that there is no password instead of asking
+            let mut switch = std::ffi::OsString::from("-p");
+            switch.push(password.unwrap_or("-"));
+            let unrar = output(
+                Command::new("unrar")
+                    .args(["x", "-o+", "-idq", "-y"])
+                    .arg(switch)
+                    .arg(archive)
+                    .arg(into),
+            );
+            match unrar {
+                Err(e) if e.kind() == io::ErrorKind::NotFound => Err(missing("rar", "unrar")),
+                // 11 is unrar's exit code for a missing or wrong password
+                Ok(out) if out.status.code() == Some(11) => Err(locked()),
+                Ok(out)
+                    if !out.status.success()
+                        && String::from_utf8_lossy(&out.stderr)
+                            .to_lowercase()
+                            .contains("password") =>
+                {
+                    Err(locked())
+                }
+                result => check(result, "unrar"),
+            }
+        }
+    }
+}
+
+/// The first directory beside `archive` named `stem`, `stem (2)`, ... that does not exist and
+/// is not in `taken`.
+pub fn extract_dir(archive: &Path, stem: &str, taken: &[PathBuf]) -> PathBuf {
+    let dir = archive.parent().unwrap_or(Path::new("."));
+    (1..)
+        .map(|n| match n {
+            1 => dir.join(stem),
+            n => dir.join(format!("{stem} ({n})")),
+        })
+        .find(|p| p.symlink_metadata().is_err() && !taken.contains(p))
+        .expect("an unused name")
+}
+
+/// Run `command` without a terminal to read from; a `NotFound` error means the program is not
+/// installed.
+fn output(command: &mut Command) -> io::Result<Output> {
+    command.stdin(std::process::Stdio::null()).output()
+}
+
+/// The error of `tool` if it failed, from what it printed.
+fn check(output: io::Result<Output>, tool: &str) -> io::Result<()> {
+    let output = output?;
+    if output.status.success() {
+        return Ok(());
+    }
+    let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+    Err(io::Error::other(if stderr.is_empty() {
+        format!("{tool} failed ({})", output.status)
+    } else {
+        stderr
+    }))
+}
+
+fn missing(extension: &str, tool: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::NotFound,
+        format!("extracting .{extension} archives needs {tool}, which was not found on the PATH"),
+    )
+}
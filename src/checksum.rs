@@ -0,0 +1,223 @@
+use std::{
+    fs::File,
+    io::{self, Read},
+    path::{Path, PathBuf},
+    sync::mpsc::Receiver,
+};
+
+use sha2::digest::DynDigest;
+
+/// The hashes a checksum list can hold.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Algorithm {
+    Md5,
+    Sha1,
+    Sha256,
+    Sha512,
+}
+
+/// Names of checksum lists as written by `md5sum`, `sha256sum` and friends, before any
+/// suffix like `.txt`.
+const LIST_NAMES: [&str; 4] = ["md5sums", "sha1sums", "sha256sums", "sha512sums"];
+
+/// Extensions of checksum lists, usually of a single file (`foo.iso.sha256`).
+const LIST_EXTENSIONS: [&str; 4] = ["md5", "sha1", "sha256", "sha512"];
+
+/// Whether `path` is a checksum list, judged by its name.
+pub fn is_list(path: &Path) -> bool {
+    let Some(name) = path.file_name() else {
+        return false;
+    };
+    let name = name.to_string_lossy().to_lowercase();
+    let stem = name.split('.').next().unwrap_or(&name);
+    LIST_NAMES.contains(&stem)
+        || path
+            .extension()
+            .is_some_and(|ext| LIST_EXTENSIONS.iter().any(|e| ext.eq_ignore_ascii_case(e)))
+}
+
+impl Algorithm {
+    /// The algorithm making digests of `len` hex digits.
+    fn of_hex_len(len: usize) -> Option<Self> {
+        match len {
+            32 => Some(Algorithm::Md5),
+            40 => Some(Algorithm::Sha1),
+            64 => Some(Algorithm::Sha256),
+            128 => Some(Algorithm::Sha512),
+            _ => None,
+        }
+    }
+
+    /// The algorithm named in a BSD-style line, e.g. `SHA256 (name) = ...`.
+    fn of_tag(tag: &str) -> Option<Self> {
+        match tag.to_ascii_uppercase().as_str() {
+            "MD5" => Some(Algorithm::Md5),
+            "SHA1" => Some(Algorithm::Sha1),
+            "SHA256" => Some(Algorithm::Sha256),
+            "SHA512" => Some(Algorithm::Sha512),
+            _ => None,
+        }
+    }
+
+    fn hasher(self) -> Box<dyn DynDigest> {
+        match self {
+            Algorithm::Md5 => Box::<md5::Md5>::default(),
+            Algorithm::Sha1 => Box::<sha1::Sha1>::default(),
+            Algorithm::Sha256 => Box::<sha2::Sha256>::default(),
+            Algorithm::Sha512 => Box::<sha2::Sha512>::default(),
+        }
+    }
+}
+
+/// A file named in a checksum list, with the digest it should have.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Listed {
+    /// As written in the list, relative to the list's directory
+    pub name: String,
+    /// Lowercase hex
+    pub expected: String,
+    pub algorithm: Algorithm,
+}
+
+/// The entries of a checksum list: GNU lines (`digest  name`, or `digest *name` for binary
+/// mode) and BSD lines (`SHA256 (name) = digest`). Comments and lines that are neither are
+/// skipped. The length of each digest tells its algorithm, so one list may mix them.
+pub fn parse_list(text: &str) -> Vec<Listed> {
+    text.lines()
+        .filter_map(|line| {
+            let line = line.trim_end_matches('\r');
+            if line.trim_start().starts_with('#') {
+                return None;
+            }
+            let (name, digest) = bsd_line(line).or_else(|| gnu_line(line))?;
+            if !digest.bytes().all(|b| b.is_ascii_hexdigit()) {
+                return None;
+            }
+            let algorithm = Algorithm::of_hex_len(digest.len())?;
+            Some(Listed {
+                name: name.to_string(),
+                expected: digest.to_ascii_lowercase(),
+                algorithm,
+            })
+        })
+        .collect()
+}
+
+/// The name and digest of a BSD-style line.
+fn bsd_line(line: &str) -> Option<(&str, &str)> {
+    let (tag, rest) = line.split_once(" (")?;
+    Algorithm::of_tag(tag)?;
+    let (name, digest) = rest.rsplit_once(") = ")?;
+    Some((name, digest.trim()))
+}
+
+/// The name and digest of a GNU-style line.
+fn gnu_line(line: &str) -> Option<(&str, &str)> {
+    let (digest, rest) = line.trim_start().split_once(' ')?;
+    let name = rest.strip_prefix([' ', '*']).unwrap_or(rest);
+    (!name.is_empty()).then_some((name, digest))
+}
+
+/// The lowercase hex digest of the file `path`.
+pub fn hash_file(path: &Path, algorithm: Algorithm) -> io::Result<String> {
+    let mut file = File::open(path)?;
+    let mut hasher = algorithm.hasher();
+    let mut buffer = vec![0u8; 64 * 1024];
+    loop {
+        let n = file.read(&mut buffer)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buffer[..n]);
+    }
+    Ok(hasher
+        .finalize()
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect())
+}
+
+/// How one listed file compared.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Outcome {
+    Match,
+    Mismatch,
+    Missing,
+    Unreadable(String),
+}
+
+impl Outcome {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Outcome::Match => "OK",
+            Outcome::Mismatch => "FAILED",
+            Outcome::Missing => "MISSING",
+            Outcome::Unreadable(_) => "ERROR",
+        }
+    }
+}
+
+/// The outcome for the entry at `index` in the list.
+pub struct Check {
+    pub index: usize,
+    pub outcome: Outcome,
+}
+
+/// Compare the file of `entry`, found from the list's directory `dir`.
+pub fn check(dir: &Path, entry: &Listed) -> Outcome {
+    match hash_file(&dir.join(&entry.name), entry.algorithm) {
+        Ok(digest) if digest == entry.expected => Outcome::Match,
+        Ok(_) => Outcome::Mismatch,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Outcome::Missing,
+        Err(e) => Outcome::Unreadable(e.to_string()),
+    }
+}
+
+/// The report popup of a checksum list being verified by the task queue: one row per listed
+/// file, filled in as the job gets to it.
+pub struct ChecksumReport {
+    pub list: PathBuf,
+    pub entries: Vec<Listed>,
+    pub outcomes: Vec<Option<Outcome>>,
+    pub selected: usize,
+    results: Receiver<Check>,
+}
+
+impl ChecksumReport {
+    pub fn new(list: PathBuf, entries: Vec<Listed>, results: Receiver<Check>) -> Self {
+        Self {
+            list,
+            outcomes: vec![None; entries.len()],
+            entries,
+            selected: 0,
+            results,
+        }
+    }
+
+    /// Take in the outcomes the job has sent since the last call.
+    pub fn refresh(&mut self) {
+        while let Ok(check) = self.results.try_recv() {
+            if let Some(outcome) = self.outcomes.get_mut(check.index) {
+                *outcome = Some(check.outcome);
+            }
+        }
+    }
+
+    pub fn checked(&self) -> usize {
+        self.outcomes.iter().flatten().count()
+    }
+
+    pub fn failed(&self) -> usize {
+        self.outcomes
+            .iter()
+            .flatten()
+            .filter(|o| **o != Outcome::Match)
+            .count()
+    }
+
+    /// The file of the selected row.
+    pub fn current(&self) -> Option<PathBuf> {
+        let entry = self.entries.get(self.selected)?;
+        Some(self.list.parent()?.join(&entry.name))
+    }
+}
@@ -0,0 +1,101 @@
+use std::path::PathBuf;
+
+use crate::app::Pane;
+use crate::format::human_size;
+use crate::fs_utils::sort_entries;
+use crate::vfs::Entry;
+use crate::viewer::Viewer;
+
+/// Directory entries or text lines read for a preview; the column only has room for a
+/// screenful of them.
+const PREVIEW_LINES: usize = 500;
+
+/// What the preview column shows for the entry under the cursor.
+pub enum Preview {
+    /// Entries of a directory, listed like the pane lists its own
+    Dir(Vec<Entry>),
+    /// First lines of a text file
+    Text(Vec<String>),
+    /// A line about a file that cannot be shown as text
+    Info(String),
+}
+
+/// The parent directory and preview beside the active pane in the column layout (like
+/// ranger's), kept in step with the pane's directory and cursor.
+#[derive(Default)]
+pub struct Columns {
+    /// Directory and entry under the cursor the columns were read for
+    key: Option<(PathBuf, Option<PathBuf>, bool)>,
+    /// Entries of the parent directory, and the row of the pane's directory among them
+    pub parent: Option<(Vec<Entry>, Option<usize>)>,
+    pub preview: Option<Preview>,
+}
+
+impl Columns {
+    /// Read the parent directory and the preview again if the pane has moved since.
+    pub fn sync(&mut self, pane: &Pane) {
+        let selected = pane.items.get(pane.selected).map(Entry::path);
+        let key = (pane.current_dir.clone(), selected, pane.show_hidden);
+        if self.key.as_ref() == Some(&key) {
+            return;
+        }
+        let (dir, _, _) = &key;
+        self.parent = dir.parent().and_then(|parent| {
+            let entries = list(pane, parent.to_path_buf(), usize::MAX)?;
+            let row = entries.iter().position(|e| e.path() == *dir);
+            Some((entries, row))
+        });
+        self.preview = pane
+            .items
+            .get(pane.selected)
+            .map(|entry| preview(pane, entry));
+        self.key = Some(key);
+    }
+
+    /// Forget what was read, so the next `sync` reads it again.
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+}
+
+fn preview(pane: &Pane, entry: &Entry) -> Preview {
+    let path = entry.path();
+    if entry.is_dir() {
+        return match list(pane, path, PREVIEW_LINES) {
+            Some(entries) => Preview::Dir(entries),
+            None => Preview::Info("cannot read the directory".into()),
+        };
+    }
+    if !pane.is_local() {
+        return Preview::Info(human_size(entry.len()));
+    }
+    match Viewer::open(&path) {
+        Some(viewer) => {
+            let lines = viewer
+                .window()
+                .iter()
+                .take(PREVIEW_LINES)
+                .cloned()
+                .collect();
+            Preview::Text(lines)
+        }
+        None => Preview::Info(format!("binary file, {}", human_size(entry.len()))),
+    }
+}
+
+/// Up to `limit` entries of `dir`, with the pane's hidden entries and sort order.
+fn list(pane: &Pane, dir: PathBuf, limit: usize) -> Option<Vec<Entry>> {
+    let mut entries: Vec<Entry> = pane
+        .vfs
+        .read_dir(&dir)
+        .ok()?
+        .filter_map(Result::ok)
+        .filter(|e| pane.show_hidden || !e.file_name().to_string_lossy().starts_with('.'))
+        .take(limit)
+        .collect();
+    match pane.sort_by {
+        Some(by) => sort_entries(&mut entries, by),
+        None => entries.sort_by_key(|e| e.file_name()),
+    }
+    Some(entries)
+}
@@ -0,0 +1,433 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use ratatui::style::Color;
+
+use crate::fs_utils::{shell_quote, CopyNames, CopyOptions};
+use crate::heat::Heat;
+use crate::keys::KeyCombo;
+use crate::mounts;
+use crate::rsync::UseRsync;
+
+/// User settings read from `<config dir>/kura/config`.
+///
+/// The file is a list of `key = value` lines; `#` starts a comment.
+pub struct Config {
+    /// Command used to compare two files or directories; the two paths are appended
+    pub diff_tool: String,
+    /// Start with listings grouped by modification date
+    pub group_by_date: bool,
+    /// Start with entry counts shown after directory names
+    pub dir_counts: bool,
+    /// Colors of the git status indicators
+    pub git_colors: GitColors,
+    /// Start with names tinted by size or age
+    pub heat: Heat,
+    /// Colors names are tinted between by size or age
+    pub heat_colors: HeatColors,
+    /// Custom commands defined with `command.<name>.*` settings
+    pub commands: Vec<UserCommand>,
+    /// Targets of the share menu, defined with `share.<name>` settings
+    pub shares: Vec<ShareTarget>,
+    /// Neovim server to open files in with `o`, instead of `$NVIM`
+    pub nvim_socket: Option<String>,
+    /// Keep timestamps and directory permissions when copying
+    pub preserve_metadata: bool,
+    /// Clone files on copy-on-write file systems when copying
+    pub reflink: bool,
+    /// Show sizes in powers of 1000 (kB, MB) instead of 1024 (KiB, MiB)
+    pub si_units: bool,
+    /// Naming of copies pasted next to their source
+    pub copy_names: CopyNames,
+    /// Which copies go through rsync, which can resume them
+    pub copy_with_rsync: UseRsync,
+    /// The rsync program those copies run
+    pub rsync_program: PathBuf,
+    /// Directories that cannot be deleted or overwritten, nor anything directly in them,
+    /// without typing their name; one `protected_path` line each
+    pub protected_paths: Vec<PathBuf>,
+    /// Ask before deleting or overwriting more than this many entries, even with `X` or `p`
+    pub confirm_over: Option<usize>,
+    /// Times `gw` overwrites a file with random bytes before deleting it
+    pub shred_passes: usize,
+    /// Directory to keep a file name index of for the finder; none when `off` or unset
+    pub search_index: Option<PathBuf>,
+    /// Command, with its arguments, that runs an operation refused for lack of permission as
+    /// root, e.g. `sudo` or `pkexec`; never offered when empty
+    pub elevate_with: String,
+    /// gpg's home directory, with the keyring signatures are checked against; gpg's own
+    /// default (`$GNUPGHOME` or `~/.gnupg`) when unset
+    pub gnupg_home: Option<PathBuf>,
+    /// Where the records of interrupted copies are kept; the platform's data directory, not
+    /// read from the config file
+    pub data_dir: Option<PathBuf>,
+    /// Where GVfs shows the phones and cameras it mounted; found from the environment, not
+    /// read from the config file
+    pub gvfs_root: Option<PathBuf>,
+}
+
+/// A command template offered in the share menu (`gs`), run for each selected entry with the
+/// placeholders of `fill_template`, e.g. `scp {path} me@server:/tmp/`.
+#[derive(Clone)]
+pub struct ShareTarget {
+    pub name: String,
+    pub run: String,
+}
+
+/// A shell command the user bound to a key or key sequence in the filer.
+#[derive(Clone)]
+pub struct UserCommand {
+    pub name: String,
+    /// Unbound when empty
+    pub keys: Vec<KeyCombo>,
+    /// Shell command line; see `expand` for the placeholders
+    pub run: String,
+    /// Reload both panes once the command exits
+    pub refresh: bool,
+    /// Show the command's output in the text viewer instead of handing it the terminal
+    pub output: bool,
+}
+
+impl UserCommand {
+    fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            keys: Vec::new(),
+            run: String::new(),
+            refresh: true,
+            output: false,
+        }
+    }
+
+    /// The command line with `%f` replaced by the shell-quoted selected paths, `%d` by the
+    /// current directory, `%D` by the other pane's directory and `%%` by `%`.
+    pub fn expand(&self, selection: &[PathBuf], dir: &Path, other_dir: &Path) -> String {
+        let quote = |p: &Path| shell_quote(&p.to_string_lossy());
+        let mut line = String::new();
+        let mut chars = self.run.chars();
+        while let Some(c) = chars.next() {
+            if c != '%' {
+                line.push(c);
+                continue;
+            }
+            match chars.next() {
+                Some('f') => {
+                    let paths: Vec<String> = selection.iter().map(|p| quote(p)).collect();
+                    line.push_str(&paths.join(" "));
+                }
+                Some('d') => line.push_str(&quote(dir)),
+                Some('D') => line.push_str(&quote(other_dir)),
+                Some('%') => line.push('%'),
+                Some(other) => {
+                    line.push('%');
+                    line.push(other);
+                }
+                None => line.push('%'),
+            }
+        }
+        line
+    }
+}
+
+pub struct GitColors {
+    pub modified: Color,
+    pub staged: Color,
+    pub untracked: Color,
+    pub ignored: Color,
+    pub conflicted: Color,
+}
+
+impl Default for GitColors {
+    fn default() -> Self {
+        Self {
+            modified: Color::Yellow,
+            staged: Color::Green,
+            untracked: Color::Magenta,
+            ignored: Color::DarkGray,
+            conflicted: Color::Red,
+        }
+    }
+}
+
+pub struct HeatColors {
+    /// Smallest or oldest
+    pub cold: Color,
+    /// Largest or newest
+    pub hot: Color,
+}
+
+impl Default for HeatColors {
+    fn default() -> Self {
+        Self {
+            cold: Color::Rgb(0x60, 0x60, 0x60),
+            hot: Color::Rgb(0xff, 0xaf, 0x00),
+        }
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            diff_tool: "vimdiff".into(),
+            group_by_date: false,
+            dir_counts: false,
+            git_colors: GitColors::default(),
+            heat: Heat::Off,
+            heat_colors: HeatColors::default(),
+            commands: Vec::new(),
+            shares: Vec::new(),
+            nvim_socket: None,
+            preserve_metadata: true,
+            reflink: true,
+            si_units: false,
+            copy_names: CopyNames::default(),
+            copy_with_rsync: UseRsync::default(),
+            rsync_program: PathBuf::from("rsync"),
+            protected_paths: Vec::new(),
+            confirm_over: None,
+            shred_passes: 3,
+            search_index: None,
+            elevate_with: if cfg!(unix) { "sudo" } else { "" }.to_string(),
+            gnupg_home: None,
+            data_dir: data_dir(),
+            gvfs_root: mounts::gvfs_root(),
+        }
+    }
+}
+
+impl Config {
+    /// Load the config file, falling back to defaults for anything missing or unreadable.
+    pub fn load() -> Self {
+        let mut config = Self::default();
+        if let Some(text) = config_dir().and_then(|d| fs::read_to_string(d.join("config")).ok()) {
+            config.parse(&text);
+        }
+        config
+    }
+
+    pub fn copy_options(&self) -> CopyOptions {
+        CopyOptions {
+            preserve: self.preserve_metadata,
+            reflink: self.reflink,
+            names: self.copy_names,
+            rsync: self.copy_with_rsync,
+        }
+    }
+
+    /// The protected directory that `path` is or is directly in.
+    pub fn protected_dir(&self, path: &Path) -> Option<&Path> {
+        self.protected_paths
+            .iter()
+            .map(PathBuf::as_path)
+            .find(|&dir| path == dir || path.parent() == Some(dir))
+    }
+
+    /// Whether `count` entries are more than `confirm_over`.
+    pub fn is_many(&self, count: usize) -> bool {
+        self.confirm_over.is_some_and(|n| count > n)
+    }
+
+    fn parse(&mut self, text: &str) {
+        for line in text.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let Some((key, value)) = line.split_once('=') else {
+                continue;
+            };
+            let value = unquote(value.trim());
+            match key.trim() {
+                "diff_tool" => self.diff_tool = value.to_string(),
+                "group_by_date" => self.group_by_date = value == "true",
+                "dir_counts" => self.dir_counts = value == "true",
+                "heat" => {
+                    if let Some(heat) = Heat::parse(value) {
+                        self.heat = heat;
+                    }
+                }
+                "nvim_socket" => self.nvim_socket = Some(value.to_string()),
+                "preserve_metadata" => self.preserve_metadata = value == "true",
+                "reflink" => self.reflink = value == "true",
+                "size_units" => self.si_units = value == "si",
+                "protected_path" => self.protected_paths.push(expand_home(value)),
+                "confirm_over" => self.confirm_over = value.parse().ok(),
+                "shred_passes" => {
+                    if let Ok(passes @ 1..) = value.parse() {
+                        self.shred_passes = passes;
+                    }
+                }
+                "search_index" => {
+                    self.search_index = match value {
+                        "" | "off" => None,
+                        dir => Some(expand_home(dir)),
+                    }
+                }
+                "elevate_with" => self.elevate_with = value.to_string(),
+                "gnupg_home" => self.gnupg_home = Some(expand_home(value)),
+                "copy_names" => {
+                    if let Some(names) = CopyNames::parse(value) {
+                        self.copy_names = names;
+                    }
+                }
+                "copy_with_rsync" => {
+                    if let Some(rsync) = UseRsync::parse(value) {
+                        self.copy_with_rsync = rsync;
+                    }
+                }
+                "rsync_program" => self.rsync_program = expand_home(value),
+                key if key.starts_with("command.") => self.parse_command(key, value),
+                key if key.starts_with("share.") => {
+                    let name = key["share.".len()..].trim().to_string();
+                    let target = ShareTarget {
+                        name,
+                        run: value.to_string(),
+                    };
+                    match self.shares.iter_mut().find(|s| s.name == target.name) {
+                        Some(share) => *share = target,
+                        None => self.shares.push(target),
+                    }
+                }
+                key => {
+                    let colors = &mut self.git_colors;
+                    let slot = match key {
+                        "git_modified_color" => &mut colors.modified,
+                        "git_staged_color" => &mut colors.staged,
+                        "git_untracked_color" => &mut colors.untracked,
+                        "git_ignored_color" => &mut colors.ignored,
+                        "git_conflicted_color" => &mut colors.conflicted,
+                        "heat_cold_color" => &mut self.heat_colors.cold,
+                        "heat_hot_color" => &mut self.heat_colors.hot,
+                        _ => continue,
+                    };
+                    if let Some(color) = parse_color(value) {
+                        *slot = color;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Apply a `command.<name>.<field>` setting.
+    fn parse_command(&mut self, key: &str, value: &str) {
+        let Some((name, field)) = key["command.".len()..].rsplit_once('.') else {
+            return;
+        };
+        let index = match self.commands.iter().position(|c| c.name == name) {
+            Some(i) => i,
+            None => {
+                self.commands.push(UserCommand::new(name));
+                self.commands.len() - 1
+            }
+        };
+        let command = &mut self.commands[index];
+        match field {
+            "key" => {
+                command.keys = KeyCombo::parse_sequence(value).unwrap_or_default();
+            }
+            "run" => command.run = value.to_string(),
+            "refresh" => command.refresh = value == "true",
+            "output" => command.output = value == "true",
+            _ => {}
+        }
+    }
+}
+
+/// Parse a color name (`red`, `darkgray`, ...), a 256-color index, or `#rrggbb`.
+pub fn parse_color(value: &str) -> Option<Color> {
+    if let Some(hex) = value.strip_prefix('#') {
+        let n = u32::from_str_radix(hex, 16)
+            .ok()
+            .filter(|_| hex.len() == 6)?;
+        return Some(Color::Rgb((n >> 16) as u8, (n >> 8) as u8, n as u8));
+    }
+    if let Ok(index) = value.parse::<u8>() {
+        return Some(Color::Indexed(index));
+    }
+    let color = match value.to_lowercase().replace(['_', '-', ' '], "").as_str() {
+        "black" => Color::Black,
+        "red" => Color::Red,
+        "green" => Color::Green,
+        "yellow" => Color::Yellow,
+        "blue" => Color::Blue,
+        "magenta" => Color::Magenta,
+        "cyan" => Color::Cyan,
+        "gray" | "grey" => Color::Gray,
+        "darkgray" | "darkgrey" => Color::DarkGray,
+        "lightred" => Color::LightRed,
+        "lightgreen" => Color::LightGreen,
+        "lightyellow" => Color::LightYellow,
+        "lightblue" => Color::LightBlue,
+        "lightmagenta" => Color::LightMagenta,
+        "lightcyan" => Color::LightCyan,
+        "white" => Color::White,
+        "reset" | "default" => Color::Reset,
+        _ => return None,
+    };
+    Some(color)
+}
+
+/// Strip one pair of matching surrounding quotes.
+fn unquote(value: &str) -> &str {
+    for q in ['"', '\''] {
+        if value.len() >= 2 && value.starts_with(q) && value.ends_with(q) {
+            return &value[1..value.len() - 1];
+        }
+    }
+    value
+}
+
+/// `path` with a leading `~` standing for the home directory.
+pub fn expand_home(path: &str) -> PathBuf {
+    match path.strip_prefix('~') {
+        Some(rest) => std::env::var_os("HOME")
+            .or_else(|| std::env::var_os("USERPROFILE"))
+            .map(|home| PathBuf::from(home).join(rest.trim_start_matches(['/', '\\'])))
+            .unwrap_or_else(|| PathBuf::from(path)),
+        None => PathBuf::from(path),
+    }
+}
+
+/// Directory holding kura's data such as the visit history: `$XDG_DATA_HOME/kura`,
+/// `~/.local/share/kura` or `%LOCALAPPDATA%\kura`.
+pub fn data_dir() -> Option<PathBuf> {
+    let base = std::env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .or_else(|| {
+            if cfg!(windows) {
+                std::env::var_os("LOCALAPPDATA").map(PathBuf::from)
+            } else {
+                std::env::var_os("HOME").map(|h| PathBuf::from(h).join(".local/share"))
+            }
+        })?;
+    Some(base.join("kura"))
+}
+
+/// Directory holding kura's caches such as the file name index: `$XDG_CACHE_HOME/kura`,
+/// `~/.cache/kura` or `%LOCALAPPDATA%\kura\cache`.
+pub fn cache_dir() -> Option<PathBuf> {
+    if let Some(base) = std::env::var_os("XDG_CACHE_HOME") {
+        return Some(PathBuf::from(base).join("kura"));
+    }
+    if cfg!(windows) {
+        std::env::var_os("LOCALAPPDATA").map(|d| PathBuf::from(d).join("kura").join("cache"))
+    } else {
+        std::env::var_os("HOME").map(|h| PathBuf::from(h).join(".cache/kura"))
+    }
+}
+
+/// Directory holding kura's config: `$XDG_CONFIG_HOME/kura`, `~/.config/kura` or `%APPDATA%\kura`.
+pub fn config_dir() -> Option<PathBuf> {
+    let base = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| {
+            if cfg!(windows) {
+                std::env::var_os("APPDATA").map(PathBuf::from)
+            } else {
+                std::env::var_os("HOME").map(|h| PathBuf::from(h).join(".config"))
+            }
+        })?;
+    Some(base.join("kura"))
+}
@@ -0,0 +1,207 @@
+use std::path::Path;
+
+/// Lines of unchanged context shown around each change.
+const CONTEXT: usize = 3;
+
+/// Most edits searched for; files further apart than this are shown as one replaced block.
+/// The search keeps about `MAX_EDITS²` positions to recover the edit script.
+const MAX_EDITS: usize = 2000;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum LineKind {
+    Context,
+    Added,
+    Removed,
+    /// `@@ -a,b +c,d @@` hunk header
+    Hunk,
+}
+
+pub struct DiffLine {
+    pub kind: LineKind,
+    pub text: String,
+}
+
+/// A unified diff of two text files.
+pub struct DiffView {
+    pub title: String,
+    pub lines: Vec<DiffLine>,
+    pub offset: usize,
+}
+
+impl DiffView {
+    pub fn new(a: &Path, b: &Path, old: &str, new: &str) -> Self {
+        let old: Vec<&str> = old.lines().collect();
+        let new: Vec<&str> = new.lines().collect();
+        let lines = unified(&old, &new, &diff_ops(&old, &new));
+        Self {
+            title: format!("{} ↔ {}", a.display(), b.display()),
+            lines,
+            offset: 0,
+        }
+    }
+
+    pub fn scroll_down(&mut self, count: usize) {
+        let max = self.lines.len().saturating_sub(1);
+        self.offset = self.offset.saturating_add(count).min(max);
+    }
+
+    pub fn scroll_up(&mut self, count: usize) {
+        self.offset = self.offset.saturating_sub(count);
+    }
+
+    pub fn go_top(&mut self) {
+        self.offset = 0;
+    }
+
+    pub fn go_bottom(&mut self) {
+        self.offset = self.lines.len().saturating_sub(1);
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Op {
+    Equal,
+    Delete,
+    Insert,
+}
+
+/// Shortest edit script from `a` to `b` (Myers' algorithm).
+fn diff_ops(a: &[&str], b: &[&str]) -> Vec<Op> {
+    // strip the common prefix and suffix so the search only covers the changed middle
+    let prefix = a.iter().zip(b).take_while(|(x, y)| x == y).count();
+    let suffix = a[prefix..]
+        .iter()
+        .rev()
+        .zip(b[prefix..].iter().rev())
+        .take_while(|(x, y)| x == y)
+        .count();
+    let (ma, mb) = (&a[prefix..a.len() - suffix], &b[prefix..b.len() - suffix]);
+
+    let mut ops = vec![Op::Equal; prefix];
+    ops.extend(myers(ma, mb));
+    ops.resize(ops.len() + suffix, Op::Equal);
+    ops
+}
+
+fn myers(a: &[&str], b: &[&str]) -> Vec<Op> {
+    let (n, m) = (a.len() as isize, b.len() as isize);
+    let max = (n + m) as usize;
+    let offset = max as isize + 1;
+    let mut v = vec![0isize; 2 * max + 3];
+    // for each step `d`, the furthest positions on diagonals `-d - 1..=d + 1` before it, all
+    // the walk back reads
+    let mut trace: Vec<Vec<isize>> = Vec::new();
+    'search: for d in 0..=max as isize {
+        if d as usize > MAX_EDITS {
+            // too different to search: replace the whole middle
+            let mut ops = vec![Op::Delete; a.len()];
+            ops.resize(a.len() + b.len(), Op::Insert);
+            return ops;
+        }
+        trace.push(v[(offset - d - 1) as usize..=(offset + d + 1) as usize].to_vec());
+        let mut k = -d;
+        while k <= d {
+            let idx = (k + offset) as usize;
+            let mut x = if k == -d || (k != d && v[idx - 1] < v[idx + 1]) {
+                v[idx + 1]
+            } else {
+                v[idx - 1] + 1
+            };
+            let mut y = x - k;
+            while x < n && y < m && a[x as usize] == b[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            v[idx] = x;
+            if x >= n && y >= m {
+                break 'search;
+            }
+            k += 2;
+        }
+    }
+
+    // walk the trace backwards to recover the edit script
+    let mut ops = Vec::new();
+    let (mut x, mut y) = (n, m);
+    for (d, v) in trace.iter().enumerate().rev() {
+        let d = d as isize;
+        let k = x - y;
+        let idx = (k + d + 1) as usize;
+        let prev_k = if k == -d || (k != d && v[idx - 1] < v[idx + 1]) {
+            k + 1
+        } else {
+            k - 1
+        };
+        let prev_x = v[(prev_k + d + 1) as usize];
+        let prev_y = prev_x - prev_k;
+        while x > prev_x && y > prev_y {
+            ops.push(Op::Equal);
+            x -= 1;
+            y -= 1;
+        }
+        if d > 0 {
+            ops.push(if x == prev_x { Op::Insert } else { Op::Delete });
+        }
+        x = prev_x;
+        y = prev_y;
+    }
+    ops.reverse();
+    ops
+}
+
+/// Render an edit script as unified-diff hunks with `CONTEXT` lines around changes.
+fn unified(a: &[&str], b: &[&str], ops: &[Op]) -> Vec<DiffLine> {
+    let mut out = Vec::new();
+    // (op, old line index, new line index) for every step
+    let mut steps = Vec::with_capacity(ops.len());
+    let (mut i, mut j) = (0, 0);
+    for &op in ops {
+        steps.push((op, i, j));
+        match op {
+            Op::Equal => {
+                i += 1;
+                j += 1;
+            }
+            Op::Delete => i += 1,
+            Op::Insert => j += 1,
+        }
+    }
+
+    let changes: Vec<usize> = (0..steps.len())
+        .filter(|&s| steps[s].0 != Op::Equal)
+        .collect();
+    let mut c = 0;
+    while c < changes.len() {
+        let start = changes[c].saturating_sub(CONTEXT);
+        let mut end = (changes[c] + CONTEXT + 1).min(steps.len());
+        // merge changes whose context windows overlap
+        while c + 1 < changes.len() && changes[c + 1] <= end + CONTEXT {
+            c += 1;
+            end = (changes[c] + CONTEXT + 1).min(steps.len());
+        }
+        c += 1;
+
+        let hunk = &steps[start..end];
+        let old_count = hunk.iter().filter(|s| s.0 != Op::Insert).count();
+        let new_count = hunk.iter().filter(|s| s.0 != Op::Delete).count();
+        out.push(DiffLine {
+            kind: LineKind::Hunk,
+            text: format!(
+                "@@ -{},{} +{},{} @@",
+                hunk[0].1 + 1,
+                old_count,
+                hunk[0].2 + 1,
+                new_count
+            ),
+        });
+        for &(op, i, j) in hunk {
+            let (kind, text) = match op {
+                Op::Equal => (LineKind::Context, format!(" {}", a[i])),
+                Op::Delete => (LineKind::Removed, format!("-{}", a[i])),
+                Op::Insert => (LineKind::Added, format!("+{}", b[j])),
+            };
+            out.push(DiffLine { kind, text });
+        }
+    }
+    out
+}
@@ -0,0 +1,47 @@
+use std::{
+    fs,
+    path::PathBuf,
+    sync::mpsc::{self, Receiver},
+    thread,
+    time::SystemTime,
+};
+
+/// What is known about a directory listed in a pane.
+#[derive(Clone, Copy)]
+pub struct DirStats {
+    /// Modification time of the directory when the stats were taken; it changes when an entry
+    /// is added, removed or renamed, which makes the stats stale
+    pub modified: Option<SystemTime>,
+    /// Number of entries, once counted
+    pub entries: Option<usize>,
+    /// Total size of the files under it, when something has computed it
+    pub size: Option<u64>,
+}
+
+impl DirStats {
+    pub fn new(modified: Option<SystemTime>) -> Self {
+        Self {
+            modified,
+            entries: None,
+            size: None,
+        }
+    }
+}
+
+/// Count the entries of each of `dirs` on a background thread, one directory at a time.
+///
+/// The receiver yields each count as soon as it is known; dropping it stops the count.
+pub fn spawn_counts(dirs: Vec<PathBuf>) -> Receiver<(PathBuf, usize)> {
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        for dir in dirs {
+            let Ok(entries) = fs::read_dir(&dir) else {
+                continue;
+            };
+            if tx.send((dir, entries.count())).is_err() {
+                break;
+            }
+        }
+    });
+    rx
+}
@@ -0,0 +1,140 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::config::data_dir;
+use crate::fs_utils::{SizeDisplay, SortBy, SortSpec};
+
+/// How a pane lists its directory.
+#[derive(Clone, PartialEq)]
+pub struct ViewSettings {
+    pub sort_by: Option<SortSpec>,
+    pub show_hidden: bool,
+    pub filter: String,
+    pub size_display: SizeDisplay,
+    pub group_by_date: bool,
+}
+
+/// View settings remembered for some directories with `gv` (like ranger's `setlocal`), given
+/// back to them whenever a pane shows them.
+///
+/// The default store starts empty and is never saved.
+#[derive(Default)]
+pub struct DirViews {
+    views: HashMap<PathBuf, ViewSettings>,
+    file: Option<PathBuf>,
+}
+
+impl DirViews {
+    /// Load the store from the data directory; an empty one if there is none.
+    pub fn load() -> Self {
+        let file = data_dir().map(|d| d.join("views"));
+        let views = file
+            .as_ref()
+            .and_then(|f| fs::read_to_string(f).ok())
+            .map(|text| text.lines().filter_map(decode).collect())
+            .unwrap_or_default();
+        Self { views, file }
+    }
+
+    pub fn get(&self, dir: &Path) -> Option<&ViewSettings> {
+        self.views.get(dir)
+    }
+
+    /// Remember `view` for `dir`, saving the store if that changes it.
+    pub fn remember(&mut self, dir: &Path, view: ViewSettings) {
+        if self.views.get(dir) != Some(&view) {
+            self.views.insert(dir.to_path_buf(), view);
+            self.save();
+        }
+    }
+
+    pub fn forget(&mut self, dir: &Path) {
+        if self.views.remove(dir).is_some() {
+            self.save();
+        }
+    }
+
+    fn save(&self) {
+        let Some(file) = &self.file else {
+            return;
+        };
+        let text: String = self
+            .views
+            .iter()
+            .map(|(dir, view)| encode(dir, view))
+            .collect();
+        if let Some(dir) = file.parent() {
+            let _ = fs::create_dir_all(dir);
+        }
+        let _ = fs::write(file, text);
+    }
+}
+
+/// A line of the store: sort order, hidden entries, size column, date grouping, filter and
+/// the directory, separated by tabs, e.g. `mtime!,name\t1\thuman\t0\t\t/home/me/Downloads`.
+fn encode(dir: &Path, view: &ViewSettings) -> String {
+    let sort = match view.sort_by {
+        None => "-".to_string(),
+        Some(spec) => {
+            let reverse = if spec.reverse { "!" } else { "" };
+            match spec.secondary {
+                Some(by) => format!("{}{reverse},{}", spec.primary.label(), by.label()),
+                None => format!("{}{reverse}", spec.primary.label()),
+            }
+        }
+    };
+    let size = match view.size_display {
+        SizeDisplay::Hidden => "hidden",
+        SizeDisplay::Human => "human",
+        SizeDisplay::Exact => "exact",
+    };
+    format!(
+        "{sort}\t{}\t{size}\t{}\t{}\t{}\n",
+        u8::from(view.show_hidden),
+        u8::from(view.group_by_date),
+        view.filter,
+        dir.display()
+    )
+}
+
+fn decode(line: &str) -> Option<(PathBuf, ViewSettings)> {
+    let fields: Vec<&str> = line.splitn(6, '\t').collect();
+    let [sort, hidden, size, grouped, filter, dir] = fields[..] else {
+        return None;
+    };
+    let by = |label: &str| SortBy::ALL.into_iter().find(|by| by.label() == label);
+    let sort_by = match sort {
+        "-" => None,
+        sort => {
+            let (primary, secondary) = match sort.split_once(',') {
+                Some((primary, secondary)) => (primary, Some(by(secondary)?)),
+                None => (sort, None),
+            };
+            let (primary, reverse) = match primary.strip_suffix('!') {
+                Some(primary) => (primary, true),
+                None => (primary, false),
+            };
+            Some(SortSpec {
+                primary: by(primary)?,
+                secondary,
+                reverse,
+            })
+        }
+    };
+    let size_display = match size {
+        "human" => SizeDisplay::Human,
+        "exact" => SizeDisplay::Exact,
+        _ => SizeDisplay::Hidden,
+    };
+    let view = ViewSettings {
+        sort_by,
+        show_hidden: hidden == "1",
+        filter: filter.to_string(),
+        size_display,
+        group_by_date: grouped == "1",
+    };
+    Some((PathBuf::from(dir), view))
+}
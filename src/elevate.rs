@@ -0,0 +1,135 @@
+use std::{
+    error::Error,
+    ffi::OsString,
+    fmt, io,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use crate::fs_utils::shell_quote;
+
+/// A file operation that failed for lack of permission, to run again as root with the
+/// system's own tools.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Privileged {
+    Delete {
+        paths: Vec<PathBuf>,
+    },
+    Copy {
+        sources: Vec<PathBuf>,
+        dest: PathBuf,
+    },
+    /// Copy `source` to the path `target`, under a new name
+    CopyAs {
+        source: PathBuf,
+        target: PathBuf,
+    },
+    Move {
+        sources: Vec<PathBuf>,
+        dest: PathBuf,
+    },
+    Rename {
+        from: PathBuf,
+        to: PathBuf,
+    },
+}
+
+impl Privileged {
+    /// One-line summary, as in the queue popup.
+    pub fn describe(&self) -> String {
+        match self {
+            Privileged::Delete { paths } => format!("delete {} item(s)", paths.len()),
+            Privileged::Copy { sources, dest } => {
+                format!("copy {} item(s) to {}", sources.len(), dest.display())
+            }
+            Privileged::CopyAs { source, target } => {
+                format!("copy {} to {}", source.display(), target.display())
+            }
+            Privileged::Move { sources, dest } => {
+                format!("move {} item(s) to {}", sources.len(), dest.display())
+            }
+            Privileged::Rename { from, to } => {
+                format!("rename {} to {}", from.display(), to.display())
+            }
+        }
+    }
+
+    /// The program and arguments doing the operation.
+    fn args(&self) -> Vec<OsString> {
+        let (program, options, paths): (&str, &[&str], Vec<&PathBuf>) = match self {
+            Privileged::Delete { paths } => ("rm", &["-rf"], paths.iter().collect()),
+            Privileged::Copy { sources, dest } => {
+                ("cp", &["-R"], sources.iter().chain([dest]).collect())
+            }
+            Privileged::CopyAs { source, target } => ("cp", &["-R"], vec![source, target]),
+            Privileged::Move { sources, dest } => {
+                ("mv", &[], sources.iter().chain([dest]).collect())
+            }
+            Privileged::Rename { from, to } => ("mv", &[], vec![from, to]),
+        };
+        let mut args: Vec<OsString> = [program].iter().chain(options).map(|s| s.into()).collect();
+        // names starting with `-` are not options
+        args.push("--".into());
+        args.extend(paths.into_iter().map(|p| p.into()));
+        args
+    }
+
+    /// The operation run through `elevate_with`, e.g. `sudo` or `pkexec`, which may have
+    /// arguments of its own. `None` when it is empty.
+    pub fn command(&self, elevate_with: &str) -> Option<Command> {
+        let mut words = elevate_with.split_whitespace();
+        let mut command = Command::new(words.next()?);
+        command.args(words).args(self.args());
+        Some(command)
+    }
+
+    /// The command line of `command`, shown before it runs.
+    pub fn command_line(&self, elevate_with: &str) -> String {
+        let args = self.args();
+        let args = args.iter().map(|arg| shell_quote(&arg.to_string_lossy()));
+        elevate_with
+            .split_whitespace()
+            .map(str::to_string)
+            .chain(args)
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+/// The error of an operation refused for lack of permission, to offer running `retry` as
+/// root instead.
+#[derive(Debug)]
+pub struct Denied {
+    pub retry: Privileged,
+    /// What was refused, e.g. `/etc/hosts: Permission denied (os error 13)`
+    pub reason: String,
+}
+
+impl fmt::Display for Denied {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.reason)
+    }
+}
+
+impl Error for Denied {}
+
+/// `e`, met working on `path`, as a `Denied` error with `retry` if it is a permission error,
+/// else unchanged.
+pub fn denied(e: io::Error, path: &Path, retry: impl FnOnce() -> Privileged) -> io::Error {
+    if e.kind() == io::ErrorKind::PermissionDenied {
+        io::Error::new(
+            e.kind(),
+            Denied {
+                retry: retry(),
+                reason: format!("{}: {e}", path.display()),
+            },
+        )
+    } else {
+        e
+    }
+}
+
+/// The `Denied` error `e` carries, if any.
+pub fn as_denied(e: &io::Error) -> Option<&Denied> {
+    e.get_ref().and_then(|e| e.downcast_ref::<Denied>())
+}
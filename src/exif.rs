@@ -0,0 +1,264 @@
+use std::{fs::File, io::Read, path::Path};
+
+use image::DynamicImage;
+
+/// Bytes read from the start of a file when looking for EXIF data; the APP1 segment holding it
+/// comes first in JPEG files and cannot exceed 64 KiB.
+const HEAD: u64 = 128 * 1024;
+
+/// Photo metadata from the EXIF block of a JPEG or TIFF file.
+#[derive(Default)]
+pub struct Exif {
+    pub make: Option<String>,
+    pub model: Option<String>,
+    /// `YYYY:MM:DD HH:MM:SS`, as recorded
+    pub taken: Option<String>,
+    /// Seconds
+    pub exposure: Option<(u32, u32)>,
+    pub f_number: Option<f64>,
+    pub iso: Option<u32>,
+    /// Millimetres
+    pub focal_length: Option<f64>,
+    /// Latitude and longitude in degrees, negative south and west
+    pub gps: Option<(f64, f64)>,
+    /// How the stored pixels map to the upright picture, from 1 (as stored) to 8
+    pub orientation: Option<u16>,
+}
+
+/// The EXIF data of the JPEG or TIFF file at `path`, if it has any.
+pub fn read(path: &Path) -> Option<Exif> {
+    let mut head = Vec::new();
+    File::open(path)
+        .ok()?
+        .take(HEAD)
+        .read_to_end(&mut head)
+        .ok()?;
+    let tiff = if head.starts_with(b"II*\0") || head.starts_with(b"MM\0*") {
+        &head[..]
+    } else {
+        jpeg_app1(&head)?
+    };
+    parse(tiff)
+}
+
+/// The TIFF structure inside the `Exif` APP1 segment of a JPEG file.
+fn jpeg_app1(data: &[u8]) -> Option<&[u8]> {
+    if !data.starts_with(&[0xff, 0xd8]) {
+        return None;
+    }
+    let mut pos = 2;
+    while let Some(&[0xff, marker, hi, lo]) = data.get(pos..pos + 4) {
+        // start of scan: the image data follows
+        if marker == 0xda {
+            return None;
+        }
+        let len = usize::from(u16::from_be_bytes([hi, lo]));
+        let segment = data.get(pos + 4..pos + 2 + len)?;
+        if marker == 0xe1 && segment.starts_with(b"Exif\0\0") {
+            return Some(&segment[6..]);
+        }
+        pos += 2 + len;
+    }
+    None
+}
+
+/// Byte order and bounds-checked reads of a TIFF structure.
+struct Tiff<'a> {
+    data: &'a [u8],
+    little_endian: bool,
+}
+
+impl Tiff<'_> {
+    fn u16(&self, at: usize) -> Option<u16> {
+        let b: [u8; 2] = self.data.get(at..at + 2)?.try_into().ok()?;
+        Some(if self.little_endian {
+            u16::from_le_bytes(b)
+        } else {
+            u16::from_be_bytes(b)
+        })
+    }
+
+    fn u32(&self, at: usize) -> Option<u32> {
+        let b: [u8; 4] = self.data.get(at..at + 4)?.try_into().ok()?;
+        Some(if self.little_endian {
+            u32::from_le_bytes(b)
+        } else {
+            u32::from_be_bytes(b)
+        })
+    }
+
+    /// Tag and value offset of each entry of the IFD at `offset`.
+    ///
+    /// Values of up to four bytes are stored in the entry itself; larger ones elsewhere.
+    fn entries(&self, offset: usize) -> Vec<(u16, usize, u32)> {
+        let count = self.u16(offset).unwrap_or(0);
+        (0..usize::from(count))
+            .filter_map(|i| {
+                let entry = offset + 2 + i * 12;
+                let (tag, kind, count) =
+                    (self.u16(entry)?, self.u16(entry + 2)?, self.u32(entry + 4)?);
+                let size = match kind {
+                    1 | 2 | 7 => 1,
+                    3 => 2,
+                    4 | 9 => 4,
+                    5 | 10 => 8,
+                    _ => return None,
+                } * count as usize;
+                let value = if size <= 4 {
+                    entry + 8
+                } else {
+                    self.u32(entry + 8)? as usize
+                };
+                Some((tag, value, count))
+            })
+            .collect()
+    }
+
+    fn ascii(&self, at: usize, count: u32) -> Option<String> {
+        let bytes = self.data.get(at..at + count as usize)?;
+        let end = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+        let text = String::from_utf8_lossy(&bytes[..end]).trim().to_string();
+        (!text.is_empty()).then_some(text)
+    }
+
+    fn rational(&self, at: usize) -> Option<(u32, u32)> {
+        Some((self.u32(at)?, self.u32(at + 4)?))
+    }
+
+    fn float(&self, at: usize) -> Option<f64> {
+        let (n, d) = self.rational(at)?;
+        (d != 0).then(|| f64::from(n) / f64::from(d))
+    }
+
+    /// Degrees from the three degree, minute and second rationals of a GPS coordinate.
+    fn degrees(&self, at: usize) -> Option<f64> {
+        Some(self.float(at)? + self.float(at + 8)? / 60.0 + self.float(at + 16)? / 3600.0)
+    }
+}
+
+fn parse(data: &[u8]) -> Option<Exif> {
+    let tiff = Tiff {
+        data,
+        little_endian: data.starts_with(b"II"),
+    };
+    let mut exif = Exif::default();
+    let (mut sub_ifd, mut gps_ifd) = (None, None);
+    for (tag, at, count) in tiff.entries(tiff.u32(4)? as usize) {
+        match tag {
+            0x010f => exif.make = tiff.ascii(at, count),
+            0x0110 => exif.model = tiff.ascii(at, count),
+            0x0112 => exif.orientation = tiff.u16(at),
+            0x0132 => exif.taken = tiff.ascii(at, count),
+            0x8769 => sub_ifd = tiff.u32(at),
+            0x8825 => gps_ifd = tiff.u32(at),
+            _ => {}
+        }
+    }
+    for (tag, at, count) in sub_ifd.map_or_else(Vec::new, |o| tiff.entries(o as usize)) {
+        match tag {
+            // the original capture time wins over the file's DateTime
+            0x9003 => exif.taken = tiff.ascii(at, count).or(exif.taken.take()),
+            0x829a => exif.exposure = tiff.rational(at),
+            0x829d => exif.f_number = tiff.float(at),
+            0x8827 => exif.iso = tiff.u16(at).map(u32::from),
+            0x920a => exif.focal_length = tiff.float(at),
+            _ => {}
+        }
+    }
+    if let Some(offset) = gps_ifd {
+        let (mut lat, mut lon, mut south, mut west) = (None, None, false, false);
+        for (tag, at, _) in tiff.entries(offset as usize) {
+            match tag {
+                0x0001 => south = tiff.data.get(at) == Some(&b'S'),
+                0x0002 => lat = tiff.degrees(at),
+                0x0003 => west = tiff.data.get(at) == Some(&b'W'),
+                0x0004 => lon = tiff.degrees(at),
+                _ => {}
+            }
+        }
+        if let (Some(lat), Some(lon)) = (lat, lon) {
+            exif.gps = Some((
+                if south { -lat } else { lat },
+                if west { -lon } else { lon },
+            ));
+        }
+    }
+    Some(exif)
+}
+
+impl Exif {
+    /// Lines for the inspector, leaving out what the file does not record.
+    pub fn lines(&self) -> Vec<String> {
+        let mut lines = Vec::new();
+        let camera = match (&self.make, &self.model) {
+            // models often repeat the make
+            (Some(make), Some(model)) if model.starts_with(make.as_str()) => Some(model.clone()),
+            (Some(make), Some(model)) => Some(format!("{make} {model}")),
+            (make, model) => make.clone().or(model.clone()),
+        };
+        if let Some(camera) = camera {
+            lines.push(format!("Camera:   {camera}"));
+        }
+        if let Some(taken) = &self.taken {
+            // EXIF separates the date with colons
+            let taken = taken.replacen(':', "-", 2);
+            lines.push(format!("Taken:    {taken}"));
+        }
+        let mut settings = Vec::new();
+        if let Some((n, d)) = self.exposure.filter(|&(n, d)| n > 0 && d > 0) {
+            settings.push(if n >= d {
+                format!("{} s", f64::from(n) / f64::from(d))
+            } else {
+                format!("1/{} s", (f64::from(d) / f64::from(n)).round())
+            });
+        }
+        if let Some(f) = self.f_number {
+            settings.push(format!("f/{f:.1}"));
+        }
+        if let Some(iso) = self.iso {
+            settings.push(format!("ISO {iso}"));
+        }
+        if let Some(mm) = self.focal_length {
+            settings.push(format!("{mm:.1} mm"));
+        }
+        if !settings.is_empty() {
+            lines.push(format!("Exposure: {}", settings.join("  ")));
+        }
+        if let Some((lat, lon)) = self.gps {
+            lines.push(format!("GPS:      {lat:.6}, {lon:.6}"));
+        }
+        let rotation = match self.orientation {
+            Some(2) => Some("mirrored"),
+            Some(3) => Some("180°"),
+            Some(4) => Some("180°, mirrored"),
+            Some(5) => Some("90° clockwise, mirrored"),
+            Some(6) => Some("90° clockwise"),
+            Some(7) => Some("90° counterclockwise, mirrored"),
+            Some(8) => Some("90° counterclockwise"),
+            _ => None,
+        };
+        if let Some(rotation) = rotation {
+            lines.push(format!("Rotation: {rotation}"));
+        }
+        lines
+    }
+}
+
+/// `image` turned upright according to the EXIF `orientation`.
+pub fn orient(image: DynamicImage, orientation: u16) -> DynamicImage {
+    match orientation {
+        2 => image.fliph(),
+        3 => image.rotate180(),
+        4 => image.flipv(),
+        5 => image.rotate90().fliph(),
+        6 => image.rotate90(),
+        7 => image.rotate270().fliph(),
+        8 => image.rotate270(),
+        _ => image,
+    }
+}
+
+/// Whether `orientation` turns the picture sideways, swapping its width and height.
+pub fn is_sideways(orientation: u16) -> bool {
+    (5..=8).contains(&orientation)
+}
@@ -0,0 +1,497 @@
+use std::{
+    collections::BTreeSet,
+    fs,
+    path::{Path, PathBuf},
+    sync::{
+        mpsc::{self, Receiver, RecvTimeoutError},
+        Arc, Mutex,
+    },
+    thread,
+    time::Duration,
+};
+
+use crate::config::cache_dir;
+use crate::line_edit::LineEditor;
+
+/// Directories not worth indexing: VCS metadata, build output and caches.
+const SKIPPED_DIRS: &[&str] = &[".git", ".hg", ".svn", "node_modules", "target", ".cache"];
+
+/// Results the finder keeps; more than any screen shows.
+const FINDER_RESULTS: usize = 500;
+
+/// How often the index thread saves changes and looks for rebuild requests.
+const TICK: Duration = Duration::from_secs(1);
+
+/// Paths under `root`, searched by the finder (`gf`).
+///
+/// The persistent index of the `search_index` setting is saved in the cache directory, read
+/// back at start so results come at once, and kept up to date: scanned again in the
+/// background at start and on request, and on Linux updated by inotify events in between.
+/// Without it the finder scans the directory it searches each time it opens.
+#[derive(Clone)]
+pub struct FileIndex {
+    pub root: PathBuf,
+    shared: Arc<Mutex<Shared>>,
+    /// Asks the index thread to scan again; none for a one-off scan
+    rebuild: Option<mpsc::Sender<()>>,
+}
+
+#[derive(Default)]
+struct Shared {
+    paths: BTreeSet<PathBuf>,
+    /// Goes up with every change, so searches know when to run again
+    generation: u64,
+    scanning: bool,
+    /// Changed since last saved
+    dirty: bool,
+}
+
+impl FileIndex {
+    /// The persistent index of `root`: loaded from the cache, then scanned and watched by a
+    /// background thread that lives as long as the index does.
+    pub fn open(root: PathBuf) -> Self {
+        let file = cache_dir().map(|d| d.join("index"));
+        Self::open_at(root, file)
+    }
+
+    /// Like `open`, saved in `file` instead of the cache directory.
+    pub fn open_at(root: PathBuf, file: Option<PathBuf>) -> Self {
+        let paths = file
+            .as_ref()
+            .and_then(|f| load(f, &root))
+            .unwrap_or_default();
+        let shared = Arc::new(Mutex::new(Shared {
+            paths,
+            scanning: true,
+            ..Shared::default()
+        }));
+        let (rebuild, requests) = mpsc::channel();
+        let thread_shared = shared.clone();
+        let thread_root = root.clone();
+        thread::spawn(move || watch(&thread_root, &thread_shared, &requests, file.as_deref()));
+        Self {
+            root,
+            shared,
+            rebuild: Some(rebuild),
+        }
+    }
+
+    /// A one-off scan of `root`, neither saved nor watched.
+    pub fn scan(root: PathBuf) -> Self {
+        let shared = Arc::new(Mutex::new(Shared {
+            scanning: true,
+            ..Shared::default()
+        }));
+        let thread_shared = shared.clone();
+        let thread_root = root.clone();
+        thread::spawn(move || {
+            let paths = walk(&thread_root, &mut |_| {});
+            replace(&thread_shared, paths);
+        });
+        Self {
+            root,
+            shared,
+            rebuild: None,
+        }
+    }
+
+    /// Whether the index covers everything under `dir`.
+    pub fn covers(&self, dir: &Path) -> bool {
+        dir.starts_with(&self.root)
+    }
+
+    pub fn is_persistent(&self) -> bool {
+        self.rebuild.is_some()
+    }
+
+    /// Scan the whole tree again, e.g. after changes the index missed.
+    pub fn rebuild(&self) {
+        if let Some(rebuild) = &self.rebuild {
+            self.shared.lock().unwrap().scanning = true;
+            let _ = rebuild.send(());
+        }
+    }
+
+    pub fn is_scanning(&self) -> bool {
+        self.shared.lock().unwrap().scanning
+    }
+
+    pub fn generation(&self) -> u64 {
+        self.shared.lock().unwrap().generation
+    }
+
+    pub fn len(&self) -> usize {
+        self.shared.lock().unwrap().paths.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn contains(&self, path: &Path) -> bool {
+        self.shared.lock().unwrap().paths.contains(path)
+    }
+
+    /// Up to `limit` paths under `dir` fuzzily matching `query`, best first.
+    ///
+    /// The characters of each whitespace-separated word of the query must appear in the path
+    /// below `dir` in order, ignoring case. Matches in the file name, and shorter paths, rank
+    /// first.
+    pub fn search(&self, dir: &Path, query: &str, limit: usize) -> Vec<PathBuf> {
+        let words: Vec<Vec<char>> = query
+            .split_whitespace()
+            .map(|w| w.to_lowercase().chars().collect())
+            .collect();
+        let shared = self.shared.lock().unwrap();
+        let mut found: Vec<(usize, &PathBuf)> = shared
+            .paths
+            .range(dir.to_path_buf()..)
+            .take_while(|p| p.starts_with(dir))
+            .filter_map(|path| {
+                let relative = path
+                    .strip_prefix(dir)
+                    .ok()?
+                    .to_string_lossy()
+                    .to_lowercase();
+                if relative.is_empty() {
+                    return None;
+                }
+                let name_start = relative.rfind(['/', '\\']).map_or(0, |i| i + 1);
+                let mut score = relative.chars().count();
+                for word in &words {
+                    score += fuzzy_cost(&relative, name_start, word)?;
+                }
+                Some((score, path))
+            })
+            .collect();
+        found.sort_by_key(|(score, _)| *score);
+        found
+            .into_iter()
+            .take(limit)
+            .map(|(_, p)| p.clone())
+            .collect()
+    }
+}
+
+/// The finder popup: paths under `dir` matching the typed query, from the persistent index
+/// when it covers `dir` and a scan of `dir` otherwise.
+pub struct Finder {
+    pub dir: PathBuf,
+    pub index: FileIndex,
+    pub query: LineEditor,
+    pub results: Vec<PathBuf>,
+    pub selected: usize,
+    /// Query and index generation the results are for
+    searched: Option<(String, u64)>,
+}
+
+impl Finder {
+    pub fn new(dir: PathBuf, index: FileIndex) -> Self {
+        let mut finder = Self {
+            dir,
+            index,
+            query: LineEditor::default(),
+            results: Vec::new(),
+            selected: 0,
+            searched: None,
+        };
+        finder.refresh();
+        finder
+    }
+
+    /// Search again if the query or the index has changed since the last search.
+    pub fn refresh(&mut self) {
+        let key = (self.query.to_string(), self.index.generation());
+        if self.searched.as_ref() == Some(&key) {
+            return;
+        }
+        self.results = self.index.search(&self.dir, &key.0, FINDER_RESULTS);
+        self.selected = self.selected.min(self.results.len().saturating_sub(1));
+        self.searched = Some(key);
+    }
+
+    pub fn current(&self) -> Option<&PathBuf> {
+        self.results.get(self.selected)
+    }
+
+    /// Scan again: the whole persistent index, or `dir` for a one-off scan.
+    pub fn rescan(&mut self) {
+        if self.index.is_persistent() {
+            self.index.rebuild();
+        } else {
+            self.index = FileIndex::scan(self.dir.clone());
+        }
+        self.searched = None;
+    }
+}
+
+/// How badly `word` matches `text` as a subsequence: characters matched before `name_start`
+/// cost more than those in the file name. `None` if it does not match at all.
+fn fuzzy_cost(text: &str, name_start: usize, word: &[char]) -> Option<usize> {
+    let mut cost = 0;
+    let mut chars = text.char_indices();
+    for &wanted in word {
+        let (at, _) = chars.find(|&(_, c)| c == wanted)?;
+        if at < name_start {
+            cost += 10;
+        }
+    }
+    Some(cost)
+}
+
+/// Every path under `root`, calling `on_dir` with each directory on the way.
+fn walk(root: &Path, on_dir: &mut dyn FnMut(&Path)) -> BTreeSet<PathBuf> {
+    let mut paths = BTreeSet::new();
+    let mut stack = vec![root.to_path_buf()];
+    while let Some(dir) = stack.pop() {
+        on_dir(&dir);
+        let Ok(entries) = fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            // symbolic links are indexed, not followed
+            if entry.file_type().is_ok_and(|t| t.is_dir()) {
+                let name = entry.file_name();
+                if SKIPPED_DIRS.iter().any(|s| name == *s) {
+                    continue;
+                }
+                stack.push(path.clone());
+            }
+            paths.insert(path);
+        }
+    }
+    paths
+}
+
+fn replace(shared: &Mutex<Shared>, paths: BTreeSet<PathBuf>) {
+    let mut shared = shared.lock().unwrap();
+    shared.paths = paths;
+    shared.generation += 1;
+    shared.scanning = false;
+    shared.dirty = true;
+}
+
+/// The index thread: scan, then keep the index up to date until it is dropped.
+fn watch(root: &Path, shared: &Mutex<Shared>, requests: &Receiver<()>, file: Option<&Path>) {
+    let mut watcher = Watcher::new();
+    let scan = |watcher: &mut Option<Watcher>| {
+        if let Some(w) = watcher {
+            w.clear();
+        }
+        let paths = walk(root, &mut |dir| {
+            if let Some(w) = watcher {
+                w.add(dir);
+            }
+        });
+        replace(shared, paths);
+    };
+    scan(&mut watcher);
+    loop {
+        let overflowed = match &mut watcher {
+            Some(w) => w.apply_events(shared),
+            None => false,
+        };
+        if overflowed {
+            scan(&mut watcher);
+        }
+        let dirty = std::mem::take(&mut shared.lock().unwrap().dirty);
+        if let (true, Some(file)) = (dirty, file) {
+            save(file, root, &shared.lock().unwrap().paths);
+        }
+        // the watcher waits for events itself
+        let wait = if watcher.is_some() {
+            Duration::ZERO
+        } else {
+            TICK
+        };
+        match requests.recv_timeout(wait) {
+            Ok(()) => scan(&mut watcher),
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => return,
+        }
+    }
+}
+
+/// The cache file: the root on the first line, then one path below it per line.
+fn save(file: &Path, root: &Path, paths: &BTreeSet<PathBuf>) {
+    let mut text = format!("{}\n", root.display());
+    for path in paths {
+        if let Ok(relative) = path.strip_prefix(root) {
+            text.push_str(&relative.to_string_lossy());
+            text.push('\n');
+        }
+    }
+    if let Some(dir) = file.parent() {
+        let _ = fs::create_dir_all(dir);
+    }
+    let _ = fs::write(file, text);
+}
+
+/// The paths saved in `file`, if it was saved for `root`.
+fn load(file: &Path, root: &Path) -> Option<BTreeSet<PathBuf>> {
+    let text = fs::read_to_string(file).ok()?;
+    let mut lines = text.lines();
+    if Path::new(lines.next()?) != root {
+        return None;
+    }
+    Some(lines.map(|l| root.join(l)).collect())
+}
+
+#[cfg(target_os = "linux")]
+use linux::Watcher;
+
+#[cfg(not(target_os = "linux"))]
+struct Watcher;
+
+#[cfg(not(target_os = "linux"))]
+impl Watcher {
+    fn new() -> Option<Self> {
+        None
+    }
+
+    fn clear(&mut self) {}
+
+    fn add(&mut self, _dir: &Path) {}
+
+    fn apply_events(&mut self, _shared: &Mutex<Shared>) -> bool {
+        false
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod linux {
+    use std::{
+        collections::HashMap,
+        ffi::{CString, OsStr},
+        os::unix::ffi::OsStrExt,
+        path::{Path, PathBuf},
+        sync::Mutex,
+    };
+
+    use super::{walk, Shared, TICK};
+
+    const EVENTS: u32 = libc::IN_CREATE | libc::IN_DELETE | libc::IN_MOVED_FROM | libc::IN_MOVED_TO;
+
+    /// Directories watched with inotify, by watch descriptor.
+    pub struct Watcher {
+        fd: i32,
+        dirs: HashMap<i32, PathBuf>,
+    }
+
+    impl Watcher {
+        pub fn new() -> Option<Self> {
+            let fd = unsafe { libc::inotify_init1(libc::IN_CLOEXEC | libc::IN_NONBLOCK) };
+            (fd >= 0).then(|| Self {
+                fd,
+                dirs: HashMap::new(),
+            })
+        }
+
+        pub fn clear(&mut self) {
+            for wd in self.dirs.keys() {
+                unsafe { libc::inotify_rm_watch(self.fd, *wd) };
+            }
+            self.dirs.clear();
+        }
+
+        /// Watch `dir`; past the system's limit on watches it is silently left out.
+        pub fn add(&mut self, dir: &Path) {
+            let Ok(path) = CString::new(dir.as_os_str().as_bytes()) else {
+                return;
+            };
+            let wd = unsafe { libc::inotify_add_watch(self.fd, path.as_ptr(), EVENTS) };
+            if wd >= 0 {
+                self.dirs.insert(wd, dir.to_path_buf());
+            }
+        }
+
+        fn forget(&mut self, dir: &Path) {
+            let gone: Vec<i32> = self
+                .dirs
+                .iter()
+                .filter(|(_, d)| d.starts_with(dir))
+                .map(|(wd, _)| *wd)
+                .collect();
+            for wd in gone {
+                unsafe { libc::inotify_rm_watch(self.fd, wd) };
+                self.dirs.remove(&wd);
+            }
+        }
+
+        /// Wait up to a tick for events and apply them to the index; returns true if events
+        /// were lost and the tree needs scanning again.
+        pub fn apply_events(&mut self, shared: &Mutex<Shared>) -> bool {
+            let mut poll = libc::pollfd {
+                fd: self.fd,
+                events: libc::POLLIN,
+                revents: 0,
+            };
+            if unsafe { libc::poll(&mut poll, 1, TICK.as_millis() as i32) } <= 0 {
+                return false;
+            }
+            let mut buffer = [0u8; 64 * 1024];
+            let read = unsafe { libc::read(self.fd, buffer.as_mut_ptr().cast(), buffer.len()) };
+            if read <= 0 {
+                return false;
+            }
+            let header = std::mem::size_of::<libc::inotify_event>();
+            let mut at = 0;
+            while at + header <= read as usize {
+                let event: libc::inotify_event =
+                    unsafe { std::ptr::read_unaligned(buffer[at..].as_ptr().cast()) };
+                let name = &buffer[at + header..at + header + event.len as usize];
+                at += header + event.len as usize;
+                if event.mask & libc::IN_Q_OVERFLOW != 0 {
+                    return true;
+                }
+                if event.mask & libc::IN_IGNORED != 0 {
+                    self.dirs.remove(&event.wd);
+                    continue;
+                }
+                let Some(dir) = self.dirs.get(&event.wd) else {
+                    continue;
+                };
+                // the name is padded with NULs
+                let name = name.split(|&b| b == 0).next().unwrap_or_default();
+                let path = dir.join(OsStr::from_bytes(name));
+                let is_dir = event.mask & libc::IN_ISDIR != 0;
+                if event.mask & (libc::IN_CREATE | libc::IN_MOVED_TO) != 0 {
+                    let mut added = if is_dir {
+                        walk(&path, &mut |d| self.add(d))
+                    } else {
+                        Default::default()
+                    };
+                    added.insert(path);
+                    let mut shared = shared.lock().unwrap();
+                    shared.paths.append(&mut added);
+                    shared.generation += 1;
+                    shared.dirty = true;
+                } else {
+                    if is_dir {
+                        self.forget(&path);
+                    }
+                    let mut shared = shared.lock().unwrap();
+                    let gone: Vec<PathBuf> = shared
+                        .paths
+                        .range(path.clone()..)
+                        .take_while(|p| p.starts_with(&path))
+                        .cloned()
+                        .collect();
+                    for p in &gone {
+                        shared.paths.remove(p);
+                    }
+                    shared.generation += 1;
+                    shared.dirty = true;
+                }
+            }
+            false
+        }
+    }
+
+    impl Drop for Watcher {
+        fn drop(&mut self) {
+            unsafe { libc::close(self.fd) };
+        }
+    }
+}
@@ -0,0 +1,112 @@
+use std::{fs::File, io, io::Read, path::Path};
+
+/// A file format recognized from the first bytes of a file.
+#[derive(Clone, Copy)]
+pub struct FileType {
+    pub mime: &'static str,
+    pub description: &'static str,
+}
+
+impl FileType {
+    const fn new(mime: &'static str, description: &'static str) -> Self {
+        Self { mime, description }
+    }
+
+    pub fn is_audio(&self) -> bool {
+        self.mime.starts_with("audio/")
+    }
+
+    pub fn is_video(&self) -> bool {
+        self.mime.starts_with("video/")
+    }
+}
+
+/// Leading bytes of formats, with their MIME type and description, checked in order.
+const SIGNATURES: &[(&[u8], &str, &str)] = &[
+    (b"\x89PNG\r\n\x1a\n", "image/png", "PNG image"),
+    (b"\xff\xd8\xff", "image/jpeg", "JPEG image"),
+    (b"GIF87a", "image/gif", "GIF image"),
+    (b"GIF89a", "image/gif", "GIF image"),
+    (b"II*\0", "image/tiff", "TIFF image"),
+    (b"MM\0*", "image/tiff", "TIFF image"),
+    (b"\0\0\x01\0", "image/x-icon", "icon"),
+    (b"%PDF-", "application/pdf", "PDF document"),
+    (b"PK\x03\x04", "application/zip", "ZIP archive"),
+    (b"\x1f\x8b", "application/gzip", "gzip data"),
+    (b"BZh", "application/x-bzip2", "bzip2 data"),
+    (b"\xfd7zXZ\0", "application/x-xz", "xz data"),
+    (b"\x28\xb5\x2f\xfd", "application/zstd", "zstd data"),
+    (
+        b"7z\xbc\xaf\x27\x1c",
+        "application/x-7z-compressed",
+        "7-Zip archive",
+    ),
+    (b"Rar!\x1a\x07", "application/vnd.rar", "RAR archive"),
+    (b"\x7fELF", "application/x-executable", "ELF executable"),
+    (b"MZ", "application/x-msdownload", "Windows executable"),
+    (
+        b"\xcf\xfa\xed\xfe",
+        "application/x-mach-binary",
+        "Mach-O executable",
+    ),
+    (
+        b"\xfe\xed\xfa\xcf",
+        "application/x-mach-binary",
+        "Mach-O executable",
+    ),
+    (b"\0asm", "application/wasm", "WebAssembly module"),
+    (
+        b"SQLite format 3\0",
+        "application/vnd.sqlite3",
+        "SQLite database",
+    ),
+    (b"ID3", "audio/mpeg", "MP3 audio"),
+    (b"\xff\xfb", "audio/mpeg", "MP3 audio"),
+    (b"\xff\xf3", "audio/mpeg", "MP3 audio"),
+    (b"fLaC", "audio/flac", "FLAC audio"),
+    (b"OggS", "audio/ogg", "Ogg audio"),
+    (b"\x1a\x45\xdf\xa3", "video/x-matroska", "Matroska video"),
+    (b"FLV", "video/x-flv", "Flash video"),
+];
+
+/// The format of the file at `path` judged by its content, or `None` if it is not one of the
+/// known binary formats (text files included).
+pub fn sniff(path: &Path) -> io::Result<Option<FileType>> {
+    let mut head = Vec::with_capacity(512);
+    File::open(path)?.take(512).read_to_end(&mut head)?;
+    Ok(detect(&head))
+}
+
+/// The format of a file starting with `head`.
+fn detect(head: &[u8]) -> Option<FileType> {
+    let at = |offset: usize, magic: &[u8]| head.get(offset..offset + magic.len()) == Some(magic);
+    // containers whose content type sits after a size field
+    if at(0, b"RIFF") {
+        return match head.get(8..12)? {
+            b"WEBP" => Some(FileType::new("image/webp", "WebP image")),
+            b"WAVE" => Some(FileType::new("audio/wav", "WAV audio")),
+            b"AVI " => Some(FileType::new("video/x-msvideo", "AVI video")),
+            _ => None,
+        };
+    }
+    if at(4, b"ftyp") {
+        return Some(match head.get(8..12)? {
+            b"M4A " | b"M4B " => FileType::new("audio/mp4", "MPEG-4 audio"),
+            b"heic" | b"heix" | b"mif1" => FileType::new("image/heic", "HEIF image"),
+            b"avif" => FileType::new("image/avif", "AVIF image"),
+            b"qt  " => FileType::new("video/quicktime", "QuickTime video"),
+            _ => FileType::new("video/mp4", "MPEG-4 video"),
+        });
+    }
+    if at(257, b"ustar") {
+        return Some(FileType::new("application/x-tar", "tar archive"));
+    }
+    // four reserved zero bytes in the BMP header rule out text that happens to start with BM
+    if at(0, b"BM") && head.get(6..10) == Some(&[0; 4]) {
+        return Some(FileType::new("image/bmp", "BMP image"));
+    }
+    SIGNATURES
+        .iter()
+        .find(|(magic, _, _)| head.starts_with(magic))
+        .map(|&(_, mime, description)| FileType::new(mime, description))
+}
@@ -0,0 +1,87 @@
+use std::{
+    sync::atomic::{AtomicBool, Ordering},
+    time::SystemTime,
+};
+
+use chrono::{DateTime, Local};
+
+/// Whether sizes are shown in SI units (kB, MB: powers of 1000) rather than binary ones
+/// (KiB, MiB: powers of 1024). Set once from the config at startup; a process-wide setting
+/// saves threading it to every place that prints a size.
+static SI_UNITS: AtomicBool = AtomicBool::new(false);
+
+pub fn set_si_units(si: bool) {
+    SI_UNITS.store(si, Ordering::Relaxed);
+}
+
+/// `bytes` with one decimal in the configured units, e.g. `512 B`, `1.5 KiB` or `1.5 kB`.
+pub fn human_size(bytes: u64) -> String {
+    let (base, units) = if SI_UNITS.load(Ordering::Relaxed) {
+        (1000.0, ["kB", "MB", "GB", "TB", "PB", "EB"])
+    } else {
+        (1024.0, ["KiB", "MiB", "GiB", "TiB", "PiB", "EiB"])
+    };
+    if (bytes as f64) < base {
+        return format!("{bytes} B");
+    }
+    let mut value = bytes as f64 / base;
+    let mut unit = 0;
+    while value >= base && unit + 1 < units.len() {
+        value /= base;
+        unit += 1;
+    }
+    format!("{value:.1} {}", units[unit])
+}
+
+/// `n` with `,` between groups of three digits.
+pub fn thousands(n: u64) -> String {
+    let digits = n.to_string();
+    let mut out = String::with_capacity(digits.len() + digits.len() / 3);
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            out.push(',');
+        }
+        out.push(c);
+    }
+    out
+}
+
+/// How long ago `time` was, in the largest whole unit, e.g. `3 days ago`.
+pub fn relative_time(time: SystemTime) -> String {
+    let Ok(elapsed) = SystemTime::now().duration_since(time) else {
+        return "in the future".to_string();
+    };
+    let seconds = elapsed.as_secs();
+    let (count, unit) = match seconds {
+        0..=59 => return "just now".to_string(),
+        60..=3_599 => (seconds / 60, "minute"),
+        3_600..=86_399 => (seconds / 3_600, "hour"),
+        86_400..=172_799 => return "yesterday".to_string(),
+        172_800..=1_209_599 => (seconds / 86_400, "day"),
+        1_209_600..=5_183_999 => (seconds / 604_800, "week"),
+        5_184_000..=31_535_999 => (seconds / 2_592_000, "month"),
+        _ => (seconds / 31_536_000, "year"),
+    };
+    let plural = if count == 1 { "" } else { "s" };
+    format!("{count} {unit}{plural} ago")
+}
+
+/// `time` in the local time zone, in the date order of the user's locale
+/// (`LC_ALL`, `LC_TIME` or `LANG`), falling back to ISO 8601.
+pub fn absolute_time(time: SystemTime) -> String {
+    let locale = ["LC_ALL", "LC_TIME", "LANG"]
+        .iter()
+        .find_map(|var| std::env::var(var).ok().filter(|v| !v.is_empty()))
+        .unwrap_or_default();
+    let region = locale.split(['.', '@']).next().unwrap_or_default();
+    let pattern = match region.split('_').next().unwrap_or_default() {
+        "ja" | "zh" => "%Y/%m/%d %H:%M:%S",
+        "ko" => "%Y. %m. %d. %H:%M:%S",
+        "de" | "ru" | "pl" | "cs" | "fi" | "nb" | "da" | "tr" => "%d.%m.%Y %H:%M:%S",
+        "en" if region == "en_US" => "%m/%d/%Y %I:%M:%S %p",
+        "en" | "fr" | "es" | "it" | "pt" | "el" => "%d/%m/%Y %H:%M:%S",
+        "nl" => "%d-%m-%Y %H:%M:%S",
+        _ => "%Y-%m-%d %H:%M:%S",
+    };
+    DateTime::<Local>::from(time).format(pattern).to_string()
+}
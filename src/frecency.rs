@@ -0,0 +1,125 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::config::data_dir;
+
+/// When the ranks add up to more than this, they are all scaled down and the least used
+/// directories are forgotten, so the history stays small and favors recent habits.
+const MAX_TOTAL_RANK: f64 = 10_000.0;
+
+/// Directories the user has visited, ranked by how often and how recently (like zoxide).
+///
+/// The default history starts empty and is never saved.
+#[derive(Default)]
+pub struct History {
+    /// Visit rank and last visit time (seconds since the epoch) of each directory
+    entries: HashMap<PathBuf, (f64, u64)>,
+    file: Option<PathBuf>,
+}
+
+impl History {
+    /// Load the history from the data directory; an empty history if there is none.
+    pub fn load() -> Self {
+        let file = data_dir().map(|d| d.join("history"));
+        let mut entries = HashMap::new();
+        if let Some(text) = file.as_ref().and_then(|f| fs::read_to_string(f).ok()) {
+            for line in text.lines() {
+                let mut fields = line.splitn(3, '\t');
+                if let (Some(rank), Some(time), Some(path)) =
+                    (fields.next(), fields.next(), fields.next())
+                {
+                    if let (Ok(rank), Ok(time)) = (rank.parse(), time.parse()) {
+                        entries.insert(PathBuf::from(path), (rank, time));
+                    }
+                }
+            }
+        }
+        Self { entries, file }
+    }
+
+    /// Count a visit to `dir` and save the history.
+    pub fn record(&mut self, dir: &Path) {
+        let now = now();
+        let entry = self.entries.entry(dir.to_path_buf()).or_insert((0.0, now));
+        entry.0 += 1.0;
+        entry.1 = now;
+        if self.entries.values().map(|e| e.0).sum::<f64>() > MAX_TOTAL_RANK {
+            for entry in self.entries.values_mut() {
+                entry.0 *= 0.9;
+            }
+            self.entries.retain(|_, e| e.0 >= 1.0);
+        }
+        self.save();
+    }
+
+    fn save(&self) {
+        let Some(file) = &self.file else {
+            return;
+        };
+        let text: String = self
+            .entries
+            .iter()
+            .map(|(path, (rank, time))| format!("{rank}\t{time}\t{}\n", path.display()))
+            .collect();
+        if let Some(dir) = file.parent() {
+            let _ = fs::create_dir_all(dir);
+        }
+        let _ = fs::write(file, text);
+    }
+
+    /// Existing directories matching `query`, best first.
+    ///
+    /// Every whitespace-separated word of the query must appear in the path, in order and
+    /// ignoring case; the last word must match within the final path component.
+    pub fn query(&self, query: &str) -> Vec<PathBuf> {
+        let now = now();
+        let words: Vec<String> = query.split_whitespace().map(str::to_lowercase).collect();
+        let mut found: Vec<(f64, &PathBuf)> = self
+            .entries
+            .iter()
+            .filter(|(path, _)| matches(path, &words) && path.is_dir())
+            .map(|(path, &(rank, time))| (frecency(rank, now.saturating_sub(time)), path))
+            .collect();
+        found.sort_by(|a, b| b.0.total_cmp(&a.0));
+        found.into_iter().map(|(_, path)| path.clone()).collect()
+    }
+}
+
+fn matches(path: &Path, words: &[String]) -> bool {
+    let text = path.to_string_lossy().to_lowercase();
+    let mut rest = text.as_str();
+    for word in words {
+        match rest.find(word.as_str()) {
+            Some(at) => rest = &rest[at + word.len()..],
+            None => return false,
+        }
+    }
+    // the last word should name the directory itself, not one of its parents
+    words.last().is_none_or(|last| {
+        path.file_name()
+            .is_some_and(|n| n.to_string_lossy().to_lowercase().contains(last.as_str()))
+    })
+}
+
+/// Rank weighted by how long ago the last visit was.
+fn frecency(rank: f64, age_secs: u64) -> f64 {
+    const HOUR: u64 = 3600;
+    let weight = match age_secs {
+        a if a < HOUR => 4.0,
+        a if a < 24 * HOUR => 2.0,
+        a if a < 7 * 24 * HOUR => 0.5,
+        _ => 0.25,
+    };
+    rank * weight
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
@@ -1,12 +1,21 @@
 use std::{
-    cmp::Reverse,
-    fs::{self, DirEntry},
-    io,
-    path::Path,
-    time::UNIX_EPOCH,
+    cmp::{Ordering, Reverse},
+    fs::{self, File},
+    io::{self, BufReader, Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
 };
 
+use chrono::{DateTime, Datelike, Local};
+use regex::RegexBuilder;
+
+use crate::file_type;
+use crate::format::{human_size, thousands};
+use crate::rsync::UseRsync;
+use crate::vfs::Entry;
+
 /// Criteria for sorting the file list.
+#[derive(Clone, Copy, PartialEq)]
 pub enum SortBy {
     Modified,
     Created,
@@ -14,6 +23,218 @@ pub enum SortBy {
     Name,
 }
 
+impl SortBy {
+    /// In the order of `SORT_OPTIONS`.
+    pub const ALL: [SortBy; 4] = [
+        SortBy::Modified,
+        SortBy::Created,
+        SortBy::Size,
+        SortBy::Name,
+    ];
+
+    /// Short name for pane titles.
+    pub fn label(self) -> &'static str {
+        match self {
+            SortBy::Modified => "mtime",
+            SortBy::Created => "ctime",
+            SortBy::Size => "size",
+            SortBy::Name => "name",
+        }
+    }
+
+    /// Whether the natural order is descending: largest first for sizes, oldest and A first
+    /// otherwise.
+    fn descending(self) -> bool {
+        self == SortBy::Size
+    }
+
+    /// Compare in the natural order.
+    fn compare(self, a: &Entry, b: &Entry) -> Ordering {
+        match self {
+            SortBy::Modified => a
+                .modified()
+                .unwrap_or(UNIX_EPOCH)
+                .cmp(&b.modified().unwrap_or(UNIX_EPOCH)),
+            SortBy::Created => a
+                .created()
+                .unwrap_or(UNIX_EPOCH)
+                .cmp(&b.created().unwrap_or(UNIX_EPOCH)),
+            SortBy::Size => b.len().cmp(&a.len()),
+            SortBy::Name => a
+                .file_name()
+                .to_string_lossy()
+                .to_lowercase()
+                .cmp(&b.file_name().to_string_lossy().to_lowercase()),
+        }
+    }
+}
+
+/// Sort order of a pane: entries equal by `primary`, in its natural direction or the other
+/// way with `reverse`, are ordered by `secondary`, e.g. size then name.
+#[derive(Clone, Copy, PartialEq)]
+pub struct SortSpec {
+    pub primary: SortBy,
+    pub secondary: Option<SortBy>,
+    pub reverse: bool,
+}
+
+impl Default for SortSpec {
+    fn default() -> Self {
+        Self::new(SortBy::Name)
+    }
+}
+
+impl SortSpec {
+    pub fn new(primary: SortBy) -> Self {
+        Self {
+            primary,
+            secondary: None,
+            reverse: false,
+        }
+    }
+
+    /// Sort by `by` first: picking the current primary key again inverts the direction.
+    pub fn choose(self, by: SortBy) -> Self {
+        if by == self.primary {
+            return Self {
+                reverse: !self.reverse,
+                ..self
+            };
+        }
+        Self {
+            primary: by,
+            secondary: self.secondary.filter(|s| *s != by),
+            reverse: false,
+        }
+    }
+
+    /// Break ties by `by`, or stop breaking them if it already does.
+    pub fn then_by(self, by: SortBy) -> Self {
+        let secondary = if by == self.primary || self.secondary == Some(by) {
+            None
+        } else {
+            Some(by)
+        };
+        Self { secondary, ..self }
+    }
+
+    /// Keys with their direction for pane titles, e.g. `size↓,name↑`.
+    pub fn label(self) -> String {
+        let arrow = |descending| if descending { "↓" } else { "↑" };
+        let primary = format!(
+            "{}{}",
+            self.primary.label(),
+            arrow(self.primary.descending() != self.reverse)
+        );
+        match self.secondary {
+            Some(by) => format!("{primary},{}{}", by.label(), arrow(by.descending())),
+            None => primary,
+        }
+    }
+
+    pub fn compare(self, a: &Entry, b: &Entry) -> Ordering {
+        let primary = self.primary.compare(a, b);
+        let primary = if self.reverse {
+            primary.reverse()
+        } else {
+            primary
+        };
+        primary.then_with(|| self.secondary.map_or(Ordering::Equal, |s| s.compare(a, b)))
+    }
+}
+
+/// How the size column of the listing is shown.
+#[derive(Clone, Copy, PartialEq)]
+pub enum SizeDisplay {
+    Hidden,
+    /// Binary units, e.g. `1.5 MiB`
+    Human,
+    /// Exact byte count with thousands separators, e.g. `1,572,864`
+    Exact,
+}
+
+impl SizeDisplay {
+    pub fn next(self) -> Self {
+        match self {
+            SizeDisplay::Hidden => SizeDisplay::Human,
+            SizeDisplay::Human => SizeDisplay::Exact,
+            SizeDisplay::Exact => SizeDisplay::Hidden,
+        }
+    }
+
+    pub fn format(self, bytes: u64) -> String {
+        match self {
+            SizeDisplay::Hidden => String::new(),
+            SizeDisplay::Human => human_size(bytes),
+            SizeDisplay::Exact => thousands(bytes),
+        }
+    }
+}
+
+/// Number of files under `path` (or 1 if it is a file) and their total size, not following symlinks.
+pub fn tree_size(path: &Path) -> (usize, u64) {
+    let Ok(metadata) = path.symlink_metadata() else {
+        return (0, 0);
+    };
+    if !metadata.is_dir() {
+        return (1, metadata.len());
+    }
+    fs::read_dir(path)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|e| tree_size(&e.path()))
+        .fold((0, 0), |(files, bytes), (f, b)| (files + f, bytes + b))
+}
+
+/// Modification-date buckets used by the grouped listing, newest first.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DateGroup {
+    Today,
+    Yesterday,
+    ThisWeek,
+    Older,
+}
+
+impl DateGroup {
+    pub fn label(self) -> &'static str {
+        match self {
+            DateGroup::Today => "Today",
+            DateGroup::Yesterday => "Yesterday",
+            DateGroup::ThisWeek => "This week",
+            DateGroup::Older => "Older",
+        }
+    }
+
+    /// Bucket for `modified` relative to the local calendar date of `now`.
+    pub fn of(modified: SystemTime, now: DateTime<Local>) -> Self {
+        let today = now.date_naive();
+        let date = DateTime::<Local>::from(modified).date_naive();
+        let days_ago = (today - date).num_days();
+        let week_start = i64::from(today.weekday().num_days_from_monday());
+        if days_ago <= 0 {
+            DateGroup::Today
+        } else if days_ago == 1 {
+            DateGroup::Yesterday
+        } else if days_ago <= week_start {
+            DateGroup::ThisWeek
+        } else {
+            DateGroup::Older
+        }
+    }
+}
+
+/// Sort entries newest first and return the date bucket of each.
+pub fn group_by_date(items: &mut [Entry]) -> Vec<DateGroup> {
+    let modified = |e: &Entry| e.modified().unwrap_or(UNIX_EPOCH);
+    items.sort_by_key(|e| Reverse(modified(e)));
+    let now = Local::now();
+    items
+        .iter()
+        .map(|e| DateGroup::of(modified(e), now))
+        .collect()
+}
+
 /// Labels for sort options in the popup.
 pub static SORT_OPTIONS: &[&str] = &[
     "Last modified date",
@@ -22,71 +243,683 @@ pub static SORT_OPTIONS: &[&str] = &[
     "Alphabetical",
 ];
 
-/// Apply the chosen sort order to the given pane.
-pub fn apply_sort(pane: &mut crate::app::Pane, by: SortBy) {
-    match by {
-        SortBy::Modified => pane.items.sort_by(|a, b| {
-            let ma = a
-                .metadata()
-                .and_then(|m| m.modified())
-                .unwrap_or(UNIX_EPOCH);
-            let mb = b
-                .metadata()
-                .and_then(|m| m.modified())
-                .unwrap_or(UNIX_EPOCH);
-            ma.cmp(&mb)
-        }),
-        SortBy::Created => pane.items.sort_by(|a, b| {
-            let ca = a.metadata().and_then(|m| m.created()).unwrap_or(UNIX_EPOCH);
-            let cb = b.metadata().and_then(|m| m.created()).unwrap_or(UNIX_EPOCH);
-            ca.cmp(&cb)
-        }),
-        SortBy::Size => pane
-            .items
-            .sort_by_key(|e| Reverse(e.metadata().map(|m| m.len()).unwrap_or(0))),
-        SortBy::Name => pane
-            .items
-            .sort_by_key(|e| e.file_name().to_string_lossy().to_lowercase()),
-    }
+/// Sort entries by `spec`; by default oldest, largest or alphabetically first.
+pub fn sort_entries(items: &mut [Entry], spec: SortSpec) {
+    items.sort_by(|a, b| spec.compare(a, b));
+}
+
+/// Apply the chosen sort order to the given pane; it is kept when the pane is reloaded.
+pub fn apply_sort(pane: &mut crate::app::Pane, spec: SortSpec) {
+    sort_entries(&mut pane.items, spec);
+    pane.sort_by = Some(spec);
+    // an explicit sort order replaces the date grouping
+    pane.group_by_date = false;
+    pane.groups.clear();
     pane.selected = 0;
-    pane.marked.clear();
 }
 
-/// Find the next entry matching `query` (case-insensitive) after `start`, wrapping around.
-pub fn find_match(entries: &[DirEntry], query: &str, start: usize) -> Option<usize> {
+/// Find the next entry matching `query` after `start` (or the previous one before it, if
+/// `backward`), wrapping around.
+///
+/// With `regex` the query is a regular expression, and an invalid one matches nothing;
+/// otherwise names containing it match. Case is ignored unless the query has an uppercase letter.
+pub fn find_match(
+    entries: &[Entry],
+    query: &str,
+    regex: bool,
+    start: usize,
+    backward: bool,
+) -> Option<usize> {
     if query.is_empty() || entries.is_empty() {
         return None;
     }
-    let q = query.to_lowercase();
+    let pattern = if regex {
+        query.to_string()
+    } else {
+        regex::escape(query)
+    };
+    let matcher = RegexBuilder::new(&pattern)
+        .case_insensitive(!query.chars().any(char::is_uppercase))
+        .build()
+        .ok()?;
     let total = entries.len();
-    for i in 1..=total {
-        let idx = (start + i) % total;
-        let name = entries[idx].file_name().to_string_lossy().to_lowercase();
-        if name.contains(&q) {
-            return Some(idx);
+    (1..=total)
+        .map(|i| {
+            if backward {
+                (start + total - i) % total
+            } else {
+                (start + i) % total
+            }
+        })
+        .find(|&idx| matcher.is_match(&entries[idx].file_name().to_string_lossy()))
+}
+
+/// Whether `name` contains `query`, ignoring case unless the query has an uppercase letter.
+pub fn contains_smart_case(name: &str, query: &str) -> bool {
+    if query.chars().any(char::is_uppercase) {
+        name.contains(query)
+    } else {
+        name.to_lowercase().contains(&query.to_lowercase())
+    }
+}
+
+/// Match `name` against a shell-style glob supporting `*`, `?` and `[...]` classes.
+pub fn glob_match(pattern: &str, name: &str) -> bool {
+    let p: Vec<char> = pattern.chars().collect();
+    let n: Vec<char> = name.chars().collect();
+    // position to resume from after the last `*`: (pattern index, name index)
+    let mut backtrack: Option<(usize, usize)> = None;
+    let (mut pi, mut ni) = (0, 0);
+    while ni < n.len() {
+        match p.get(pi) {
+            Some('*') => {
+                backtrack = Some((pi, ni));
+                pi += 1;
+                continue;
+            }
+            Some('?') => {
+                pi += 1;
+                ni += 1;
+                continue;
+            }
+            Some('[') => {
+                if let Some((matched, len)) = match_class(&p[pi..], n[ni]) {
+                    if matched {
+                        pi += len;
+                        ni += 1;
+                        continue;
+                    }
+                } else if n[ni] == '[' {
+                    // unterminated class: treat `[` literally
+                    pi += 1;
+                    ni += 1;
+                    continue;
+                }
+            }
+            Some(&c) if c == n[ni] => {
+                pi += 1;
+                ni += 1;
+                continue;
+            }
+            _ => {}
+        }
+        match backtrack {
+            Some((star, matched)) => {
+                pi = star + 1;
+                ni = matched + 1;
+                backtrack = Some((star, matched + 1));
+            }
+            None => return false,
+        }
+    }
+    p[pi..].iter().all(|&c| c == '*')
+}
+
+/// Match `c` against the `[...]` class at the start of `p`.
+/// Returns whether it matched and the length of the class, or `None` if unterminated.
+fn match_class(p: &[char], c: char) -> Option<(bool, usize)> {
+    let mut i = 1;
+    let negate = matches!(p.get(i), Some('!') | Some('^'));
+    if negate {
+        i += 1;
+    }
+    let mut matched = false;
+    let mut first = true;
+    while let Some(&ch) = p.get(i) {
+        if ch == ']' && !first {
+            return Some((matched != negate, i + 1));
+        }
+        first = false;
+        if p.get(i + 1) == Some(&'-') && p.get(i + 2).is_some_and(|&e| e != ']') {
+            if (ch..=p[i + 2]).contains(&c) {
+                matched = true;
+            }
+            i += 3;
+        } else {
+            if ch == c {
+                matched = true;
+            }
+            i += 1;
+        }
+    }
+    None
+}
+
+/// Reason a proposed file name cannot be used as-is.
+pub enum NameProblem {
+    Empty,
+    Separator,
+    /// Not allowed on this platform, with the reason
+    Invalid(String),
+    /// Another entry already has this name; renaming would overwrite it
+    Exists,
+    /// This many directories on the way to a typed path do not exist and would be created
+    MissingDirs(usize),
+}
+
+impl NameProblem {
+    pub fn message(&self) -> String {
+        match self {
+            NameProblem::Empty => "name is empty".into(),
+            NameProblem::Separator => "name contains a path separator".into(),
+            NameProblem::Invalid(reason) => reason.clone(),
+            NameProblem::Exists => "already exists; press Enter again to overwrite".into(),
+            NameProblem::MissingDirs(1) => {
+                "creates 1 directory; press Enter again to confirm".into()
+            }
+            NameProblem::MissingDirs(n) => {
+                format!("creates {n} directories; press Enter again to confirm")
+            }
+        }
+    }
+
+    /// Problems that can be overridden by confirming.
+    pub fn is_overridable(&self) -> bool {
+        matches!(self, NameProblem::Exists | NameProblem::MissingDirs(_))
+    }
+}
+
+/// Check whether `original` in `dir` can be renamed to `name`.
+pub fn validate_name(dir: &Path, original: &str, name: &str) -> Option<NameProblem> {
+    if let Some(problem) = check_name(name) {
+        return Some(problem);
+    }
+    if name != original {
+        let target = dir.join(name);
+        if target.symlink_metadata().is_ok() {
+            // on case-insensitive filesystems a case-only rename finds the file itself
+            let same_file = match (target.canonicalize(), dir.join(original).canonicalize()) {
+                (Ok(a), Ok(b)) => a == b,
+                _ => false,
+            };
+            if !same_file {
+                return Some(NameProblem::Exists);
+            }
+        }
+    }
+    None
+}
+
+/// Check a path typed for a new entry in `dir`, like `a/b/c/file.txt`, whose missing
+/// directories are created along with it: every part must be a valid name, and the parts that
+/// exist must be directories.
+pub fn validate_path(dir: &Path, path: &str) -> Option<NameProblem> {
+    let parts: Vec<&str> = path.split(is_separator).collect();
+    if let Some(problem) = parts.iter().find_map(|part| check_name(part)) {
+        return Some(problem);
+    }
+    let (name, parents) = parts.split_last()?;
+    let mut parent = dir.to_path_buf();
+    for (i, part) in parents.iter().enumerate() {
+        parent.push(part);
+        match parent.metadata() {
+            Ok(meta) if meta.is_dir() => {}
+            Ok(_) => {
+                return Some(NameProblem::Invalid(format!("'{part}' is not a directory")));
+            }
+            Err(_) => return Some(NameProblem::MissingDirs(parents.len() - i)),
+        }
+    }
+    validate_name(&parent, "", name)
+}
+
+/// `path` as Windows file functions take it beyond `MAX_PATH` (260 characters): absolute,
+/// with the `\\?\` prefix, or `\\?\UNC\` for a share (`\\server\share`). The prefix
+/// also turns off Windows' parsing of `/`, `.` and `..`, so those are resolved first.
+#[cfg(windows)]
+pub fn long_path(path: &Path) -> PathBuf {
+    use std::ffi::OsString;
+    use std::path::{Component, Prefix};
+
+    let Ok(absolute) = std::path::absolute(path) else {
+        return path.to_path_buf();
+    };
+    let mut components = absolute.components();
+    let mut long = match components.next() {
+        Some(Component::Prefix(prefix)) => match prefix.kind() {
+            Prefix::Disk(_) => OsString::from(r"\\?\"),
+            Prefix::UNC(server, share) => {
+                let mut long = OsString::from(r"\\?\UNC\");
+                long.push(server);
+                long.push(r"\");
+                long.push(share);
+                long.push(r"\");
+                return PathBuf::from(long).join(components.skip(1).collect::<PathBuf>());
+            }
+            // already verbatim, or a device such as `\\.\COM1`
+            _ => return absolute,
+        },
+        _ => return absolute,
+    };
+    long.push(absolute.as_os_str());
+    PathBuf::from(long)
+}
+
+/// Other platforms have no such limit: `path` as it is.
+#[cfg(not(windows))]
+pub fn long_path(path: &Path) -> PathBuf {
+    path.to_path_buf()
+}
+
+/// Whether `c` separates the parts of a typed path.
+pub fn is_separator(c: char) -> bool {
+    c == '/' || (cfg!(windows) && c == '\\')
+}
+
+/// Check that `name` can name an entry on this platform.
+fn check_name(name: &str) -> Option<NameProblem> {
+    if name.is_empty() {
+        return Some(NameProblem::Empty);
+    }
+    if name.contains(is_separator) {
+        return Some(NameProblem::Separator);
+    }
+    if name == "." || name == ".." || name.contains('\0') {
+        return Some(NameProblem::Invalid(format!(
+            "'{name}' is not a valid name"
+        )));
+    }
+    if cfg!(windows) {
+        if let Some(c) = name
+            .chars()
+            .find(|c| "<>:\"|?*".contains(*c) || (*c as u32) < 32)
+        {
+            return Some(NameProblem::Invalid(format!(
+                "'{}' is not allowed on Windows",
+                c.escape_default()
+            )));
+        }
+        if name.ends_with('.') || name.ends_with(' ') {
+            return Some(NameProblem::Invalid(
+                "names cannot end with '.' or ' ' on Windows".into(),
+            ));
+        }
+        let stem = name.split('.').next().unwrap_or(name).to_uppercase();
+        let reserved = matches!(stem.as_str(), "CON" | "PRN" | "AUX" | "NUL")
+            || ((stem.starts_with("COM") || stem.starts_with("LPT"))
+                && stem.len() == 4
+                && stem[3..].chars().all(|c| ('1'..='9').contains(&c)));
+        if reserved {
+            return Some(NameProblem::Invalid(format!(
+                "'{stem}' is a reserved name on Windows"
+            )));
         }
     }
     None
 }
 
+/// Quote `s` for a POSIX shell, leaving plain words untouched.
+pub fn shell_quote(s: &str) -> String {
+    let plain = !s.is_empty()
+        && s.chars()
+            .all(|c| c.is_alphanumeric() || "-_./:@%+=,".contains(c));
+    if plain {
+        s.to_string()
+    } else {
+        format!("'{}'", s.replace('\'', "'\\''"))
+    }
+}
+
+/// Escape a path as in a `file://` URL or a `.trashinfo` file.
+pub fn percent_encode(s: &str) -> String {
+    s.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{b:02X}"),
+        })
+        .collect()
+}
+
+/// `s` with its `%XX` escapes turned back into bytes; `None` if that is not UTF-8.
+pub fn percent_decode(s: &str) -> Option<String> {
+    let bytes = s.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = (bytes[i] == b'%')
+            .then(|| s.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(b) => {
+                decoded.push(b);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8(decoded).ok()
+}
+
+/// Result of comparing two files byte by byte.
+pub struct BinaryDiff {
+    pub len_a: u64,
+    pub len_b: u64,
+    /// Offset of the first differing byte, if the files differ
+    pub first_difference: Option<u64>,
+    /// Number of blocks compared when sampling, and how many of them differed
+    pub sampled_blocks: usize,
+    pub differing_blocks: usize,
+}
+
+const DIFF_BLOCK: usize = 4096;
+const DIFF_SAMPLES: u64 = 256;
+
+/// Compare two files without loading either fully into memory.
+///
+/// The first differing offset is found by streaming both files; the share of
+/// differing blocks is estimated from evenly spaced samples.
+pub fn binary_diff(a: &Path, b: &Path) -> io::Result<BinaryDiff> {
+    let len_a = fs::metadata(a)?.len();
+    let len_b = fs::metadata(b)?.len();
+
+    let mut ra = BufReader::new(File::open(a)?);
+    let mut rb = BufReader::new(File::open(b)?);
+    let mut first_difference = None;
+    let (mut buf_a, mut buf_b) = (vec![0; 64 * 1024], vec![0; 64 * 1024]);
+    let mut pos = 0u64;
+    loop {
+        let na = read_full(&mut ra, &mut buf_a)?;
+        let nb = read_full(&mut rb, &mut buf_b)?;
+        let n = na.min(nb);
+        if let Some(i) = (0..n).find(|&i| buf_a[i] != buf_b[i]) {
+            first_difference = Some(pos + i as u64);
+            break;
+        }
+        if na != nb {
+            first_difference = Some(pos + n as u64);
+            break;
+        }
+        if n == 0 {
+            break;
+        }
+        pos += n as u64;
+    }
+
+    let (mut sampled_blocks, mut differing_blocks) = (0, 0);
+    if first_difference.is_some() {
+        let mut fa = File::open(a)?;
+        let mut fb = File::open(b)?;
+        let longest = len_a.max(len_b);
+        let blocks = longest.div_ceil(DIFF_BLOCK as u64);
+        let step = blocks.div_ceil(DIFF_SAMPLES).max(1);
+        let (mut block_a, mut block_b) = (vec![0; DIFF_BLOCK], vec![0; DIFF_BLOCK]);
+        let mut block = 0;
+        while block < blocks {
+            let offset = block * DIFF_BLOCK as u64;
+            fa.seek(SeekFrom::Start(offset))?;
+            fb.seek(SeekFrom::Start(offset))?;
+            let na = read_full(&mut fa, &mut block_a)?;
+            let nb = read_full(&mut fb, &mut block_b)?;
+            sampled_blocks += 1;
+            if na != nb || block_a[..na] != block_b[..nb] {
+                differing_blocks += 1;
+            }
+            block += step;
+        }
+    }
+
+    Ok(BinaryDiff {
+        len_a,
+        len_b,
+        first_difference,
+        sampled_blocks,
+        differing_blocks,
+    })
+}
+
+/// Fill `buf` as far as possible, stopping early only at end of file.
+fn read_full(r: &mut impl Read, buf: &mut [u8]) -> io::Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match r.read(&mut buf[filled..])? {
+            0 => break,
+            n => filled += n,
+        }
+    }
+    Ok(filled)
+}
+
+/// How files are copied by the task queue.
+#[derive(Clone, Copy)]
+pub struct CopyOptions {
+    /// Give copies the modification and access times of their sources, and directories their
+    /// permissions; file permissions are always copied
+    pub preserve: bool,
+    /// First try to clone the file, sharing its data blocks on copy-on-write file systems
+    pub reflink: bool,
+    /// Naming of copies pasted into the directory of their source
+    pub names: CopyNames,
+    /// Which copies are handed to rsync
+    pub rsync: UseRsync,
+}
+
+/// How a copy pasted next to its source is named, instead of overwriting the source.
+#[derive(Clone, Copy, Default, PartialEq)]
+pub enum CopyNames {
+    /// `name (copy).txt`, `name (copy 2).txt`
+    #[default]
+    Copy,
+    /// `name (1).txt`, `name (2).txt`
+    Number,
+    /// `name - Copy.txt`, `name - Copy (2).txt`, like Windows Explorer
+    Windows,
+}
+
+impl CopyNames {
+    /// The scheme for a `copy_names` setting.
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "copy" => Some(CopyNames::Copy),
+            "number" => Some(CopyNames::Number),
+            "windows" => Some(CopyNames::Windows),
+            _ => None,
+        }
+    }
+
+    /// The name of the `n`th copy (from 1) of an entry named `stem` with `ext` (dot
+    /// included, or empty).
+    fn name(self, stem: &str, ext: &str, n: usize) -> String {
+        match (self, n) {
+            (CopyNames::Copy, 1) => format!("{stem} (copy){ext}"),
+            (CopyNames::Copy, n) => format!("{stem} (copy {n}){ext}"),
+            (CopyNames::Number, n) => format!("{stem} ({n}){ext}"),
+            (CopyNames::Windows, 1) => format!("{stem} - Copy{ext}"),
+            (CopyNames::Windows, n) => format!("{stem} - Copy ({n}){ext}"),
+        }
+    }
+}
+
+/// The first path in `dir` named after `src` by `names` that is not taken. The extension of a
+/// file stays at the end; a directory's name is taken whole.
+pub fn unique_copy_path(src: &Path, dir: &Path, names: CopyNames) -> PathBuf {
+    let name = src
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let (stem, ext) = match src.extension() {
+        Some(ext) if !src.is_dir() => (
+            name[..name.len() - ext.len() - 1].to_string(),
+            format!(".{}", ext.to_string_lossy()),
+        ),
+        _ => (name, String::new()),
+    };
+    (1..)
+        .map(|n| dir.join(names.name(&stem, &ext, n)))
+        .find(|p| p.symlink_metadata().is_err())
+        .expect("an unused name")
+}
+
+/// Whether `a` and `b` name the same entry, e.g. through different case on a
+/// case-insensitive filesystem.
+pub fn same_entry(a: &Path, b: &Path) -> bool {
+    match (a.canonicalize(), b.canonicalize()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false,
+    }
+}
+
+/// Copy the file `src` to `dst`.
+///
+/// The data goes through `fs::copy`, which already uses `copy_file_range` on Linux and
+/// `clonefile` on macOS; with `reflink`, an explicit clone (`FICLONE`) is tried first on Linux.
+pub fn copy_file(src: &Path, dst: &Path, options: CopyOptions) -> io::Result<()> {
+    if !(options.reflink && reflink(src, dst)) {
+        fs::copy(src, dst)?;
+    }
+    if options.preserve {
+        copy_times(src, dst)?;
+    }
+    Ok(())
+}
+
+/// Clone `src` to a new file `dst`; returns whether it worked, leaving no `dst` behind if not.
+#[cfg(target_os = "linux")]
+fn reflink(src: &Path, dst: &Path) -> bool {
+    use std::os::unix::{fs::PermissionsExt, io::AsRawFd};
+
+    let (Ok(source), Ok(meta)) = (File::open(src), fs::metadata(src)) else {
+        return false;
+    };
+    let Ok(dest) = fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(dst)
+    else {
+        return false;
+    };
+    // SAFETY: both descriptors are open for the duration of the call
+    let cloned = unsafe { libc::ioctl(dest.as_raw_fd(), libc::FICLONE, source.as_raw_fd()) } == 0;
+    if !cloned
+        || dest
+            .set_permissions(fs::Permissions::from_mode(meta.permissions().mode()))
+            .is_err()
+    {
+        drop(dest);
+        let _ = fs::remove_file(dst);
+        return false;
+    }
+    true
+}
+
+#[cfg(not(target_os = "linux"))]
+fn reflink(_src: &Path, _dst: &Path) -> bool {
+    false
+}
+
+fn copy_times(src: &Path, dst: &Path) -> io::Result<()> {
+    let meta = fs::metadata(src)?;
+    let times = fs::FileTimes::new()
+        .set_accessed(meta.accessed()?)
+        .set_modified(meta.modified()?);
+    // directories cannot be opened for writing on every platform; read access is enough to
+    // set times on Unix, and Windows needs directories opened with backup semantics
+    let file = if meta.is_dir() {
+        open_dir(dst)?
+    } else {
+        fs::OpenOptions::new().write(true).open(dst)?
+    };
+    file.set_times(times)
+}
+
+#[cfg(unix)]
+fn open_dir(path: &Path) -> io::Result<File> {
+    File::open(path)
+}
+
+#[cfg(windows)]
+fn open_dir(path: &Path) -> io::Result<File> {
+    use std::os::windows::fs::OpenOptionsExt;
+    // FILE_FLAG_BACKUP_SEMANTICS
+    fs::OpenOptions::new()
+        .write(true)
+        .custom_flags(0x0200_0000)
+        .open(path)
+}
+
 /// Recursively copy a directory.
-pub fn copy_dir_recursively(src: &Path, dst: &Path) -> io::Result<()> {
+pub fn copy_dir_recursively(src: &Path, dst: &Path, options: CopyOptions) -> io::Result<()> {
+    copy_dir_with(src, dst, options, &mut |src, dst| {
+        copy_file(src, dst, options)
+    })
+}
+
+/// Recursively copy a directory, handing each file to `copy_one` with the path to copy it to.
+pub fn copy_dir_with(
+    src: &Path,
+    dst: &Path,
+    options: CopyOptions,
+    copy_one: &mut dyn FnMut(&Path, &Path) -> io::Result<()>,
+) -> io::Result<()> {
     fs::create_dir_all(dst)?;
     for entry in fs::read_dir(src)? {
         let entry = entry?;
         let path = entry.path();
         let dst_path = dst.join(entry.file_name());
         if path.is_dir() {
-            copy_dir_recursively(&path, &dst_path)?;
+            copy_dir_with(&path, &dst_path, options, copy_one)?;
         } else {
-            fs::copy(&path, &dst_path)?;
+            copy_one(&path, &dst_path)?;
         }
     }
+    if options.preserve {
+        // after the contents, which would change the modification time again
+        fs::set_permissions(dst, fs::metadata(src)?.permissions())?;
+        copy_times(src, dst)?;
+    }
     Ok(())
 }
 
-/// Simple image-detection by file extension.
+/// Create `dst` as a hard link to `src`, or as a symbolic link to its absolute path.
+pub fn make_link(src: &Path, dst: &Path, hard: bool) -> io::Result<()> {
+    if hard {
+        fs::hard_link(src, dst)
+    } else {
+        symlink(&src.canonicalize()?, dst)
+    }
+}
+
+#[cfg(unix)]
+fn symlink(target: &Path, link: &Path) -> io::Result<()> {
+    std::os::unix::fs::symlink(target, link)
+}
+
+#[cfg(windows)]
+fn symlink(target: &Path, link: &Path) -> io::Result<()> {
+    // Windows has separate links for files and directories
+    if target.is_dir() {
+        std::os::windows::fs::symlink_dir(target, link)
+    } else {
+        std::os::windows::fs::symlink_file(target, link)
+    }
+}
+
+/// Image formats the image viewer can show.
+const VIEWABLE_IMAGES: &[&str] = &[
+    "image/png",
+    "image/jpeg",
+    "image/gif",
+    "image/bmp",
+    "image/tiff",
+    "image/webp",
+    "image/x-icon",
+];
+
+/// Whether `path` is an image the viewer can show, judged by its content, or by its extension
+/// when it cannot be read.
 pub fn is_image(path: &Path) -> bool {
+    match file_type::sniff(path) {
+        Ok(kind) => kind.is_some_and(|k| VIEWABLE_IMAGES.contains(&k.mime)),
+        Err(_) => !path.is_dir() && has_image_extension(path),
+    }
+}
+
+fn has_image_extension(path: &Path) -> bool {
     match path
         .extension()
         .and_then(|e| e.to_str())
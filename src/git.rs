@@ -0,0 +1,220 @@
+use std::{
+    collections::{HashMap, HashSet},
+    ffi::OsString,
+    io::{BufRead, BufReader},
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    sync::mpsc::{self, Receiver},
+    thread,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// Status of an entry relative to the git index and work tree.
+///
+/// Variants are ordered by priority: a directory shows the most important status of its contents.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum GitStatus {
+    Ignored,
+    Untracked,
+    Staged,
+    Modified,
+    Conflicted,
+}
+
+impl GitStatus {
+    /// Porcelain `XY` code to status.
+    fn parse(xy: &[u8]) -> Option<Self> {
+        let (x, y) = (*xy.first()?, *xy.get(1)?);
+        Some(match (x, y) {
+            (b'?', b'?') => GitStatus::Untracked,
+            (b'!', b'!') => GitStatus::Ignored,
+            (b'U', _) | (_, b'U') | (b'A', b'A') | (b'D', b'D') => GitStatus::Conflicted,
+            (_, b' ') => GitStatus::Staged,
+            _ => GitStatus::Modified,
+        })
+    }
+
+    pub fn symbol(self) -> &'static str {
+        match self {
+            GitStatus::Ignored => "!",
+            GitStatus::Untracked => "?",
+            GitStatus::Staged => "S",
+            GitStatus::Modified => "M",
+            GitStatus::Conflicted => "U",
+        }
+    }
+}
+
+/// Git state of one directory: the current branch and the status of its entries by name.
+pub struct GitInfo {
+    pub branch: String,
+    pub statuses: HashMap<OsString, GitStatus>,
+}
+
+/// Query `git status` for `dir` on a background thread.
+///
+/// The receiver yields one `GitInfo`, or nothing if `dir` is not inside a git work tree.
+pub fn spawn_status(dir: PathBuf) -> Receiver<GitInfo> {
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        if let Some(info) = status(&dir) {
+            let _ = tx.send(info);
+        }
+    });
+    rx
+}
+
+fn status(dir: &Path) -> Option<GitInfo> {
+    let root = git(dir, &["rev-parse", "--show-toplevel"])?;
+    let root = PathBuf::from(String::from_utf8_lossy(&root).trim());
+    let prefix = dir.canonicalize().ok()?;
+    let prefix = prefix
+        .strip_prefix(root.canonicalize().ok()?)
+        .ok()?
+        .to_path_buf();
+
+    let output = git(
+        dir,
+        &["status", "--porcelain", "-b", "-z", "--ignored", "--", "."],
+    )?;
+    let mut branch = String::new();
+    let mut statuses: HashMap<OsString, GitStatus> = HashMap::new();
+    let mut records = output.split(|&b| b == 0);
+    while let Some(record) = records.next() {
+        if record.len() < 4 {
+            continue;
+        }
+        let (xy, path) = (&record[..2], String::from_utf8_lossy(&record[3..]));
+        if xy == b"##" {
+            branch = parse_branch(&path);
+            continue;
+        }
+        if xy[0] == b'R' || xy[0] == b'C' {
+            // renames and copies are followed by the original path
+            records.next();
+        }
+        let Some(status) = GitStatus::parse(xy) else {
+            continue;
+        };
+        // attribute the status to the entry of `dir` that contains the path
+        let Ok(rel) = Path::new(path.as_ref()).strip_prefix(&prefix) else {
+            continue;
+        };
+        if let Some(first) = rel.components().next() {
+            let slot = statuses
+                .entry(first.as_os_str().to_os_string())
+                .or_insert(status);
+            *slot = (*slot).max(status);
+        }
+    }
+    Some(GitInfo { branch, statuses })
+}
+
+/// The most recent commit touching an entry.
+#[derive(Clone)]
+pub struct CommitInfo {
+    pub summary: String,
+    pub time: SystemTime,
+}
+
+impl CommitInfo {
+    /// Rough age of the commit, e.g. `3 days ago`.
+    pub fn age(&self, now: SystemTime) -> String {
+        let secs = now
+            .duration_since(self.time)
+            .unwrap_or(Duration::ZERO)
+            .as_secs();
+        let (n, unit) = match secs {
+            0..=59 => return "just now".into(),
+            60..=3599 => (secs / 60, "minute"),
+            3600..=86399 => (secs / 3600, "hour"),
+            86400..=2_591_999 => (secs / 86400, "day"),
+            2_592_000..=31_535_999 => (secs / 2_592_000, "month"),
+            _ => (secs / 31_536_000, "year"),
+        };
+        format!("{n} {unit}{} ago", if n == 1 { "" } else { "s" })
+    }
+}
+
+/// Find the last commit touching each of `names` in `dir` on a background thread.
+///
+/// The history is walked newest first and the walk stops once every name has been seen, so
+/// entries that were never committed cost a full walk but recent ones are found quickly.
+pub fn spawn_last_commits(
+    dir: PathBuf,
+    names: Vec<OsString>,
+) -> Receiver<HashMap<OsString, CommitInfo>> {
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        if let Some(commits) = last_commits(&dir, names) {
+            let _ = tx.send(commits);
+        }
+    });
+    rx
+}
+
+fn last_commits(dir: &Path, names: Vec<OsString>) -> Option<HashMap<OsString, CommitInfo>> {
+    let mut wanted: HashSet<OsString> = names.into_iter().collect();
+    let mut child = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args([
+            "log",
+            "--format=%x01%ct %s",
+            "--name-only",
+            "--relative",
+            "--",
+            ".",
+        ])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .ok()?;
+    let stdout = child.stdout.take()?;
+    let mut commits = HashMap::new();
+    let mut current: Option<CommitInfo> = None;
+    for line in BufReader::new(stdout).lines() {
+        let Ok(line) = line else {
+            break;
+        };
+        if let Some(header) = line.strip_prefix('\u{1}') {
+            let (time, summary) = header.split_once(' ').unwrap_or((header, ""));
+            current = time.parse().ok().map(|secs| CommitInfo {
+                summary: summary.to_string(),
+                time: UNIX_EPOCH + Duration::from_secs(secs),
+            });
+        } else if let (Some(commit), Some(first)) = (&current, Path::new(&line).components().next())
+        {
+            let name = first.as_os_str().to_os_string();
+            if wanted.remove(&name) {
+                commits.insert(name, commit.clone());
+                if wanted.is_empty() {
+                    break;
+                }
+            }
+        }
+    }
+    let _ = child.kill();
+    let _ = child.wait();
+    Some(commits)
+}
+
+/// Branch name from a porcelain `## branch...upstream` header.
+fn parse_branch(header: &str) -> String {
+    let header = header
+        .strip_prefix("No commits yet on ")
+        .or_else(|| header.strip_prefix("Initial commit on "))
+        .unwrap_or(header);
+    let name = header.split("...").next().unwrap_or(header);
+    name.split(' ').next().unwrap_or(name).to_string()
+}
+
+fn git(dir: &Path, args: &[&str]) -> Option<Vec<u8>> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        .output()
+        .ok()?;
+    output.status.success().then_some(output.stdout)
+}
@@ -0,0 +1,82 @@
+use std::time::SystemTime;
+
+use ratatui::style::Color;
+
+use crate::vfs::Entry;
+
+/// Entries changed longer ago than this are as cold as they get.
+const COLDEST_AGE_SECS: f64 = 365.0 * 24.0 * 3600.0;
+
+/// What the names in a listing are tinted by, to spot huge or freshly changed files.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Heat {
+    Off,
+    /// Larger files are hotter
+    Size,
+    /// Recently modified entries are hotter
+    Age,
+}
+
+impl Heat {
+    pub fn next(self) -> Self {
+        match self {
+            Heat::Off => Heat::Size,
+            Heat::Size => Heat::Age,
+            Heat::Age => Heat::Off,
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "off" => Some(Heat::Off),
+            "size" => Some(Heat::Size),
+            "age" => Some(Heat::Age),
+            _ => None,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Heat::Off => "off",
+            Heat::Size => "size",
+            Heat::Age => "age",
+        }
+    }
+
+    /// How hot `entry` is, from 0 to 1: its size on a log scale up to `largest`, the largest
+    /// file listed, or how recently it was modified, up to a year ago. Directories have no
+    /// size heat, and nothing has any with `Off`.
+    pub fn level(self, entry: &Entry, largest: u64, now: SystemTime) -> Option<f64> {
+        match self {
+            Heat::Off => None,
+            Heat::Size if entry.is_dir() => None,
+            Heat::Size => {
+                let largest = (largest as f64).ln_1p();
+                Some(if largest > 0.0 {
+                    (entry.len() as f64).ln_1p() / largest
+                } else {
+                    0.0
+                })
+            }
+            Heat::Age => {
+                let age = now.duration_since(entry.modified()?).unwrap_or_default();
+                let cold =
+                    (age.as_secs_f64() / 3600.0).ln_1p() / (COLDEST_AGE_SECS / 3600.0).ln_1p();
+                Some(1.0 - cold.min(1.0))
+            }
+        }
+    }
+}
+
+/// The color `level` of the way from `cold` to `hot`: blended when both are `#rrggbb`, and
+/// otherwise whichever is nearer.
+pub fn blend(cold: Color, hot: Color, level: f64) -> Color {
+    match (cold, hot) {
+        (Color::Rgb(r1, g1, b1), Color::Rgb(r2, g2, b2)) => {
+            let mix = |a: u8, b: u8| (a as f64 + (b as f64 - a as f64) * level).round() as u8;
+            Color::Rgb(mix(r1, r2), mix(g1, g2), mix(b1, b2))
+        }
+        _ if level < 0.5 => cold,
+        _ => hot,
+    }
+}
@@ -0,0 +1,182 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use image::{imageops::FilterType, DynamicImage, GenericImageView};
+
+use crate::exif;
+
+/// Entries of the image actions popup, in the order of `ImageOp::from_menu`.
+pub static IMAGE_ACTIONS: &[&str] = &[
+    "Convert to PNG",
+    "Convert to JPEG",
+    "Convert to BMP",
+    "Convert to TIFF",
+    "Resize to width...",
+    "Strip metadata",
+];
+
+/// A batch operation on image files, run on the task queue.
+#[derive(Clone, Copy)]
+pub enum ImageOp {
+    /// Write a copy in the format of this extension next to the original
+    Convert(&'static str),
+    /// Write a copy scaled down to this many pixels wide, named e.g. `photo-800w.jpg`
+    Resize(u32),
+    /// Remove EXIF and other metadata in place
+    Strip,
+}
+
+impl ImageOp {
+    /// The action at `index` of `IMAGE_ACTIONS`; `None` for resizing, which needs a width.
+    pub fn from_menu(index: usize) -> Option<Self> {
+        Some(match index {
+            0 => ImageOp::Convert("png"),
+            1 => ImageOp::Convert("jpg"),
+            2 => ImageOp::Convert("bmp"),
+            3 => ImageOp::Convert("tiff"),
+            5 => ImageOp::Strip,
+            _ => return None,
+        })
+    }
+
+    pub fn describe(&self, count: usize) -> String {
+        match self {
+            ImageOp::Convert(ext) => format!("convert {count} image(s) to {ext}"),
+            ImageOp::Resize(width) => format!("resize {count} image(s) to {width} px wide"),
+            ImageOp::Strip => format!("strip metadata from {count} image(s)"),
+        }
+    }
+
+    /// Apply the operation to the image at `path`.
+    pub fn apply(&self, path: &Path) -> io::Result<()> {
+        match *self {
+            ImageOp::Convert(ext) => {
+                let dest = path.with_extension(ext);
+                if dest == path {
+                    return Ok(());
+                }
+                save(&open(path)?, &dest)
+            }
+            ImageOp::Resize(width) => {
+                let img = open(path)?;
+                // never scale up
+                if img.width() <= width {
+                    return Ok(());
+                }
+                let height = (u64::from(img.height()) * u64::from(width) / u64::from(img.width()))
+                    .max(1) as u32;
+                let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+                let mut name = format!("{stem}-{width}w");
+                if let Some(ext) = path.extension() {
+                    name = format!("{name}.{}", ext.to_string_lossy());
+                }
+                let resized = img.resize_exact(width, height, FilterType::Lanczos3);
+                save(&resized, &path.with_file_name(name))
+            }
+            ImageOp::Strip => strip(path),
+        }
+    }
+}
+
+/// Decode the image at `path`, turned upright by its EXIF orientation, which re-encoding drops.
+fn open(path: &Path) -> io::Result<DynamicImage> {
+    let img = image::open(path).map_err(io::Error::other)?;
+    Ok(match exif::read(path).and_then(|e| e.orientation) {
+        Some(orientation) => exif::orient(img, orientation),
+        None => img,
+    })
+}
+
+fn save(img: &DynamicImage, dest: &Path) -> io::Result<()> {
+    // JPEG has no alpha channel
+    let is_jpeg = dest
+        .extension()
+        .is_some_and(|e| e.eq_ignore_ascii_case("jpg") || e.eq_ignore_ascii_case("jpeg"));
+    let result = if is_jpeg && img.color().has_alpha() {
+        DynamicImage::ImageRgb8(img.to_rgb8()).save(dest)
+    } else {
+        img.save(dest)
+    };
+    result.map_err(io::Error::other)
+}
+
+/// Remove metadata from the image at `path`, replacing it through a temporary file.
+///
+/// JPEG and PNG files keep their image data byte for byte; other formats are re-encoded.
+fn strip(path: &Path) -> io::Result<()> {
+    let data = fs::read(path)?;
+    let upright = exif::read(path)
+        .and_then(|e| e.orientation)
+        .is_none_or(|o| o == 1);
+    let stripped = if data.starts_with(&[0xff, 0xd8]) && upright {
+        strip_jpeg(&data)
+    } else if data.starts_with(b"\x89PNG\r\n\x1a\n") {
+        strip_png(&data)
+    } else {
+        None
+    };
+    let temp = temp_sibling(path);
+    match stripped {
+        Some(bytes) => fs::write(&temp, bytes)?,
+        // the extension of `temp` would not tell `save` the format
+        None => {
+            let format = image::ImageFormat::from_path(path).map_err(io::Error::other)?;
+            open(path)?
+                .save_with_format(&temp, format)
+                .map_err(io::Error::other)?
+        }
+    }
+    fs::rename(&temp, path)
+}
+
+fn temp_sibling(path: &Path) -> PathBuf {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    path.with_file_name(format!(".{name}.kura-tmp"))
+}
+
+/// `data` without its EXIF, XMP, IPTC and comment segments; `None` if it is malformed.
+fn strip_jpeg(data: &[u8]) -> Option<Vec<u8>> {
+    let mut out = data[..2].to_vec();
+    let mut pos = 2;
+    loop {
+        let &[0xff, marker] = data.get(pos..pos + 2)? else {
+            return None;
+        };
+        // start of scan: the entropy-coded data runs to the end
+        if marker == 0xda {
+            out.extend_from_slice(&data[pos..]);
+            return Some(out);
+        }
+        let len = usize::from(u16::from_be_bytes(
+            data.get(pos + 2..pos + 4)?.try_into().ok()?,
+        ));
+        let segment = data.get(pos..pos + 2 + len)?;
+        // APP1 holds EXIF and XMP, APP13 IPTC; the ICC profile (APP2) and Adobe colour
+        // transform (APP14) are needed to show the colours right
+        if !matches!(marker, 0xe1 | 0xed | 0xfe) {
+            out.extend_from_slice(segment);
+        }
+        pos += 2 + len;
+    }
+}
+
+/// `data` without its text, time and EXIF chunks; `None` if it is malformed.
+fn strip_png(data: &[u8]) -> Option<Vec<u8>> {
+    let mut out = data[..8].to_vec();
+    let mut pos = 8;
+    while pos < data.len() {
+        let len = u32::from_be_bytes(data.get(pos..pos + 4)?.try_into().ok()?) as usize;
+        // length, type, data and CRC
+        let chunk = data.get(pos..pos + 12 + len)?;
+        if !matches!(
+            &chunk[4..8],
+            b"tEXt" | b"zTXt" | b"iTXt" | b"eXIf" | b"tIME"
+        ) {
+            out.extend_from_slice(chunk);
+        }
+        pos += 12 + len;
+    }
+    Some(out)
+}
@@ -1,41 +1,69 @@
 use crossterm::{
-    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode},
+    cursor::MoveTo,
+    event::{
+        self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEvent, KeyModifiers,
+        MouseButton, MouseEvent, MouseEventKind,
+    },
     execute,
-    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+    terminal::{
+        disable_raw_mode, enable_raw_mode, Clear, ClearType, EnterAlternateScreen,
+        LeaveAlternateScreen,
+    },
 };
 use ratatui::{backend::Backend, Terminal};
+use std::process::Command;
 use std::time::Duration;
 use std::{fs, io, io::Write, path::Path, path::PathBuf};
-use viuer::{print_from_file, Config};
+use viuer::{print, print_from_file, Config};
+
+use kura_core::actions::{
+    apply_paste, apply_sync, apply_transfer, archive_selection, bulk_rename, compare_binary,
+    compare_directories, copy_as_arguments, copy_size, create_entry, delete_items, edit_xattr,
+    extract_selection, find_hard_links, inspect, invert_marks, mark_all, mark_by_pattern,
+    mark_where, move_clipboard, paste, paste_as, paste_as_name, paste_links, preview_sync,
+    remove_quarantine, request_delete, request_shred, resume_copy, run_for_each, run_script,
+    search, search_hard_links, shred_items, toggle_basket, toggle_mark, transfer_basket,
+    transfer_to_other_pane, trash_items, validate_new_entry,
+};
+use kura_core::app::{App, Pane, Picker, ViewAlign};
+use kura_core::archive::Extraction;
+use kura_core::config::UserCommand;
+use kura_core::elevate::Privileged;
+use kura_core::exif;
+use kura_core::fs_utils::{
+    apply_sort, is_image, validate_name, validate_path, SortBy, SortSpec, SORT_OPTIONS,
+};
+use kura_core::image_ops::{ImageOp, IMAGE_ACTIONS};
+use kura_core::keys::{KeyCombo, KeySequence, Keymap, Lookup, PendingCommand};
+use kura_core::line_edit::LineEditor;
+use kura_core::links::{LinkScan, LinkSearch};
+use kura_core::media;
+use kura_core::mode::{Mode, PaneType, TypeToConfirm};
+use kura_core::notification::Notifications;
+use kura_core::predicate::Predicate;
+use kura_core::registers::Registers;
+use kura_core::runner::shell_command;
+use kura_core::tasks::JobKind;
+use kura_core::todo_scan::TodoList;
+use kura_core::vfs::EntryChange;
+use kura_core::viewer::Viewer;
+use tracing::{debug, warn};
 
-use crate::actions::{copy_selection, delete_items, paste, toggle_mark};
-use crate::app::App;
-use crate::fs_utils::{apply_sort, find_match, is_image, SortBy, SORT_OPTIONS};
-use crate::mode::{Mode, PaneType};
+use crate::ui::{breadcrumb_at, breadcrumbs};
 
-/// Display the image at `path` using `viuer` and wait for Enter to return.
-pub fn show_image<B: Backend + Write>(terminal: &mut Terminal<B>, path: &Path) -> io::Result<()> {
+/// Leave raw mode and the alternate screen so an external program can use the terminal.
+pub fn suspend_tui<B: Backend + Write>(terminal: &mut Terminal<B>) -> io::Result<()> {
     disable_raw_mode()?;
     execute!(
         terminal.backend_mut(),
         LeaveAlternateScreen,
         DisableMouseCapture
     )?;
-    let config = Config::default();
-    if let Err(err) = print_from_file(path.to_string_lossy().as_ref(), &config) {
-        eprintln!("Failed to display image: {}", err);
-    }
+    Ok(())
+}
 
-    loop {
-        if event::poll(Duration::from_millis(100))? {
-            if let Event::Key(key) = event::read()? {
-                if key.code == KeyCode::Enter {
-                    break;
-                }
-            }
-        }
-    }
-    // restore alternate screen and clear it before redrawing the UI
+/// Restore the alternate screen and raw mode after `suspend_tui`, clearing it before redrawing.
+pub fn resume_tui<B: Backend + Write>(terminal: &mut Terminal<B>) -> io::Result<()> {
     execute!(
         terminal.backend_mut(),
         EnterAlternateScreen,
@@ -47,328 +75,2261 @@ pub fn show_image<B: Backend + Write>(terminal: &mut Terminal<B>, path: &Path) -
     Ok(())
 }
 
+/// Where the main loop gets its events: the terminal, or scripted events in tests.
+pub trait InputSource {
+    /// The next event, or `None` if none arrived within `timeout`.
+    fn next_event(&mut self, timeout: Duration) -> io::Result<Option<Event>>;
+}
+
+/// Events typed in the terminal.
+pub struct TerminalInput;
+
+impl InputSource for TerminalInput {
+    fn next_event(&mut self, timeout: Duration) -> io::Result<Option<Event>> {
+        if event::poll(timeout)? {
+            event::read().map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+/// Display `images[index]` using `viuer` under a caption with its name, resolution and position.
+/// `→`/`n` and `←`/`p` step through the other images; Enter or Esc returns.
+pub fn show_images<B: Backend + Write>(
+    terminal: &mut Terminal<B>,
+    input: &mut dyn InputSource,
+    images: &[PathBuf],
+    mut index: usize,
+) -> io::Result<()> {
+    suspend_tui(terminal)?;
+    // leave the first row for the caption
+    let config = Config {
+        y: 1,
+        ..Config::default()
+    };
+    let mut stdout = io::stdout();
+    while let Some(path) = images.get(index) {
+        execute!(stdout, Clear(ClearType::All), MoveTo(0, 0))?;
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        let orientation = exif::read(path)
+            .and_then(|e| e.orientation)
+            .filter(|&o| o != 1);
+        let resolution = match image::image_dimensions(path) {
+            Ok((w, h)) if orientation.is_some_and(exif::is_sideways) => format!("{h}x{w}"),
+            Ok((w, h)) => format!("{w}x{h}"),
+            Err(_) => "?".to_string(),
+        };
+        write!(
+            stdout,
+            "{name}  {resolution}  {}/{}",
+            index + 1,
+            images.len()
+        )?;
+        stdout.flush()?;
+        let printed = match orientation {
+            // sideways phone photos are stored as shot and turned by the orientation tag
+            Some(orientation) => image::open(path)
+                .map_err(|e| e.to_string())
+                .and_then(|img| {
+                    print(&exif::orient(img, orientation), &config).map_err(|e| e.to_string())
+                }),
+            None => {
+                print_from_file(path.to_string_lossy().as_ref(), &config).map_err(|e| e.to_string())
+            }
+        };
+        // shown in place of the image, since the TUI is not up to take a notification
+        if let Err(err) = printed {
+            write!(stdout, "\r\ncannot display image: {err}")?;
+            stdout.flush()?;
+        }
+
+        // raw mode only while waiting, so arrow keys arrive without Enter
+        enable_raw_mode()?;
+        let key = loop {
+            if let Some(Event::Key(key)) = input.next_event(Duration::from_millis(100))? {
+                break key.code;
+            }
+        };
+        disable_raw_mode()?;
+        match key {
+            KeyCode::Right | KeyCode::Char('n') | KeyCode::Char('l') => {
+                index = (index + 1).min(images.len() - 1);
+            }
+            KeyCode::Left | KeyCode::Char('p') | KeyCode::Char('h') => {
+                index = index.saturating_sub(1);
+            }
+            KeyCode::Enter | KeyCode::Esc => break,
+            _ => {}
+        }
+    }
+    resume_tui(terminal)
+}
+
+/// Run `cmd` with the terminal handed over to it, returning to the UI when it exits.
+pub fn run_in_terminal<B: Backend + Write>(
+    terminal: &mut Terminal<B>,
+    notifications: &mut Notifications,
+    cmd: &mut Command,
+) -> io::Result<()> {
+    suspend_tui(terminal)?;
+    let result = cmd.status();
+    resume_tui(terminal)?;
+    if let Err(err) = result {
+        notifications.error(format!(
+            "cannot run {}: {err}",
+            cmd.get_program().to_string_lossy()
+        ));
+    }
+    Ok(())
+}
+
+/// Run `retry` as root through `elevate_with` on the terminal, where `sudo` or `pkexec` can ask
+/// for a password, then reload the panes.
+fn run_elevated<B: Backend + Write>(
+    terminal: &mut Terminal<B>,
+    app: &mut App,
+    retry: &Privileged,
+) -> io::Result<()> {
+    let Some(mut command) = retry.command(&app.config.elevate_with) else {
+        return Ok(());
+    };
+    suspend_tui(terminal)?;
+    println!("{}", retry.command_line(&app.config.elevate_with));
+    let status = command.status();
+    resume_tui(terminal)?;
+    match status {
+        Ok(status) if status.success() => app
+            .notifications
+            .info(format!("{} as root: done", retry.describe())),
+        Ok(status) => app
+            .notifications
+            .error(format!("{} as root: {status}", retry.describe())),
+        Err(e) => app.notifications.error(format!(
+            "cannot run {}: {e}",
+            command.get_program().to_string_lossy()
+        )),
+    }
+    app.refresh_panes();
+    Ok(())
+}
+
+/// Spawn the user's shell in `dir` and wait for it to exit.
+pub fn open_shell<B: Backend + Write>(
+    terminal: &mut Terminal<B>,
+    notifications: &mut Notifications,
+    dir: &Path,
+) -> io::Result<()> {
+    let shell = std::env::var("SHELL").unwrap_or_else(|_| {
+        if cfg!(windows) {
+            std::env::var("COMSPEC").unwrap_or_else(|_| "cmd.exe".into())
+        } else {
+            "/bin/sh".into()
+        }
+    });
+    run_in_terminal(
+        terminal,
+        notifications,
+        Command::new(shell).current_dir(dir),
+    )
+}
+
+/// Launch the configured diff tool on two paths.
+pub fn open_diff_tool<B: Backend + Write>(
+    terminal: &mut Terminal<B>,
+    notifications: &mut Notifications,
+    tool: &str,
+    a: &Path,
+    b: &Path,
+) -> io::Result<()> {
+    let mut words = tool.split_whitespace();
+    let Some(program) = words.next() else {
+        return Ok(());
+    };
+    run_in_terminal(
+        terminal,
+        notifications,
+        Command::new(program).args(words).arg(a).arg(b),
+    )
+}
+
+/// Open `paths` in `$VISUAL`/`$EDITOR` and wait for it to exit.
+pub fn run_editor<B: Backend + Write>(
+    terminal: &mut Terminal<B>,
+    notifications: &mut Notifications,
+    paths: &[PathBuf],
+) -> io::Result<()> {
+    let editor = std::env::var("VISUAL")
+        .or_else(|_| std::env::var("EDITOR"))
+        .unwrap_or_else(|_| if cfg!(windows) { "notepad" } else { "vi" }.into());
+    let mut words = editor.split_whitespace();
+    match words.next() {
+        Some(program) => run_in_terminal(
+            terminal,
+            notifications,
+            Command::new(program).args(words).args(paths),
+        ),
+        None => Ok(()),
+    }
+}
+
+/// Open the selected files in the parent Neovim when there is one, or else in the editor.
+pub fn open_in_editor<B: Backend + Write>(
+    terminal: &mut Terminal<B>,
+    app: &mut App,
+) -> io::Result<()> {
+    let files: Vec<PathBuf> = app
+        .current_pane()
+        .selection()
+        .into_iter()
+        .filter(|p| !p.is_dir())
+        .collect();
+    if files.is_empty() {
+        return Ok(());
+    }
+    app.current_pane_mut().marked.clear();
+    let Some(server) = kura_core::nvim::server(&app.config) else {
+        run_editor(terminal, &mut app.notifications, &files)?;
+        app.refresh_panes();
+        return Ok(());
+    };
+    if let Err(e) = files
+        .iter()
+        .try_for_each(|f| kura_core::nvim::open(&server, f))
+    {
+        app.mode = Mode::Message {
+            title: "Neovim".into(),
+            lines: vec![format!("Could not open in Neovim at {server}: {e}")],
+        };
+    }
+    Ok(())
+}
+
+/// Let the user edit `names` in `$VISUAL`/`$EDITOR`, one per line, and return the edited lines.
+pub fn edit_names<B: Backend + Write>(
+    terminal: &mut Terminal<B>,
+    notifications: &mut Notifications,
+    names: &[String],
+) -> io::Result<Vec<String>> {
+    let path = std::env::temp_dir().join(format!("kura-rename-{}.txt", std::process::id()));
+    fs::write(&path, names.join("\n") + "\n")?;
+    run_editor(terminal, notifications, std::slice::from_ref(&path))?;
+    let edited = fs::read_to_string(&path);
+    let _ = fs::remove_file(&path);
+    Ok(edited?
+        .lines()
+        .map(|l| l.trim_end_matches('\r').to_string())
+        .filter(|l| !l.is_empty())
+        .collect())
+}
+
+/// Run a custom command from the config on the selection of the active pane.
+pub fn run_user_command<B: Backend + Write>(
+    terminal: &mut Terminal<B>,
+    app: &mut App,
+    command: &UserCommand,
+) -> io::Result<()> {
+    let selection = app.current_pane_mut().selection();
+    let dir = app.current_pane().current_dir.clone();
+    let line = command.expand(&selection, &dir, &app.other_pane().current_dir);
+    let mut shell = shell_command(&line);
+    shell.current_dir(&dir);
+    app.current_pane_mut().marked.clear();
+    if command.output {
+        match shell.output() {
+            Ok(output) => {
+                let path =
+                    std::env::temp_dir().join(format!("kura-output-{}.txt", std::process::id()));
+                let mut text = output.stdout;
+                text.extend_from_slice(&output.stderr);
+                fs::write(&path, text)?;
+                if let Some(mut viewer) = Viewer::open(&path) {
+                    viewer.title = format!("{} ({})", command.name, output.status);
+                    app.mode = Mode::Viewer(viewer);
+                }
+            }
+            Err(e) => {
+                app.mode = Mode::Message {
+                    title: command.name.clone(),
+                    lines: vec![format!("Failed to run '{line}': {e}")],
+                };
+            }
+        }
+    } else {
+        run_in_terminal(terminal, &mut app.notifications, &mut shell)?;
+    }
+    if command.refresh {
+        app.refresh_panes();
+    }
+    Ok(())
+}
+
+/// Filer keys that still work while the active pane shows a remote directory.
+const REMOTE_KEYS: &str = "jkhliEWmb|<>vV/nNfrsLtA*+-][OT.#aezwgGxX";
+
+/// Filer keys that compare or sync the two panes, which needs both to be local.
+const BOTH_LOCAL_KEYS: &str = "UC=DB";
+
+/// Whether `key` is a filer command that only works on the local file system and a pane
+/// involved in it is remote.
+fn blocked_by_remote(app: &App, key: KeyCombo) -> bool {
+    // Ctrl and Alt keys either move around the listing or run the user's bindings, which are
+    // checked when they run
+    let (KeyCode::Char(c), true) = (key.code, key.is_plain()) else {
+        return false;
+    };
+    if !app.current_pane().is_local() {
+        return !REMOTE_KEYS.contains(c);
+    }
+    !app.other_pane().is_local() && BOTH_LOCAL_KEYS.contains(c)
+}
+
+/// Columns moved by one `h`/`l` press in the unwrapped text viewer.
+const HSCROLL_STEP: usize = 8;
+
+/// What a key sequence in the keymaps of `run_app` does.
+enum Binding {
+    Top,
+    Bottom,
+    Align(ViewAlign),
+    Jump,
+    /// Scroll by half a page (Ctrl-d/u) or a page (Ctrl-f/b)
+    Page {
+        down: bool,
+        half: bool,
+    },
+    /// Paste the yanked entries as symbolic links, or hard links
+    PasteLinks {
+        hard: bool,
+    },
+    /// Paste the single yanked entry under a new name
+    PasteAs,
+    /// Show or hide the directory entry counts
+    Counts,
+    /// Compare the directories of the two panes
+    CompareDirs,
+    /// Go back or forward in the jump list
+    JumpList {
+        back: bool,
+    },
+    /// Pick a directory on the path in the pane title
+    Breadcrumbs,
+    /// Create a file or directory
+    Create,
+    /// Remember the view settings for the current directory, or forget them
+    RememberView,
+    /// Tint names by size, then age, then not at all
+    Heat,
+    /// Switch between the two panes and the column layout
+    Columns,
+    /// Run a command for each selected entry
+    ForEach,
+    /// Find a file below the current directory
+    Find,
+    /// Show the entry under the cursor in the desktop's file manager
+    Reveal,
+    /// Send the selected entries to a share target from the config
+    Share,
+    /// Unpack the selected archives
+    Extract,
+    /// Overwrite the selected entries before deleting them
+    Shred,
+    /// Check the detached signature of the file under the cursor
+    Verify,
+    /// Run a command whenever the directory under the cursor changes, or stop doing so
+    Watch,
+    /// List the broken links under the current directory
+    BrokenLinks,
+    /// List the other names of the file under the cursor
+    HardLinks,
+    /// Mark (or unmark) the entries meeting typed conditions
+    MarkWhere {
+        mark: bool,
+    },
+    /// Custom command, by index in the config
+    Command(usize),
+    /// Plugin function bound to these keys
+    Script(Vec<KeyCombo>),
+}
+
+/// What `d`, `y` and `c` do to the entries covered by their motion.
+#[derive(Clone, Copy)]
+enum Operator {
+    Trash,
+    Yank,
+    Rename,
+}
+
+impl Operator {
+    fn key(self) -> char {
+        match self {
+            Operator::Trash => 'd',
+            Operator::Yank => 'y',
+            Operator::Rename => 'c',
+        }
+    }
+}
+
+/// Modes that take a count and the `gg`/`G` motions.
+fn is_navigable(mode: &Mode) -> bool {
+    matches!(
+        mode,
+        Mode::Filer
+            | Mode::Viewer(_)
+            | Mode::Compare { .. }
+            | Mode::Diff(_)
+            | Mode::LogViewer(_)
+            | Mode::Todos(_)
+            | Mode::Table(_)
+            | Mode::Tree(_)
+    ) && !mode.is_prompt()
+}
+
+/// Read a key answering a y/N popup, or typing the name it asks for when `protected`:
+/// `Some(true)` to go ahead, `Some(false)` to cancel and `None` to keep asking.
+fn confirmation(protected: &mut Option<TypeToConfirm>, key: KeyEvent) -> Option<bool> {
+    match (key.code, protected) {
+        (KeyCode::Esc, _) => Some(false),
+        (KeyCode::Enter, Some(protected)) => protected.is_typed().then_some(true),
+        (_, Some(protected)) => {
+            protected.buffer.handle(key);
+            None
+        }
+        (KeyCode::Char('y') | KeyCode::Enter, None) => Some(true),
+        (KeyCode::Char('n'), None) => Some(false),
+        _ => None,
+    }
+}
+
+/// `gg` and `G`, shared by the viewers and lists.
+fn motion_keymap() -> Keymap<Binding> {
+    let mut keymap = Keymap::default();
+    keymap.bind(vec![KeyCombo::char('g'); 2], Binding::Top);
+    keymap.bind(vec![KeyCombo::char('G')], Binding::Bottom);
+    keymap
+}
+
+/// Filer key sequences that are not a single plain key: kura's own, then the custom commands
+/// and plugin functions, which cannot take the place of kura's.
+fn filer_keymap(app: &App) -> Keymap<Binding> {
+    let mut keymap = Keymap::default();
+    for keys in app.scripts.bound_keys() {
+        keymap.bind(keys.clone(), Binding::Script(keys));
+    }
+    for (i, command) in app.config.commands.iter().enumerate() {
+        if !command.keys.is_empty() {
+            keymap.bind(command.keys.clone(), Binding::Command(i));
+        }
+    }
+    keymap.bind(vec![KeyCombo::char('g'); 2], Binding::Top);
+    keymap.bind(vec![KeyCombo::char('G')], Binding::Bottom);
+    let z = |c| vec![KeyCombo::char('z'), KeyCombo::char(c)];
+    keymap.bind(z('z'), Binding::Align(ViewAlign::Center));
+    keymap.bind(z('t'), Binding::Align(ViewAlign::Top));
+    keymap.bind(z('b'), Binding::Align(ViewAlign::Bottom));
+    keymap.bind(z('j'), Binding::Jump);
+    let g = |c| vec![KeyCombo::char('g'), KeyCombo::char(c)];
+    keymap.bind(g('l'), Binding::PasteLinks { hard: false });
+    keymap.bind(g('L'), Binding::PasteLinks { hard: true });
+    keymap.bind(g('p'), Binding::PasteAs);
+    keymap.bind(g('c'), Binding::Counts);
+    keymap.bind(g('='), Binding::CompareDirs);
+    keymap.bind(g('b'), Binding::Breadcrumbs);
+    keymap.bind(g('n'), Binding::Create);
+    keymap.bind(g('v'), Binding::RememberView);
+    keymap.bind(g('h'), Binding::Heat);
+    keymap.bind(g('m'), Binding::Columns);
+    keymap.bind(g('!'), Binding::ForEach);
+    keymap.bind(g('f'), Binding::Find);
+    keymap.bind(g('r'), Binding::Reveal);
+    keymap.bind(g('s'), Binding::Share);
+    keymap.bind(g('x'), Binding::Extract);
+    keymap.bind(g('w'), Binding::Shred);
+    keymap.bind(g('V'), Binding::Verify);
+    keymap.bind(g('W'), Binding::Watch);
+    keymap.bind(g('B'), Binding::BrokenLinks);
+    keymap.bind(g('i'), Binding::HardLinks);
+    keymap.bind(g('+'), Binding::MarkWhere { mark: true });
+    keymap.bind(g('-'), Binding::MarkWhere { mark: false });
+    keymap.bind(vec![KeyCombo::ctrl('o')], Binding::JumpList { back: true });
+    keymap.bind(vec![KeyCombo::ctrl('i')], Binding::JumpList { back: false });
+    for (c, down, half) in [
+        ('d', true, true),
+        ('u', false, true),
+        ('f', true, false),
+        ('b', false, false),
+    ] {
+        keymap.bind(vec![KeyCombo::ctrl(c)], Binding::Page { down, half });
+    }
+    keymap
+}
+
+/// Move to the first or last line or entry of whatever the mode shows.
+fn go_to_end(app: &mut App, top: bool) {
+    match &mut app.mode {
+        Mode::Filer => {
+            let pane = app.current_pane_mut();
+            if top {
+                pane.selected = 0;
+            } else {
+                pane.selected = pane.items.len().saturating_sub(1);
+                while pane.is_hidden(pane.selected) {
+                    pane.selected -= 1;
+                }
+            }
+        }
+        Mode::Viewer(viewer) if top => viewer.go_top(),
+        Mode::Viewer(viewer) => viewer.go_bottom(),
+        Mode::Diff(diff) if top => diff.go_top(),
+        Mode::Diff(diff) => diff.go_bottom(),
+        Mode::LogViewer(log) if top => log.go_top(),
+        Mode::LogViewer(log) => log.go_bottom(),
+        Mode::Todos(list) if top => list.go_top(),
+        Mode::Todos(list) => list.go_bottom(),
+        Mode::Table(table) if top => table.go_top(),
+        Mode::Table(table) => table.go_bottom(),
+        Mode::Tree(tree) if top => tree.go_top(),
+        Mode::Tree(tree) => tree.go_bottom(),
+        Mode::Compare { left, right, sync } => {
+            let mut targets = Vec::new();
+            if *sync || app.active == PaneType::Left {
+                targets.push(left);
+            }
+            if *sync || app.active == PaneType::Right {
+                targets.push(right);
+            }
+            for viewer in targets {
+                if top {
+                    viewer.go_top();
+                } else {
+                    viewer.go_bottom();
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+/// The entries from the cursor to where `motion` takes it, in listing order and without those
+/// folded away; the cursor ends on the first of them, as after an operator in Vim.
+fn motion_range(app: &mut App, motion: impl FnOnce(&mut App)) -> Vec<PathBuf> {
+    let start = app.current_pane().selected;
+    motion(app);
+    let pane = app.current_pane_mut();
+    let (first, last) = (start.min(pane.selected), start.max(pane.selected));
+    pane.selected = first;
+    (first..=last)
+        .filter(|&i| !pane.is_hidden(i))
+        .filter_map(|i| pane.items.get(i))
+        .map(|e| e.path())
+        .collect()
+}
+
+fn apply_operator<B: Backend + Write>(
+    terminal: &mut Terminal<B>,
+    app: &mut App,
+    operator: Operator,
+    items: Vec<PathBuf>,
+    register: Option<char>,
+) -> io::Result<()> {
+    match operator {
+        Operator::Trash => trash_items(app, &items),
+        Operator::Yank => {
+            app.current_pane_mut().marked.clear();
+            app.registers.yank(register, items);
+        }
+        Operator::Rename => rename_entries(terminal, app, &items)?,
+    }
+    Ok(())
+}
+
+/// Rename `sources` in the active pane: a single entry in the rename prompt, several in the
+/// editor.
+fn rename_entries<B: Backend + Write>(
+    terminal: &mut Terminal<B>,
+    app: &mut App,
+    sources: &[PathBuf],
+) -> io::Result<()> {
+    if let [source] = sources {
+        let pane = app.current_pane();
+        if let Some(mode) = pane
+            .items
+            .iter()
+            .find(|e| e.path() == *source)
+            .map(Mode::rename)
+        {
+            app.mode = mode;
+        }
+        return Ok(());
+    }
+    edit_and_rename(terminal, app, sources)
+}
+
+/// Rename `sources` to the names the user gives them in the editor.
+fn edit_and_rename<B: Backend + Write>(
+    terminal: &mut Terminal<B>,
+    app: &mut App,
+    sources: &[PathBuf],
+) -> io::Result<()> {
+    let names: Vec<String> = sources
+        .iter()
+        .filter_map(|p| p.file_name())
+        .map(|n| n.to_string_lossy().into_owned())
+        .collect();
+    if !names.is_empty() {
+        let edited = edit_names(terminal, &mut app.notifications, &names)?;
+        bulk_rename(app, sources, &edited);
+    }
+    Ok(())
+}
+
+/// The directories on the path of `pane`, from the root.
+fn crumb_dirs(pane: &Pane) -> Vec<PathBuf> {
+    breadcrumbs(pane)
+        .into_iter()
+        .filter_map(|(_, dir)| dir)
+        .collect()
+}
+
+/// A left click on a breadcrumb in a pane title goes to that directory in that pane.
+fn click(app: &mut App, mouse: MouseEvent) {
+    if mouse.kind != MouseEventKind::Down(MouseButton::Left)
+        || !matches!(app.mode, Mode::Filer | Mode::Breadcrumbs { .. })
+    {
+        return;
+    }
+    let (column, row) = (mouse.column, mouse.row);
+    let (dir, side) = match breadcrumb_at(&app.left, column, row) {
+        Some(dir) => (dir, PaneType::Left),
+        None => match breadcrumb_at(&app.right, column, row) {
+            Some(dir) => (dir, PaneType::Right),
+            None => return,
+        },
+    };
+    if app.active != side {
+        app.switch_pane();
+    }
+    app.mode = Mode::Filer;
+    app.change_dir(dir);
+}
+
+/// Refuse a command that needs the active pane to be local.
+fn refuse_remote(app: &mut App) {
+    app.mode = Mode::Message {
+        title: "Remote directory".into(),
+        lines: vec!["This command only works on local directories".into()],
+    };
+}
+
 /// Main event loop: handles input and dispatches actions.
-pub fn run_app<B: Backend + Write>(terminal: &mut Terminal<B>, app: &mut App) -> io::Result<()> {
-    let mut prefix: usize = 0;
-    let mut last_key_g = false;
+pub fn run_app<B: Backend + Write>(
+    terminal: &mut Terminal<B>,
+    app: &mut App,
+    input: &mut dyn InputSource,
+) -> io::Result<()> {
+    let mut command: PendingCommand<Operator> = PendingCommand::default();
+    let motions = motion_keymap();
+    let filer_keys = filer_keymap(app);
+    let mut sequence = KeySequence::default();
+    // register picked in the registers popup, for the next filer key
+    let mut next_register: Option<char> = None;
+    let mut last_mode = "";
     loop {
+        app.poll_tasks();
+        app.left.poll_git();
+        app.right.poll_git();
+        app.left.poll_commits();
+        app.right.poll_commits();
+        app.left.poll_counts();
+        app.right.poll_counts();
+        app.left.store_view();
+        app.right.store_view();
+        app.sync_columns();
+        app.poll_program();
+        app.poll_verification();
+        app.poll_watch();
+        match &mut app.mode {
+            Mode::Todos(list) => {
+                list.poll();
+            }
+            Mode::Find(finder) => finder.refresh(),
+            Mode::Checksums(report) => report.refresh(),
+            Mode::Links(links) => links.refresh(),
+            Mode::Viewer(viewer) if viewer.follow => viewer.follow_tick(),
+            _ => {}
+        }
+        if app.mode.name() != last_mode {
+            debug!(from = last_mode, to = app.mode.name(), "mode");
+            // popups are how most failures are reported
+            if let Mode::Message { title, lines } = &app.mode {
+                warn!(title = %title, "{}", lines.join(" / "));
+            }
+            last_mode = app.mode.name();
+        }
         terminal.draw(|f| crate::ui::ui(f, app))?;
-        if event::poll(Duration::from_millis(100))? {
-            if let Event::Key(key) = event::read()? {
-                if key.code == KeyCode::Char('q') {
-                    return Ok(());
+        let event = input.next_event(Duration::from_millis(100))?;
+        if let Some(Event::Mouse(mouse)) = event {
+            click(app, mouse);
+            continue;
+        }
+        if let Some(Event::Key(key)) = event {
+            debug!(key = ?key.code, modifiers = ?key.modifiers, "key");
+            app.notifications.dismiss();
+            if key.code == KeyCode::Char('q') && !app.mode.is_prompt() {
+                if app.picker == Some(Picker::Dir) && app.current_pane().is_local() {
+                    app.chosen = Some(vec![app.current_pane().current_dir.clone()]);
                 }
-                let mut rename_target: Option<String> = None;
-                let mut sort_choice: Option<SortBy> = None;
+                return Ok(());
+            }
+            // leave the picker without picking anything
+            if key.code == KeyCode::Char('Q') && app.picker.is_some() && !app.mode.is_prompt() {
+                return Ok(());
+            }
+            let mut rename_target: Option<String> = None;
+            let mut sort_choice: Option<SortSpec> = None;
 
-                if let KeyCode::Char(c) = key.code {
-                    if (matches!(app.mode, Mode::Filer) || matches!(app.mode, Mode::Viewer { .. }))
-                        && c.is_ascii_digit()
-                    {
-                        prefix = prefix
-                            .saturating_mul(10)
-                            .saturating_add(c.to_digit(10).unwrap() as usize);
+            let combo = KeyCombo::from(key);
+            if let KeyCode::Char(c) = combo.code {
+                if is_navigable(&app.mode) && combo.is_plain() && c.is_ascii_digit() {
+                    command.digit(c.to_digit(10).unwrap());
+                    continue;
+                }
+            }
+            let (count, operator) = command.take();
+            let register = next_register.take();
+            // `gg` and `G` outside the filer, whose keymap has them along with its other sequences
+            if is_navigable(&app.mode) && !matches!(app.mode, Mode::Filer) {
+                let pending = sequence.is_pending();
+                match sequence.feed(&motions, combo) {
+                    Lookup::Matched(binding) => {
+                        go_to_end(app, matches!(binding, Binding::Top));
                         continue;
                     }
+                    Lookup::Pending => continue,
+                    // the key broke off a sequence
+                    Lookup::Unbound if pending => continue,
+                    Lookup::Unbound => {}
                 }
-                let count = if prefix > 0 { prefix } else { 1 };
-                prefix = 0;
-
-                // Vim-style 'gg' (go to top) and 'G' (go to bottom)
-                if let KeyCode::Char(c) = key.code {
-                    match c {
-                        'g' => {
-                            if last_key_g {
-                                last_key_g = false;
-                                match &mut app.mode {
-                                    Mode::Filer => app.current_pane_mut().selected = 0,
-                                    Mode::Viewer { offset, .. } => *offset = 0,
-                                    _ => {}
-                                }
-                            } else {
-                                last_key_g = true;
-                            }
-                            continue;
-                        }
-                        'G' => {
-                            last_key_g = false;
-                            match &mut app.mode {
-                                Mode::Filer => {
-                                    let pane = app.current_pane_mut();
-                                    pane.selected = pane.items.len().saturating_sub(1);
-                                }
-                                Mode::Viewer {
-                                    offset, content, ..
-                                } => {
-                                    let total = content.lines().count();
-                                    *offset = total.saturating_sub(1) as u16;
-                                }
-                                _ => {}
-                            }
-                            continue;
+            }
+
+            // Log viewer: record navigation, filter prompt and pretty-printing
+            if let Mode::LogViewer(log) = &mut app.mode {
+                if let Some(input) = &mut log.editing {
+                    match key.code {
+                        KeyCode::Enter => {
+                            let filter = input.to_string();
+                            log.editing = None;
+                            log.set_filter(filter);
+                        }
+                        KeyCode::Esc => log.editing = None,
+                        _ => {
+                            input.handle(key);
                         }
-                        _ => last_key_g = false,
                     }
-                }
-
-                // Visual selection mode
-                if let Mode::Visual { anchor } = app.mode {
+                } else {
                     match key.code {
-                        KeyCode::Char('j') => {
-                            for _ in 0..count {
-                                app.on_down();
-                            }
-                            let pane = app.current_pane_mut();
-                            pane.marked.clear();
-                            let end = pane.selected;
-                            let (lo, hi) = if anchor <= end {
-                                (anchor, end)
-                            } else {
-                                (end, anchor)
-                            };
-                            for i in lo..=hi {
-                                pane.marked.insert(i);
-                            }
+                        KeyCode::Char('j') => log.down(count),
+                        KeyCode::Char('k') => log.up(count),
+                        KeyCode::Char('f') | KeyCode::Char('/') => {
+                            log.editing = Some(LineEditor::new(log.filter.as_str()));
                         }
-                        KeyCode::Char('k') => {
-                            for _ in 0..count {
-                                app.on_up();
-                            }
-                            let pane = app.current_pane_mut();
-                            pane.marked.clear();
-                            let end = pane.selected;
-                            let (lo, hi) = if anchor <= end {
-                                (anchor, end)
+                        KeyCode::Char('p') => log.expanded = !log.expanded,
+                        KeyCode::Enter | KeyCode::Esc => {
+                            if log.expanded {
+                                log.expanded = false;
                             } else {
-                                (end, anchor)
-                            };
-                            for i in lo..=hi {
-                                pane.marked.insert(i);
+                                app.mode = Mode::Filer;
                             }
                         }
-                        KeyCode::Char('V') | KeyCode::Esc => {
-                            app.mode = Mode::Filer;
-                        }
                         _ => {}
                     }
-                    continue;
                 }
+                continue;
+            }
 
-                // Search mode: edit query and jump to matching entries
-                if let Mode::Search { query } = &mut app.mode {
-                    match key.code {
-                        KeyCode::Char(c) => query.push(c),
-                        KeyCode::Backspace => {
-                            query.pop();
-                        }
-                        KeyCode::Enter | KeyCode::Esc => {
-                            app.mode = Mode::Filer;
+            // Marker scan results: Enter opens the match in the viewer, the list is kept
+            if let Mode::Todos(list) = &mut app.mode {
+                match key.code {
+                    KeyCode::Char('j') => list.down(count),
+                    KeyCode::Char('k') => list.up(count),
+                    KeyCode::Char('r') => *list = TodoList::scan(list.root.clone()),
+                    KeyCode::Enter => {
+                        let viewer = list.current().and_then(|m| {
+                            let mut viewer = Viewer::open(&m.path)?;
+                            viewer.goto_line(m.line - 1);
+                            Some(viewer)
+                        });
+                        if let Some(viewer) = viewer {
+                            if let Mode::Todos(list) = std::mem::replace(&mut app.mode, Mode::Filer)
+                            {
+                                app.todos = Some(list);
+                            }
+                            app.mode = Mode::Viewer(viewer);
                         }
-                        _ => {}
                     }
-                }
-                let q_opt = if let Mode::Search { query } = &app.mode {
-                    Some(query.clone())
-                } else {
-                    None
-                };
-                if let Some(q) = q_opt {
-                    let pane = app.current_pane_mut();
-                    if let Some(idx) = find_match(&pane.items, &q, pane.selected) {
-                        pane.selected = idx;
+                    KeyCode::Esc => {
+                        if let Mode::Todos(list) = std::mem::replace(&mut app.mode, Mode::Filer) {
+                            app.todos = Some(list);
+                        }
                     }
-                    continue;
+                    _ => {}
                 }
+                continue;
+            }
 
-                // Rename mode
-                if let Mode::Rename { buffer, .. } = &mut app.mode {
-                    match key.code {
-                        KeyCode::Char(c) => buffer.push(c),
-                        KeyCode::Backspace => {
-                            buffer.pop();
+            // Visual selection mode
+            if let Mode::Visual { anchor } = app.mode {
+                match key.code {
+                    KeyCode::Char('j') => {
+                        for _ in 0..count {
+                            app.on_down();
                         }
-                        KeyCode::Enter => {
-                            rename_target = Some(buffer.clone());
-                            app.mode = Mode::Filer;
+                        let pane = app.current_pane_mut();
+                        pane.marked.clear();
+                        let end = pane.selected;
+                        let (lo, hi) = if anchor <= end {
+                            (anchor, end)
+                        } else {
+                            (end, anchor)
+                        };
+                        for i in lo..=hi {
+                            pane.set_mark(i, true);
                         }
-                        KeyCode::Esc => {
-                            app.mode = Mode::Filer;
+                    }
+                    KeyCode::Char('k') => {
+                        for _ in 0..count {
+                            app.on_up();
                         }
-                        _ => {}
+                        let pane = app.current_pane_mut();
+                        pane.marked.clear();
+                        let end = pane.selected;
+                        let (lo, hi) = if anchor <= end {
+                            (anchor, end)
+                        } else {
+                            (end, anchor)
+                        };
+                        for i in lo..=hi {
+                            pane.set_mark(i, true);
+                        }
+                    }
+                    KeyCode::Char('V') | KeyCode::Esc => {
+                        app.mode = Mode::Filer;
                     }
+                    _ => {}
                 }
+                continue;
+            }
 
-                // Sort mode
-                if let Mode::Sort { selected } = &mut app.mode {
-                    match key.code {
-                        KeyCode::Down | KeyCode::Char('j') => {
-                            *selected = (*selected + 1) % SORT_OPTIONS.len();
-                        }
-                        KeyCode::Up | KeyCode::Char('k') => {
-                            *selected = (*selected + SORT_OPTIONS.len() - 1) % SORT_OPTIONS.len();
-                        }
-                        KeyCode::Enter => {
-                            let by = match *selected {
-                                0 => SortBy::Modified,
-                                1 => SortBy::Created,
-                                2 => SortBy::Size,
-                                _ => SortBy::Name,
-                            };
-                            sort_choice = Some(by);
-                            app.mode = Mode::Filer;
-                        }
-                        KeyCode::Esc => {
-                            app.mode = Mode::Filer;
+            // Search mode: edit query and jump to matching entries
+            if let Mode::Search { query, regex } = &mut app.mode {
+                match key.code {
+                    KeyCode::Enter => {
+                        app.search = query.to_string();
+                        app.search_regex = *regex;
+                        app.mode = Mode::Filer;
+                    }
+                    KeyCode::Esc => {
+                        app.mode = Mode::Filer;
+                    }
+                    KeyCode::Char('r') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                        *regex = !*regex;
+                        app.search_regex = *regex;
+                    }
+                    _ => {
+                        if query.handle(key) {
+                            let (query, regex) = (query.to_string(), *regex);
+                            search(app.current_pane_mut(), &query, regex, false);
                         }
-                        _ => {}
                     }
                 }
+                continue;
+            }
 
-                // Commit rename
-                if let Some(new_name) = rename_target {
-                    let pane = app.current_pane_mut();
-                    let old = pane.items[pane.selected].path();
-                    let newp = old.with_file_name(&new_name);
-                    if let Err(e) = fs::rename(&old, &newp) {
-                        eprintln!("Failed to rename {:?} to {:?}: {}", old, newp, e);
+            // Narrowing mode: the listing follows the query as it is typed
+            if let Mode::Filter { query } = &mut app.mode {
+                match key.code {
+                    KeyCode::Enter => app.mode = Mode::Filer,
+                    KeyCode::Esc => {
+                        app.mode = Mode::Filer;
+                        app.current_pane_mut().set_filter("");
                     }
-                    if pane.refresh().is_ok() {
-                        if let Some(pos) = pane
-                            .items
-                            .iter()
-                            .position(|e| e.file_name().to_string_lossy() == new_name)
-                        {
-                            pane.selected = pos;
+                    _ => {
+                        if query.handle(key) {
+                            let query = query.to_string();
+                            app.current_pane_mut().set_filter(&query);
                         }
                     }
-                    continue;
                 }
+                continue;
+            }
 
-                // Commit sort
-                if let Some(by) = sort_choice {
-                    apply_sort(app.current_pane_mut(), by);
-                    continue;
+            // Pattern mode: mark or unmark entries matching a glob
+            if let Mode::MarkPattern { query, mark } = &mut app.mode {
+                match key.code {
+                    KeyCode::Enter => {
+                        let (pattern, mark) = (query.to_string(), *mark);
+                        app.mode = Mode::Filer;
+                        mark_by_pattern(app.current_pane_mut(), &pattern, mark);
+                    }
+                    KeyCode::Esc => {
+                        app.mode = Mode::Filer;
+                    }
+                    _ => {
+                        query.handle(key);
+                    }
                 }
+                continue;
+            }
 
-                match &mut app.mode {
-                    Mode::ConfirmDelete { items } => match key.code {
-                        KeyCode::Char('y') | KeyCode::Enter => {
-                            let to_delete = items.clone();
+            // Predicate mode: mark or unmark entries meeting the conditions; a query that does
+            // not parse stays open, its error shown in the prompt
+            if let Mode::MarkWhere { query, mark } = &mut app.mode {
+                match key.code {
+                    KeyCode::Enter => {
+                        if let Ok(predicate) = Predicate::parse(&query.to_string()) {
+                            let mark = *mark;
                             app.mode = Mode::Filer;
-                            delete_items(app, &to_delete);
+                            let count = mark_where(app.current_pane_mut(), &predicate, mark);
+                            let verb = if mark { "marked" } else { "unmarked" };
+                            app.notifications.info(format!("{verb} {count} entries"));
                         }
-                        KeyCode::Char('n') | KeyCode::Esc => {
-                            app.mode = Mode::Filer;
+                    }
+                    KeyCode::Esc => {
+                        app.mode = Mode::Filer;
+                    }
+                    _ => {
+                        query.handle(key);
+                    }
+                }
+                continue;
+            }
+
+            // Task queue popup
+            if let Mode::Tasks { selected } = &mut app.mode {
+                let total = app.tasks.jobs.len();
+                match key.code {
+                    KeyCode::Down | KeyCode::Char('j') if total > 0 => {
+                        *selected = (*selected + 1).min(total - 1);
+                    }
+                    KeyCode::Up | KeyCode::Char('k') => {
+                        *selected = selected.saturating_sub(1);
+                    }
+                    KeyCode::Char('c') => {
+                        if let Some(job) = app.tasks.jobs.get(*selected) {
+                            let id = job.id;
+                            app.tasks.cancel(id);
                         }
-                        _ => {}
-                    },
-                    Mode::Viewer { offset, .. } => match key.code {
-                        KeyCode::Char('j') => *offset = offset.saturating_add(count as u16),
-                        KeyCode::Char('k') => *offset = offset.saturating_sub(count as u16),
-                        KeyCode::Enter => app.mode = Mode::Filer,
-                        _ => {}
-                    },
-                    Mode::Filer => match key.code {
-                        KeyCode::Char('j') => (0..count).for_each(|_| app.on_down()),
-                        KeyCode::Char('k') => (0..count).for_each(|_| app.on_up()),
-                        KeyCode::Char('x') => {
-                            let items: Vec<PathBuf> = {
-                                let pane = app.current_pane_mut();
-                                let mut sel = Vec::new();
-                                if !pane.marked.is_empty() {
-                                    for &i in &pane.marked {
-                                        if let Some(e) = pane.items.get(i) {
-                                            sel.push(e.path());
-                                        }
-                                    }
-                                } else if let Some(e) = pane.items.get(pane.selected) {
-                                    sel.push(e.path());
-                                }
-                                sel
-                            };
-                            app.mode = Mode::ConfirmDelete { items };
-                        }
-                        KeyCode::Char('X') => {
-                            let items: Vec<PathBuf> = {
-                                let pane = app.current_pane_mut();
-                                let mut sel = Vec::new();
-                                if !pane.marked.is_empty() {
-                                    for &i in &pane.marked {
-                                        if let Some(e) = pane.items.get(i) {
-                                            sel.push(e.path());
-                                        }
-                                    }
-                                } else if let Some(e) = pane.items.get(pane.selected) {
-                                    sel.push(e.path());
-                                }
-                                sel
-                            };
-                            delete_items(app, &items);
+                    }
+                    KeyCode::Char('r') => {
+                        if let Some(job) = app.tasks.jobs.get(*selected) {
+                            let id = job.id;
+                            app.tasks.resume(id);
                         }
-                        KeyCode::Enter => {
-                            let (is_img, path) = {
-                                let pane = app.current_pane_mut();
-                                pane.items
-                                    .get(pane.selected)
-                                    .map(|entry| {
-                                        let p = entry.path();
-                                        (is_image(&p), p)
-                                    })
-                                    .unwrap_or((false, PathBuf::new()))
-                            };
-                            if is_img {
-                                app.switch_pane();
-                                show_image(terminal, &path)?;
-                            } else {
-                                app.on_enter();
-                            }
+                    }
+                    KeyCode::Char('C') => {
+                        app.tasks.clear_finished();
+                        *selected = 0;
+                    }
+                    KeyCode::Char('v') => {
+                        let report = app.tasks.jobs.get(*selected).and_then(|j| j.report.clone());
+                        if let Some(viewer) = report.and_then(|r| Viewer::open(&r)) {
+                            app.mode = Mode::Viewer(viewer);
                         }
-                        KeyCode::Char('h') => match app.active {
-                            PaneType::Left => app.on_left(),
-                            PaneType::Right => app.switch_pane(),
-                        },
-                        KeyCode::Char('l') => match app.active {
-                            PaneType::Left => app.switch_pane(),
-                            PaneType::Right => app.on_left(),
-                        },
-                        KeyCode::Char('V') => {
-                            let pane = app.current_pane_mut();
-                            let anchor = pane.selected;
-                            pane.marked.clear();
-                            pane.marked.insert(anchor);
-                            app.mode = Mode::Visual { anchor };
-                        }
-                        KeyCode::Char('/') => {
-                            app.mode = Mode::Search {
-                                query: String::new(),
-                            };
+                    }
+                    KeyCode::Char('T') | KeyCode::Enter | KeyCode::Esc => {
+                        app.mode = Mode::Filer;
+                    }
+                    _ => {}
+                }
+                continue;
+            }
+
+            if let Mode::Basket { selected } = &mut app.mode {
+                let count = app.basket.len();
+                match key.code {
+                    KeyCode::Down | KeyCode::Char('j') => {
+                        *selected = (*selected + 1).min(count.saturating_sub(1));
+                    }
+                    KeyCode::Up | KeyCode::Char('k') => {
+                        *selected = selected.saturating_sub(1);
+                    }
+                    KeyCode::Delete | KeyCode::Backspace if *selected < count => {
+                        app.basket.remove(*selected);
+                        *selected = (*selected).min(count.saturating_sub(2));
+                    }
+                    KeyCode::Char('c') => {
+                        app.basket.clear();
+                        *selected = 0;
+                    }
+                    KeyCode::Enter => {
+                        let target = app.basket.get(*selected).cloned();
+                        app.mode = Mode::Filer;
+                        if let Some(path) = target {
+                            app.go_to(&path);
                         }
-                        KeyCode::Char('r') => {
-                            let pane = app.current_pane_mut();
-                            if let Some(entry) = pane.items.get(pane.selected) {
-                                let name = entry.file_name().to_string_lossy().into_owned();
-                                app.mode = Mode::Rename {
-                                    original: name.clone(),
-                                    buffer: name,
-                                };
-                            }
+                    }
+                    KeyCode::Char('p') | KeyCode::Char('P') => {
+                        app.mode = Mode::Filer;
+                        transfer_basket(app, key.code == KeyCode::Char('P'));
+                    }
+                    KeyCode::Char('x') => {
+                        let items = app.basket.clone();
+                        app.mode = Mode::Filer;
+                        request_delete(app, items, true);
+                    }
+                    KeyCode::Char('d') => {
+                        let items = app.basket.clone();
+                        app.mode = Mode::Filer;
+                        trash_items(app, &items);
+                    }
+                    KeyCode::Esc => app.mode = Mode::Filer,
+                    _ => {}
+                }
+                continue;
+            }
+
+            if let Mode::Sync { to_right, to_left } = &mut app.mode {
+                match key.code {
+                    KeyCode::Char('y') | KeyCode::Enter => {
+                        let (to_right, to_left) =
+                            (std::mem::take(to_right), std::mem::take(to_left));
+                        app.mode = Mode::Filer;
+                        apply_sync(app, to_right, to_left);
+                    }
+                    KeyCode::Char('n') | KeyCode::Esc => app.mode = Mode::Filer,
+                    _ => {}
+                }
+                continue;
+            }
+
+            if let Mode::Jump { query, selected } = &mut app.mode {
+                match key.code {
+                    KeyCode::Down => *selected += 1,
+                    KeyCode::Up => *selected = selected.saturating_sub(1),
+                    KeyCode::Enter => {
+                        let target = app.history.query(query).into_iter().nth(*selected);
+                        app.mode = Mode::Filer;
+                        if let Some(dir) = target {
+                            app.change_dir(dir);
                         }
-                        KeyCode::Char('s') => {
-                            app.mode = Mode::Sort { selected: 0 };
+                    }
+                    KeyCode::Esc => app.mode = Mode::Filer,
+                    _ => {
+                        if query.handle(key) {
+                            *selected = 0;
                         }
-                        KeyCode::Char('v') => {
-                            toggle_mark(app.current_pane_mut());
+                    }
+                }
+                continue;
+            }
+
+            if let Mode::Checksums(report) = &mut app.mode {
+                match key.code {
+                    KeyCode::Down | KeyCode::Char('j') => {
+                        report.selected = (report.selected + 1).min(report.entries.len() - 1);
+                    }
+                    KeyCode::Up | KeyCode::Char('k') => {
+                        report.selected = report.selected.saturating_sub(1);
+                    }
+                    KeyCode::Enter => {
+                        let target = report.current();
+                        app.mode = Mode::Filer;
+                        if let Some(path) = target {
+                            app.go_to(&path);
                         }
-                        KeyCode::Char('y') => {
-                            copy_selection(app);
+                    }
+                    KeyCode::Esc => app.mode = Mode::Filer,
+                    _ => {}
+                }
+                continue;
+            }
+
+            if let Mode::Links(links) = &mut app.mode {
+                match key.code {
+                    KeyCode::Down | KeyCode::Char('j') => {
+                        links.selected =
+                            (links.selected + 1).min(links.links.len().saturating_sub(1));
+                    }
+                    KeyCode::Up | KeyCode::Char('k') => {
+                        links.selected = links.selected.saturating_sub(1);
+                    }
+                    KeyCode::Enter => {
+                        let target = links.current().cloned();
+                        app.mode = Mode::Filer;
+                        if let Some(path) = target {
+                            app.go_to(&path);
                         }
-                        KeyCode::Char('p') => {
-                            paste(app);
+                    }
+                    KeyCode::Char('d') => {
+                        let target: Vec<_> = links.current().cloned().into_iter().collect();
+                        app.mode = Mode::Filer;
+                        request_delete(app, target, true);
+                    }
+                    // not while the scan may still find more
+                    KeyCode::Char('D') if !links.is_scanning() => {
+                        let all = std::mem::take(&mut links.links);
+                        app.mode = Mode::Filer;
+                        request_delete(app, all, true);
+                    }
+                    KeyCode::Esc => app.mode = Mode::Filer,
+                    _ => {}
+                }
+                continue;
+            }
+
+            if let Mode::Find(finder) = &mut app.mode {
+                match key.code {
+                    KeyCode::Down => finder.selected += 1,
+                    KeyCode::Up => finder.selected = finder.selected.saturating_sub(1),
+                    KeyCode::Char('r') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                        finder.rescan();
+                    }
+                    KeyCode::Enter => {
+                        let target = finder.current().cloned();
+                        app.mode = Mode::Filer;
+                        if let Some(path) = target {
+                            app.go_to(&path);
                         }
-                        _ => {}
+                    }
+                    KeyCode::Esc => app.mode = Mode::Filer,
+                    _ => {
+                        if finder.query.handle(key) {
+                            finder.selected = 0;
+                        }
+                    }
+                }
+                if let Mode::Find(finder) = &mut app.mode {
+                    finder.refresh();
+                }
+                continue;
+            }
+
+            if let Mode::Run { program, args } = &mut app.mode {
+                match key.code {
+                    KeyCode::Enter => {
+                        let (program, args) = (program.clone(), args.to_string());
+                        app.mode = Mode::Filer;
+                        app.start_program(&program, &args);
+                    }
+                    KeyCode::Esc => app.mode = Mode::Filer,
+                    _ => {
+                        args.handle(key);
+                    }
+                }
+                continue;
+            }
+
+            if let Mode::Xattr { path, input } = &mut app.mode {
+                match key.code {
+                    KeyCode::Enter => {
+                        let (path, input) = (std::mem::take(path), input.to_string());
+                        edit_xattr(app, &path, &input);
+                    }
+                    KeyCode::Esc => inspect(app),
+                    _ => {
+                        input.handle(key);
+                    }
+                }
+                continue;
+            }
+
+            if let Mode::HardLinkScope { path, scope } = &mut app.mode {
+                match key.code {
+                    KeyCode::Enter => {
+                        let (path, scope) = (std::mem::take(path), scope.to_string());
+                        app.mode = Mode::Filer;
+                        search_hard_links(app, path, &scope);
+                    }
+                    KeyCode::Esc => app.mode = Mode::Filer,
+                    _ => {
+                        scope.handle(key);
+                    }
+                }
+                continue;
+            }
+
+            if let Mode::Watch { dir, template } = &mut app.mode {
+                match key.code {
+                    KeyCode::Enter if !template.to_string().trim().is_empty() => {
+                        let (dir, template) = (std::mem::take(dir), template.to_string());
+                        app.mode = Mode::Filer;
+                        app.start_watch(dir, template);
+                    }
+                    KeyCode::Esc => app.mode = Mode::Filer,
+                    _ => {
+                        template.handle(key);
+                    }
+                }
+                continue;
+            }
+
+            if let Mode::ForEach { paths, template } = &mut app.mode {
+                match key.code {
+                    KeyCode::Enter if !template.to_string().trim().is_empty() => {
+                        let (paths, template) = (std::mem::take(paths), template.to_string());
+                        app.mode = Mode::Filer;
+                        run_for_each(app, paths, template, None);
+                    }
+                    KeyCode::Esc => app.mode = Mode::Filer,
+                    _ => {
+                        template.handle(key);
+                    }
+                }
+                continue;
+            }
+
+            if let Mode::ArchivePassword {
+                extraction,
+                password,
+            } = &mut app.mode
+            {
+                match key.code {
+                    KeyCode::Enter if !password.is_empty() => {
+                        let extraction = Extraction {
+                            password: Some(password.to_string()),
+                            ..extraction.clone()
+                        };
+                        app.mode = Mode::Filer;
+                        app.tasks.enqueue(JobKind::Extract(extraction));
+                    }
+                    KeyCode::Esc => app.mode = Mode::Filer,
+                    _ => {
+                        password.handle(key);
+                    }
+                }
+                continue;
+            }
+
+            if let Mode::Share { paths, selected } = &mut app.mode {
+                let count = app.config.shares.len();
+                let mut close = false;
+                let picked = match key.code {
+                    KeyCode::Down | KeyCode::Char('j') => {
+                        *selected = (*selected + 1).min(count.saturating_sub(1));
+                        None
+                    }
+                    KeyCode::Up | KeyCode::Char('k') => {
+                        *selected = selected.saturating_sub(1);
+                        None
+                    }
+                    KeyCode::Enter => Some(*selected),
+                    // the first nine targets are a digit away
+                    KeyCode::Char(c @ '1'..='9') => Some(c as usize - '1' as usize),
+                    KeyCode::Esc => {
+                        close = true;
+                        None
+                    }
+                    _ => None,
+                };
+                if let Some(share) = picked.and_then(|i| app.config.shares.get(i)).cloned() {
+                    let paths = std::mem::take(paths);
+                    app.mode = Mode::Filer;
+                    run_for_each(app, paths, share.run, Some(share.name));
+                } else if close {
+                    app.mode = Mode::Filer;
+                }
+                continue;
+            }
+
+            if let Mode::ImageActions { paths, selected } = &mut app.mode {
+                match key.code {
+                    KeyCode::Down | KeyCode::Char('j') => {
+                        *selected = (*selected + 1).min(IMAGE_ACTIONS.len() - 1);
+                    }
+                    KeyCode::Up | KeyCode::Char('k') => {
+                        *selected = selected.saturating_sub(1);
+                    }
+                    KeyCode::Enter => {
+                        let paths = std::mem::take(paths);
+                        app.mode = match ImageOp::from_menu(*selected) {
+                            Some(op) => {
+                                app.tasks.enqueue(JobKind::Images { paths, op });
+                                Mode::Filer
+                            }
+                            None => Mode::ResizeImages {
+                                paths,
+                                width: LineEditor::default(),
+                            },
+                        };
+                    }
+                    KeyCode::Esc => app.mode = Mode::Filer,
+                    _ => {}
+                }
+                continue;
+            }
+
+            if let Mode::ResizeImages { paths, width } = &mut app.mode {
+                match key.code {
+                    KeyCode::Enter => match width.trim().parse::<u32>() {
+                        Ok(width) if width > 0 => {
+                            let paths = std::mem::take(paths);
+                            app.mode = Mode::Filer;
+                            app.tasks.enqueue(JobKind::Images {
+                                paths,
+                                op: ImageOp::Resize(width),
+                            });
+                        }
+                        _ => app.notifications.warn("enter a width in pixels"),
+                    },
+                    KeyCode::Esc => app.mode = Mode::Filer,
+                    _ => {
+                        width.handle(key);
+                    }
+                }
+                continue;
+            }
+
+            if let Mode::Location { buffer } = &mut app.mode {
+                match key.code {
+                    KeyCode::Enter => {
+                        let location = buffer.trim().to_string();
+                        app.mode = Mode::Filer;
+                        if !location.is_empty() {
+                            app.open_location(&location);
+                        }
+                    }
+                    KeyCode::Esc => app.mode = Mode::Filer,
+                    _ => {
+                        buffer.handle(key);
+                    }
+                }
+                continue;
+            }
+
+            // Registers popup: pick a register by name, or paste the highlighted one
+            if let Mode::Registers { selected } = &mut app.mode {
+                let total = app.registers.list().len();
+                match key.code {
+                    KeyCode::Down if total > 0 => *selected = (*selected + 1).min(total - 1),
+                    KeyCode::Up => *selected = selected.saturating_sub(1),
+                    KeyCode::Enter => {
+                        let name = app.registers.list().get(*selected).map(|(c, _)| *c);
+                        app.mode = Mode::Filer;
+                        if let Some(name) = name {
+                            paste(app, Some(name));
+                        }
+                    }
+                    KeyCode::Char(c) if Registers::is_register(c) => {
+                        next_register = Some(c);
+                        app.mode = Mode::Filer;
+                    }
+                    KeyCode::Esc => app.mode = Mode::Filer,
+                    _ => {}
+                }
+                continue;
+            }
+
+            if let Mode::Message { .. } | Mode::Notifications = app.mode {
+                app.mode = Mode::Filer;
+                continue;
+            }
+
+            if let Mode::ConfirmElevate { retry, .. } = &app.mode {
+                match confirmation(&mut None, key) {
+                    Some(true) => {
+                        let retry = retry.clone();
+                        app.mode = Mode::Filer;
+                        run_elevated(terminal, app, &retry)?;
+                    }
+                    Some(false) => app.mode = Mode::Filer,
+                    None => {}
+                }
+                continue;
+            }
+
+            if let Mode::ConfirmResume { sources, dest, .. } = &app.mode {
+                let (sources, dest) = (sources.clone(), dest.clone());
+                match key.code {
+                    KeyCode::Char('y') | KeyCode::Enter => {
+                        app.mode = Mode::Filer;
+                        resume_copy(app, sources, dest, true);
+                    }
+                    KeyCode::Char('n') => {
+                        app.mode = Mode::Filer;
+                        resume_copy(app, sources, dest, false);
+                    }
+                    KeyCode::Esc => app.mode = Mode::Filer,
+                    _ => {}
+                }
+                continue;
+            }
+
+            if let Mode::Inspect {
+                path,
+                size,
+                video,
+                quarantined,
+                ..
+            } = &app.mode
+            {
+                match (key.code, video) {
+                    (KeyCode::Char('y'), _) => {
+                        let size = *size;
+                        copy_size(app, size);
+                    }
+                    (KeyCode::Char('a'), _) => {
+                        app.mode = Mode::Xattr {
+                            path: path.clone(),
+                            input: LineEditor::new(""),
+                        };
+                        continue;
+                    }
+                    (KeyCode::Char('Q'), _) if *quarantined => {
+                        let path = path.clone();
+                        remove_quarantine(app, &path);
+                        continue;
+                    }
+                    (KeyCode::Char('t'), Some(video)) => match media::thumbnail(video) {
+                        Ok(thumbnail) => {
+                            show_images(terminal, input, std::slice::from_ref(&thumbnail), 0)?;
+                            let _ = fs::remove_file(thumbnail);
+                        }
+                        Err(e) => app.notifications.error(format!("no thumbnail: {e}")),
                     },
                     _ => {}
                 }
+                app.mode = Mode::Filer;
+                continue;
+            }
+
+            // Rename mode
+            let dir = app.current_pane().current_dir.clone();
+            if let Mode::Rename {
+                original,
+                buffer,
+                overwrite,
+                ..
+            } = &mut app.mode
+            {
+                match key.code {
+                    KeyCode::Enter => match validate_name(&dir, original, buffer) {
+                        Some(problem) if problem.is_overridable() && !*overwrite => {
+                            *overwrite = true;
+                        }
+                        Some(problem) if !problem.is_overridable() => {}
+                        // committed below, once the entry is known to be unchanged
+                        _ => rename_target = Some(buffer.to_string()),
+                    },
+                    KeyCode::Esc => {
+                        app.mode = Mode::Filer;
+                    }
+                    _ => {
+                        if buffer.handle(key) {
+                            *overwrite = false;
+                        }
+                    }
+                }
+            }
+
+            // Paste-as prompt
+            if let Mode::PasteAs {
+                source,
+                buffer,
+                confirmed,
+            } = &mut app.mode
+            {
+                match key.code {
+                    KeyCode::Enter => match validate_path(&dir, buffer) {
+                        Some(problem) if problem.is_overridable() && !*confirmed => {
+                            *confirmed = true;
+                        }
+                        Some(problem) if !problem.is_overridable() => {}
+                        _ => {
+                            let (source, name) = (source.clone(), buffer.to_string());
+                            app.mode = Mode::Filer;
+                            paste_as_name(app, source, &name);
+                        }
+                    },
+                    KeyCode::Esc => app.mode = Mode::Filer,
+                    _ => {
+                        if buffer.handle(key) {
+                            *confirmed = false;
+                        }
+                    }
+                }
+                continue;
+            }
+
+            // Create prompt
+            if let Mode::Create { buffer, confirmed } = &mut app.mode {
+                match key.code {
+                    KeyCode::Enter => match validate_new_entry(&dir, buffer) {
+                        Some(problem) if problem.is_overridable() && !*confirmed => {
+                            *confirmed = true;
+                        }
+                        Some(problem) if !problem.is_overridable() => {}
+                        _ => {
+                            let path = buffer.to_string();
+                            app.mode = Mode::Filer;
+                            create_entry(app, &path);
+                        }
+                    },
+                    KeyCode::Esc => app.mode = Mode::Filer,
+                    _ => {
+                        if buffer.handle(key) {
+                            *confirmed = false;
+                        }
+                    }
+                }
+                continue;
+            }
+
+            if let Mode::Mounts { mounts, selected } = &mut app.mode {
+                match key.code {
+                    KeyCode::Down | KeyCode::Char('j') => {
+                        *selected = (*selected + 1).min(mounts.len().saturating_sub(1));
+                    }
+                    KeyCode::Up | KeyCode::Char('k') => {
+                        *selected = selected.saturating_sub(1);
+                    }
+                    KeyCode::Enter | KeyCode::Char('l') => {
+                        let target = mounts
+                            .get(*selected)
+                            .map(|m| (m.path.clone(), m.mount_with.clone()));
+                        app.mode = Mode::Filer;
+                        match target {
+                            Some((_, Some(uri))) => app.open_location(&uri),
+                            Some((dir, None)) if app.current_pane().is_local() => {
+                                app.change_dir(dir);
+                            }
+                            Some((dir, None)) => app.open_location(&dir.to_string_lossy()),
+                            None => {}
+                        }
+                    }
+                    KeyCode::Esc | KeyCode::Char('h') => app.mode = Mode::Filer,
+                    _ => {}
+                }
+                continue;
+            }
+
+            // Breadcrumbs: pick a directory on the path
+            if let Mode::Breadcrumbs { selected } = app.mode {
+                let dirs = crumb_dirs(app.current_pane());
+                match key.code {
+                    KeyCode::Left | KeyCode::Char('h') => {
+                        app.mode = Mode::Breadcrumbs {
+                            selected: selected.saturating_sub(1),
+                        };
+                    }
+                    KeyCode::Right | KeyCode::Char('l') => {
+                        app.mode = Mode::Breadcrumbs {
+                            selected: (selected + 1).min(dirs.len().saturating_sub(1)),
+                        };
+                    }
+                    KeyCode::Enter => {
+                        app.mode = Mode::Filer;
+                        if let Some(dir) = dirs.get(selected) {
+                            app.change_dir(dir.clone());
+                        }
+                    }
+                    KeyCode::Esc => app.mode = Mode::Filer,
+                    _ => {}
+                }
+                continue;
+            }
+
+            // Sort mode
+            let spec = app.current_pane().sort_by.unwrap_or_default();
+            if let Mode::Sort { selected } = &mut app.mode {
+                match key.code {
+                    KeyCode::Down | KeyCode::Char('j') => {
+                        *selected = (*selected + 1) % SORT_OPTIONS.len();
+                    }
+                    KeyCode::Up | KeyCode::Char('k') => {
+                        *selected = (*selected + SORT_OPTIONS.len() - 1) % SORT_OPTIONS.len();
+                    }
+                    // `s` again picks the current order, so `ss` inverts it
+                    KeyCode::Enter | KeyCode::Char('s') => {
+                        sort_choice = Some(spec.choose(SortBy::ALL[*selected]));
+                        app.mode = Mode::Filer;
+                    }
+                    KeyCode::Tab => {
+                        sort_choice = Some(spec.then_by(SortBy::ALL[*selected]));
+                    }
+                    KeyCode::Esc => {
+                        app.mode = Mode::Filer;
+                    }
+                    _ => {}
+                }
+            }
+
+            // Commit rename
+            if let Some(new_name) = rename_target {
+                let Mode::Rename {
+                    original,
+                    stamp,
+                    conflict,
+                    ..
+                } = &app.mode
+                else {
+                    continue;
+                };
+                let (original, stamp, confirmed) = (original.clone(), *stamp, *conflict);
+                // the listing may have changed while the prompt was open, so the entry is
+                // looked up again by name
+                match app.current_pane().entry_change(&original, stamp) {
+                    // a replaced entry is renamed once the user has seen the warning
+                    Some(EntryChange::Replaced) if confirmed == Some(EntryChange::Replaced) => {}
+                    Some(change) => {
+                        if let Mode::Rename { conflict, .. } = &mut app.mode {
+                            *conflict = Some(change);
+                        }
+                        continue;
+                    }
+                    None => {}
+                }
+                app.mode = Mode::Filer;
+                let pane = app.current_pane_mut();
+                let old = pane.current_dir.join(&original);
+                let newp = old.with_file_name(&new_name);
+                match pane.vfs.rename(&old, &newp) {
+                    Ok(()) => {}
+                    Err(e) if e.kind() == io::ErrorKind::PermissionDenied && pane.is_local() => {
+                        let reason = format!("{}: {e}", old.display());
+                        app.offer_elevation(
+                            Privileged::Rename {
+                                from: old,
+                                to: newp,
+                            },
+                            reason,
+                        );
+                    }
+                    Err(e) => app.notifications.error(format!(
+                        "cannot rename {} to {new_name}: {e}",
+                        old.display()
+                    )),
+                }
+                let pane = app.current_pane_mut();
+                if pane.refresh().is_ok() {
+                    if let Some(pos) = pane
+                        .items
+                        .iter()
+                        .position(|e| e.file_name().to_string_lossy() == new_name)
+                    {
+                        pane.selected = pos;
+                    }
+                }
+                continue;
+            }
+
+            // Commit sort
+            if let Some(spec) = sort_choice {
+                apply_sort(app.current_pane_mut(), spec);
+                continue;
+            }
+
+            // the motion of an operator, `d3j`, `yG` or `cgg`, or the operator key again for
+            // the marked entries, or `count` entries from the cursor (`dd`, `3yy`)
+            if let Some(operator) = operator {
+                let pending = sequence.is_pending();
+                let items = match sequence.feed(&motions, combo) {
+                    Lookup::Matched(binding) => {
+                        let top = matches!(binding, Binding::Top);
+                        motion_range(app, |app| go_to_end(app, top))
+                    }
+                    Lookup::Pending => {
+                        command.start(operator, count);
+                        next_register = register;
+                        continue;
+                    }
+                    // any other key cancels the operator
+                    Lookup::Unbound if pending || !combo.is_plain() => continue,
+                    Lookup::Unbound => match combo.code {
+                        KeyCode::Char('j') | KeyCode::Down => {
+                            motion_range(app, |app| (0..count).for_each(|_| app.on_down()))
+                        }
+                        KeyCode::Char('k') | KeyCode::Up => {
+                            motion_range(app, |app| (0..count).for_each(|_| app.on_up()))
+                        }
+                        KeyCode::Char(c) if c == operator.key() && count == 1 => {
+                            app.current_pane().selection()
+                        }
+                        KeyCode::Char(c) if c == operator.key() => {
+                            motion_range(app, |app| (1..count).for_each(|_| app.on_down()))
+                        }
+                        _ => continue,
+                    },
+                };
+                apply_operator(terminal, app, operator, items, register)?;
+                continue;
+            }
+
+            if matches!(app.mode, Mode::Filer)
+                && !sequence.is_pending()
+                && blocked_by_remote(app, combo)
+            {
+                refuse_remote(app);
+                continue;
+            }
+
+            // a filer key kura does not handle itself, looked up in `filer_keys`
+            let mut to_keymap = false;
+            match &mut app.mode {
+                Mode::ConfirmDelete { items, protected } => match confirmation(protected, key) {
+                    Some(true) => {
+                        let to_delete = items.clone();
+                        app.mode = Mode::Filer;
+                        delete_items(app, &to_delete);
+                    }
+                    Some(false) => app.mode = Mode::Filer,
+                    None => {}
+                },
+                Mode::ConfirmShred { items, protected } => match confirmation(protected, key) {
+                    Some(true) => {
+                        let to_shred = std::mem::take(items);
+                        app.mode = Mode::Filer;
+                        shred_items(app, to_shred);
+                    }
+                    Some(false) => app.mode = Mode::Filer,
+                    None => {}
+                },
+                Mode::ConfirmTransfer {
+                    sources,
+                    dest,
+                    paste,
+                    hard_links: true,
+                    ..
+                } if key.code == KeyCode::Char('h') => {
+                    let (sources, dest, paste) = (std::mem::take(sources), dest.clone(), *paste);
+                    app.mode = Mode::Filer;
+                    if !paste {
+                        app.current_pane_mut().marked.clear();
+                    }
+                    app.tasks.enqueue(JobKind::Link {
+                        sources,
+                        dest,
+                        hard: true,
+                    });
+                }
+                Mode::ConfirmTransfer {
+                    sources,
+                    dest,
+                    move_items,
+                    protected,
+                    paste,
+                    ..
+                } => match confirmation(protected, key) {
+                    Some(true) => {
+                        let (sources, dest, move_items, paste) =
+                            (sources.clone(), dest.clone(), *move_items, *paste);
+                        app.mode = Mode::Filer;
+                        if paste {
+                            apply_paste(app, sources, dest, move_items);
+                        } else {
+                            apply_transfer(app, sources, dest, move_items);
+                        }
+                    }
+                    Some(false) => app.mode = Mode::Filer,
+                    None => {}
+                },
+                Mode::Viewer(viewer) => match (&mut viewer.editing, key.code) {
+                    (Some(query), KeyCode::Enter) => {
+                        let pattern = query.to_string();
+                        viewer.editing = None;
+                        viewer.set_search(pattern);
+                    }
+                    (Some(_), KeyCode::Esc) => viewer.editing = None,
+                    (Some(query), _) => {
+                        query.handle(key);
+                    }
+                    (None, KeyCode::Char('j')) => viewer.scroll_down(count),
+                    (None, KeyCode::Char('k')) => viewer.scroll_up(count),
+                    (None, KeyCode::Char('/')) => viewer.editing = Some(LineEditor::default()),
+                    (None, KeyCode::Char('n')) => viewer.next_match(),
+                    (None, KeyCode::Char('N')) => viewer.prev_match(),
+                    (None, KeyCode::Char('F')) => viewer.toggle_follow(),
+                    (None, KeyCode::Char('w')) => viewer.toggle_wrap(),
+                    (None, KeyCode::Char('h')) => {
+                        viewer.scroll_columns(-((count * HSCROLL_STEP) as isize))
+                    }
+                    (None, KeyCode::Char('l')) => {
+                        viewer.scroll_columns((count * HSCROLL_STEP) as isize)
+                    }
+                    (None, KeyCode::Esc) => viewer.set_search(String::new()),
+                    (None, KeyCode::Enter) => app.mode = Mode::Filer,
+                    _ => {}
+                },
+                Mode::Tree(tree) => match (std::mem::take(&mut tree.pending_z), key.code) {
+                    (true, KeyCode::Char(c)) => tree.fold_command(c),
+                    (_, KeyCode::Char('z')) => tree.pending_z = true,
+                    (_, KeyCode::Char('j')) => tree.down(count),
+                    (_, KeyCode::Char('k')) => tree.up(count),
+                    (_, KeyCode::Enter | KeyCode::Esc) => app.mode = Mode::Filer,
+                    _ => {}
+                },
+                Mode::Table(table) => match key.code {
+                    KeyCode::Char('j') => table.down(count),
+                    KeyCode::Char('k') => table.up(count),
+                    KeyCode::Char('l') => table.right(count),
+                    KeyCode::Char('h') => table.left(count),
+                    KeyCode::Enter | KeyCode::Esc => app.mode = Mode::Filer,
+                    _ => {}
+                },
+                Mode::Diff(diff) => match key.code {
+                    KeyCode::Char('j') => diff.scroll_down(count),
+                    KeyCode::Char('k') => diff.scroll_up(count),
+                    KeyCode::Enter | KeyCode::Esc => app.mode = Mode::Filer,
+                    _ => {}
+                },
+                Mode::Compare { left, right, sync } => match key.code {
+                    KeyCode::Char('j') | KeyCode::Char('k') => {
+                        let down = key.code == KeyCode::Char('j');
+                        let mut targets = Vec::new();
+                        if *sync || app.active == PaneType::Left {
+                            targets.push(left);
+                        }
+                        if *sync || app.active == PaneType::Right {
+                            targets.push(right);
+                        }
+                        for viewer in targets {
+                            if down {
+                                viewer.scroll_down(count);
+                            } else {
+                                viewer.scroll_up(count);
+                            }
+                        }
+                    }
+                    KeyCode::Char('s') => {
+                        *sync = !*sync;
+                        if *sync {
+                            // start synchronized scrolling from the focused viewer's position
+                            let offset = match app.active {
+                                PaneType::Left => left.offset(),
+                                PaneType::Right => right.offset(),
+                            };
+                            left.goto_line(offset);
+                            right.goto_line(offset);
+                        }
+                    }
+                    KeyCode::Char('h') | KeyCode::Char('l') | KeyCode::Tab => {
+                        app.switch_pane();
+                    }
+                    KeyCode::Enter | KeyCode::Esc => app.mode = Mode::Filer,
+                    _ => {}
+                },
+                Mode::Filer => match key.code {
+                    _ if sequence.is_pending() || !combo.is_plain() => to_keymap = true,
+                    KeyCode::Char('j') => (0..count).for_each(|_| app.on_down()),
+                    KeyCode::Char('k') => (0..count).for_each(|_| app.on_up()),
+                    // `X` only asks when the entries are protected or many
+                    KeyCode::Char(c @ ('x' | 'X')) => {
+                        let items = app.current_pane_mut().selection();
+                        request_delete(app, items, c == 'x');
+                    }
+                    KeyCode::Char('w') => {
+                        app.mode = Mode::Notifications;
+                    }
+                    KeyCode::Char('a') => {
+                        toggle_basket(app);
+                    }
+                    KeyCode::Char('e') => {
+                        app.open_basket();
+                    }
+                    KeyCode::Char('#') => {
+                        app.dry_run = !app.dry_run;
+                        app.notifications.info(if app.dry_run {
+                            "dry run on: paste, move and delete only report what they would do"
+                        } else {
+                            "dry run off"
+                        });
+                    }
+                    // operators act on the marked entries at once, or wait for a motion
+                    KeyCode::Char(c @ ('d' | 'y' | 'c')) => {
+                        let operator = match c {
+                            'd' => Operator::Trash,
+                            'y' => Operator::Yank,
+                            _ => Operator::Rename,
+                        };
+                        if app.current_pane().marked.is_empty() {
+                            command.start(operator, count);
+                            next_register = register;
+                        } else {
+                            let items = app.current_pane().selection();
+                            apply_operator(terminal, app, operator, items, register)?;
+                        }
+                    }
+                    KeyCode::Enter if app.picker.is_some() && app.pick() => return Ok(()),
+                    KeyCode::Enter => {
+                        let (is_img, path) = {
+                            let pane = app.current_pane_mut();
+                            pane.items
+                                .get(pane.selected)
+                                .filter(|_| pane.is_local())
+                                .map(|entry| {
+                                    let p = entry.path();
+                                    (is_image(&p), p)
+                                })
+                                .unwrap_or((false, PathBuf::new()))
+                        };
+                        if is_img {
+                            let images: Vec<PathBuf> = app
+                                .current_pane()
+                                .items
+                                .iter()
+                                .map(|e| e.path())
+                                .filter(|p| is_image(p))
+                                .collect();
+                            let index = images.iter().position(|p| *p == path).unwrap_or(0);
+                            app.switch_pane();
+                            show_images(terminal, input, &images, index)?;
+                        } else {
+                            app.on_enter();
+                        }
+                    }
+                    KeyCode::Char('i') => {
+                        app.open_in_other_pane();
+                    }
+                    KeyCode::Char('E') => {
+                        app.equalize_panes();
+                    }
+                    KeyCode::Char('W') => {
+                        app.swap_panes();
+                    }
+                    KeyCode::Char('m') => {
+                        app.layout.single = !app.layout.single;
+                    }
+                    KeyCode::Char('b') => {
+                        app.open_mounts();
+                    }
+                    KeyCode::Char('!') => {
+                        let pane = app.current_pane();
+                        match pane.items.get(pane.selected) {
+                            Some(entry) if !entry.is_dir() && entry.is_executable() => {
+                                app.mode = Mode::Run {
+                                    program: entry.path(),
+                                    args: LineEditor::default(),
+                                };
+                            }
+                            _ => app.notifications.warn("not an executable file"),
+                        }
+                    }
+                    KeyCode::Char('F') => {
+                        let paths: Vec<PathBuf> = app
+                            .current_pane()
+                            .selection()
+                            .into_iter()
+                            .filter(|p| is_image(p))
+                            .collect();
+                        if paths.is_empty() {
+                            app.notifications.warn("no image files selected");
+                        } else {
+                            app.current_pane_mut().marked.clear();
+                            app.mode = Mode::ImageActions { paths, selected: 0 };
+                        }
+                    }
+                    KeyCode::Char('|') => {
+                        app.layout.stacked = !app.layout.stacked;
+                    }
+                    KeyCode::Char('>') | KeyCode::Char('<') => {
+                        let grow = key.code == KeyCode::Char('>');
+                        app.layout.resize(&app.active, grow);
+                    }
+                    // the column layout only shows the active pane, so `h` and `l` move in it
+                    KeyCode::Char('h') if app.layout.columns => app.on_left(),
+                    KeyCode::Char('l') if app.layout.columns => app.on_enter(),
+                    KeyCode::Char('h') => match app.active {
+                        PaneType::Left => app.on_left(),
+                        PaneType::Right => app.switch_pane(),
+                    },
+                    KeyCode::Char('l') => match app.active {
+                        PaneType::Left => app.switch_pane(),
+                        PaneType::Right => app.on_left(),
+                    },
+                    KeyCode::Char('V') => {
+                        let pane = app.current_pane_mut();
+                        let anchor = pane.selected;
+                        pane.marked.clear();
+                        pane.set_mark(anchor, true);
+                        app.mode = Mode::Visual { anchor };
+                    }
+                    KeyCode::Char('/') => {
+                        app.mode = Mode::Search {
+                            query: LineEditor::default(),
+                            regex: app.search_regex,
+                        };
+                    }
+                    KeyCode::Char('f') => {
+                        let filter = app.current_pane().filter.clone();
+                        app.mode = Mode::Filter {
+                            query: LineEditor::new(filter),
+                        };
+                    }
+                    KeyCode::Char('n') | KeyCode::Char('N') => {
+                        let (query, regex) = (app.search.clone(), app.search_regex);
+                        let backward = key.code == KeyCode::Char('N');
+                        search(app.current_pane_mut(), &query, regex, backward);
+                    }
+                    KeyCode::Char('r') => {
+                        let pane = app.current_pane_mut();
+                        if let Some(entry) = pane.items.get(pane.selected) {
+                            app.mode = Mode::rename(entry);
+                        }
+                    }
+                    KeyCode::Char('R') => {
+                        let sources = app.current_pane().selection();
+                        edit_and_rename(terminal, app, &sources)?;
+                    }
+                    KeyCode::Char('s') => {
+                        let primary = app.current_pane().sort_by.unwrap_or_default().primary;
+                        app.mode = Mode::Sort {
+                            selected: SortBy::ALL
+                                .iter()
+                                .position(|by| *by == primary)
+                                .unwrap_or(0),
+                        };
+                    }
+                    KeyCode::Char('v') => {
+                        toggle_mark(app.current_pane_mut());
+                    }
+                    KeyCode::Char('+') | KeyCode::Char('-') => {
+                        app.mode = Mode::MarkPattern {
+                            query: LineEditor::default(),
+                            mark: key.code == KeyCode::Char('+'),
+                        };
+                    }
+                    KeyCode::Char('L') => {
+                        let pane = app.current_pane_mut();
+                        pane.size_display = pane.size_display.next();
+                    }
+                    KeyCode::Char('I') => {
+                        inspect(app);
+                    }
+                    KeyCode::Char(']') | KeyCode::Char('[') => {
+                        app.current_pane_mut()
+                            .turn_page(key.code == KeyCode::Char(']'));
+                    }
+                    KeyCode::Char('o') => {
+                        open_in_editor(terminal, app)?;
+                    }
+                    KeyCode::Char('O') => {
+                        app.mode = Mode::Location {
+                            buffer: LineEditor::default(),
+                        };
+                    }
+                    KeyCode::Char('K') => {
+                        app.current_pane_mut().toggle_root_lock();
+                    }
+                    KeyCode::Char('M') => {
+                        app.open_todos();
+                    }
+                    KeyCode::Char('H') => {
+                        app.current_pane_mut().toggle_commits();
+                    }
+                    KeyCode::Char('.') => {
+                        app.current_pane_mut().toggle_hidden();
+                    }
+                    KeyCode::Char('t') => {
+                        app.current_pane_mut().toggle_grouping();
+                    }
+                    // most terminals send Tab for Ctrl-i, which only folds date groups
+                    KeyCode::Tab if app.current_pane().groups.is_empty() => app.walk_jumps(false),
+                    KeyCode::Tab => {
+                        app.current_pane_mut().toggle_group_fold();
+                    }
+                    KeyCode::Char('A') => {
+                        mark_all(app.current_pane_mut());
+                    }
+                    KeyCode::Char('*') => {
+                        invert_marks(app.current_pane_mut());
+                    }
+                    KeyCode::Char('"') => {
+                        app.mode = Mode::Registers { selected: 0 };
+                    }
+                    KeyCode::Char('Y') => {
+                        copy_as_arguments(app);
+                    }
+                    KeyCode::Char('p') => {
+                        paste(app, register);
+                    }
+                    KeyCode::Char('P') => {
+                        move_clipboard(app, register);
+                    }
+                    KeyCode::Char('C') => {
+                        app.open_compare();
+                    }
+                    KeyCode::Char('U') => {
+                        preview_sync(app);
+                    }
+                    KeyCode::Char('=') => {
+                        app.open_diff();
+                    }
+                    KeyCode::Char('B') => {
+                        compare_binary(app);
+                    }
+                    KeyCode::Char('D') => {
+                        if let Some((a, b)) = app.pair_selection() {
+                            let tool = app.config.diff_tool.clone();
+                            open_diff_tool(terminal, &mut app.notifications, &tool, &a, &b)?;
+                            app.refresh_panes();
+                        }
+                    }
+                    KeyCode::F(5) => {
+                        transfer_to_other_pane(app, false);
+                    }
+                    KeyCode::F(6) => {
+                        transfer_to_other_pane(app, true);
+                    }
+                    KeyCode::Char('T') => {
+                        app.mode = Mode::Tasks { selected: 0 };
+                    }
+                    KeyCode::Char('Z') => {
+                        archive_selection(app);
+                    }
+                    KeyCode::Char('S') => {
+                        let dir = app.current_pane_mut().current_dir.clone();
+                        open_shell(terminal, &mut app.notifications, &dir)?;
+                        app.refresh_panes();
+                    }
+                    _ => to_keymap = true,
+                },
+                _ => {}
+            }
+
+            if !to_keymap {
+                continue;
+            }
+            match sequence.feed(&filer_keys, combo) {
+                Lookup::Matched(Binding::Top) => go_to_end(app, true),
+                Lookup::Matched(Binding::Bottom) => go_to_end(app, false),
+                Lookup::Matched(Binding::Align(align)) => app.current_pane().align(*align),
+                Lookup::Matched(Binding::Jump) => {
+                    if app.current_pane().is_local() {
+                        app.mode = Mode::Jump {
+                            query: LineEditor::default(),
+                            selected: 0,
+                        };
+                    }
+                }
+                Lookup::Matched(Binding::Page { down, half }) => {
+                    let pane = app.current_pane_mut();
+                    let height = pane.view_height.get();
+                    // like vim, a full page keeps two rows of context
+                    let page = if *half {
+                        height / 2
+                    } else {
+                        height.saturating_sub(2)
+                    };
+                    let rows = (page.max(1) * count) as isize;
+                    pane.scroll_rows(if *down { rows } else { -rows });
+                }
+                Lookup::Matched(
+                    Binding::PasteLinks { .. }
+                    | Binding::PasteAs
+                    | Binding::Create
+                    | Binding::RememberView
+                    | Binding::ForEach
+                    | Binding::Find
+                    | Binding::Reveal
+                    | Binding::Share
+                    | Binding::Extract
+                    | Binding::Shred
+                    | Binding::Verify
+                    | Binding::Watch
+                    | Binding::BrokenLinks
+                    | Binding::HardLinks
+                    | Binding::Command(_)
+                    | Binding::Script(_),
+                ) if !app.current_pane().is_local() => {
+                    refuse_remote(app);
+                }
+                Lookup::Matched(Binding::Command(i)) => {
+                    let command = app.config.commands[*i].clone();
+                    run_user_command(terminal, app, &command)?;
+                }
+                Lookup::Matched(Binding::PasteLinks { hard }) => paste_links(app, register, *hard),
+                Lookup::Matched(Binding::PasteAs) => paste_as(app, register),
+                Lookup::Matched(Binding::Counts) => app.current_pane_mut().toggle_counts(),
+                Lookup::Matched(Binding::CompareDirs)
+                    if !app.current_pane().is_local() || !app.other_pane().is_local() =>
+                {
+                    refuse_remote(app);
+                }
+                Lookup::Matched(Binding::CompareDirs) => compare_directories(app),
+                Lookup::Matched(Binding::JumpList { back }) => app.walk_jumps(*back),
+                Lookup::Matched(Binding::RememberView) => {
+                    let pane = app.current_pane_mut();
+                    let dir = pane.current_dir.display().to_string();
+                    let message = if pane.toggle_remembered_view() {
+                        format!("view settings remembered for {dir}")
+                    } else {
+                        format!("view settings for {dir} forgotten")
+                    };
+                    app.notifications.info(message);
+                }
+                Lookup::Matched(Binding::Columns) => {
+                    app.layout.columns = !app.layout.columns;
+                    app.columns.clear();
+                }
+                Lookup::Matched(Binding::Find) => app.open_finder(),
+                Lookup::Matched(Binding::Reveal) => app.reveal_selected(),
+                Lookup::Matched(Binding::Extract) => extract_selection(app),
+                Lookup::Matched(Binding::Verify) => app.verify_selected(),
+                Lookup::Matched(Binding::Watch) if app.watching.is_some() => app.stop_watch(),
+                Lookup::Matched(Binding::HardLinks) => find_hard_links(app),
+                Lookup::Matched(Binding::BrokenLinks) => {
+                    let dir = app.current_pane().current_dir.clone();
+                    app.mode = Mode::Links(LinkScan::start(dir, LinkSearch::Broken));
+                }
+                Lookup::Matched(Binding::Watch) => {
+                    let pane = app.current_pane();
+                    let dir = match pane.items.get(pane.selected) {
+                        Some(entry) if entry.is_dir() => entry.path(),
+                        _ => pane.current_dir.clone(),
+                    };
+                    app.mode = Mode::Watch {
+                        dir,
+                        template: LineEditor::default(),
+                    };
+                }
+                Lookup::Matched(Binding::Shred) => {
+                    let items = app.current_pane().selection();
+                    request_shred(app, items);
+                }
+                Lookup::Matched(Binding::Share) => {
+                    let paths = app.current_pane().selection();
+                    if app.config.shares.is_empty() {
+                        app.notifications
+                            .warn("no share targets: add `share.<name> = <command>` to the config");
+                    } else if !paths.is_empty() {
+                        app.mode = Mode::Share { paths, selected: 0 };
+                    }
+                }
+                Lookup::Matched(Binding::ForEach) => {
+                    let paths = app.current_pane().selection();
+                    if !paths.is_empty() {
+                        app.mode = Mode::ForEach {
+                            paths,
+                            template: LineEditor::default(),
+                        };
+                    }
+                }
+                Lookup::Matched(Binding::MarkWhere { mark }) => {
+                    app.mode = Mode::MarkWhere {
+                        query: LineEditor::default(),
+                        mark: *mark,
+                    };
+                }
+                Lookup::Matched(Binding::Heat) => {
+                    let pane = app.current_pane_mut();
+                    pane.heat = pane.heat.next();
+                }
+                Lookup::Matched(Binding::Create) => {
+                    app.mode = Mode::Create {
+                        buffer: LineEditor::default(),
+                        confirmed: false,
+                    };
+                }
+                Lookup::Matched(Binding::Breadcrumbs) => {
+                    // the parent to start with
+                    let dirs = crumb_dirs(app.current_pane());
+                    app.mode = Mode::Breadcrumbs {
+                        selected: dirs.len().saturating_sub(2),
+                    };
+                }
+                Lookup::Matched(Binding::Script(keys)) => run_script(app, keys),
+                // a register picked for `gp` waits for the end of the sequence
+                Lookup::Pending => next_register = register,
+                Lookup::Unbound => {}
             }
         }
     }
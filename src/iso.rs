@@ -0,0 +1,279 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs::File,
+    io::{self, Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use chrono::{FixedOffset, NaiveDate, TimeZone};
+
+use crate::vfs::{Entry, RemoteEntry, VfsProvider};
+
+const SECTOR: u64 = 2048;
+
+/// Where the volume descriptors start, after the system area.
+const FIRST_DESCRIPTOR: u64 = 16;
+
+/// Deepest directory listed; ISO9660 allows 8 levels, Rock Ridge images go further.
+const MAX_DEPTH: usize = 64;
+
+/// Whether `path` is an ISO9660 image (`.iso`, or a raw `.img` holding one), by its content.
+pub fn is_iso(path: &Path) -> bool {
+    let Ok(mut file) = File::open(path) else {
+        return false;
+    };
+    let mut magic = [0u8; 5];
+    file.seek(SeekFrom::Start(FIRST_DESCRIPTOR * SECTOR + 1))
+        .is_ok()
+        && file.read_exact(&mut magic).is_ok()
+        && &magic == b"CD001"
+}
+
+/// A file in the image: where its data lies and what the listing shows.
+#[derive(Clone)]
+struct IsoFile {
+    name: String,
+    is_dir: bool,
+    /// First sector of the data
+    extent: u32,
+    len: u64,
+    modified: Option<SystemTime>,
+}
+
+/// The ISO9660 file system in a disk image, read-only, browsed like a remote directory tree
+/// without mounting it. Joliet names are used when the image has them, then Rock Ridge names,
+/// then the plain ISO9660 ones without their `;1` version.
+pub struct IsoFs {
+    image: PathBuf,
+    /// Entries of each directory, by path in the image (`/` for the root)
+    dirs: HashMap<PathBuf, Vec<IsoFile>>,
+}
+
+impl IsoFs {
+    /// Read the directory tree of the image `image`.
+    pub fn open(image: &Path) -> io::Result<Self> {
+        let mut file = File::open(image)?;
+        let mut root = None;
+        for n in FIRST_DESCRIPTOR.. {
+            let descriptor = read_at(&mut file, n * SECTOR, SECTOR as usize)?;
+            if &descriptor[1..6] != b"CD001" {
+                return Err(invalid("not an ISO9660 image"));
+            }
+            match descriptor[0] {
+                // primary volume descriptor
+                1 if root.is_none() => root = Some((record(&descriptor[156..])?, false)),
+                // a supplementary one with a UCS-2 escape sequence is Joliet
+                2 if matches!(&descriptor[88..91], b"%/@" | b"%/C" | b"%/E") => {
+                    root = Some((record(&descriptor[156..])?, true));
+                }
+                // terminator
+                255 => break,
+                _ => {}
+            }
+        }
+        let (root, joliet) = root.ok_or_else(|| invalid("no primary volume descriptor"))?;
+        let mut fs = Self {
+            image: image.to_path_buf(),
+            dirs: HashMap::new(),
+        };
+        // a corrupt image could list a directory inside itself or one of its parents, so each
+        // directory's data is read once
+        let mut read = HashSet::new();
+        let mut stack = vec![(PathBuf::from("/"), root, 0)];
+        while let Some((path, dir, depth)) = stack.pop() {
+            if !read.insert(dir.extent) || fs.dirs.contains_key(&path) {
+                continue;
+            }
+            let entries = read_dir(&mut file, &dir, joliet)?;
+            if depth < MAX_DEPTH {
+                for entry in entries.iter().filter(|e| e.is_dir) {
+                    stack.push((path.join(&entry.name), entry.clone(), depth + 1));
+                }
+            }
+            fs.dirs.insert(path, entries);
+        }
+        Ok(fs)
+    }
+
+    fn find(&self, path: &Path) -> Option<&IsoFile> {
+        let name = path.file_name()?.to_string_lossy();
+        self.dirs
+            .get(path.parent()?)?
+            .iter()
+            .find(|e| e.name == name)
+    }
+}
+
+impl VfsProvider for IsoFs {
+    fn label(&self) -> String {
+        format!("iso:{}", self.image.display())
+    }
+
+    fn read_dir(&self, dir: &Path) -> io::Result<Box<dyn Iterator<Item = io::Result<Entry>> + '_>> {
+        let dir = dir.to_path_buf();
+        let entries = self
+            .dirs
+            .get(&dir)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no such directory"))?;
+        Ok(Box::new(entries.iter().map(move |e| {
+            Ok(Entry::Remote(RemoteEntry {
+                path: dir.join(&e.name),
+                is_dir: e.is_dir,
+                is_symlink: false,
+                len: e.len,
+                modified: e.modified,
+                mode: None,
+            }))
+        })))
+    }
+
+    fn rename(&self, _from: &Path, _to: &Path) -> io::Result<()> {
+        Err(read_only())
+    }
+
+    fn create_dir(&self, _path: &Path) -> io::Result<()> {
+        Err(read_only())
+    }
+
+    fn remove_file(&self, _path: &Path) -> io::Result<()> {
+        Err(read_only())
+    }
+
+    fn remove_dir(&self, _path: &Path) -> io::Result<()> {
+        Err(read_only())
+    }
+
+    fn download(&self, path: &Path, dest: &Path) -> io::Result<()> {
+        let entry = self
+            .find(path)
+            .filter(|e| !e.is_dir)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no such file"))?;
+        let mut image = File::open(&self.image)?;
+        image.seek(SeekFrom::Start(entry.extent as u64 * SECTOR))?;
+        let copied = io::copy(&mut image.take(entry.len), &mut File::create(dest)?)?;
+        if copied < entry.len {
+            return Err(invalid("the image is truncated"));
+        }
+        Ok(())
+    }
+
+    fn upload(&self, _source: &Path, _path: &Path) -> io::Result<()> {
+        Err(read_only())
+    }
+
+    fn image(&self) -> Option<PathBuf> {
+        Some(self.image.clone())
+    }
+}
+
+/// The entries of the directory `dir`, without `.` and `..`.
+fn read_dir(file: &mut File, dir: &IsoFile, joliet: bool) -> io::Result<Vec<IsoFile>> {
+    let data = read_at(file, dir.extent as u64 * SECTOR, dir.len as usize)?;
+    let mut entries = Vec::new();
+    let mut at = 0;
+    while at < data.len() {
+        let len = data[at] as usize;
+        if len == 0 {
+            // records do not cross sectors: the rest of this one is padding
+            at = (at / SECTOR as usize + 1) * SECTOR as usize;
+            continue;
+        }
+        let bytes = data
+            .get(at..at + len)
+            .ok_or_else(|| invalid("bad record"))?;
+        at += len;
+        let mut entry = record(bytes)?;
+        let raw_name = bytes
+            .get(33..33 + bytes[32] as usize)
+            .ok_or_else(|| invalid("bad record"))?;
+        if raw_name == [0] || raw_name == [1] {
+            continue;
+        }
+        entry.name = if joliet {
+            let units: Vec<u16> = raw_name
+                .chunks_exact(2)
+                .map(|c| u16::from_be_bytes([c[0], c[1]]))
+                .collect();
+            strip_version(&String::from_utf16_lossy(&units))
+        } else {
+            // the system use area after the (even-padded) name may hold a Rock Ridge name
+            let system_use = bytes
+                .get((33 + raw_name.len() + 1) & !1..)
+                .unwrap_or_default();
+            rock_ridge_name(system_use)
+                .unwrap_or_else(|| strip_version(&String::from_utf8_lossy(raw_name)))
+        };
+        entries.push(entry);
+    }
+    Ok(entries)
+}
+
+/// A directory record, without its name.
+fn record(bytes: &[u8]) -> io::Result<IsoFile> {
+    if bytes.len() < 34 {
+        return Err(invalid("bad record"));
+    }
+    let le =
+        |at: usize| u32::from_le_bytes([bytes[at], bytes[at + 1], bytes[at + 2], bytes[at + 3]]);
+    Ok(IsoFile {
+        name: String::new(),
+        is_dir: bytes[25] & 2 != 0,
+        extent: le(2),
+        len: le(10) as u64,
+        modified: recorded_time(&bytes[18..25]),
+    })
+}
+
+/// Years since 1900, month, day, hour, minute, second and offset from UTC in 15 minutes.
+fn recorded_time(t: &[u8]) -> Option<SystemTime> {
+    let offset = FixedOffset::east_opt(t[6] as i8 as i32 * 15 * 60)?;
+    let time = NaiveDate::from_ymd_opt(1900 + t[0] as i32, t[1] as u32, t[2] as u32)?.and_hms_opt(
+        t[3] as u32,
+        t[4] as u32,
+        t[5] as u32,
+    )?;
+    let time = offset.from_local_datetime(&time).single()?;
+    Some(SystemTime::from(time))
+}
+
+/// The name in a Rock Ridge `NM` entry, if the system use area has one.
+fn rock_ridge_name(mut area: &[u8]) -> Option<String> {
+    let mut name = Vec::new();
+    while area.len() >= 4 {
+        let len = area[2] as usize;
+        if len < 4 || len > area.len() {
+            break;
+        }
+        if &area[..2] == b"NM" && len > 5 {
+            // the name goes on in the next `NM` entry while the CONTINUE flag is set
+            name.extend_from_slice(&area[5..len]);
+            if area[4] & 1 == 0 {
+                return Some(String::from_utf8_lossy(&name).into_owned());
+            }
+        }
+        area = &area[len..];
+    }
+    None
+}
+
+/// `NAME.TXT;1` as `NAME.TXT`, and `NAME.;1` as `NAME`.
+fn strip_version(name: &str) -> String {
+    let name = name.split(';').next().unwrap_or(name);
+    name.strip_suffix('.').unwrap_or(name).to_string()
+}
+
+fn read_at(file: &mut File, offset: u64, len: usize) -> io::Result<Vec<u8>> {
+    let mut buffer = vec![0; len];
+    file.seek(SeekFrom::Start(offset))?;
+    file.read_exact(&mut buffer)?;
+    Ok(buffer)
+}
+
+fn invalid(why: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, why.to_string())
+}
+
+fn read_only() -> io::Error {
+    io::Error::new(io::ErrorKind::Unsupported, "disk images are read-only")
+}
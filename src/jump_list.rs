@@ -0,0 +1,59 @@
+use std::{ffi::OsString, path::PathBuf, rc::Rc};
+
+use crate::vfs::VfsProvider;
+
+/// Places kept behind and ahead of the current one.
+const LENGTH: usize = 100;
+
+/// A place a pane was at: its directory, on its file system, and the entry under the cursor.
+#[derive(Clone)]
+pub struct Jump {
+    pub vfs: Rc<dyn VfsProvider>,
+    pub dir: PathBuf,
+    pub selected: Option<OsString>,
+}
+
+/// The directories a pane went through, to walk back and forth with Ctrl-o and Ctrl-i like
+/// a browser's history.
+#[derive(Default)]
+pub struct JumpList {
+    /// Most recent last
+    back: Vec<Jump>,
+    /// Next one last
+    forward: Vec<Jump>,
+}
+
+impl JumpList {
+    /// Remember `from` when the pane leaves it for a new place, which drops the places ahead.
+    pub fn record(&mut self, from: Jump) {
+        self.forward.clear();
+        push(&mut self.back, from);
+    }
+
+    /// The place before the current one.
+    pub fn back(&mut self) -> Option<Jump> {
+        self.back.pop()
+    }
+
+    /// The place the pane came back from.
+    pub fn forward(&mut self) -> Option<Jump> {
+        self.forward.pop()
+    }
+
+    /// The pane went back from `from`, which is now ahead of it.
+    pub fn went_back(&mut self, from: Jump) {
+        push(&mut self.forward, from);
+    }
+
+    /// The pane went forward from `from`, which is now behind it.
+    pub fn went_forward(&mut self, from: Jump) {
+        push(&mut self.back, from);
+    }
+}
+
+fn push(jumps: &mut Vec<Jump>, jump: Jump) {
+    if jumps.len() == LENGTH {
+        jumps.remove(0);
+    }
+    jumps.push(jump);
+}
@@ -0,0 +1,274 @@
+use std::fmt;
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+/// A key together with the Ctrl and Alt modifiers held down with it.
+///
+/// Shift is folded into the character (`G` rather than Shift-`g`), as terminals report it
+/// inconsistently, so it is only kept for keys that are not characters (`<S-Tab>`).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct KeyCombo {
+    pub code: KeyCode,
+    pub modifiers: KeyModifiers,
+}
+
+impl KeyCombo {
+    pub const fn new(code: KeyCode, modifiers: KeyModifiers) -> Self {
+        Self { code, modifiers }
+    }
+
+    pub const fn char(c: char) -> Self {
+        Self::new(KeyCode::Char(c), KeyModifiers::NONE)
+    }
+
+    pub const fn ctrl(c: char) -> Self {
+        Self::new(KeyCode::Char(c), KeyModifiers::CONTROL)
+    }
+
+    /// Whether neither Ctrl nor Alt is held.
+    pub fn is_plain(&self) -> bool {
+        !self
+            .modifiers
+            .intersects(KeyModifiers::CONTROL | KeyModifiers::ALT)
+    }
+
+    /// Parse a sequence written like a Vim mapping: characters stand for themselves and
+    /// `<...>` names a key with modifiers, e.g. `gx`, `<C-t>`, `<A-Enter>` or `<F5>`. A `<`
+    /// that does not start a key name is the `<` key itself; `<lt>` always is.
+    pub fn parse_sequence(text: &str) -> Result<Vec<KeyCombo>, String> {
+        let mut keys = Vec::new();
+        let mut rest = text;
+        while let Some(c) = rest.chars().next() {
+            let named = rest
+                .strip_prefix('<')
+                .and_then(|r| r.split_once('>'))
+                .and_then(|(name, after)| Some((Self::parse_name(name)?, after)));
+            match named {
+                Some((key, after)) => {
+                    keys.push(key);
+                    rest = after;
+                }
+                None => {
+                    keys.push(Self::char(c));
+                    rest = &rest[c.len_utf8()..];
+                }
+            }
+        }
+        if keys.is_empty() {
+            return Err("empty key sequence".into());
+        }
+        Ok(keys)
+    }
+
+    /// A key name between `<` and `>`: modifier prefixes (`C-`, `A-` or `M-`, `S-`) followed
+    /// by a character or a special key name.
+    fn parse_name(name: &str) -> Option<KeyCombo> {
+        let mut modifiers = KeyModifiers::NONE;
+        let mut rest = name;
+        loop {
+            let modifier = match rest.get(..2).map(str::to_ascii_uppercase).as_deref() {
+                Some("C-") => KeyModifiers::CONTROL,
+                Some("A-" | "M-") => KeyModifiers::ALT,
+                Some("S-") => KeyModifiers::SHIFT,
+                _ => break,
+            };
+            modifiers |= modifier;
+            rest = &rest[2..];
+        }
+        let mut chars = rest.chars();
+        let code = match (chars.next(), chars.next()) {
+            (Some(c), None) => KeyCode::Char(c),
+            _ => match rest.to_ascii_lowercase().as_str() {
+                "enter" | "cr" | "return" => KeyCode::Enter,
+                "esc" => KeyCode::Esc,
+                "tab" => KeyCode::Tab,
+                "bs" | "backspace" => KeyCode::Backspace,
+                "del" | "delete" => KeyCode::Delete,
+                "space" => KeyCode::Char(' '),
+                "lt" => KeyCode::Char('<'),
+                "gt" => KeyCode::Char('>'),
+                "up" => KeyCode::Up,
+                "down" => KeyCode::Down,
+                "left" => KeyCode::Left,
+                "right" => KeyCode::Right,
+                "home" => KeyCode::Home,
+                "end" => KeyCode::End,
+                "pageup" => KeyCode::PageUp,
+                "pagedown" => KeyCode::PageDown,
+                "insert" => KeyCode::Insert,
+                f => KeyCode::F(f.strip_prefix('f')?.parse().ok().filter(|n| *n > 0)?),
+            },
+        };
+        Some(KeyCombo::from(KeyEvent::new(code, modifiers)))
+    }
+}
+
+impl From<KeyEvent> for KeyCombo {
+    fn from(key: KeyEvent) -> Self {
+        let mut modifiers =
+            key.modifiers & (KeyModifiers::CONTROL | KeyModifiers::ALT | KeyModifiers::SHIFT);
+        let code = match key.code {
+            KeyCode::Char(c) => {
+                modifiers.remove(KeyModifiers::SHIFT);
+                // terminals send Ctrl-Shift-x as Ctrl-x
+                KeyCode::Char(if modifiers.is_empty() {
+                    c
+                } else {
+                    c.to_ascii_lowercase()
+                })
+            }
+            KeyCode::BackTab => {
+                modifiers.insert(KeyModifiers::SHIFT);
+                KeyCode::Tab
+            }
+            code => code,
+        };
+        Self { code, modifiers }
+    }
+}
+
+impl fmt::Display for KeyCombo {
+    /// The notation `parse_sequence` reads.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self.code {
+            KeyCode::Char('<') => "lt".to_string(),
+            KeyCode::Char(' ') => "Space".to_string(),
+            KeyCode::Char(c) if self.is_plain() => return write!(f, "{c}"),
+            KeyCode::Char(c) => c.to_string(),
+            KeyCode::Enter => "Enter".to_string(),
+            KeyCode::Esc => "Esc".to_string(),
+            KeyCode::Tab => "Tab".to_string(),
+            KeyCode::Backspace => "BS".to_string(),
+            KeyCode::Delete => "Del".to_string(),
+            KeyCode::F(n) => format!("F{n}"),
+            code => format!("{code:?}"),
+        };
+        let mut prefix = String::new();
+        if self.modifiers.contains(KeyModifiers::CONTROL) {
+            prefix.push_str("C-");
+        }
+        if self.modifiers.contains(KeyModifiers::ALT) {
+            prefix.push_str("A-");
+        }
+        if self.modifiers.contains(KeyModifiers::SHIFT) {
+            prefix.push_str("S-");
+        }
+        write!(f, "<{prefix}{name}>")
+    }
+}
+
+/// What a sequence of keys is bound to so far.
+pub enum Lookup<'a, T> {
+    Matched(&'a T),
+    /// The keys are the start of one or more longer bindings
+    Pending,
+    Unbound,
+}
+
+/// Bindings of key sequences (`gg`, `zt`, `<C-d>`) to values.
+pub struct Keymap<T> {
+    bindings: Vec<(Vec<KeyCombo>, T)>,
+}
+
+impl<T> Default for Keymap<T> {
+    fn default() -> Self {
+        Self {
+            bindings: Vec::new(),
+        }
+    }
+}
+
+impl<T> Keymap<T> {
+    /// Bind `keys` to `value`, replacing an earlier binding of the same sequence.
+    pub fn bind(&mut self, keys: Vec<KeyCombo>, value: T) {
+        self.bindings.retain(|(k, _)| *k != keys);
+        self.bindings.push((keys, value));
+    }
+
+    pub fn lookup(&self, keys: &[KeyCombo]) -> Lookup<'_, T> {
+        let mut pending = false;
+        for (bound, value) in &self.bindings {
+            if bound == keys {
+                return Lookup::Matched(value);
+            }
+            pending |= bound.starts_with(keys);
+        }
+        if pending {
+            Lookup::Pending
+        } else {
+            Lookup::Unbound
+        }
+    }
+}
+
+/// The keys typed so far of a sequence that is not complete yet.
+#[derive(Default)]
+pub struct KeySequence {
+    keys: Vec<KeyCombo>,
+}
+
+impl KeySequence {
+    pub fn is_pending(&self) -> bool {
+        !self.keys.is_empty()
+    }
+
+    pub fn keys(&self) -> &[KeyCombo] {
+        &self.keys
+    }
+
+    pub fn clear(&mut self) {
+        self.keys.clear();
+    }
+
+    /// Add `key` to the sequence and look it up in `keymap`. A sequence that can no longer
+    /// match is dropped, `key` included, like an unknown command in Vim.
+    pub fn feed<'a, T>(&mut self, keymap: &'a Keymap<T>, key: KeyCombo) -> Lookup<'a, T> {
+        self.keys.push(key);
+        let lookup = keymap.lookup(&self.keys);
+        if !matches!(lookup, Lookup::Pending) {
+            self.keys.clear();
+        }
+        lookup
+    }
+}
+
+/// The count and operator typed so far of a Vim-style command: `3` then `j`, or an operator
+/// waiting for its motion, like `d`, `d3` or `2d3` before `j`.
+pub struct PendingCommand<O> {
+    count: usize,
+    /// Operator with the count typed before it
+    operator: Option<(O, usize)>,
+}
+
+impl<O> Default for PendingCommand<O> {
+    fn default() -> Self {
+        Self {
+            count: 0,
+            operator: None,
+        }
+    }
+}
+
+impl<O> PendingCommand<O> {
+    /// Add a digit to the count being typed.
+    pub fn digit(&mut self, digit: u32) {
+        self.count = self.count.saturating_mul(10).saturating_add(digit as usize);
+    }
+
+    /// Wait for the motion of `operator`, to be applied `count` times.
+    pub fn start(&mut self, operator: O, count: usize) {
+        self.operator = Some((operator, count));
+        self.count = 0;
+    }
+
+    /// Finish the command: the count for the motion, the counts before and after an operator
+    /// multiplied (`2d3j` moves six entries), and the operator if one is waiting.
+    pub fn take(&mut self) -> (usize, Option<O>) {
+        let count = self.count.max(1);
+        self.count = 0;
+        match self.operator.take() {
+            Some((operator, before)) => (before.saturating_mul(count), Some(operator)),
+            None => (count, None),
+        }
+    }
+}
@@ -0,0 +1,59 @@
+//! The file manager engine behind kura: panes and their listings, file operations and the
+//! task queue, sorting, virtual file systems and viewers, with no terminal I/O, so other
+//! frontends and tests can drive it.
+
+pub mod actions;
+pub mod app;
+pub mod archive;
+pub mod checksum;
+pub mod clipboard;
+pub mod columns;
+pub mod config;
+pub mod diff;
+pub mod dir_stats;
+pub mod dir_views;
+pub mod elevate;
+pub mod exif;
+pub mod file_index;
+pub mod file_type;
+pub mod format;
+pub mod frecency;
+pub mod fs_utils;
+pub mod git;
+pub mod heat;
+pub mod image_ops;
+pub mod iso;
+pub mod jump_list;
+pub mod keys;
+pub mod line_edit;
+pub mod links;
+pub mod log_view;
+pub mod logging;
+pub mod ls_colors;
+pub mod media;
+pub mod mode;
+pub mod mounts;
+pub mod notification;
+pub mod nvim;
+pub mod predicate;
+pub mod registers;
+pub mod rename;
+pub mod resume;
+pub mod rsync;
+pub mod runner;
+pub mod s3;
+pub mod scripting;
+pub mod sftp;
+pub mod shred;
+pub mod signature;
+pub mod sync;
+pub mod table_view;
+pub mod tasks;
+pub mod todo_scan;
+pub mod trash;
+pub mod tree_view;
+pub mod vfs;
+pub mod viewer;
+pub mod watch;
+pub mod webdav;
+pub mod xattr;
@@ -0,0 +1,105 @@
+use std::{fmt, ops::Deref};
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use unicode_width::UnicodeWidthStr;
+
+/// One line of text being typed into a prompt, with a cursor that can move within it.
+#[derive(Clone, Default)]
+pub struct LineEditor {
+    text: String,
+    /// Byte offset of the cursor, always on a character boundary
+    cursor: usize,
+}
+
+impl LineEditor {
+    /// Start editing `text` with the cursor at its end.
+    pub fn new(text: impl Into<String>) -> Self {
+        let text = text.into();
+        Self {
+            cursor: text.len(),
+            text,
+        }
+    }
+
+    /// Display width of the text before the cursor, for placing the terminal cursor.
+    pub fn cursor_width(&self) -> usize {
+        self.text[..self.cursor].width()
+    }
+
+    /// The text with each character shown as `*`, as for a password, and the cursor's column
+    /// in it.
+    pub fn masked(&self) -> (String, usize) {
+        (
+            "*".repeat(self.text.chars().count()),
+            self.text[..self.cursor].chars().count(),
+        )
+    }
+
+    /// Apply an editing key: characters are inserted at the cursor, `←`/`→`/`Home`/`End`
+    /// move it, `Backspace`/`Delete` delete around it, and `Ctrl-W` deletes the word before it.
+    ///
+    /// Returns whether the text changed. Other keys, such as `Enter` and `Esc`, are ignored
+    /// and left to the prompt.
+    pub fn handle(&mut self, key: KeyEvent) -> bool {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        match key.code {
+            KeyCode::Char('w') if ctrl => {
+                let before = self.text[..self.cursor].trim_end();
+                let start = before
+                    .char_indices()
+                    .rev()
+                    .find(|(_, c)| c.is_whitespace() || *c == '/')
+                    .map_or(0, |(i, c)| i + c.len_utf8());
+                let changed = start < self.cursor;
+                self.text.replace_range(start..self.cursor, "");
+                self.cursor = start;
+                return changed;
+            }
+            KeyCode::Char(_) if ctrl => {}
+            KeyCode::Char(c) => {
+                self.text.insert(self.cursor, c);
+                self.cursor += c.len_utf8();
+                return true;
+            }
+            KeyCode::Backspace => {
+                if let Some(c) = self.text[..self.cursor].chars().next_back() {
+                    self.cursor -= c.len_utf8();
+                    self.text.remove(self.cursor);
+                    return true;
+                }
+            }
+            KeyCode::Delete if self.cursor < self.text.len() => {
+                self.text.remove(self.cursor);
+                return true;
+            }
+            KeyCode::Left => {
+                if let Some(c) = self.text[..self.cursor].chars().next_back() {
+                    self.cursor -= c.len_utf8();
+                }
+            }
+            KeyCode::Right => {
+                if let Some(c) = self.text[self.cursor..].chars().next() {
+                    self.cursor += c.len_utf8();
+                }
+            }
+            KeyCode::Home => self.cursor = 0,
+            KeyCode::End => self.cursor = self.text.len(),
+            _ => {}
+        }
+        false
+    }
+}
+
+impl Deref for LineEditor {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.text
+    }
+}
+
+impl fmt::Display for LineEditor {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.text)
+    }
+}
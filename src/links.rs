@@ -0,0 +1,204 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::mpsc::{self, Receiver},
+    thread,
+};
+
+/// Links followed before a chain counts as a loop, as in Linux's path resolution.
+const MAX_HOPS: usize = 40;
+
+/// How a chain of symbolic links ends.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ChainEnd {
+    /// At an existing file or directory
+    Resolved,
+    /// At a path where nothing exists
+    Broken,
+    /// Back at a link already followed, or after too many links
+    Loop,
+}
+
+/// The targets of the symbolic link `path`, link after link, as written in each, and how the
+/// chain ends. Empty when `path` is not a link.
+pub fn link_chain(path: &Path) -> (Vec<PathBuf>, ChainEnd) {
+    let mut targets = Vec::new();
+    let mut seen = vec![path.to_path_buf()];
+    let mut link = path.to_path_buf();
+    while let Ok(target) = fs::read_link(&link) {
+        // a relative target is relative to the link's directory
+        let next = link.parent().unwrap_or(Path::new("")).join(&target);
+        targets.push(target);
+        if seen.contains(&next) || targets.len() >= MAX_HOPS {
+            return (targets, ChainEnd::Loop);
+        }
+        seen.push(next.clone());
+        link = next;
+    }
+    let end = if link.symlink_metadata().is_ok() {
+        ChainEnd::Resolved
+    } else {
+        ChainEnd::Broken
+    };
+    (targets, end)
+}
+
+/// What a `LinkScan` looks for.
+#[derive(Clone, Debug, PartialEq)]
+pub enum LinkSearch {
+    /// Symbolic links to nothing
+    Broken,
+    /// Other names of the file `of`, which has `count` names in all
+    HardLinks { of: PathBuf, id: FileId, count: u64 },
+}
+
+/// Links under a directory, found by a scan in the background, listed for going to them or
+/// deleting them.
+pub struct LinkScan {
+    pub dir: PathBuf,
+    pub search: LinkSearch,
+    pub links: Vec<PathBuf>,
+    pub selected: usize,
+    /// Links found by the scan, until it has finished
+    scan: Option<Receiver<PathBuf>>,
+}
+
+impl LinkScan {
+    /// Start scanning `dir` and everything under it, without following links to directories.
+    /// A search for hard links stays on the file's file system and stops once it has found
+    /// them all.
+    pub fn start(dir: PathBuf, search: LinkSearch) -> Self {
+        let (tx, rx) = mpsc::channel();
+        let root = dir.clone();
+        let wanted = search.clone();
+        thread::spawn(move || {
+            let mut left = match wanted {
+                LinkSearch::HardLinks { count, .. } => count,
+                LinkSearch::Broken => u64::MAX,
+            };
+            let mut stack = vec![root];
+            while let Some(dir) = stack.pop() {
+                let Ok(entries) = fs::read_dir(&dir) else {
+                    continue;
+                };
+                for entry in entries.flatten() {
+                    let path = entry.path();
+                    let Ok(file_type) = entry.file_type() else {
+                        continue;
+                    };
+                    let found = match &wanted {
+                        LinkSearch::Broken if file_type.is_dir() => {
+                            stack.push(path);
+                            continue;
+                        }
+                        LinkSearch::Broken => file_type.is_symlink() && path.metadata().is_err(),
+                        LinkSearch::HardLinks { id, .. } => {
+                            let Ok(meta) = entry.metadata() else {
+                                continue;
+                            };
+                            if file_type.is_dir() {
+                                if file_id(&meta).is_some_and(|d| d.device == id.device) {
+                                    stack.push(path);
+                                }
+                                continue;
+                            }
+                            file_id(&meta) == Some(*id)
+                        }
+                    };
+                    if !found {
+                        continue;
+                    }
+                    let itself = matches!(&wanted, LinkSearch::HardLinks { of, .. } if *of == path);
+                    // fails once the popup is closed
+                    if !itself && tx.send(path).is_err() {
+                        return;
+                    }
+                    left -= 1;
+                    if left == 0 {
+                        return;
+                    }
+                }
+            }
+        });
+        Self {
+            dir,
+            search,
+            links: Vec::new(),
+            selected: 0,
+            scan: Some(rx),
+        }
+    }
+
+    /// Take in the links found since the last call.
+    pub fn refresh(&mut self) {
+        let Some(rx) = &self.scan else {
+            return;
+        };
+        loop {
+            match rx.try_recv() {
+                Ok(link) => self.links.push(link),
+                Err(mpsc::TryRecvError::Empty) => break,
+                Err(mpsc::TryRecvError::Disconnected) => {
+                    self.scan = None;
+                    self.links.sort();
+                    break;
+                }
+            }
+        }
+    }
+
+    pub fn is_scanning(&self) -> bool {
+        self.scan.is_some()
+    }
+
+    pub fn current(&self) -> Option<&PathBuf> {
+        self.links.get(self.selected)
+    }
+}
+
+/// The file system and the file within it, telling whether two paths are hard links to the
+/// same file.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FileId {
+    pub device: u64,
+    pub inode: u64,
+}
+
+#[cfg(unix)]
+pub fn file_id(meta: &fs::Metadata) -> Option<FileId> {
+    use std::os::unix::fs::MetadataExt;
+    Some(FileId {
+        device: meta.dev(),
+        inode: meta.ino(),
+    })
+}
+
+/// Not available through the standard library on other platforms.
+#[cfg(not(unix))]
+pub fn file_id(_meta: &fs::Metadata) -> Option<FileId> {
+    None
+}
+
+/// Number of names the file has, its hard links.
+#[cfg(unix)]
+pub fn link_count(meta: &fs::Metadata) -> Option<u64> {
+    use std::os::unix::fs::MetadataExt;
+    Some(meta.nlink())
+}
+
+#[cfg(not(unix))]
+pub fn link_count(_meta: &fs::Metadata) -> Option<u64> {
+    None
+}
+
+/// Whether all of `sources` are files that can get hard links in the directory `dest`: they
+/// must be on its file system. `None` where that cannot be told.
+pub fn can_hard_link(sources: &[PathBuf], dest: &Path) -> Option<bool> {
+    let device = |path: &Path| {
+        let meta = path.metadata().ok()?;
+        Some((file_id(&meta)?.device, meta.is_file()))
+    };
+    let (dest, _) = device(dest)?;
+    let mut sources = sources.iter().map(|source| device(source));
+    Some(sources.all(|source| source == Some((dest, true))))
+}
@@ -0,0 +1,211 @@
+use std::{
+    fs,
+    io::{BufRead, BufReader},
+    path::Path,
+};
+
+use serde_json::{Map, Value};
+
+use crate::line_edit::LineEditor;
+
+const TIME_KEYS: &[&str] = &["time", "timestamp", "ts", "@timestamp", "datetime"];
+const LEVEL_KEYS: &[&str] = &["level", "lvl", "severity", "loglevel"];
+const MESSAGE_KEYS: &[&str] = &["msg", "message", "event"];
+
+/// Severity of a record, used for coloring.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Level {
+    Trace,
+    Debug,
+    Info,
+    Warn,
+    Error,
+    Unknown,
+}
+
+impl Level {
+    fn parse(s: &str) -> Self {
+        match s.to_ascii_lowercase().as_str() {
+            "trace" | "10" => Level::Trace,
+            "debug" | "20" => Level::Debug,
+            "info" | "information" | "notice" | "30" => Level::Info,
+            "warn" | "warning" | "40" => Level::Warn,
+            "error" | "err" | "fatal" | "critical" | "crit" | "panic" | "50" | "60" => Level::Error,
+            _ => Level::Unknown,
+        }
+    }
+}
+
+/// One line of the log: the parsed object (if it was valid JSON) and its summary.
+pub struct Record {
+    pub raw: String,
+    pub value: Option<Value>,
+    pub level: Level,
+    /// `time LEVEL message key=value ...`
+    pub summary: String,
+}
+
+impl Record {
+    fn parse(raw: String) -> Self {
+        let value = serde_json::from_str::<Value>(&raw)
+            .ok()
+            .filter(Value::is_object);
+        let Some(Value::Object(map)) = &value else {
+            return Self {
+                summary: raw.clone(),
+                raw,
+                value: None,
+                level: Level::Unknown,
+            };
+        };
+        let header = [
+            first_field(map, TIME_KEYS),
+            first_field(map, LEVEL_KEYS),
+            first_field(map, MESSAGE_KEYS),
+        ];
+        let level = header[1]
+            .as_ref()
+            .map(|(_, l)| Level::parse(l))
+            .unwrap_or(Level::Unknown);
+        let shown: Vec<&str> = header.iter().flatten().map(|f| f.0).collect();
+        let mut parts: Vec<String> = header.iter().flatten().map(|f| f.1.clone()).collect();
+        for (key, v) in map {
+            if !shown.contains(&key.as_str()) {
+                parts.push(format!("{key}={}", scalar(v)));
+            }
+        }
+        Self {
+            summary: parts.join(" "),
+            raw,
+            value,
+            level,
+        }
+    }
+
+    /// Whether the record satisfies every whitespace-separated term of `filter`.
+    ///
+    /// `key=value` terms match a field containing `value`; other terms match the summary.
+    fn matches(&self, filter: &str) -> bool {
+        filter.split_whitespace().all(|term| {
+            if let (Some((key, want)), Some(Value::Object(map))) =
+                (term.split_once('='), &self.value)
+            {
+                map.get(key)
+                    .map(|v| scalar(v).to_lowercase().contains(&want.to_lowercase()))
+                    .unwrap_or(false)
+            } else {
+                self.summary.to_lowercase().contains(&term.to_lowercase())
+            }
+        })
+    }
+
+    /// The record as indented JSON, or the raw line if it is not JSON.
+    pub fn pretty(&self) -> String {
+        self.value
+            .as_ref()
+            .and_then(|v| serde_json::to_string_pretty(v).ok())
+            .unwrap_or_else(|| self.raw.clone())
+    }
+}
+
+fn first_field<'a>(map: &Map<String, Value>, keys: &[&'a str]) -> Option<(&'a str, String)> {
+    keys.iter()
+        .find_map(|&k| map.get(k).map(|v| (k, scalar(v))))
+}
+
+/// Render a JSON value without quotes around plain strings.
+fn scalar(v: &Value) -> String {
+    match v {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+/// Viewer for JSON-lines logs: one record per row with level coloring and filtering.
+pub struct LogViewer {
+    pub title: String,
+    pub records: Vec<Record>,
+    pub filter: String,
+    /// Filter text being edited, if the filter prompt is open
+    pub editing: Option<LineEditor>,
+    /// Indices into `records` that pass the filter
+    pub visible: Vec<usize>,
+    /// Position of the cursor within `visible`
+    pub cursor: usize,
+    /// Show the record under the cursor pretty-printed
+    pub expanded: bool,
+}
+
+impl LogViewer {
+    pub fn open(path: &Path) -> Option<Self> {
+        let text = fs::read_to_string(path).ok()?;
+        let records: Vec<Record> = text
+            .lines()
+            .filter(|l| !l.trim().is_empty())
+            .map(|l| Record::parse(l.to_string()))
+            .collect();
+        let title = path
+            .file_name()
+            .map(|os_str| os_str.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        Some(Self {
+            title,
+            visible: (0..records.len()).collect(),
+            records,
+            filter: String::new(),
+            editing: None,
+            cursor: 0,
+            expanded: false,
+        })
+    }
+
+    pub fn set_filter(&mut self, filter: String) {
+        self.visible = (0..self.records.len())
+            .filter(|&i| self.records[i].matches(&filter))
+            .collect();
+        self.filter = filter;
+        self.cursor = 0;
+    }
+
+    pub fn current(&self) -> Option<&Record> {
+        self.visible.get(self.cursor).map(|&i| &self.records[i])
+    }
+
+    pub fn down(&mut self, count: usize) {
+        self.cursor = (self.cursor + count).min(self.visible.len().saturating_sub(1));
+    }
+
+    pub fn up(&mut self, count: usize) {
+        self.cursor = self.cursor.saturating_sub(count);
+    }
+
+    pub fn go_top(&mut self) {
+        self.cursor = 0;
+    }
+
+    pub fn go_bottom(&mut self) {
+        self.cursor = self.visible.len().saturating_sub(1);
+    }
+}
+
+/// `.jsonl`/`.ndjson` files, and `.log` files whose first line is a JSON object.
+pub fn is_json_log(path: &Path) -> bool {
+    let ext = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(str::to_lowercase);
+    match ext.as_deref() {
+        Some("jsonl") | Some("ndjson") => true,
+        Some("log") => fs::File::open(path)
+            .ok()
+            .and_then(|f| BufReader::new(f).lines().next())
+            .and_then(|l| l.ok())
+            .map(|l| {
+                serde_json::from_str::<Value>(l.trim())
+                    .map(|v| v.is_object())
+                    .unwrap_or(false)
+            })
+            .unwrap_or(false),
+        _ => false,
+    }
+}
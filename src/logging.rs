@@ -0,0 +1,48 @@
+use std::{
+    fs::{self, OpenOptions},
+    io,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use tracing_subscriber::EnvFilter;
+
+use crate::config::data_dir;
+
+/// Filter used when `RUST_LOG` is not set.
+const DEFAULT_FILTER: &str = "kura=debug";
+
+/// Start recording actions, mode changes and file system errors, for diagnosing problems
+/// after the fact.
+///
+/// Logging is on when `file` is given or `RUST_LOG` is set; without a file the log goes to
+/// `kura.log` in the data directory. The terminal belongs to the UI, so nothing is ever logged
+/// there.
+pub fn init(file: Option<PathBuf>) -> io::Result<()> {
+    let filter = std::env::var_os("RUST_LOG").filter(|v| !v.is_empty());
+    let Some(path) = file.or_else(|| {
+        filter
+            .as_ref()
+            .and_then(|_| data_dir())
+            .map(|d| d.join("kura.log"))
+    }) else {
+        return Ok(());
+    };
+    let log = open(&path)?;
+    let filter =
+        EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(DEFAULT_FILTER));
+    tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(Mutex::new(log))
+        .with_ansi(false)
+        .init();
+    tracing::info!(version = env!("CARGO_PKG_VERSION"), "kura started");
+    Ok(())
+}
+
+fn open(path: &Path) -> io::Result<fs::File> {
+    if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) {
+        fs::create_dir_all(dir)?;
+    }
+    OpenOptions::new().create(true).append(true).open(path)
+}
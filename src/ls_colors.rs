@@ -0,0 +1,164 @@
+use std::{collections::HashMap, fs::Metadata, path::Path};
+
+use ratatui::style::{Color, Modifier, Style};
+
+/// Entry styles from the `LS_COLORS` environment variable (as set by `dircolors`).
+pub struct LsColors {
+    /// Styles for file types: `di`, `ln`, `ex`, `fi`, ...
+    kinds: HashMap<String, Style>,
+    /// Styles for `*suffix` patterns, longest suffix first
+    suffixes: Vec<(String, Style)>,
+}
+
+impl LsColors {
+    /// Parse `LS_COLORS`; `None` when it is unset or empty, so the built-in theme is used.
+    pub fn from_env() -> Option<Self> {
+        let value = std::env::var("LS_COLORS").ok()?;
+        let colors = Self::parse(&value);
+        (!colors.kinds.is_empty() || !colors.suffixes.is_empty()).then_some(colors)
+    }
+
+    fn parse(value: &str) -> Self {
+        let mut kinds = HashMap::new();
+        let mut suffixes = Vec::new();
+        for item in value.split(':') {
+            let Some((key, codes)) = item.split_once('=') else {
+                continue;
+            };
+            let style = parse_sgr(codes);
+            match key.strip_prefix('*') {
+                Some(suffix) => suffixes.push((suffix.to_lowercase(), style)),
+                None => {
+                    kinds.insert(key.to_string(), style);
+                }
+            }
+        }
+        suffixes.sort_by_key(|(s, _)| std::cmp::Reverse(s.len()));
+        Self { kinds, suffixes }
+    }
+
+    /// Style for the entry at `path`, or `None` if `LS_COLORS` says nothing about it.
+    ///
+    /// `meta` is the entry's own (not followed) metadata.
+    pub fn style(&self, path: &Path, meta: &Metadata) -> Option<Style> {
+        let kind = if meta.is_symlink() {
+            if path.metadata().is_err() {
+                "or"
+            } else {
+                "ln"
+            }
+        } else if meta.is_dir() {
+            dir_kind(meta)
+        } else if let Some(kind) = special_kind(meta) {
+            kind
+        } else if is_executable(meta) {
+            "ex"
+        } else {
+            ""
+        };
+        if let Some(style) = self.kinds.get(kind) {
+            return Some(*style);
+        }
+        if !meta.is_dir() {
+            let name = path.file_name()?.to_string_lossy().to_lowercase();
+            if let Some((_, style)) = self.suffixes.iter().find(|(s, _)| name.ends_with(s)) {
+                return Some(*style);
+            }
+        }
+        self.kinds.get("fi").filter(|_| meta.is_file()).copied()
+    }
+}
+
+#[cfg(unix)]
+fn dir_kind(meta: &Metadata) -> &'static str {
+    use std::os::unix::fs::PermissionsExt;
+    let mode = meta.permissions().mode();
+    match (mode & 0o1000 != 0, mode & 0o002 != 0) {
+        (true, true) => "tw",
+        (false, true) => "ow",
+        (true, false) => "st",
+        (false, false) => "di",
+    }
+}
+
+#[cfg(not(unix))]
+fn dir_kind(_meta: &Metadata) -> &'static str {
+    "di"
+}
+
+/// Pipes, sockets, devices and setuid/setgid files.
+#[cfg(unix)]
+fn special_kind(meta: &Metadata) -> Option<&'static str> {
+    use std::os::unix::fs::{FileTypeExt, PermissionsExt};
+    let ft = meta.file_type();
+    let mode = meta.permissions().mode();
+    Some(if ft.is_fifo() {
+        "pi"
+    } else if ft.is_socket() {
+        "so"
+    } else if ft.is_block_device() {
+        "bd"
+    } else if ft.is_char_device() {
+        "cd"
+    } else if mode & 0o4000 != 0 {
+        "su"
+    } else if mode & 0o2000 != 0 {
+        "sg"
+    } else {
+        return None;
+    })
+}
+
+#[cfg(not(unix))]
+fn special_kind(_meta: &Metadata) -> Option<&'static str> {
+    None
+}
+
+#[cfg(unix)]
+fn is_executable(meta: &Metadata) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    meta.is_file() && meta.permissions().mode() & 0o111 != 0
+}
+
+#[cfg(not(unix))]
+fn is_executable(_meta: &Metadata) -> bool {
+    false
+}
+
+/// Convert an SGR parameter list such as `01;38;5;208` to a style.
+fn parse_sgr(codes: &str) -> Style {
+    let mut style = Style::default();
+    let mut codes = codes.split(';').map(|c| c.parse::<u8>().unwrap_or(0));
+    while let Some(code) = codes.next() {
+        style = match code {
+            0 => Style::default(),
+            1 => style.add_modifier(Modifier::BOLD),
+            2 => style.add_modifier(Modifier::DIM),
+            3 => style.add_modifier(Modifier::ITALIC),
+            4 => style.add_modifier(Modifier::UNDERLINED),
+            5 => style.add_modifier(Modifier::SLOW_BLINK),
+            7 => style.add_modifier(Modifier::REVERSED),
+            30..=37 => style.fg(Color::Indexed(code - 30)),
+            90..=97 => style.fg(Color::Indexed(code - 90 + 8)),
+            40..=47 => style.bg(Color::Indexed(code - 40)),
+            100..=107 => style.bg(Color::Indexed(code - 100 + 8)),
+            38 | 48 => {
+                let color = match codes.next() {
+                    Some(5) => codes.next().map(Color::Indexed),
+                    Some(2) => match (codes.next(), codes.next(), codes.next()) {
+                        (Some(r), Some(g), Some(b)) => Some(Color::Rgb(r, g, b)),
+                        _ => None,
+                    },
+                    _ => None,
+                };
+                match (code, color) {
+                    (38, Some(c)) => style.fg(c),
+                    (_, Some(c)) => style.bg(c),
+                    _ => style,
+                }
+            }
+            _ => style,
+        };
+    }
+    style
+}
@@ -1,37 +1,172 @@
-mod actions;
-mod app;
-mod fs_utils;
 mod input;
-mod mode;
+#[cfg(test)]
+mod test_support;
+#[cfg(test)]
+mod tests;
 mod ui;
 
-use app::App;
 use crossterm::{
+    cursor::Show,
     event::{DisableMouseCapture, EnableMouseCapture},
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
-use input::run_app;
+use input::{run_app, TerminalInput};
+use kura_core::app::{App, Picker};
+use kura_core::logging;
 use ratatui::{backend::CrosstermBackend, Terminal};
-use std::io;
+use std::{
+    fs,
+    io::{self, IsTerminal, Write},
+    path::PathBuf,
+    process,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+const USAGE: &str = "\
+usage: kura [--choose-files | --choose-dir] [--output FILE] [--log-file FILE]
+
+  --choose-files  pick files: Enter on a file, or with entries marked, prints their paths
+  --choose-dir    pick a directory: q prints the active pane's directory
+  --output FILE   write the picked paths to FILE instead of stdout
+  --log-file FILE record actions and errors in FILE (filtered by RUST_LOG, default kura=debug)";
+
+/// Command-line options.
+struct Options {
+    picker: Option<Picker>,
+    output: Option<PathBuf>,
+    log_file: Option<PathBuf>,
+}
+
+fn parse_args() -> Result<Options, String> {
+    let mut options = Options {
+        picker: None,
+        output: None,
+        log_file: None,
+    };
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--choose-files" => options.picker = Some(Picker::Files),
+            "--choose-dir" => options.picker = Some(Picker::Dir),
+            "--output" => {
+                let file = args.next().ok_or("--output needs a file name")?;
+                options.output = Some(file.into());
+            }
+            "--log-file" => {
+                let file = args.next().ok_or("--log-file needs a file name")?;
+                options.log_file = Some(file.into());
+            }
+            "-h" | "--help" => return Err(String::new()),
+            _ => {
+                if let Some(file) = arg.strip_prefix("--output=") {
+                    options.output = Some(file.into());
+                } else if let Some(file) = arg.strip_prefix("--log-file=") {
+                    options.log_file = Some(file.into());
+                } else {
+                    return Err(format!("unknown argument '{arg}'"));
+                }
+            }
+        }
+    }
+    Ok(options)
+}
+
+/// The terminal to draw on: stdout, or the terminal itself when stdout is captured by a script.
+fn open_screen() -> io::Result<Box<dyn Write>> {
+    if io::stdout().is_terminal() {
+        return Ok(Box::new(io::stdout()));
+    }
+    let tty = if cfg!(windows) { "CONOUT$" } else { "/dev/tty" };
+    Ok(Box::new(fs::OpenOptions::new().write(true).open(tty)?))
+}
+
+/// Whether the terminal is in raw mode on the alternate screen and needs restoring.
+static TUI_ACTIVE: AtomicBool = AtomicBool::new(false);
+
+/// Put the terminal back the way the shell expects it: cooked mode, the main screen and a
+/// visible cursor. Does nothing the second time, since leaving the alternate screen twice
+/// would move the cursor back over what was printed in between.
+fn restore_terminal() {
+    if !TUI_ACTIVE.swap(false, Ordering::SeqCst) {
+        return;
+    }
+    let _ = disable_raw_mode();
+    if let Ok(mut screen) = open_screen() {
+        let _ = execute!(screen, LeaveAlternateScreen, DisableMouseCapture, Show);
+    }
+}
+
+/// Restores the terminal when dropped, whether `main` returns early with an error or
+/// unwinds from a panic.
+struct TerminalGuard;
+
+impl TerminalGuard {
+    fn enter() -> Self {
+        TUI_ACTIVE.store(true, Ordering::SeqCst);
+        TerminalGuard
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        restore_terminal();
+    }
+}
+
+/// Restore the terminal before the panic message is printed, so it is readable and the shell
+/// is usable afterwards without `reset`.
+fn install_panic_hook() {
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        restore_terminal();
+        tracing::error!("panic: {info}");
+        default_hook(info);
+    }));
+}
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let options = match parse_args() {
+        Ok(options) => options,
+        Err(e) => {
+            if !e.is_empty() {
+                eprintln!("kura: {e}");
+            }
+            eprintln!("{USAGE}");
+            process::exit(2);
+        }
+    };
+    if let Err(e) = logging::init(options.log_file.clone()) {
+        eprintln!("kura: cannot open the log file: {e}");
+        process::exit(2);
+    }
+    let mut screen = open_screen()?;
+    install_panic_hook();
+    let guard = TerminalGuard::enter();
     enable_raw_mode()?;
-    let mut stdout = io::stdout();
-    execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
-    let backend = CrosstermBackend::new(stdout);
+    execute!(screen, EnterAlternateScreen, EnableMouseCapture)?;
+    let backend = CrosstermBackend::new(screen);
     let mut terminal = Terminal::new(backend)?;
     let mut app = App::new()?;
-    let res = run_app(&mut terminal, &mut app);
-    disable_raw_mode()?;
-    execute!(
-        terminal.backend_mut(),
-        LeaveAlternateScreen,
-        DisableMouseCapture
-    )?;
-    terminal.show_cursor()?;
+    app.picker = options.picker;
+    let res = run_app(&mut terminal, &mut app, &mut TerminalInput);
+    drop(guard);
     if let Err(err) = res {
+        tracing::error!("exiting on error: {err}");
         eprintln!("{err:?}");
     }
+    if app.picker.is_some() {
+        let Some(chosen) = app.chosen else {
+            process::exit(1);
+        };
+        let text: String = chosen
+            .iter()
+            .map(|p| format!("{}\n", p.display()))
+            .collect();
+        match options.output {
+            Some(file) => fs::write(file, text)?,
+            None => io::stdout().write_all(text.as_bytes())?,
+        }
+    }
     Ok(())
 }
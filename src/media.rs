@@ -0,0 +1,287 @@
+use std::{
+    fs::File,
+    io::{self, Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
+
+use serde_json::Value;
+
+use crate::file_type;
+use crate::format::thousands;
+
+const AUDIO_EXTS: &[&str] = &[
+    "mp3", "flac", "ogg", "oga", "opus", "wav", "m4a", "aac", "wma", "aiff", "ape",
+];
+const VIDEO_EXTS: &[&str] = &[
+    "mp4", "mkv", "webm", "avi", "mov", "wmv", "flv", "m4v", "mpg", "mpeg", "ts", "3gp",
+];
+
+/// Tags worth showing, with their labels, in order.
+const TAGS: &[(&str, &str)] = &[
+    ("title", "Title"),
+    ("artist", "Artist"),
+    ("album", "Album"),
+    ("date", "Date"),
+    ("genre", "Genre"),
+    ("track", "Track"),
+];
+
+fn has_extension(path: &Path, exts: &[&str]) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| exts.contains(&e.to_lowercase().as_str()))
+}
+
+/// Whether `path` is an audio file, judged by its content if it is a known format and by its
+/// extension otherwise.
+pub fn is_audio(path: &Path) -> bool {
+    match file_type::sniff(path) {
+        Ok(Some(kind)) => kind.is_audio(),
+        _ => has_extension(path, AUDIO_EXTS),
+    }
+}
+
+/// Whether `path` is a video file, judged like `is_audio`.
+pub fn is_video(path: &Path) -> bool {
+    match file_type::sniff(path) {
+        Ok(Some(kind)) => kind.is_video(),
+        _ => has_extension(path, VIDEO_EXTS),
+    }
+}
+
+/// Lines describing a media file for the inspector: duration, streams, bitrate and tags.
+///
+/// Uses `ffprobe` when it is installed; otherwise only the ID3 tags of MP3 files are read.
+pub fn describe(path: &Path) -> Vec<String> {
+    if let Some(lines) = ffprobe(path) {
+        return lines;
+    }
+    let tags = id3v2(path).or_else(|| id3v1(path)).unwrap_or_default();
+    let mut lines = tag_lines(|key| tags.iter().find(|(k, _)| *k == key).map(|(_, v)| v.clone()));
+    if lines.is_empty() {
+        lines.push("(install ffprobe for duration, codecs and bitrate)".into());
+    }
+    lines
+}
+
+/// `Label:    value` lines for the known tags `tag` finds.
+fn tag_lines(tag: impl Fn(&str) -> Option<String>) -> Vec<String> {
+    TAGS.iter()
+        .filter_map(|(key, label)| {
+            let value = tag(key).filter(|v| !v.trim().is_empty())?;
+            Some(format!("{:<10}{}", format!("{label}:"), value.trim()))
+        })
+        .collect()
+}
+
+fn ffprobe(path: &Path) -> Option<Vec<String>> {
+    let output = Command::new("ffprobe")
+        .args([
+            "-v",
+            "quiet",
+            "-print_format",
+            "json",
+            "-show_format",
+            "-show_streams",
+        ])
+        .arg(path)
+        .stdin(Stdio::null())
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let probe: Value = serde_json::from_slice(&output.stdout).ok()?;
+    let format = &probe["format"];
+    // ffprobe reports numbers as strings
+    let number = |v: &Value| v.as_str().and_then(|s| s.parse::<f64>().ok());
+
+    let mut lines = Vec::new();
+    if let Some(seconds) = number(&format["duration"]) {
+        lines.push(format!("Duration: {}", duration(seconds)));
+    }
+    if let Some(name) = format["format_long_name"].as_str() {
+        lines.push(format!("Format:   {name}"));
+    }
+    if let Some(bits) = number(&format["bit_rate"]) {
+        lines.push(format!(
+            "Bitrate:  {} kb/s",
+            thousands((bits / 1000.0).round() as u64)
+        ));
+    }
+    for stream in probe["streams"].as_array().into_iter().flatten() {
+        let codec = stream["codec_name"].as_str().unwrap_or("?");
+        match stream["codec_type"].as_str() {
+            Some("video") => {
+                let mut line = format!("Video:    {codec}");
+                if let (Some(w), Some(h)) = (stream["width"].as_u64(), stream["height"].as_u64()) {
+                    line.push_str(&format!(" {w}x{h}"));
+                }
+                let rate = stream["avg_frame_rate"].as_str().and_then(|r| {
+                    let (n, d) = r.split_once('/')?;
+                    let (n, d) = (n.parse::<f64>().ok()?, d.parse::<f64>().ok()?);
+                    (d > 0.0).then(|| n / d)
+                });
+                if let Some(fps) = rate.filter(|&fps| fps > 0.0) {
+                    line.push_str(&format!(" {:.2} fps", fps));
+                }
+                lines.push(line);
+            }
+            Some("audio") => {
+                let mut line = format!("Audio:    {codec}");
+                if let Some(rate) = number(&stream["sample_rate"]) {
+                    line.push_str(&format!(" {rate} Hz"));
+                }
+                if let Some(channels) = stream["channels"].as_u64() {
+                    line.push_str(&format!(" {channels} ch"));
+                }
+                lines.push(line);
+            }
+            _ => {}
+        }
+    }
+    // tag names vary in case between containers
+    let tags = format["tags"].as_object();
+    lines.extend(tag_lines(|key| {
+        tags?
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(key))
+            .and_then(|(_, v)| v.as_str())
+            .map(String::from)
+    }));
+    Some(lines)
+}
+
+/// `seconds` as `h:mm:ss`, or `m:ss` under an hour.
+fn duration(seconds: f64) -> String {
+    let total = seconds.round() as u64;
+    let (h, m, s) = (total / 3600, total / 60 % 60, total % 60);
+    if h > 0 {
+        format!("{h}:{m:02}:{s:02}")
+    } else {
+        format!("{m}:{s:02}")
+    }
+}
+
+/// Text frames of an ID3v2.3 or v2.4 tag at the start of the file, keyed like ffprobe's tags.
+fn id3v2(path: &Path) -> Option<Vec<(&'static str, String)>> {
+    let mut file = File::open(path).ok()?;
+    let mut header = [0u8; 10];
+    file.read_exact(&mut header).ok()?;
+    let version = header[3];
+    if &header[..3] != b"ID3" || !(3..=4).contains(&version) {
+        return None;
+    }
+    let mut tag = vec![0u8; syncsafe(&header[6..10]) as usize];
+    file.read_exact(&mut tag).ok()?;
+    let mut pos = 0;
+    if header[5] & 0x40 != 0 {
+        // skip the extended header; its size excludes itself in v2.3
+        let size = tag.get(..4)?;
+        pos = if version == 4 {
+            syncsafe(size) as usize
+        } else {
+            u32::from_be_bytes(size.try_into().ok()?) as usize + 4
+        };
+    }
+
+    let mut frames = Vec::new();
+    while let Some(frame) = tag.get(pos..pos + 10) {
+        if frame[0] == 0 {
+            // padding
+            break;
+        }
+        let size = if version == 4 {
+            syncsafe(&frame[4..8])
+        } else {
+            u32::from_be_bytes(frame[4..8].try_into().ok()?)
+        } as usize;
+        let body = tag.get(pos + 10..pos + 10 + size)?;
+        let key = match &frame[..4] {
+            b"TIT2" => Some("title"),
+            b"TPE1" => Some("artist"),
+            b"TALB" => Some("album"),
+            b"TYER" | b"TDRC" => Some("date"),
+            b"TCON" => Some("genre"),
+            b"TRCK" => Some("track"),
+            _ => None,
+        };
+        if let (Some(key), Some((&encoding, text))) = (key, body.split_first()) {
+            frames.push((key, decode_text(encoding, text)));
+        }
+        pos += 10 + size;
+    }
+    Some(frames)
+}
+
+/// A 28-bit integer stored in the low 7 bits of four bytes.
+fn syncsafe(bytes: &[u8]) -> u32 {
+    bytes.iter().fold(0, |n, &b| (n << 7) | u32::from(b & 0x7f))
+}
+
+/// An ID3v2 text frame body in the given encoding: Latin-1, UTF-16 with BOM, UTF-16BE or UTF-8.
+fn decode_text(encoding: u8, text: &[u8]) -> String {
+    let text = match encoding {
+        1 | 2 => {
+            let mut units: Vec<u16> = text
+                .chunks_exact(2)
+                .map(|c| u16::from_be_bytes([c[0], c[1]]))
+                .collect();
+            if units.first() == Some(&0xfffe) {
+                // little-endian byte order mark
+                units = units.iter().map(|u| u.swap_bytes()).collect();
+            }
+            let units = units.strip_prefix(&[0xfeff]).unwrap_or(&units);
+            String::from_utf16_lossy(units)
+        }
+        3 => String::from_utf8_lossy(text).into_owned(),
+        _ => text.iter().map(|&b| char::from(b)).collect(),
+    };
+    // v2.4 separates multiple values with NUL
+    text.trim_end_matches('\0').replace('\0', " / ")
+}
+
+/// Title, artist, album and year from an ID3v1 tag in the last 128 bytes of the file.
+fn id3v1(path: &Path) -> Option<Vec<(&'static str, String)>> {
+    let mut file = File::open(path).ok()?;
+    file.seek(SeekFrom::End(-128)).ok()?;
+    let mut tag = [0u8; 128];
+    file.read_exact(&mut tag).ok()?;
+    if &tag[..3] != b"TAG" {
+        return None;
+    }
+    let field = |range: std::ops::Range<usize>| {
+        let bytes = &tag[range];
+        let end = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+        bytes[..end]
+            .iter()
+            .map(|&b| char::from(b))
+            .collect::<String>()
+    };
+    Some(vec![
+        ("title", field(3..33)),
+        ("artist", field(33..63)),
+        ("album", field(63..93)),
+        ("date", field(93..97)),
+    ])
+}
+
+/// Extract a representative frame of the video at `path` into a temporary PNG with `ffmpeg`.
+pub fn thumbnail(path: &Path) -> io::Result<PathBuf> {
+    let out = std::env::temp_dir().join(format!("kura-thumbnail-{}.png", std::process::id()));
+    let status = Command::new("ffmpeg")
+        .args(["-v", "error", "-y", "-i"])
+        .arg(path)
+        // the thumbnail filter picks the most representative of the first frames
+        .args(["-vf", "thumbnail", "-frames:v", "1"])
+        .arg(&out)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()?;
+    if !status.success() {
+        return Err(io::Error::other(format!("ffmpeg exited with {status}")));
+    }
+    Ok(out)
+}
@@ -1,5 +1,20 @@
 use std::path::PathBuf;
 
+use crate::archive::Extraction;
+use crate::checksum::ChecksumReport;
+use crate::diff::DiffView;
+use crate::elevate::Privileged;
+use crate::file_index::Finder;
+use crate::line_edit::LineEditor;
+use crate::links::LinkScan;
+use crate::log_view::LogViewer;
+use crate::mounts::Mount;
+use crate::table_view::TableView;
+use crate::todo_scan::TodoList;
+use crate::tree_view::TreeView;
+use crate::vfs::{Entry, EntryChange, EntryStamp};
+use crate::viewer::Viewer;
+
 #[derive(PartialEq)]
 pub enum PaneType {
     Left,
@@ -12,25 +27,337 @@ pub enum Mode {
     Visual {
         anchor: usize,
     },
-    Viewer {
-        content: String,
-        title: String,
-        offset: u16,
+    Viewer(Viewer),
+    /// Two viewers side by side; the active pane decides which one scrolls
+    Compare {
+        left: Box<Viewer>,
+        right: Box<Viewer>,
+        /// Scroll both viewers together
+        sync: bool,
     },
+    /// Unified diff between two files
+    Diff(DiffView),
+    /// JSON-lines log viewer
+    LogViewer(LogViewer),
+    /// CSV/TSV file shown as an aligned table
+    Table(TableView),
+    /// JSON/YAML document with foldable objects and arrays
+    Tree(TreeView),
+    /// TODO/FIXME/HACK markers found under a directory, grouped by file
+    Todos(TodoList),
     ConfirmDelete {
         items: Vec<PathBuf>,
+        /// Name to type instead of `y`, when an item is in a protected directory
+        protected: Option<TypeToConfirm>,
+    },
+    /// Confirm overwriting and deleting `items`, which is kept apart from deleting
+    ConfirmShred {
+        items: Vec<PathBuf>,
+        /// Name to type instead of `y`, when an item is in a protected directory
+        protected: Option<TypeToConfirm>,
+    },
+    /// Offer to run `retry` as root, after the operation was refused for lack of permission
+    ConfirmElevate {
+        retry: Privileged,
+        reason: String,
+    },
+    /// Offer to resume a copy of `sources` into `dest` that was interrupted after `done`
+    /// files, or to copy everything again
+    ConfirmResume {
+        sources: Vec<PathBuf>,
+        dest: PathBuf,
+        done: usize,
+    },
+    /// Confirm copying (or moving) the selection into the other pane's directory, or pasting
+    /// yanked entries into the current one
+    ConfirmTransfer {
+        sources: Vec<PathBuf>,
+        dest: PathBuf,
+        move_items: bool,
+        /// Number of sources whose name already exists in `dest`
+        conflicts: usize,
+        /// Name to type instead of `y`, when an overwritten entry is in a protected directory
+        protected: Option<TypeToConfirm>,
+        /// Pasting with `p` or `P` rather than copying to the other pane
+        paste: bool,
+        /// Whether to offer hard links instead of copies, the sources being files on the
+        /// destination's file system
+        hard_links: bool,
     },
     /// Search mode: prompt for a query and jump to matching entries
     Search {
-        query: String,
+        query: LineEditor,
+        /// Treat the query as a regular expression
+        regex: bool,
     },
     /// Rename mode: inline editing of the selected filename
     Rename {
         original: String,
-        buffer: String,
+        /// The entry as listed when the prompt opened
+        stamp: EntryStamp,
+        buffer: LineEditor,
+        /// The user confirmed overwriting an existing entry with this name
+        overwrite: bool,
+        /// The entry was found changed when committing; Enter checks again
+        conflict: Option<EntryChange>,
+    },
+    /// Prompt for the name to paste the single yanked entry `source` under
+    PasteAs {
+        source: PathBuf,
+        /// A name, or a path like `backup/2024/notes.txt` in directories created as needed
+        buffer: LineEditor,
+        /// The user confirmed overwriting an existing entry or creating the missing directories
+        confirmed: bool,
+    },
+    /// Prompt for the path of a new file, or directory if it ends with a separator, in the
+    /// active pane's directory
+    Create {
+        buffer: LineEditor,
+        /// The user confirmed creating the missing directories on the way
+        confirmed: bool,
+    },
+    /// Narrowing mode: list only the entries whose names contain `query`, as it is typed
+    Filter {
+        query: LineEditor,
+    },
+    /// Pattern mode: prompt for a glob and mark (or unmark) matching entries
+    MarkPattern {
+        query: LineEditor,
+        mark: bool,
+    },
+    /// Predicate mode: prompt for conditions on the listed entries, like `modified<7d and
+    /// size>10M`, and mark (or unmark) those meeting them
+    MarkWhere {
+        query: LineEditor,
+        mark: bool,
+    },
+    /// Task queue popup (index of the highlighted job)
+    Tasks {
+        selected: usize,
+    },
+    /// Prompt for the arguments to run the executable `program` with
+    Run {
+        program: PathBuf,
+        args: LineEditor,
+    },
+    /// Prompt for a command to run for each of `paths` on the task queue, with placeholders for
+    /// the parts of each path
+    ForEach {
+        paths: Vec<PathBuf>,
+        template: LineEditor,
+    },
+    /// Prompt for a command to run whenever something under `dir` changes
+    Watch {
+        dir: PathBuf,
+        template: LineEditor,
+    },
+    /// Prompt for the password of an encrypted archive, shown masked, to extract it again with
+    ArchivePassword {
+        extraction: Extraction,
+        password: LineEditor,
+    },
+    /// Prompt for a local directory or `sftp://` or `dav://` URL to show in the active pane
+    Location {
+        buffer: LineEditor,
+    },
+    /// Find a file under the active pane's directory by fuzzy matching its path
+    Find(Finder),
+    /// How the files named in a checksum list compare, filled in as they are hashed
+    Checksums(ChecksumReport),
+    /// Broken links, or the other names of a file, under a directory, as the scan finds them
+    Links(LinkScan),
+    /// Prompt for the directory to look for the other names of the file `path` in
+    HardLinkScope {
+        path: PathBuf,
+        scope: LineEditor,
+    },
+    /// Jump to a directory from the visit history, filtered by `query`
+    Jump {
+        query: LineEditor,
+        selected: usize,
+    },
+    /// Recent notifications, most recent first; any key closes it
+    Notifications,
+    /// Yank history and named registers; typing a register name picks it for the next
+    /// `y`, `p` or `P`
+    Registers {
+        selected: usize,
+    },
+    /// Informational popup; any key closes it
+    Message {
+        title: String,
+        lines: Vec<String>,
+    },
+    /// Details of the entry under the cursor; `y` copies its exact size
+    Inspect {
+        path: PathBuf,
+        lines: Vec<String>,
+        size: u64,
+        /// Set for videos, whose thumbnail `t` shows
+        video: Option<PathBuf>,
+        /// Whether macOS's quarantine attribute is set, which `Q` removes
+        quarantined: bool,
+    },
+    /// Prompt for an extended attribute of `path` to set, as `name=value`, or to remove, as
+    /// `-name`
+    Xattr {
+        path: PathBuf,
+        input: LineEditor,
+    },
+    /// Preview of a directory sync; nothing is copied until confirmed
+    Sync {
+        to_right: Vec<PathBuf>,
+        to_left: Vec<PathBuf>,
+    },
+    /// Mounted file systems (drives on Windows) to move the active pane to
+    Mounts {
+        mounts: Vec<Mount>,
+        selected: usize,
+    },
+    /// Files collected from any directory into the basket, to copy, move or delete together
+    Basket {
+        selected: usize,
+    },
+    /// Batch actions for the selected image files
+    ImageActions {
+        paths: Vec<PathBuf>,
+        selected: usize,
+    },
+    /// Share targets from the config to send the selected entries with
+    Share {
+        paths: Vec<PathBuf>,
+        selected: usize,
+    },
+    /// Prompt for the width to scale the selected images down to
+    ResizeImages {
+        paths: Vec<PathBuf>,
+        width: LineEditor,
     },
     /// Sort mode: choose a sort order for the file list
     Sort {
         selected: usize,
     },
+    /// Pick a directory on the active pane's path, by its breadcrumb in the title
+    Breadcrumbs {
+        /// Counted from the root
+        selected: usize,
+    },
+}
+
+/// The name of a protected directory, to be typed to confirm deleting or overwriting
+/// something in it.
+pub struct TypeToConfirm {
+    pub name: String,
+    pub buffer: LineEditor,
+}
+
+impl TypeToConfirm {
+    pub fn new(name: String) -> Self {
+        Self {
+            name,
+            buffer: LineEditor::default(),
+        }
+    }
+
+    pub fn is_typed(&self) -> bool {
+        *self.buffer == self.name
+    }
+}
+
+impl Mode {
+    /// Prompt for a new name of `entry`.
+    pub fn rename(entry: &Entry) -> Self {
+        let name = entry.file_name().to_string_lossy().into_owned();
+        Mode::Rename {
+            buffer: LineEditor::new(name.as_str()),
+            original: name,
+            stamp: entry.stamp(),
+            overwrite: false,
+            conflict: None,
+        }
+    }
+
+    /// Name of the mode for the debug log.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Mode::Filer => "Filer",
+            Mode::Visual { .. } => "Visual",
+            Mode::Viewer(_) => "Viewer",
+            Mode::Compare { .. } => "Compare",
+            Mode::Diff(_) => "Diff",
+            Mode::LogViewer(_) => "LogViewer",
+            Mode::Table(_) => "Table",
+            Mode::Tree(_) => "Tree",
+            Mode::Todos(_) => "Todos",
+            Mode::ConfirmDelete { .. } => "ConfirmDelete",
+            Mode::ConfirmShred { .. } => "ConfirmShred",
+            Mode::ConfirmElevate { .. } => "ConfirmElevate",
+            Mode::ConfirmResume { .. } => "ConfirmResume",
+            Mode::ConfirmTransfer { .. } => "ConfirmTransfer",
+            Mode::Search { .. } => "Search",
+            Mode::Rename { .. } => "Rename",
+            Mode::PasteAs { .. } => "PasteAs",
+            Mode::Create { .. } => "Create",
+            Mode::Filter { .. } => "Filter",
+            Mode::MarkPattern { .. } => "MarkPattern",
+            Mode::MarkWhere { .. } => "MarkWhere",
+            Mode::Tasks { .. } => "Tasks",
+            Mode::Run { .. } => "Run",
+            Mode::ForEach { .. } => "ForEach",
+            Mode::Watch { .. } => "Watch",
+            Mode::ArchivePassword { .. } => "ArchivePassword",
+            Mode::Location { .. } => "Location",
+            Mode::Jump { .. } => "Jump",
+            Mode::Find(_) => "Find",
+            Mode::Checksums(_) => "Checksums",
+            Mode::Links(_) => "Links",
+            Mode::HardLinkScope { .. } => "HardLinkScope",
+            Mode::Notifications => "Notifications",
+            Mode::Registers { .. } => "Registers",
+            Mode::Message { .. } => "Message",
+            Mode::Inspect { .. } => "Inspect",
+            Mode::Xattr { .. } => "Xattr",
+            Mode::Sync { .. } => "Sync",
+            Mode::Mounts { .. } => "Mounts",
+            Mode::Basket { .. } => "Basket",
+            Mode::ImageActions { .. } => "ImageActions",
+            Mode::Share { .. } => "Share",
+            Mode::ResizeImages { .. } => "ResizeImages",
+            Mode::Sort { .. } => "Sort",
+            Mode::Breadcrumbs { .. } => "Breadcrumbs",
+        }
+    }
+
+    /// Whether keystrokes are typed into a prompt rather than interpreted as commands.
+    pub fn is_prompt(&self) -> bool {
+        match self {
+            Mode::Search { .. }
+            | Mode::Rename { .. }
+            | Mode::PasteAs { .. }
+            | Mode::Create { .. }
+            | Mode::MarkPattern { .. }
+            | Mode::MarkWhere { .. }
+            | Mode::Filter { .. }
+            | Mode::Jump { .. }
+            | Mode::Find(_)
+            | Mode::Location { .. }
+            | Mode::Run { .. }
+            | Mode::ForEach { .. }
+            | Mode::Watch { .. }
+            | Mode::HardLinkScope { .. }
+            | Mode::Xattr { .. }
+            | Mode::ArchivePassword { .. }
+            | Mode::ResizeImages { .. }
+            // a register name is typed
+            | Mode::Registers { .. } => true,
+            Mode::ConfirmDelete { protected, .. }
+            | Mode::ConfirmShred { protected, .. }
+            | Mode::ConfirmTransfer { protected, .. } => {
+                protected.is_some()
+            }
+            Mode::LogViewer(log) => log.editing.is_some(),
+            Mode::Viewer(viewer) => viewer.editing.is_some(),
+            _ => false,
+        }
+    }
 }
@@ -0,0 +1,410 @@
+use std::{
+    fs,
+    io::{self, Read},
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    sync::mpsc,
+    thread,
+    time::Duration,
+};
+
+use crate::fs_utils::{percent_decode, percent_encode};
+
+/// Pseudo file systems left out of the mount list; they hold no user files.
+const PSEUDO_FS: &[&str] = &[
+    "autofs",
+    "binfmt_misc",
+    "bpf",
+    "cgroup",
+    "cgroup2",
+    "configfs",
+    "debugfs",
+    "devpts",
+    "devtmpfs",
+    "efivarfs",
+    "fusectl",
+    "hugetlbfs",
+    "mqueue",
+    "nsfs",
+    "proc",
+    "pstore",
+    "securityfs",
+    "sysfs",
+    "tracefs",
+];
+
+/// Types of file systems whose data lives on another machine.
+const NETWORK_FS: &[&str] = &[
+    "9p",
+    "afpfs",
+    "ceph",
+    "cifs",
+    "davfs",
+    "fuse.gvfsd-fuse",
+    "fuse.rclone",
+    "fuse.sshfs",
+    "glusterfs",
+    "nfs",
+    "nfs4",
+    "smb3",
+    "smbfs",
+    "webdav",
+];
+
+/// URI schemes of the devices GVfs makes browsable: phones and media players over MTP, and
+/// cameras over PTP.
+const DEVICE_SCHEMES: [&str; 2] = ["mtp", "gphoto2"];
+
+/// A mounted file system, a drive on Windows, or a phone or camera.
+pub struct Mount {
+    pub path: PathBuf,
+    /// Device or source, e.g. `/dev/sda1`; empty for Windows drives
+    pub device: String,
+    /// File system type, e.g. `ext4`; empty for Windows drives
+    pub fs_type: String,
+    pub space: Option<Space>,
+    /// For a phone or camera GVfs knows about but has not mounted yet, the URI mounting it
+    pub mount_with: Option<String>,
+}
+
+impl Mount {
+    /// Whether this is a phone or camera, known by its name rather than its path.
+    pub fn is_device(&self) -> bool {
+        DEVICE_SCHEMES.contains(&self.fs_type.as_str())
+    }
+
+    /// Name shown in the mount list.
+    pub fn label(&self) -> String {
+        if self.is_device() {
+            self.device.clone()
+        } else {
+            self.path.display().to_string()
+        }
+    }
+}
+
+/// Free and total bytes of a file system.
+#[derive(Clone, Copy)]
+pub struct Space {
+    /// Bytes available to unprivileged users
+    pub free: u64,
+    pub total: u64,
+}
+
+/// Longest wait for `gio` to list the devices, which it can spend on a slow one.
+const GIO_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// The mounted file systems and the phones and cameras found, by mount point; those GVfs
+/// has mounted are looked for in `gvfs`.
+pub fn list(gvfs: Option<&Path>) -> Vec<Mount> {
+    let mut mounts: Vec<Mount> = mount_points()
+        .into_iter()
+        .filter(|(_, _, fs_type)| !PSEUDO_FS.contains(&fs_type.as_str()))
+        .map(|(path, device, fs_type)| Mount {
+            space: space(&path).ok(),
+            path,
+            device,
+            fs_type,
+            mount_with: None,
+        })
+        .collect();
+    mounts.extend(gvfs.map(devices_in).unwrap_or_default());
+    mounts.sort_by(|a, b| a.path.cmp(&b.path));
+    mounts.dedup_by(|a, b| a.path == b.path);
+    mounts
+}
+
+/// Whether `path` is on a network file system, such as an NFS export or an SMB share.
+pub fn is_network(path: &Path) -> bool {
+    // `\\server\share`, and the same as `\\?\UNC\server\share`
+    if let Some(std::path::Component::Prefix(prefix)) = path.components().next() {
+        if matches!(
+            prefix.kind(),
+            std::path::Prefix::UNC(..) | std::path::Prefix::VerbatimUNC(..)
+        ) {
+            return true;
+        }
+    }
+    mount_points()
+        .into_iter()
+        .filter(|(mount, _, _)| path.starts_with(mount))
+        .max_by_key(|(mount, _, _)| mount.as_os_str().len())
+        .is_some_and(|(_, _, fs_type)| NETWORK_FS.contains(&fs_type.as_str()))
+}
+
+/// The directory GVfs shows its mounts in, through FUSE.
+#[cfg(target_os = "linux")]
+pub fn gvfs_root() -> Option<PathBuf> {
+    let runtime = std::env::var_os("XDG_RUNTIME_DIR")
+        .map(PathBuf::from)
+        // SAFETY: getuid cannot fail
+        .unwrap_or_else(|| PathBuf::from(format!("/run/user/{}", unsafe { libc::getuid() })));
+    Some(runtime.join("gvfs"))
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn gvfs_root() -> Option<PathBuf> {
+    None
+}
+
+/// Phones and cameras: those GVfs has mounted in `root`, then those `gio mount -li` lists as
+/// ready to mount.
+fn devices_in(root: &Path) -> Vec<Mount> {
+    let mut devices: Vec<Mount> = fs::read_dir(root)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let (scheme, host) = name.split_once(":host=")?;
+            DEVICE_SCHEMES.contains(&scheme).then(|| Mount {
+                space: space(&entry.path()).ok(),
+                path: entry.path(),
+                // e.g. `Google_Pixel_7_28121FDH2000XF`
+                device: percent_decode(host)
+                    .unwrap_or_else(|| host.to_string())
+                    .replace('_', " "),
+                fs_type: scheme.to_string(),
+                mount_with: None,
+            })
+        })
+        .collect();
+    let Some(output) = output_within(Command::new("gio").args(["mount", "-li"]), GIO_TIMEOUT)
+    else {
+        return devices;
+    };
+    // `Volume(0): Pixel 7`, then indented details including `activation_root=mtp://...`
+    let mut volume = "";
+    for line in output.lines() {
+        let line = line.trim();
+        if let Some((_, name)) = line
+            .split_once("): ")
+            .filter(|(kind, _)| kind.starts_with("Volume("))
+        {
+            volume = name;
+        } else if let Some(uri) = line.strip_prefix("activation_root=") {
+            let Some(path) = device_path(root, uri).filter(|path| !path.exists()) else {
+                continue;
+            };
+            devices.push(Mount {
+                path,
+                device: volume.to_string(),
+                fs_type: uri.split("://").next().unwrap_or_default().to_string(),
+                space: None,
+                mount_with: Some(uri.to_string()),
+            });
+        }
+    }
+    devices
+}
+
+/// What `command` writes to stdout, unless it fails to start or takes longer than `timeout`,
+/// when it is killed.
+fn output_within(command: &mut Command, timeout: Duration) -> Option<String> {
+    let mut child = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .ok()?;
+    let mut stdout = child.stdout.take()?;
+    let (sender, received) = mpsc::channel();
+    thread::spawn(move || {
+        let mut text = String::new();
+        let _ = stdout.read_to_string(&mut text);
+        let _ = sender.send(text);
+    });
+    let text = received.recv_timeout(timeout).ok();
+    if text.is_none() {
+        let _ = child.kill();
+    }
+    let _ = child.wait();
+    text
+}
+
+/// Where GVfs, showing its mounts in `root`, shows `uri`, e.g.
+/// `mtp://Google_Pixel_7_28121FDH2000XF/DCIM`, once the device is mounted; `None` for other
+/// schemes.
+pub fn device_path(root: &Path, uri: &str) -> Option<PathBuf> {
+    let (scheme, rest) = uri.split_once("://")?;
+    if !DEVICE_SCHEMES.contains(&scheme) {
+        return None;
+    }
+    let (host, path) = rest.split_once('/').unwrap_or((rest, ""));
+    if host.is_empty() {
+        return None;
+    }
+    let device = root.join(format!("{scheme}:host={}", percent_encode(host)));
+    Some(device.join(percent_decode(path)?))
+}
+
+/// Mount the phone or camera `uri` through GVfs, showing its mounts in `gvfs`, unless it
+/// already is, returning the local path of `uri`.
+pub fn mount_device(gvfs: Option<&Path>, uri: &str) -> io::Result<PathBuf> {
+    let path = gvfs
+        .and_then(|root| device_path(root, uri))
+        .ok_or_else(|| {
+            io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "expected mtp://device/path or gphoto2://device/path, reached through GVfs on Linux",
+        )
+        })?;
+    if path.exists() {
+        return Ok(path);
+    }
+    let output = Command::new("gio")
+        .args(["mount", uri])
+        .output()
+        .map_err(|e| match e.kind() {
+            io::ErrorKind::NotFound => io::Error::new(
+                e.kind(),
+                "gio not found: phones and cameras are reached through GVfs",
+            ),
+            _ => e,
+        })?;
+    if !output.status.success() {
+        // e.g. the phone still asks whether to allow access
+        let message = String::from_utf8_lossy(&output.stderr).trim().to_string();
+        return Err(io::Error::other(message));
+    }
+    Ok(path)
+}
+
+/// Mount point, device and type of each mounted file system, from `/proc/mounts`.
+#[cfg(target_os = "linux")]
+fn mount_points() -> Vec<(PathBuf, String, String)> {
+    let table = std::fs::read_to_string("/proc/mounts").unwrap_or_default();
+    table
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split(' ');
+            let device = unescape(fields.next()?);
+            let path = unescape(fields.next()?);
+            let fs_type = fields.next()?.to_string();
+            Some((PathBuf::from(path), device, fs_type))
+        })
+        .collect()
+}
+
+/// `/proc/mounts` writes spaces and other special characters as octal escapes like `\040`.
+#[cfg(target_os = "linux")]
+fn unescape(field: &str) -> String {
+    let bytes = field.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let octal = bytes.get(i + 1..i + 4).and_then(|d| {
+            std::str::from_utf8(d)
+                .ok()
+                .and_then(|d| u8::from_str_radix(d, 8).ok())
+        });
+        match (bytes[i], octal) {
+            (b'\\', Some(b)) => {
+                out.push(b);
+                i += 4;
+            }
+            (b, _) => {
+                out.push(b);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+/// Mount point, device and type of each mounted file system, from `getmntinfo`.
+#[cfg(all(unix, not(target_os = "linux")))]
+fn mount_points() -> Vec<(PathBuf, String, String)> {
+    use std::ffi::CStr;
+
+    let text = |chars: &[libc::c_char]| {
+        // SAFETY: the kernel fills these fields with NUL-terminated strings
+        unsafe { CStr::from_ptr(chars.as_ptr()) }
+            .to_string_lossy()
+            .into_owned()
+    };
+    let mut buf: *mut libc::statfs = std::ptr::null_mut();
+    // SAFETY: getmntinfo points `buf` at a buffer it owns, valid until the next call
+    let count = unsafe { libc::getmntinfo(&mut buf, libc::MNT_NOWAIT) };
+    if count <= 0 || buf.is_null() {
+        return Vec::new();
+    }
+    // SAFETY: `buf` holds `count` entries
+    let entries = unsafe { std::slice::from_raw_parts(buf, count as usize) };
+    entries
+        .iter()
+        .map(|e| {
+            (
+                PathBuf::from(text(&e.f_mntonname)),
+                text(&e.f_mntfromname),
+                text(&e.f_fstypename),
+            )
+        })
+        .collect()
+}
+
+/// The root of each drive letter present, e.g. `C:\`, then the network shares connected,
+/// e.g. `\\server\share`, with the drive letter they are mapped to, if any.
+#[cfg(windows)]
+fn mount_points() -> Vec<(PathBuf, String, String)> {
+    let mut mounts: Vec<_> = (b'A'..=b'Z')
+        .map(|letter| PathBuf::from(format!("{}:\\", letter as char)))
+        .filter(|root| root.exists())
+        .map(|root| (root, String::new(), String::new()))
+        .collect();
+    // `OK           Z:        \\server\share        Microsoft Windows Network`
+    if let Ok(output) = Command::new("net").arg("use").output() {
+        for line in String::from_utf8_lossy(&output.stdout).lines() {
+            let words: Vec<&str> = line.split_whitespace().collect();
+            let Some(share) = words.iter().find(|w| w.starts_with(r"\\")) else {
+                continue;
+            };
+            let letter = words.iter().find(|w| w.len() == 2 && w.ends_with(':'));
+            mounts.push((
+                PathBuf::from(share),
+                letter.map_or_else(String::new, |l| l.to_string()),
+                "smb".to_string(),
+            ));
+        }
+    }
+    mounts
+}
+
+/// Free and total space of the file system holding `path`.
+#[cfg(unix)]
+// the statvfs field types differ between platforms
+#[allow(clippy::useless_conversion)]
+pub fn space(path: &Path) -> io::Result<Space> {
+    use std::os::unix::ffi::OsStrExt;
+
+    let path = std::ffi::CString::new(path.as_os_str().as_bytes())?;
+    // SAFETY: statvfs only writes to the zeroed struct it is given
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statvfs(path.as_ptr(), &mut stat) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    let block = u64::from(stat.f_frsize);
+    Ok(Space {
+        free: u64::from(stat.f_bavail) * block,
+        total: u64::from(stat.f_blocks) * block,
+    })
+}
+
+/// Free and total space of the volume holding `path`.
+#[cfg(windows)]
+pub fn space(path: &Path) -> io::Result<Space> {
+    use std::os::windows::ffi::OsStrExt;
+    use windows_sys::Win32::Storage::FileSystem::GetDiskFreeSpaceExW;
+
+    // a share's root needs its trailing backslash
+    let path = crate::fs_utils::long_path(path).join("");
+    let wide: Vec<u16> = path.as_os_str().encode_wide().chain(Some(0)).collect();
+    let (mut free, mut total) = (0u64, 0u64);
+    // SAFETY: `wide` is NUL-terminated and the out pointers are valid for writes
+    let ok =
+        unsafe { GetDiskFreeSpaceExW(wide.as_ptr(), &mut free, &mut total, std::ptr::null_mut()) };
+    if ok == 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(Space { free, total })
+}
@@ -0,0 +1,94 @@
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant, SystemTime},
+};
+
+/// Notifications kept for the log popup.
+const HISTORY: usize = 50;
+
+/// How long an info or warning message stays in the status bar; errors stay until a key is
+/// pressed.
+const SHOWN_FOR: Duration = Duration::from_secs(5);
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+
+impl Severity {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Severity::Info => "info",
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        }
+    }
+}
+
+pub struct Notification {
+    pub severity: Severity,
+    pub message: String,
+    pub time: SystemTime,
+}
+
+/// Messages for the user, shown in the status bar instead of being printed, which would
+/// garble the screen under raw mode.
+#[derive(Default)]
+pub struct Notifications {
+    /// Most recent last
+    history: VecDeque<Notification>,
+    /// When the latest notification went up, while it is still in the status bar
+    shown_since: Option<Instant>,
+}
+
+impl Notifications {
+    pub fn push(&mut self, severity: Severity, message: impl Into<String>) {
+        let message = message.into();
+        match severity {
+            Severity::Info => tracing::info!("{message}"),
+            Severity::Warning => tracing::warn!("{message}"),
+            Severity::Error => tracing::error!("{message}"),
+        }
+        if self.history.len() == HISTORY {
+            self.history.pop_front();
+        }
+        self.history.push_back(Notification {
+            severity,
+            message,
+            time: SystemTime::now(),
+        });
+        self.shown_since = Some(Instant::now());
+    }
+
+    pub fn info(&mut self, message: impl Into<String>) {
+        self.push(Severity::Info, message);
+    }
+
+    pub fn warn(&mut self, message: impl Into<String>) {
+        self.push(Severity::Warning, message);
+    }
+
+    pub fn error(&mut self, message: impl Into<String>) {
+        self.push(Severity::Error, message);
+    }
+
+    /// The notification to show in the status bar, if there is a recent one.
+    pub fn current(&self) -> Option<&Notification> {
+        let since = self.shown_since?;
+        self.history
+            .back()
+            .filter(|n| n.severity == Severity::Error || since.elapsed() < SHOWN_FOR)
+    }
+
+    /// Take the latest notification off the status bar; it stays in the history.
+    pub fn dismiss(&mut self) {
+        self.shown_since = None;
+    }
+
+    /// Past notifications, most recent first.
+    pub fn history(&self) -> impl Iterator<Item = &Notification> {
+        self.history.iter().rev()
+    }
+}
@@ -0,0 +1,123 @@
+use std::{
+    io::{self, Read, Write},
+    path::Path,
+    time::Duration,
+};
+
+use crate::config::Config;
+
+/// Runs in the editor: switch to the previous window, so a kura drawer in a terminal window
+/// stays open, then edit the path passed as the argument.
+const OPEN_LUA: &str = "\
+local path = ...
+local prev = vim.fn.win_getid(vim.fn.winnr('#'))
+if prev ~= 0 and prev ~= vim.api.nvim_get_current_win() then
+  vim.api.nvim_set_current_win(prev)
+end
+vim.cmd('edit ' .. vim.fn.fnameescape(path))";
+
+/// Address of the Neovim instance to open files in: `nvim_socket` from the config, or the
+/// `$NVIM` variable Neovim sets in its terminal buffers.
+pub fn server(config: &Config) -> Option<String> {
+    config
+        .nvim_socket
+        .clone()
+        .or_else(|| std::env::var("NVIM").ok())
+        .or_else(|| std::env::var("NVIM_LISTEN_ADDRESS").ok())
+        .filter(|s| !s.is_empty())
+}
+
+/// Ask the Neovim instance at `address` (a socket path, named pipe or `host:port`) to edit
+/// `path`, through its msgpack-RPC API.
+pub fn open(address: &str, path: &Path) -> io::Result<()> {
+    let mut request = Vec::new();
+    // [type = request, msgid, method, params]
+    request.push(0x94);
+    request.push(0x00);
+    request.push(0x01);
+    write_str(&mut request, "nvim_exec_lua");
+    request.push(0x92);
+    write_str(&mut request, OPEN_LUA);
+    request.push(0x91);
+    write_str(&mut request, &path.to_string_lossy());
+
+    let mut stream = connect(address)?;
+    stream.write_all(&request)?;
+    stream.flush()?;
+    let mut response = vec![0; 4096];
+    let n = stream.read(&mut response)?;
+    response.truncate(n);
+    // [type = response, msgid, error, result]; a nil error means success
+    match response.get(..4) {
+        Some([0x94, 0x01, 0x01, 0xc0]) => Ok(()),
+        Some([0x94, 0x01, 0x01, _]) => Err(io::Error::other(
+            first_str(&response[3..]).unwrap_or_else(|| "Neovim reported an error".into()),
+        )),
+        _ => Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "unexpected reply from Neovim",
+        )),
+    }
+}
+
+trait Stream: Read + Write {}
+impl<T: Read + Write> Stream for T {}
+
+fn connect(address: &str) -> io::Result<Box<dyn Stream>> {
+    let timeout = Some(Duration::from_secs(5));
+    if address.contains(':') && !address.starts_with('/') && !address.starts_with('\\') {
+        let tcp = std::net::TcpStream::connect(address)?;
+        tcp.set_read_timeout(timeout)?;
+        return Ok(Box::new(tcp));
+    }
+    #[cfg(unix)]
+    {
+        let socket = std::os::unix::net::UnixStream::connect(address)?;
+        socket.set_read_timeout(timeout)?;
+        Ok(Box::new(socket))
+    }
+    #[cfg(windows)]
+    {
+        // named pipes open like files
+        Ok(Box::new(
+            std::fs::OpenOptions::new()
+                .read(true)
+                .write(true)
+                .open(address)?,
+        ))
+    }
+}
+
+/// Append `s` as a msgpack string.
+fn write_str(buf: &mut Vec<u8>, s: &str) {
+    let len = s.len();
+    if len < 32 {
+        buf.push(0xa0 | len as u8);
+    } else if len < 0x100 {
+        buf.push(0xd9);
+        buf.push(len as u8);
+    } else if len < 0x10000 {
+        buf.push(0xda);
+        buf.extend_from_slice(&(len as u16).to_be_bytes());
+    } else {
+        buf.push(0xdb);
+        buf.extend_from_slice(&(len as u32).to_be_bytes());
+    }
+    buf.extend_from_slice(s.as_bytes());
+}
+
+/// The first msgpack string in `bytes`, which for an error reply is its message.
+fn first_str(bytes: &[u8]) -> Option<String> {
+    (0..bytes.len()).find_map(|i| {
+        let (start, len) = match bytes[i] {
+            b @ 0xa0..=0xbf => (i + 1, (b & 0x1f) as usize),
+            0xd9 => (i + 2, *bytes.get(i + 1)? as usize),
+            0xda => (
+                i + 3,
+                u16::from_be_bytes([*bytes.get(i + 1)?, *bytes.get(i + 2)?]) as usize,
+            ),
+            _ => return None,
+        };
+        Some(String::from_utf8_lossy(bytes.get(start..start + len)?).into_owned())
+    })
+}
@@ -0,0 +1,217 @@
+use std::time::{Duration, SystemTime};
+
+use crate::fs_utils::glob_match;
+use crate::vfs::Entry;
+
+/// A condition on entries typed in the mark-where prompt, e.g.
+/// `modified<7d and (ext=jpg,png or size>10M)`.
+///
+/// Conditions are `modified<AGE` (changed within `AGE`) or `modified>AGE`, with `AGE` a number
+/// of `s`, `m`, `h`, `d` or `w`; `size<SIZE` or `size>SIZE`, with an optional `k`, `M`, `G` or
+/// `T` (powers of 1024); `ext=LIST` for extensions in a comma-separated list; `name=GLOB`; and
+/// `type=dir` or `type=file`. They combine with `and` (also written `&` or left out), `or`
+/// (`|`), `not` (`!`) and parentheses; `and` binds tighter than `or`.
+pub enum Predicate {
+    And(Box<Predicate>, Box<Predicate>),
+    Or(Box<Predicate>, Box<Predicate>),
+    Not(Box<Predicate>),
+    /// Modified within `age` (`within`), or longer ago
+    Modified {
+        within: bool,
+        age: Duration,
+    },
+    /// Larger than `bytes` (`larger`), or smaller
+    Size {
+        larger: bool,
+        bytes: u64,
+    },
+    /// Extension in the list, ignoring case; an empty one matches names without one
+    Ext(Vec<String>),
+    Name(String),
+    Dir(bool),
+}
+
+impl Predicate {
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let tokens = tokenize(text);
+        if tokens.is_empty() {
+            return Err("empty condition".into());
+        }
+        let mut parser = Parser { tokens, at: 0 };
+        let predicate = parser.or()?;
+        match parser.peek() {
+            None => Ok(predicate),
+            Some(token) => Err(format!("unexpected '{token}'")),
+        }
+    }
+
+    pub fn matches(&self, entry: &Entry, now: SystemTime) -> bool {
+        match self {
+            Predicate::And(a, b) => a.matches(entry, now) && b.matches(entry, now),
+            Predicate::Or(a, b) => a.matches(entry, now) || b.matches(entry, now),
+            Predicate::Not(p) => !p.matches(entry, now),
+            Predicate::Modified { within, age } => entry
+                .modified()
+                .is_some_and(|m| (now.duration_since(m).unwrap_or_default() < *age) == *within),
+            Predicate::Size { larger, bytes } => {
+                !entry.is_dir()
+                    && if *larger {
+                        entry.len() > *bytes
+                    } else {
+                        entry.len() < *bytes
+                    }
+            }
+            Predicate::Ext(exts) => {
+                let name = entry.file_name().to_string_lossy().to_lowercase();
+                let ext = match name.rsplit_once('.') {
+                    Some((stem, ext)) if !stem.is_empty() => ext,
+                    _ => "",
+                };
+                exts.iter().any(|e| e == ext)
+            }
+            Predicate::Name(glob) => glob_match(glob, &entry.file_name().to_string_lossy()),
+            Predicate::Dir(dir) => entry.is_dir() == *dir,
+        }
+    }
+}
+
+/// Words of the query, with parentheses as tokens of their own.
+fn tokenize(text: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut word = String::new();
+    for c in text.chars() {
+        if c.is_whitespace() || c == '(' || c == ')' {
+            if !word.is_empty() {
+                tokens.push(std::mem::take(&mut word));
+            }
+            if !c.is_whitespace() {
+                tokens.push(c.to_string());
+            }
+        } else {
+            word.push(c);
+        }
+    }
+    if !word.is_empty() {
+        tokens.push(word);
+    }
+    tokens
+}
+
+struct Parser {
+    tokens: Vec<String>,
+    at: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&str> {
+        self.tokens.get(self.at).map(String::as_str)
+    }
+
+    fn next(&mut self) -> Option<String> {
+        let token = self.tokens.get(self.at).cloned();
+        self.at += 1;
+        token
+    }
+
+    fn or(&mut self) -> Result<Predicate, String> {
+        let mut left = self.and()?;
+        while matches!(self.peek(), Some("or" | "|" | "||")) {
+            self.at += 1;
+            left = Predicate::Or(Box::new(left), Box::new(self.and()?));
+        }
+        Ok(left)
+    }
+
+    fn and(&mut self) -> Result<Predicate, String> {
+        let mut left = self.not()?;
+        loop {
+            match self.peek() {
+                Some("and" | "&" | "&&") => self.at += 1,
+                // conditions next to each other must all hold
+                Some(token) if !matches!(token, "or" | "|" | "||" | ")") => {}
+                _ => return Ok(left),
+            }
+            left = Predicate::And(Box::new(left), Box::new(self.not()?));
+        }
+    }
+
+    fn not(&mut self) -> Result<Predicate, String> {
+        if matches!(self.peek(), Some("not" | "!")) {
+            self.at += 1;
+            return Ok(Predicate::Not(Box::new(self.not()?)));
+        }
+        match self.next() {
+            Some(token) if token == "(" => {
+                let inner = self.or()?;
+                match self.next().as_deref() {
+                    Some(")") => Ok(inner),
+                    _ => Err("missing ')'".into()),
+                }
+            }
+            Some(token) => condition(&token),
+            None => Err("condition expected at the end".into()),
+        }
+    }
+}
+
+fn condition(token: &str) -> Result<Predicate, String> {
+    let Some(at) = token.find(['<', '>', '=']) else {
+        return Err(format!("'{token}' is not a condition"));
+    };
+    let (field, op, value) = (&token[..at], &token[at..at + 1], &token[at + 1..]);
+    match (field, op) {
+        ("modified" | "mtime" | "age", "<" | ">") => Ok(Predicate::Modified {
+            within: op == "<",
+            age: parse_age(value).ok_or_else(|| format!("'{value}' is not an age like 7d"))?,
+        }),
+        ("size", "<" | ">") => Ok(Predicate::Size {
+            larger: op == ">",
+            bytes: parse_size(value).ok_or_else(|| format!("'{value}' is not a size like 10M"))?,
+        }),
+        ("ext", "=") => Ok(Predicate::Ext(
+            value
+                .split(',')
+                .map(|e| e.trim_start_matches('.').to_lowercase())
+                .collect(),
+        )),
+        ("name", "=") => Ok(Predicate::Name(value.to_string())),
+        ("type", "=") => match value {
+            "dir" | "d" => Ok(Predicate::Dir(true)),
+            "file" | "f" => Ok(Predicate::Dir(false)),
+            _ => Err(format!("'{value}' is not dir or file")),
+        },
+        ("modified" | "mtime" | "age" | "size" | "ext" | "name" | "type", _) => {
+            Err(format!("'{field}{op}' is not a comparison"))
+        }
+        _ => Err(format!("unknown field '{field}'")),
+    }
+}
+
+/// `30s`, `15m`, `2h`, `7d` or `4w`.
+fn parse_age(value: &str) -> Option<Duration> {
+    let unit = value.chars().last()?;
+    let secs = match unit {
+        's' => 1,
+        'm' => 60,
+        'h' => 3600,
+        'd' => 24 * 3600,
+        'w' => 7 * 24 * 3600,
+        _ => return None,
+    };
+    let n: f64 = value[..value.len() - 1].parse().ok()?;
+    Some(Duration::from_secs_f64(n * secs as f64))
+}
+
+/// A byte count with an optional `k`, `M`, `G` or `T` and `B` or `iB` after it.
+fn parse_size(value: &str) -> Option<u64> {
+    let value = value.trim_end_matches("iB").trim_end_matches(['B', 'b']);
+    let (number, power) = match value.chars().last()?.to_ascii_lowercase() {
+        'k' => (&value[..value.len() - 1], 1),
+        'm' => (&value[..value.len() - 1], 2),
+        'g' => (&value[..value.len() - 1], 3),
+        't' => (&value[..value.len() - 1], 4),
+        _ => (value, 0),
+    };
+    let n: f64 = number.parse().ok()?;
+    Some((n * 1024f64.powi(power)) as u64)
+}
@@ -0,0 +1,87 @@
+use std::{
+    collections::{BTreeMap, VecDeque},
+    path::PathBuf,
+};
+
+/// Yanks kept in the numbered registers `0` (the latest) to `9`.
+const HISTORY: usize = 10;
+
+/// Paths yanked with `y`, in registers like Vim's: every yank goes into `0` and shifts the
+/// older ones up to `9`, and `"ay` also keeps it in the named register `a` (`"Ay` appends).
+/// `p` without a register pastes the latest yank.
+#[derive(Default)]
+pub struct Registers {
+    /// Most recent first
+    history: VecDeque<Vec<PathBuf>>,
+    named: BTreeMap<char, Vec<PathBuf>>,
+}
+
+impl Registers {
+    /// Whether `c` names a register: `0`-`9`, `a`-`z`, or `A`-`Z` to append to `a`-`z`.
+    pub fn is_register(c: char) -> bool {
+        c.is_ascii_alphanumeric()
+    }
+
+    pub fn yank(&mut self, register: Option<char>, paths: Vec<PathBuf>) {
+        if paths.is_empty() {
+            return;
+        }
+        let paths = match register {
+            Some(c) if c.is_ascii_uppercase() => {
+                let named = self.named.entry(c.to_ascii_lowercase()).or_default();
+                for path in paths {
+                    if !named.contains(&path) {
+                        named.push(path);
+                    }
+                }
+                named.clone()
+            }
+            Some(c) if c.is_ascii_lowercase() => {
+                self.named.insert(c, paths.clone());
+                paths
+            }
+            _ => paths,
+        };
+        if self.history.len() == HISTORY {
+            self.history.pop_back();
+        }
+        self.history.push_front(paths);
+    }
+
+    /// The paths in `register`, or in `0` when it is `None`.
+    pub fn get(&self, register: Option<char>) -> &[PathBuf] {
+        let register = register.unwrap_or('0');
+        let paths = match register.to_digit(10) {
+            Some(n) => self.history.get(n as usize),
+            None => self.named.get(&register.to_ascii_lowercase()),
+        };
+        paths.map(Vec::as_slice).unwrap_or_default()
+    }
+
+    /// Empty `register` (`0` when `None`) and return what it held, for a move after which the
+    /// paths are gone; other registers holding the same paths are emptied too.
+    pub fn take(&mut self, register: Option<char>) -> Vec<PathBuf> {
+        let paths = self.get(register).to_vec();
+        self.forget(&paths);
+        paths
+    }
+
+    /// Empty the registers holding exactly `paths`, once they have been moved away.
+    pub fn forget(&mut self, paths: &[PathBuf]) {
+        if !paths.is_empty() {
+            self.history.retain(|p| p != paths);
+            self.named.retain(|_, p| p != paths);
+        }
+    }
+
+    /// Registers that hold something, numbered ones first, with their names.
+    pub fn list(&self) -> Vec<(char, &[PathBuf])> {
+        let numbered = self
+            .history
+            .iter()
+            .enumerate()
+            .map(|(n, paths)| (char::from(b'0' + n as u8), paths.as_slice()));
+        let named = self.named.iter().map(|(&c, paths)| (c, paths.as_slice()));
+        numbered.chain(named).collect()
+    }
+}
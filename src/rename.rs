@@ -0,0 +1,99 @@
+use std::{
+    collections::HashSet,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use crate::fs_utils::same_entry;
+
+/// Apply a batch of renames as a unit.
+///
+/// Renames are ordered so that no entry is overwritten by another entry of the batch: a rename
+/// waits until its target has been moved away, and cycles (`a -> b`, `b -> a`) are broken by
+/// moving one entry to a temporary name first. If any step fails, the steps already taken are
+/// undone in reverse order so the directory is left as it was.
+///
+/// Returns the number of entries renamed.
+pub fn apply(renames: &[(PathBuf, PathBuf)]) -> io::Result<usize> {
+    let mut pending: Vec<(PathBuf, PathBuf)> =
+        renames.iter().filter(|(f, t)| f != t).cloned().collect();
+    check(&pending)?;
+    let count = pending.len();
+
+    let mut done: Vec<(PathBuf, PathBuf)> = Vec::new();
+    let mut temp_id = 0;
+    while !pending.is_empty() {
+        let sources: HashSet<&PathBuf> = pending.iter().map(|(f, _)| f).collect();
+        let ready = pending
+            .iter()
+            .position(|(f, t)| !sources.contains(t) || same_entry(f, t));
+        let step = match ready {
+            Some(i) => pending.remove(i),
+            None => {
+                // every target is still occupied by another source: a cycle
+                let (from, to) = pending.remove(0);
+                let temp = temp_name(&from, &mut temp_id);
+                pending.push((temp.clone(), to));
+                (from, temp)
+            }
+        };
+        if let Err(e) = fs::rename(&step.0, &step.1) {
+            let failed = step.0.display().to_string();
+            return Err(match rollback(&done) {
+                Ok(()) => io::Error::new(
+                    e.kind(),
+                    format!("{failed}: {e}; all renames were rolled back"),
+                ),
+                Err(undo) => io::Error::new(
+                    e.kind(),
+                    format!("{failed}: {e}; rolling back also failed: {undo}"),
+                ),
+            });
+        }
+        done.push(step);
+    }
+    Ok(count)
+}
+
+/// Reject batches that cannot succeed before touching anything.
+fn check(renames: &[(PathBuf, PathBuf)]) -> io::Result<()> {
+    let sources: HashSet<&PathBuf> = renames.iter().map(|(f, _)| f).collect();
+    let mut targets = HashSet::new();
+    for (from, to) in renames {
+        if !targets.insert(to) {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!("{} is the target of more than one rename", to.display()),
+            ));
+        }
+        if !sources.contains(to) && to.symlink_metadata().is_ok() && !same_entry(from, to) {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!("{} already exists", to.display()),
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// An unused name next to `path` to park it on while a cycle is resolved.
+fn temp_name(path: &Path, id: &mut usize) -> PathBuf {
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    loop {
+        *id += 1;
+        let candidate = path.with_file_name(format!(".{name}.kura-rename-{id}"));
+        if candidate.symlink_metadata().is_err() {
+            return candidate;
+        }
+    }
+}
+
+fn rollback(done: &[(PathBuf, PathBuf)]) -> io::Result<()> {
+    for (from, to) in done.iter().rev() {
+        fs::rename(to, from)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,128 @@
+use std::{
+    collections::HashMap,
+    fs::{self, File, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    time::UNIX_EPOCH,
+};
+
+use sha2::{Digest, Sha256};
+use tracing::warn;
+
+/// Size and modification time (nanoseconds since the epoch) of a source file when it was
+/// copied.
+type Stamp = (u64, u128);
+
+/// The files a copy job has finished, written as it goes so that the same copy, started
+/// again after being cancelled or interrupted, can skip them. Kept in `copies/` in the data
+/// directory, one file per copy, and deleted once the copy is complete. Without a data
+/// directory nothing is recorded.
+pub struct Manifest {
+    path: Option<PathBuf>,
+    /// Source stamp of each copied file, by the path it was copied to
+    done: HashMap<PathBuf, Stamp>,
+    file: Option<File>,
+}
+
+impl Manifest {
+    /// The manifest of copying `sources` into `dest`, in `data_dir`, with what an earlier run
+    /// recorded.
+    pub fn open(data_dir: Option<&Path>, sources: &[PathBuf], dest: &Path) -> Self {
+        let path = manifest_path(data_dir, sources, dest);
+        let done = path
+            .as_deref()
+            .and_then(|p| fs::read_to_string(p).ok())
+            .map(|text| text.lines().filter_map(parse_line).collect())
+            .unwrap_or_default();
+        Self {
+            path,
+            done,
+            file: None,
+        }
+    }
+
+    /// Whether `src` was already copied to `dst` and neither has changed size since, nor
+    /// `src` its modification time.
+    pub fn is_done(&self, src: &Path, dst: &Path) -> bool {
+        let Some(&(size, modified)) = self.done.get(dst) else {
+            return false;
+        };
+        stamp(src).ok() == Some((size, modified))
+            && fs::metadata(dst).is_ok_and(|meta| meta.len() == size)
+    }
+
+    /// Note that `src` has been copied to `dst`. Failing to note it only costs copying it
+    /// again when resuming, so it is logged rather than reported.
+    pub fn record(&mut self, src: &Path, dst: &Path) {
+        if let Err(e) = self.append(src, dst) {
+            warn!(dst = %dst.display(), "cannot record copied file: {e}");
+        }
+    }
+
+    fn append(&mut self, src: &Path, dst: &Path) -> io::Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let (size, modified) = stamp(src)?;
+        if self.file.is_none() {
+            if let Some(dir) = path.parent() {
+                fs::create_dir_all(dir)?;
+            }
+            self.file = Some(OpenOptions::new().create(true).append(true).open(path)?);
+        }
+        let file = self.file.as_mut().expect("opened above");
+        // one line per file, flushed at once to survive a crash
+        writeln!(file, "{size}\t{modified}\t{}", dst.display())
+    }
+
+    /// Delete the manifest of a copy that completed.
+    pub fn finish(self) {
+        drop(self.file);
+        if let Some(path) = self.path {
+            let _ = fs::remove_file(path);
+        }
+    }
+}
+
+/// How many files a copy of `sources` into `dest` had finished, if one was recorded in
+/// `data_dir`: one that was interrupted, unless it is still queued or running.
+pub fn interrupted(data_dir: Option<&Path>, sources: &[PathBuf], dest: &Path) -> Option<usize> {
+    let text = fs::read_to_string(manifest_path(data_dir, sources, dest)?).ok()?;
+    Some(text.lines().count())
+}
+
+/// Drop the record of an interrupted copy, to copy everything again.
+pub fn forget(data_dir: Option<&Path>, sources: &[PathBuf], dest: &Path) {
+    if let Some(path) = manifest_path(data_dir, sources, dest) {
+        let _ = fs::remove_file(path);
+    }
+}
+
+fn manifest_path(data_dir: Option<&Path>, sources: &[PathBuf], dest: &Path) -> Option<PathBuf> {
+    let mut hasher = Sha256::new();
+    for path in sources.iter().map(PathBuf::as_path).chain([dest]) {
+        hasher.update(path.to_string_lossy().as_bytes());
+        hasher.update([0]);
+    }
+    let name: String = hasher.finalize()[..8]
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect();
+    Some(data_dir?.join("copies").join(name))
+}
+
+fn stamp(path: &Path) -> io::Result<Stamp> {
+    let meta = fs::metadata(path)?;
+    let modified = meta
+        .modified()?
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_nanos());
+    Ok((meta.len(), modified))
+}
+
+fn parse_line(line: &str) -> Option<(PathBuf, Stamp)> {
+    let mut fields = line.splitn(3, '\t');
+    let size = fields.next()?.parse().ok()?;
+    let modified = fields.next()?.parse().ok()?;
+    Some((PathBuf::from(fields.next()?), (size, modified)))
+}
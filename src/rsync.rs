@@ -0,0 +1,159 @@
+use std::{
+    io::{self, BufReader, Read},
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc,
+    },
+    thread,
+    time::Duration,
+};
+
+use crate::mounts;
+
+/// Which copies the task queue hands to rsync instead of copying itself.
+#[derive(Clone, Copy, Default, PartialEq)]
+pub enum UseRsync {
+    #[default]
+    Never,
+    /// Copies onto NFS, SMB and other network file systems
+    Network,
+    Always,
+}
+
+impl UseRsync {
+    /// The choice for a `copy_with_rsync` setting.
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "never" => Some(UseRsync::Never),
+            "network" => Some(UseRsync::Network),
+            "always" => Some(UseRsync::Always),
+            _ => None,
+        }
+    }
+
+    /// Whether copies into `dest` go through rsync.
+    pub fn applies_to(self, dest: &Path) -> bool {
+        match self {
+            UseRsync::Never => false,
+            UseRsync::Network => mounts::is_network(dest),
+            UseRsync::Always => true,
+        }
+    }
+}
+
+/// How far a transfer is, as rsync reports it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Transfer {
+    pub percent: u8,
+    /// e.g. `10.52MB/s`
+    pub speed: String,
+}
+
+/// Copy `sources` into the directory `dest` with the rsync `program`, calling `progress` as the transfer
+/// goes. Files already in `dest` are replaced unless they match their source, and a file cut
+/// off is kept partly copied; with `append`, for a copy that was interrupted, files shorter
+/// than their source are completed instead of copied again. Stops rsync when `cancel` is set.
+///
+/// Fails with `ErrorKind::NotFound` when rsync is not installed and `ErrorKind::Unsupported`
+/// when it is too old for `--info=progress2` (before 3.1), for the caller to copy itself.
+pub fn copy(
+    program: &Path,
+    sources: &[PathBuf],
+    dest: &Path,
+    preserve: bool,
+    append: bool,
+    cancel: &AtomicBool,
+    progress: &dyn Fn(Transfer),
+) -> io::Result<()> {
+    let mut command = Command::new(program);
+    // permissions are always kept, as by the task queue's own copies
+    command
+        .arg(if preserve { "-rlpt" } else { "-rlp" })
+        .arg("--partial");
+    if append {
+        // takes files at least as large as their source as complete, so only when resuming
+        command.arg("--append-verify");
+    }
+    command.args([
+        // counts the files first, so that the percentage covers the whole copy
+        "--no-inc-recursive",
+        "--info=progress2",
+        "--",
+    ]);
+    // absolute paths, which rsync never takes for `host:path`
+    command.args(sources).arg(dest.join(""));
+    let mut child = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+
+    let (lines, received) = mpsc::channel();
+    let stdout = child.stdout.take().expect("piped stdout");
+    thread::spawn(move || {
+        // progress lines end with `\r` while a file is copied
+        let mut line = Vec::new();
+        for byte in BufReader::new(stdout).bytes() {
+            let Ok(byte) = byte else { break };
+            if byte == b'\r' || byte == b'\n' {
+                let text = String::from_utf8_lossy(&line).into_owned();
+                if lines.send(text).is_err() {
+                    break;
+                }
+                line.clear();
+            } else {
+                line.push(byte);
+            }
+        }
+    });
+    let mut stderr = child.stderr.take().expect("piped stderr");
+    let errors = thread::spawn(move || {
+        let mut text = String::new();
+        let _ = stderr.read_to_string(&mut text);
+        text
+    });
+
+    loop {
+        if cancel.load(Ordering::Relaxed) {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Ok(());
+        }
+        match received.recv_timeout(Duration::from_millis(100)) {
+            Ok(line) => {
+                if let Some(transfer) = parse_progress(&line) {
+                    progress(transfer);
+                }
+            }
+            Err(mpsc::RecvTimeoutError::Timeout) => {}
+            Err(mpsc::RecvTimeoutError::Disconnected) => break,
+        }
+    }
+    let status = child.wait()?;
+    let errors = errors.join().unwrap_or_default();
+    match status.code() {
+        Some(0) => Ok(()),
+        // a syntax or usage error: an option this rsync does not know
+        Some(1) => Err(io::Error::new(io::ErrorKind::Unsupported, errors.trim())),
+        _ => {
+            let kind = if errors.contains("Permission denied") {
+                io::ErrorKind::PermissionDenied
+            } else {
+                io::ErrorKind::Other
+            };
+            let message = errors.lines().last().unwrap_or("rsync failed").trim();
+            Err(io::Error::new(kind, message.to_string()))
+        }
+    }
+}
+
+/// The transfer a `--info=progress2` line reports, e.g.
+/// `  1,238,099  45%  10.52MB/s    0:00:12 (xfr#3, to-chk=10/20)`.
+fn parse_progress(line: &str) -> Option<Transfer> {
+    let mut fields = line.split_whitespace().skip(1);
+    let percent = fields.next()?.strip_suffix('%')?.parse().ok()?;
+    let speed = fields.next()?.to_string();
+    Some(Transfer { percent, speed })
+}
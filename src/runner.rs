@@ -0,0 +1,196 @@
+use std::{
+    fs::File,
+    io,
+    path::{Path, PathBuf},
+    process::{Child, Command, ExitStatus, Stdio},
+};
+
+use crate::fs_utils::{percent_encode, shell_quote};
+
+/// `line` run by the platform shell: `sh -c` on Unix, `cmd /C` on Windows.
+pub fn shell_command(line: &str) -> Command {
+    let mut shell = if cfg!(windows) {
+        let mut shell = Command::new("cmd");
+        shell.arg("/C");
+        shell
+    } else {
+        let mut shell = Command::new("sh");
+        shell.arg("-c");
+        shell
+    };
+    shell.arg(line);
+    shell
+}
+
+/// `template` with `{path}`, `{name}`, `{stem}`, `{ext}` and `{dir}` replaced by the shell-quoted
+/// path, file name, name without its extension, extension and parent directory of `path`, and
+/// `{{` and `}}` by braces. Other braces are left as they are.
+pub fn fill_template(template: &str, path: &Path) -> String {
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let (stem, ext) = match name.rsplit_once('.') {
+        Some((stem, ext)) if !stem.is_empty() => (stem.to_string(), ext.to_string()),
+        _ => (name.clone(), String::new()),
+    };
+    let dir = path
+        .parent()
+        .map(|d| d.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let mut line = String::new();
+    let mut rest = template;
+    while let Some(at) = rest.find(['{', '}']) {
+        line.push_str(&rest[..at]);
+        rest = &rest[at..];
+        let value = ["{{", "}}"]
+            .into_iter()
+            .find(|escape| rest.starts_with(escape))
+            .map(|escape| (escape.len(), escape[..1].to_string()))
+            .or_else(|| {
+                let (field, _) = rest.strip_prefix('{')?.split_once('}')?;
+                let value = match field {
+                    "path" => path.to_string_lossy().into_owned(),
+                    "name" => name.clone(),
+                    "stem" => stem.clone(),
+                    "ext" => ext.clone(),
+                    "dir" => dir.clone(),
+                    _ => return None,
+                };
+                Some((field.len() + 2, shell_quote(&value)))
+            });
+        let (len, value) = value.unwrap_or((1, rest[..1].to_string()));
+        line.push_str(&value);
+        rest = &rest[len..];
+    }
+    line.push_str(rest);
+    line
+}
+
+/// Open `path` with the application the desktop associates with it, without waiting for it.
+pub fn open_default(path: &Path) -> io::Result<()> {
+    let mut command = if cfg!(windows) {
+        let mut command = Command::new("cmd");
+        // the empty argument is the window title `start` expects before a quoted path
+        command.args(["/C", "start", ""]);
+        command
+    } else if cfg!(target_os = "macos") {
+        Command::new("open")
+    } else {
+        Command::new("xdg-open")
+    };
+    command
+        .arg(path)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map(|_| ())
+}
+
+/// Show `path` selected in the desktop's file manager, without waiting for it: Explorer on
+/// Windows, Finder on macOS, and elsewhere the file manager answering the freedesktop
+/// `FileManager1` D-Bus interface, or the parent directory opened with `xdg-open` if none does.
+pub fn reveal(path: &Path) -> io::Result<()> {
+    let mut command = if cfg!(windows) {
+        let mut command = Command::new("explorer");
+        select_arg(&mut command, path);
+        command
+    } else if cfg!(target_os = "macos") {
+        let mut command = Command::new("open");
+        command.arg("-R").arg(path);
+        command
+    } else {
+        let uri = format!("file://{}", percent_encode(&path.to_string_lossy()));
+        let shown = Command::new("dbus-send")
+            .args([
+                "--session",
+                "--print-reply",
+                "--dest=org.freedesktop.FileManager1",
+                "--type=method_call",
+                "/org/freedesktop/FileManager1",
+                "org.freedesktop.FileManager1.ShowItems",
+            ])
+            .arg(format!("array:string:{uri}"))
+            .arg("string:")
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .is_ok_and(|s| s.success());
+        if shown {
+            return Ok(());
+        }
+        let mut command = Command::new("xdg-open");
+        command.arg(path.parent().unwrap_or(path));
+        command
+    };
+    command
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map(|_| ())
+}
+
+/// `/select,"<path>"` as Explorer wants it: one argument, with the quotes inside it.
+#[cfg(windows)]
+fn select_arg(command: &mut Command, path: &Path) {
+    use std::os::windows::process::CommandExt;
+    command.raw_arg(format!("/select,\"{}\"", path.display()));
+}
+
+#[cfg(not(windows))]
+fn select_arg(command: &mut Command, path: &Path) {
+    command.arg(format!("/select,{}", path.display()));
+}
+
+/// A program started from the filer, writing its output to a file shown in the text viewer.
+pub struct Running {
+    /// File name of the program
+    pub name: String,
+    /// File receiving both stdout and stderr
+    pub output: PathBuf,
+    child: Child,
+}
+
+impl Running {
+    /// Start `program` in `dir`, with `args` interpreted by the shell so quoting works as usual.
+    pub fn start(program: &Path, args: &str, dir: &Path) -> io::Result<Self> {
+        let name = program
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let is_script = program
+            .extension()
+            .is_some_and(|e| e.eq_ignore_ascii_case("ps1"));
+        let program = program.to_string_lossy();
+        let line = if cfg!(windows) && is_script {
+            // cmd would open the script in its associated editor instead of running it
+            format!("powershell -NoProfile -ExecutionPolicy Bypass -File \"{program}\" {args}")
+        } else if cfg!(windows) {
+            format!("\"{program}\" {args}")
+        } else {
+            format!("{} {args}", shell_quote(&program))
+        };
+        let output = std::env::temp_dir().join(format!("kura-run-{}.txt", std::process::id()));
+        let stdout = File::create(&output)?;
+        let stderr = stdout.try_clone()?;
+        let child = shell_command(&line)
+            .current_dir(dir)
+            .stdin(Stdio::null())
+            .stdout(stdout)
+            .stderr(stderr)
+            .spawn()?;
+        Ok(Self {
+            name,
+            output,
+            child,
+        })
+    }
+
+    /// The exit status, once the program has finished.
+    pub fn try_wait(&mut self) -> Option<ExitStatus> {
+        self.child.try_wait().ok().flatten()
+    }
+}
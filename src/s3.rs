@@ -0,0 +1,540 @@
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    fs::{self, File},
+    io,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use hmac::{Hmac, Mac};
+use sha2::{Digest, Sha256};
+use ureq::{Agent, AgentBuilder, Request, Response};
+
+use crate::config::expand_home;
+use crate::vfs::{Entry, RemoteEntry, VfsProvider};
+
+/// Region used when none is configured, as by the AWS CLI.
+const DEFAULT_REGION: &str = "us-east-1";
+
+/// Payload hash sent instead of hashing uploads, which S3 accepts over HTTPS and from
+/// S3-compatible services.
+const UNSIGNED_PAYLOAD: &str = "UNSIGNED-PAYLOAD";
+
+/// Keys that sign requests, found like the AWS CLI finds them.
+#[derive(Clone)]
+pub struct Credentials {
+    pub access_key: String,
+    pub secret_key: String,
+    pub session_token: Option<String>,
+}
+
+/// What the standard AWS settings say: the environment, then the profile (`AWS_PROFILE`,
+/// or `default`) in `~/.aws/credentials` and `~/.aws/config`.
+struct Settings {
+    credentials: Option<Credentials>,
+    region: String,
+    /// An S3-compatible service (MinIO, Cloudflare R2, ...) instead of AWS
+    endpoint: Option<String>,
+}
+
+impl Settings {
+    fn load() -> Self {
+        let env = |name: &str| std::env::var(name).ok().filter(|v| !v.is_empty());
+        let profile = env("AWS_PROFILE").unwrap_or_else(|| "default".into());
+        let credentials_file = env("AWS_SHARED_CREDENTIALS_FILE")
+            .map(PathBuf::from)
+            .unwrap_or_else(|| expand_home("~/.aws/credentials"));
+        let config_file = env("AWS_CONFIG_FILE")
+            .map(PathBuf::from)
+            .unwrap_or_else(|| expand_home("~/.aws/config"));
+        let mut file = ini_section(&credentials_file, &profile);
+        // the config file names profiles other than the default `[profile name]`
+        let config_section = if profile == "default" {
+            profile.clone()
+        } else {
+            format!("profile {profile}")
+        };
+        for (key, value) in ini_section(&config_file, &config_section) {
+            file.entry(key).or_insert(value);
+        }
+        let setting = |env_name: &str, key: &str| env(env_name).or_else(|| file.get(key).cloned());
+        let credentials = match (
+            setting("AWS_ACCESS_KEY_ID", "aws_access_key_id"),
+            setting("AWS_SECRET_ACCESS_KEY", "aws_secret_access_key"),
+        ) {
+            (Some(access_key), Some(secret_key)) => Some(Credentials {
+                access_key,
+                secret_key,
+                session_token: setting("AWS_SESSION_TOKEN", "aws_session_token"),
+            }),
+            _ => None,
+        };
+        Self {
+            credentials,
+            region: env("AWS_REGION")
+                .or_else(|| setting("AWS_DEFAULT_REGION", "region"))
+                .unwrap_or_else(|| DEFAULT_REGION.into()),
+            endpoint: env("AWS_ENDPOINT_URL_S3")
+                .or_else(|| setting("AWS_ENDPOINT_URL", "endpoint_url"))
+                .map(|url| url.trim_end_matches('/').to_string()),
+        }
+    }
+}
+
+/// The keys and values of the section `[name]` of the INI file `path`.
+fn ini_section(path: &Path, name: &str) -> HashMap<String, String> {
+    let mut values = HashMap::new();
+    let Ok(text) = fs::read_to_string(path) else {
+        return values;
+    };
+    let mut inside = false;
+    for line in text.lines().map(str::trim) {
+        if let Some(section) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            inside = section.trim() == name;
+        } else if let Some((key, value)) = line.split_once('=').filter(|_| inside) {
+            values.insert(key.trim().to_string(), value.trim().to_string());
+        }
+    }
+    values
+}
+
+/// Buckets and objects of Amazon S3 or an S3-compatible service, shown as directories: `/`
+/// lists the buckets, `/bucket/a/b` the objects under the prefix `a/b/`.
+pub struct S3Fs {
+    agent: Agent,
+    credentials: Credentials,
+    region: String,
+    /// Base URL of an S3-compatible service, addressed path-style; AWS when `None`
+    endpoint: Option<String>,
+    /// Regions of buckets outside `region`, as AWS reports them
+    bucket_regions: RefCell<HashMap<String, String>>,
+}
+
+impl S3Fs {
+    /// Connect to `s3://[bucket[/prefix]]`, returning the provider and the directory to show.
+    /// Credentials, region and endpoint come from the standard AWS environment variables and
+    /// files.
+    pub fn connect(url: &str) -> io::Result<(Self, PathBuf)> {
+        let rest = url.strip_prefix("s3://").ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "expected s3://bucket/prefix")
+        })?;
+        let settings = Settings::load();
+        let credentials = settings.credentials.ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                "no AWS credentials: set AWS_ACCESS_KEY_ID and AWS_SECRET_ACCESS_KEY, or run \
+                 `aws configure`",
+            )
+        })?;
+        let s3 = Self::new(credentials, settings.region, settings.endpoint);
+        let dir = Path::new("/").join(rest.trim_matches('/'));
+        // fails early on wrong credentials or a missing bucket
+        drop(s3.read_dir(&dir)?);
+        Ok((s3, dir))
+    }
+
+    /// Buckets in `region` of AWS, or of the S3-compatible service at the base URL `endpoint`,
+    /// signed with `credentials`.
+    pub fn new(credentials: Credentials, region: String, endpoint: Option<String>) -> Self {
+        Self {
+            agent: AgentBuilder::new()
+                .timeout_connect(Duration::from_secs(10))
+                .timeout_read(Duration::from_secs(30))
+                .build(),
+            credentials,
+            region,
+            endpoint,
+            bucket_regions: RefCell::new(HashMap::new()),
+        }
+    }
+
+    /// The region the bucket is in, asked of AWS the first time.
+    fn region_of(&self, bucket: &str) -> String {
+        if self.endpoint.is_some() {
+            return self.region.clone();
+        }
+        if let Some(region) = self.bucket_regions.borrow().get(bucket) {
+            return region.clone();
+        }
+        // AWS tells the region even when refusing an unsigned request
+        let response = match self
+            .agent
+            .head(&format!("https://{bucket}.s3.amazonaws.com"))
+            .call()
+        {
+            Ok(response) | Err(ureq::Error::Status(_, response)) => Some(response),
+            Err(_) => None,
+        };
+        let region = response
+            .and_then(|r| r.header("x-amz-bucket-region").map(str::to_string))
+            .unwrap_or_else(|| self.region.clone());
+        self.bucket_regions
+            .borrow_mut()
+            .insert(bucket.to_string(), region.clone());
+        region
+    }
+
+    /// A request for `key` in `bucket` (or for the service, without a bucket), signed with
+    /// AWS Signature Version 4. `headers` are signed too.
+    fn request(
+        &self,
+        method: &str,
+        bucket: Option<&str>,
+        key: &str,
+        query: &[(&str, &str)],
+        headers: &[(&str, &str)],
+    ) -> Request {
+        let region = bucket.map_or_else(|| self.region.clone(), |b| self.region_of(b));
+        let (base, host, path) = match (&self.endpoint, bucket) {
+            (Some(endpoint), _) => {
+                let host = endpoint
+                    .split_once("://")
+                    .map_or(endpoint.as_str(), |(_, h)| h);
+                let path = match bucket {
+                    Some(bucket) => format!("/{bucket}/{key}"),
+                    None => "/".to_string(),
+                };
+                (endpoint.clone(), host.to_string(), path)
+            }
+            (None, Some(bucket)) => {
+                let host = format!("{bucket}.s3.{region}.amazonaws.com");
+                (format!("https://{host}"), host, format!("/{key}"))
+            }
+            (None, None) => {
+                let host = format!("s3.{region}.amazonaws.com");
+                (format!("https://{host}"), host, "/".to_string())
+            }
+        };
+        let path = uri_encode(&path, false);
+        let mut query: Vec<(String, String)> = query
+            .iter()
+            .map(|(k, v)| (uri_encode(k, true), uri_encode(v, true)))
+            .collect();
+        query.sort();
+        let query = query
+            .iter()
+            .map(|(k, v)| format!("{k}={v}"))
+            .collect::<Vec<_>>()
+            .join("&");
+
+        let now = chrono::Utc::now();
+        let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+        let mut signed: Vec<(String, String)> = vec![
+            ("host".into(), host),
+            ("x-amz-content-sha256".into(), UNSIGNED_PAYLOAD.into()),
+            ("x-amz-date".into(), amz_date.clone()),
+        ];
+        if let Some(token) = &self.credentials.session_token {
+            signed.push(("x-amz-security-token".into(), token.clone()));
+        }
+        signed.extend(
+            headers
+                .iter()
+                .map(|(k, v)| (k.to_ascii_lowercase(), v.trim().to_string())),
+        );
+        signed.sort();
+        let signature = self.sign(
+            &[method, &path, &query],
+            &signed,
+            &amz_date,
+            &now.format("%Y%m%d").to_string(),
+            &region,
+        );
+
+        let url = if query.is_empty() {
+            format!("{base}{path}")
+        } else {
+            format!("{base}{path}?{query}")
+        };
+        let mut request = self
+            .agent
+            .request(method, &url)
+            .set("Authorization", &signature);
+        for (name, value) in signed.iter().filter(|(name, _)| name != "host") {
+            request = request.set(name, value);
+        }
+        request
+    }
+
+    /// The `Authorization` header of a request whose method, encoded path and query are
+    /// `line`, with the sorted lowercase `headers`.
+    fn sign(
+        &self,
+        line: &[&str; 3],
+        headers: &[(String, String)],
+        amz_date: &str,
+        date: &str,
+        region: &str,
+    ) -> String {
+        let names = headers
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect::<Vec<_>>()
+            .join(";");
+        let canonical_headers: String = headers
+            .iter()
+            .map(|(name, value)| format!("{name}:{value}\n"))
+            .collect();
+        let canonical_request = format!(
+            "{}\n{}\n{}\n{canonical_headers}\n{names}\n{UNSIGNED_PAYLOAD}",
+            line[0], line[1], line[2]
+        );
+        let scope = format!("{date}/{region}/s3/aws4_request");
+        let string_to_sign = format!(
+            "AWS4-HMAC-SHA256\n{amz_date}\n{scope}\n{}",
+            hex(&Sha256::digest(canonical_request.as_bytes()))
+        );
+        let mut key = format!("AWS4{}", self.credentials.secret_key).into_bytes();
+        for part in [date, region, "s3", "aws4_request"] {
+            key = hmac(&key, part);
+        }
+        let signature = hex(&hmac(&key, &string_to_sign));
+        format!(
+            "AWS4-HMAC-SHA256 Credential={}/{scope}, SignedHeaders={names}, Signature={signature}",
+            self.credentials.access_key
+        )
+    }
+
+    /// The objects and common prefixes right under `prefix` in `bucket`.
+    fn list_objects(&self, bucket: &str, prefix: &str) -> io::Result<Vec<RemoteEntry>> {
+        let dir = Path::new("/").join(bucket);
+        let mut entries = Vec::new();
+        let mut token: Option<String> = None;
+        loop {
+            let mut query = vec![("list-type", "2"), ("delimiter", "/"), ("prefix", prefix)];
+            if let Some(token) = &token {
+                query.push(("continuation-token", token));
+            }
+            let xml = read_text(self.request("GET", Some(bucket), "", &query, &[]).call())?;
+            let doc = roxmltree::Document::parse(&xml).map_err(io::Error::other)?;
+            for node in doc.root_element().children() {
+                let text = |name| child_text(&node, name);
+                match node.tag_name().name() {
+                    "CommonPrefixes" => {
+                        if let Some(sub) = text("Prefix") {
+                            entries.push(RemoteEntry {
+                                path: dir.join(sub.trim_end_matches('/')),
+                                is_dir: true,
+                                is_symlink: false,
+                                len: 0,
+                                modified: None,
+                                mode: None,
+                            });
+                        }
+                    }
+                    // a key ending in `/` marks a directory made by `create_dir`
+                    "Contents" => match text("Key") {
+                        Some(key) if !key.ends_with('/') => entries.push(RemoteEntry {
+                            path: dir.join(key),
+                            is_dir: false,
+                            is_symlink: false,
+                            len: text("Size").and_then(|s| s.parse().ok()).unwrap_or(0),
+                            modified: text("LastModified")
+                                .and_then(|d| chrono::DateTime::parse_from_rfc3339(d).ok())
+                                .map(SystemTime::from),
+                            mode: None,
+                        }),
+                        _ => {}
+                    },
+                    _ => {}
+                }
+            }
+            let root = doc.root_element();
+            token = child_text(&root, "NextContinuationToken")
+                .filter(|_| child_text(&root, "IsTruncated") == Some("true"))
+                .map(str::to_string);
+            if token.is_none() {
+                return Ok(entries);
+            }
+        }
+    }
+
+    fn list_buckets(&self) -> io::Result<Vec<RemoteEntry>> {
+        let xml = read_text(self.request("GET", None, "", &[], &[]).call())?;
+        let doc = roxmltree::Document::parse(&xml).map_err(io::Error::other)?;
+        Ok(doc
+            .descendants()
+            .filter(|n| n.tag_name().name() == "Bucket")
+            .filter_map(|bucket| {
+                Some(RemoteEntry {
+                    path: Path::new("/").join(child_text(&bucket, "Name")?),
+                    is_dir: true,
+                    is_symlink: false,
+                    len: 0,
+                    modified: child_text(&bucket, "CreationDate")
+                        .and_then(|d| chrono::DateTime::parse_from_rfc3339(d).ok())
+                        .map(SystemTime::from),
+                    mode: None,
+                })
+            })
+            .collect())
+    }
+}
+
+/// The bucket and key of `path`; no bucket for `/`.
+fn split(path: &Path) -> (Option<String>, String) {
+    let path = path.to_string_lossy().replace('\\', "/");
+    let path = path.trim_start_matches('/');
+    match path.split_once('/') {
+        Some((bucket, key)) => (Some(bucket.to_string()), key.to_string()),
+        None if path.is_empty() => (None, String::new()),
+        None => (Some(path.to_string()), String::new()),
+    }
+}
+
+/// The bucket and key of the object `path`, refusing the bucket list and buckets themselves.
+fn object(path: &Path) -> io::Result<(String, String)> {
+    match split(path) {
+        (Some(bucket), key) if !key.is_empty() => Ok((bucket, key)),
+        _ => Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "buckets are created and deleted in the AWS console or CLI",
+        )),
+    }
+}
+
+impl VfsProvider for S3Fs {
+    fn label(&self) -> String {
+        match &self.endpoint {
+            Some(endpoint) => format!("s3:{endpoint}"),
+            None => "s3:".to_string(),
+        }
+    }
+
+    fn read_dir(&self, dir: &Path) -> io::Result<Box<dyn Iterator<Item = io::Result<Entry>> + '_>> {
+        let entries = match split(dir) {
+            (None, _) => self.list_buckets()?,
+            (Some(bucket), key) if key.is_empty() => self.list_objects(&bucket, "")?,
+            (Some(bucket), key) => self.list_objects(&bucket, &format!("{key}/"))?,
+        };
+        Ok(Box::new(entries.into_iter().map(|e| Ok(Entry::Remote(e)))))
+    }
+
+    /// Objects cannot be renamed: they are copied to the new key and deleted.
+    fn rename(&self, from: &Path, to: &Path) -> io::Result<()> {
+        let (bucket, key) = object(from)?;
+        let (to_bucket, to_key) = object(to)?;
+        let source = uri_encode(&format!("/{bucket}/{key}"), false);
+        read_text(
+            self.request(
+                "PUT",
+                Some(&to_bucket),
+                &to_key,
+                &[],
+                &[("x-amz-copy-source", &source)],
+            )
+            .call(),
+        )?;
+        self.remove_file(from)
+    }
+
+    fn create_dir(&self, path: &Path) -> io::Result<()> {
+        let (bucket, key) = object(path)?;
+        read_text(
+            self.request("PUT", Some(&bucket), &format!("{key}/"), &[], &[])
+                .send_bytes(&[]),
+        )?;
+        Ok(())
+    }
+
+    fn remove_file(&self, path: &Path) -> io::Result<()> {
+        let (bucket, key) = object(path)?;
+        read_text(self.request("DELETE", Some(&bucket), &key, &[], &[]).call())?;
+        Ok(())
+    }
+
+    /// Prefixes exist as long as objects are under them, so only the marker `create_dir`
+    /// may have left is deleted.
+    fn remove_dir(&self, path: &Path) -> io::Result<()> {
+        let (bucket, key) = object(path)?;
+        read_text(
+            self.request("DELETE", Some(&bucket), &format!("{key}/"), &[], &[])
+                .call(),
+        )?;
+        Ok(())
+    }
+
+    fn download(&self, path: &Path, dest: &Path) -> io::Result<()> {
+        let (bucket, key) = object(path)?;
+        let response = self
+            .request("GET", Some(&bucket), &key, &[], &[])
+            .call()
+            .map_err(s3_error)?;
+        io::copy(&mut response.into_reader(), &mut File::create(dest)?).map(|_| ())
+    }
+
+    fn upload(&self, source: &Path, path: &Path) -> io::Result<()> {
+        let (bucket, key) = object(path)?;
+        let file = File::open(source)?;
+        // S3 refuses chunked uploads without a length
+        let len = file.metadata()?.len().to_string();
+        read_text(
+            self.request("PUT", Some(&bucket), &key, &[], &[])
+                .set("Content-Length", &len)
+                .send(file),
+        )?;
+        Ok(())
+    }
+}
+
+/// The body of a successful response.
+fn read_text(result: Result<Response, ureq::Error>) -> io::Result<String> {
+    result.map_err(s3_error)?.into_string()
+}
+
+/// An S3 failure as the `io::Error` of the same meaning, with the message of its XML body.
+fn s3_error(e: ureq::Error) -> io::Error {
+    match e {
+        ureq::Error::Status(code, response) => {
+            let kind = match code {
+                401 | 403 => io::ErrorKind::PermissionDenied,
+                404 => io::ErrorKind::NotFound,
+                _ => io::ErrorKind::Other,
+            };
+            let status = format!("HTTP {code} {}", response.status_text());
+            let body = response.into_string().unwrap_or_default();
+            let message = roxmltree::Document::parse(&body).ok().and_then(|doc| {
+                let root = doc.root_element();
+                Some(format!(
+                    "{}: {}",
+                    child_text(&root, "Code")?,
+                    child_text(&root, "Message").unwrap_or_default()
+                ))
+            });
+            io::Error::new(kind, message.unwrap_or(status))
+        }
+        ureq::Error::Transport(e) => io::Error::other(e.to_string()),
+    }
+}
+
+/// The text of the child element `name` of `node`, in any namespace.
+fn child_text<'a>(node: &roxmltree::Node<'a, '_>, name: &str) -> Option<&'a str> {
+    node.children()
+        .find(|n| n.tag_name().name() == name)
+        .and_then(|n| n.text())
+}
+
+/// `s` escaped as AWS Signature Version 4 wants: everything but unreserved characters, and
+/// `/` too in query parameters.
+fn uri_encode(s: &str, encode_slash: bool) -> String {
+    s.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (b as char).to_string()
+            }
+            b'/' if !encode_slash => "/".to_string(),
+            _ => format!("%{b:02X}"),
+        })
+        .collect()
+}
+
+fn hmac(key: &[u8], data: &str) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC takes keys of any length");
+    mac.update(data.as_bytes());
+    mac.finalize().into_bytes().to_vec()
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
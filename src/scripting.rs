@@ -0,0 +1,215 @@
+use std::{
+    cell::RefCell,
+    fs,
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+
+use mlua::{Function, IntoLuaMulti, Lua, RegistryKey};
+
+use crate::config::config_dir;
+use crate::keys::KeyCombo;
+
+/// Something a plugin asked the filer to do, applied once the plugin function returns.
+pub enum ScriptAction {
+    /// Change the active pane's directory
+    Cd(PathBuf),
+    /// Mark (`true`) or unmark an entry of the active pane
+    Mark(PathBuf, bool),
+    /// Show a popup with a title and text
+    Message(String, String),
+    /// Reload both panes
+    Refresh,
+}
+
+/// The filer state a plugin function can read, captured when its key is pressed.
+pub struct ScriptContext {
+    pub cwd: PathBuf,
+    pub other_cwd: PathBuf,
+    /// Entry under the cursor
+    pub cursor: Option<PathBuf>,
+    /// Marked entries, or the entry under the cursor
+    pub selection: Vec<PathBuf>,
+}
+
+/// State shared between `Scripts` and the `kura` functions registered in Lua.
+#[derive(Default)]
+struct Shared {
+    context: Option<ScriptContext>,
+    actions: Vec<ScriptAction>,
+    bindings: Vec<(Vec<KeyCombo>, RegistryKey)>,
+}
+
+/// Lua plugins loaded from `<config dir>/plugins/*.lua`.
+///
+/// Plugins see the filer only through the `kura` table: they read a snapshot of its state and
+/// queue `ScriptAction`s, so the API stays the same however `App` changes.
+pub struct Scripts {
+    lua: Lua,
+    shared: Rc<RefCell<Shared>>,
+    /// Errors raised while loading the plugins
+    pub errors: Vec<String>,
+}
+
+impl Scripts {
+    /// Run every plugin file, in name order, so they can register their key bindings.
+    pub fn load() -> Self {
+        Self::load_from(config_dir().map(|d| d.join("plugins")))
+    }
+
+    /// Run the plugin files in `dir`; no plugins when it is `None`.
+    pub fn load_from(dir: Option<PathBuf>) -> Self {
+        let lua = Lua::new();
+        let shared = Rc::new(RefCell::new(Shared::default()));
+        let mut errors = Vec::new();
+        if let Err(e) = register_api(&lua, &shared) {
+            errors.push(e.to_string());
+        }
+        let mut files: Vec<PathBuf> = dir
+            .and_then(|d| fs::read_dir(d).ok())
+            .map(|entries| {
+                entries
+                    .flatten()
+                    .map(|e| e.path())
+                    .filter(|p| p.extension().is_some_and(|e| e == "lua"))
+                    .collect()
+            })
+            .unwrap_or_default();
+        files.sort();
+        for file in files {
+            let result = fs::read_to_string(&file)
+                .map_err(mlua::Error::external)
+                .and_then(|source| {
+                    lua.load(&source)
+                        .set_name(format!("@{}", file.display()))
+                        .exec()
+                });
+            if let Err(e) = result {
+                errors.push(e.to_string());
+            }
+        }
+        Self {
+            lua,
+            shared,
+            errors,
+        }
+    }
+
+    /// The key sequences plugins have bound.
+    pub fn bound_keys(&self) -> Vec<Vec<KeyCombo>> {
+        let shared = self.shared.borrow();
+        shared.bindings.iter().map(|(k, _)| k.clone()).collect()
+    }
+
+    /// Call the function bound to `keys` and return the actions it requested.
+    pub fn run(
+        &self,
+        keys: &[KeyCombo],
+        context: ScriptContext,
+    ) -> Result<Vec<ScriptAction>, String> {
+        let function: Function = {
+            let shared = self.shared.borrow();
+            let Some((_, function)) = shared.bindings.iter().find(|(k, _)| k == keys) else {
+                return Ok(Vec::new());
+            };
+            self.lua
+                .registry_value(function)
+                .map_err(|e| e.to_string())?
+        };
+        self.shared.borrow_mut().context = Some(context);
+        let result = function.call::<_, ()>(());
+        let mut shared = self.shared.borrow_mut();
+        shared.context = None;
+        let actions = std::mem::take(&mut shared.actions);
+        result.map(|()| actions).map_err(|e| e.to_string())
+    }
+}
+
+/// Define the global `kura` table.
+fn register_api(lua: &Lua, shared: &Rc<RefCell<Shared>>) -> mlua::Result<()> {
+    let kura = lua.create_table()?;
+
+    let s = shared.clone();
+    let bind = lua.create_function(move |lua, (key, function): (String, Function)| {
+        let keys = KeyCombo::parse_sequence(&key)
+            .map_err(|e| mlua::Error::RuntimeError(format!("kura.bind: '{key}': {e}")))?;
+        let function = lua.create_registry_value(function)?;
+        let mut shared = s.borrow_mut();
+        shared.bindings.retain(|(k, _)| *k != keys);
+        shared.bindings.push((keys, function));
+        Ok(())
+    })?;
+    kura.set("bind", bind)?;
+
+    fn text(p: &Path) -> String {
+        p.to_string_lossy().into_owned()
+    }
+    kura.set(
+        "selection",
+        reader(lua, shared, |c| {
+            c.selection.iter().map(|p| text(p)).collect::<Vec<_>>()
+        })?,
+    )?;
+    kura.set("cwd", reader(lua, shared, |c| text(&c.cwd))?)?;
+    kura.set("other_cwd", reader(lua, shared, |c| text(&c.other_cwd))?)?;
+    kura.set(
+        "cursor",
+        reader(lua, shared, |c| c.cursor.as_deref().map(text))?,
+    )?;
+
+    let s = shared.clone();
+    let cd = lua.create_function(move |_, path: String| {
+        s.borrow_mut().actions.push(ScriptAction::Cd(path.into()));
+        Ok(())
+    })?;
+    kura.set("cd", cd)?;
+
+    for (name, mark) in [("mark", true), ("unmark", false)] {
+        let s = shared.clone();
+        let function = lua.create_function(move |_, path: String| {
+            s.borrow_mut()
+                .actions
+                .push(ScriptAction::Mark(path.into(), mark));
+            Ok(())
+        })?;
+        kura.set(name, function)?;
+    }
+
+    let s = shared.clone();
+    let message = lua.create_function(move |_, (text, title): (String, Option<String>)| {
+        let title = title.unwrap_or_else(|| "Plugin".into());
+        s.borrow_mut()
+            .actions
+            .push(ScriptAction::Message(title, text));
+        Ok(())
+    })?;
+    kura.set("message", message)?;
+
+    let s = shared.clone();
+    let refresh = lua.create_function(move |_, ()| {
+        s.borrow_mut().actions.push(ScriptAction::Refresh);
+        Ok(())
+    })?;
+    kura.set("refresh", refresh)?;
+
+    lua.globals().set("kura", kura)
+}
+
+/// A `kura` function returning part of the filer state; it fails outside a bound function,
+/// where there is no state to read.
+fn reader<'lua, R>(
+    lua: &'lua Lua,
+    shared: &Rc<RefCell<Shared>>,
+    f: fn(&ScriptContext) -> R,
+) -> mlua::Result<Function<'lua>>
+where
+    R: IntoLuaMulti<'lua> + 'static,
+{
+    let s = shared.clone();
+    lua.create_function(move |_, ()| match &s.borrow().context {
+        Some(context) => Ok(f(context)),
+        None => Err(mlua::Error::RuntimeError(
+            "the filer state is only available inside a bound function".into(),
+        )),
+    })
+}
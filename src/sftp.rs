@@ -0,0 +1,187 @@
+use std::{
+    fs::File,
+    io,
+    net::TcpStream,
+    path::{Path, PathBuf},
+    time::{Duration, UNIX_EPOCH},
+};
+
+use ssh2::{CheckResult, KnownHostFileKind, RenameFlags, Session, Sftp};
+
+use crate::vfs::{Entry, RemoteEntry, VfsProvider};
+
+/// Private keys tried, in order, when the SSH agent cannot authenticate.
+const KEY_FILES: &[&str] = &["id_ed25519", "id_ecdsa", "id_rsa"];
+
+/// A directory tree on a server, reached over SFTP.
+pub struct SftpFs {
+    label: String,
+    sftp: Sftp,
+    // the SFTP channel needs its session alive
+    _session: Session,
+}
+
+impl SftpFs {
+    /// Connect to `sftp://[user@]host[:port][/path]`, returning the provider and the directory
+    /// to show (the login directory when no path is given).
+    ///
+    /// The host key must already be in `~/.ssh/known_hosts`; authentication goes through the
+    /// SSH agent, then the default key files in `~/.ssh`.
+    pub fn connect(url: &str) -> io::Result<(Self, PathBuf)> {
+        let invalid = || {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "expected sftp://[user@]host[:port]/path",
+            )
+        };
+        let rest = url.strip_prefix("sftp://").ok_or_else(invalid)?;
+        let (authority, path) = match rest.find('/') {
+            Some(i) => rest.split_at(i),
+            None => (rest, ""),
+        };
+        let (user, host_port) = match authority.rsplit_once('@') {
+            Some((user, host)) => (user.to_string(), host),
+            None => (
+                std::env::var("USER")
+                    .or_else(|_| std::env::var("USERNAME"))
+                    .map_err(|_| invalid())?,
+                authority,
+            ),
+        };
+        let (host, port) = match host_port.rsplit_once(':') {
+            Some((host, port)) => (host, port.parse().map_err(|_| invalid())?),
+            None => (host_port, 22),
+        };
+        if host.is_empty() {
+            return Err(invalid());
+        }
+
+        let tcp = TcpStream::connect((host, port))?;
+        tcp.set_read_timeout(Some(Duration::from_secs(30)))?;
+        let mut session = Session::new()?;
+        session.set_tcp_stream(tcp);
+        session.handshake()?;
+        check_host_key(&session, host, port)?;
+        authenticate(&session, &user)?;
+        let sftp = session.sftp()?;
+        let dir = if path.is_empty() {
+            sftp.realpath(Path::new("."))?
+        } else {
+            PathBuf::from(path)
+        };
+        let label = if port == 22 {
+            format!("sftp://{user}@{host}")
+        } else {
+            format!("sftp://{user}@{host}:{port}")
+        };
+        Ok((
+            Self {
+                label,
+                sftp,
+                _session: session,
+            },
+            dir,
+        ))
+    }
+}
+
+fn home() -> Option<PathBuf> {
+    std::env::var_os("HOME")
+        .or_else(|| std::env::var_os("USERPROFILE"))
+        .map(PathBuf::from)
+}
+
+/// Refuse servers whose key is not recorded in `~/.ssh/known_hosts`.
+fn check_host_key(session: &Session, host: &str, port: u16) -> io::Result<()> {
+    let refuse = |why: &str| {
+        Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            format!("host key of {host} {why}"),
+        ))
+    };
+    let mut known = session.known_hosts()?;
+    if let Some(file) = home().map(|h| h.join(".ssh/known_hosts")) {
+        let _ = known.read_file(&file, KnownHostFileKind::OpenSSH);
+    }
+    let Some((key, _)) = session.host_key() else {
+        return refuse("was not sent");
+    };
+    match known.check_port(host, port, key) {
+        CheckResult::Match => Ok(()),
+        CheckResult::Mismatch => refuse("does not match ~/.ssh/known_hosts"),
+        CheckResult::NotFound | CheckResult::Failure => {
+            refuse("is not in ~/.ssh/known_hosts; connect once with ssh to add it")
+        }
+    }
+}
+
+fn authenticate(session: &Session, user: &str) -> io::Result<()> {
+    if session.userauth_agent(user).is_ok() && session.authenticated() {
+        return Ok(());
+    }
+    let dir = home().map(|h| h.join(".ssh")).unwrap_or_default();
+    for name in KEY_FILES {
+        let key = dir.join(name);
+        if key.exists() && session.userauth_pubkey_file(user, None, &key, None).is_ok() {
+            return Ok(());
+        }
+    }
+    Err(io::Error::new(
+        io::ErrorKind::PermissionDenied,
+        format!("could not authenticate as {user} with the SSH agent or ~/.ssh keys"),
+    ))
+}
+
+impl VfsProvider for SftpFs {
+    fn label(&self) -> String {
+        self.label.clone()
+    }
+
+    fn read_dir(&self, dir: &Path) -> io::Result<Box<dyn Iterator<Item = io::Result<Entry>> + '_>> {
+        let entries = self.sftp.readdir(dir)?;
+        Ok(Box::new(entries.into_iter().map(move |(path, stat)| {
+            let is_dir = if stat.file_type().is_symlink() {
+                self.sftp.stat(&path).is_ok_and(|s| s.is_dir())
+            } else {
+                stat.is_dir()
+            };
+            Ok(Entry::Remote(RemoteEntry {
+                is_dir,
+                is_symlink: stat.file_type().is_symlink(),
+                len: stat.size.unwrap_or(0),
+                modified: stat.mtime.map(|t| UNIX_EPOCH + Duration::from_secs(t)),
+                mode: stat.perm,
+                path,
+            }))
+        })))
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> io::Result<()> {
+        // without OVERWRITE, an existing target makes the rename fail instead of being replaced
+        Ok(self
+            .sftp
+            .rename(from, to, Some(RenameFlags::ATOMIC | RenameFlags::NATIVE))?)
+    }
+
+    fn create_dir(&self, path: &Path) -> io::Result<()> {
+        Ok(self.sftp.mkdir(path, 0o755)?)
+    }
+
+    fn remove_file(&self, path: &Path) -> io::Result<()> {
+        Ok(self.sftp.unlink(path)?)
+    }
+
+    fn remove_dir(&self, path: &Path) -> io::Result<()> {
+        Ok(self.sftp.rmdir(path)?)
+    }
+
+    fn download(&self, path: &Path, dest: &Path) -> io::Result<()> {
+        let mut remote = self.sftp.open(path)?;
+        io::copy(&mut remote, &mut File::create(dest)?).map(|_| ())
+    }
+
+    fn upload(&self, source: &Path, path: &Path) -> io::Result<()> {
+        let mut remote = self.sftp.create(path)?;
+        io::copy(&mut File::open(source)?, &mut remote).map(|_| ())
+    }
+}
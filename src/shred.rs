@@ -0,0 +1,72 @@
+use std::{
+    fs::{self, OpenOptions},
+    io::{self, Seek, SeekFrom, Write},
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// Bytes written at a time.
+const CHUNK: usize = 64 * 1024;
+
+/// Overwrite the file `path` with random bytes `passes` times and then with zeros, flushing each
+/// pass to the disk, before emptying, renaming and deleting it.
+///
+/// This only helps where a write lands on the blocks the file had: SSDs, copy-on-write and
+/// journaling file systems may keep the old data elsewhere.
+pub fn shred_file(path: &Path, passes: usize) -> io::Result<()> {
+    let mut file = OpenOptions::new().write(true).open(path)?;
+    let len = file.metadata()?.len();
+    let mut noise = Noise::new();
+    let mut buffer = vec![0u8; CHUNK];
+    for pass in 0..=passes {
+        file.seek(SeekFrom::Start(0))?;
+        let mut left = len;
+        while left > 0 {
+            let n = left.min(CHUNK as u64) as usize;
+            if pass < passes {
+                noise.fill(&mut buffer[..n]);
+            } else {
+                buffer[..n].fill(0);
+            }
+            file.write_all(&buffer[..n])?;
+            left -= n as u64;
+        }
+        file.sync_data()?;
+    }
+    file.set_len(0)?;
+    file.sync_all()?;
+    drop(file);
+    // the name would stay in the directory's blocks too
+    let name_len = path.file_name().map_or(1, |n| n.len());
+    let hidden = path.with_file_name("0".repeat(name_len));
+    let path = if hidden.symlink_metadata().is_err() && fs::rename(path, &hidden).is_ok() {
+        hidden.as_path()
+    } else {
+        path
+    };
+    fs::remove_file(path)
+}
+
+/// xorshift64*: fast bytes that do not repeat, which is all overwriting needs; they are not
+/// meant to be unpredictable.
+struct Noise(u64);
+
+impl Noise {
+    fn new() -> Self {
+        let seed = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_nanos() as u64)
+            ^ (std::process::id() as u64) << 32;
+        Self(seed | 1)
+    }
+
+    fn fill(&mut self, buffer: &mut [u8]) {
+        for chunk in buffer.chunks_mut(8) {
+            self.0 ^= self.0 >> 12;
+            self.0 ^= self.0 << 25;
+            self.0 ^= self.0 >> 27;
+            let bytes = self.0.wrapping_mul(0x2545_f491_4f6c_dd1d).to_le_bytes();
+            chunk.copy_from_slice(&bytes[..chunk.len()]);
+        }
+    }
+}
@@ -0,0 +1,170 @@
+use std::{
+    io,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    sync::mpsc::{self, Receiver},
+    thread,
+};
+
+/// Extensions of detached signatures, tried in this order.
+const EXTENSIONS: [&str; 3] = ["sig", "asc", "sign"];
+
+/// The signed file and its detached signature, for `path` being either of them.
+pub fn signed_pair(path: &Path) -> Option<(PathBuf, PathBuf)> {
+    let is_signature = path
+        .extension()
+        .is_some_and(|ext| EXTENSIONS.iter().any(|e| ext.eq_ignore_ascii_case(e)));
+    if is_signature {
+        let data = path.with_extension("");
+        return data.is_file().then(|| (data, path.to_path_buf()));
+    }
+    EXTENSIONS.iter().find_map(|ext| {
+        let mut name = path.file_name()?.to_os_string();
+        name.push(format!(".{ext}"));
+        let signature = path.with_file_name(name);
+        signature.is_file().then(|| (path.to_path_buf(), signature))
+    })
+}
+
+/// What checking a signature found.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Verdict {
+    /// Made by `signer` with a key in the keyring; `trusted` is false when the key is
+    /// expired, revoked or not certified as the signer's
+    Good { signer: String, trusted: bool },
+    /// The file or the signature was changed after signing
+    Bad { signer: String },
+    /// The key with this id is not in the keyring
+    UnknownKey(String),
+    /// Nothing could be checked, e.g. without gpg
+    Error(String),
+}
+
+impl Verdict {
+    /// One line for the status bar and the properties popup.
+    pub fn describe(&self) -> String {
+        match self {
+            Verdict::Good {
+                signer,
+                trusted: true,
+            } => format!("good signature from {signer}"),
+            Verdict::Good { signer, .. } => {
+                format!("good signature from {signer}, but the key is not trusted")
+            }
+            Verdict::Bad { signer } => format!("BAD signature (claims to be from {signer})"),
+            Verdict::UnknownKey(key) => {
+                format!("cannot check: public key {key} is not in the keyring")
+            }
+            Verdict::Error(e) => format!("cannot check: {e}"),
+        }
+    }
+}
+
+/// Check the detached `signature` of `data` on another thread, with gpg (using the keyring in
+/// `gnupg_home`, or its default one) or else Sequoia's `sq`.
+pub fn spawn_verify(
+    data: PathBuf,
+    signature: PathBuf,
+    gnupg_home: Option<PathBuf>,
+) -> Receiver<(PathBuf, Verdict)> {
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        let verdict = match gpg(&data, &signature, gnupg_home.as_deref()) {
+            Err(e) if e.kind() == io::ErrorKind::NotFound => match sq(&data, &signature) {
+                Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                    Verdict::Error("neither gpg nor sq is installed".into())
+                }
+                result => result.unwrap_or_else(|e| Verdict::Error(e.to_string())),
+            },
+            result => result.unwrap_or_else(|e| Verdict::Error(e.to_string())),
+        };
+        let _ = tx.send((data, verdict));
+    });
+    rx
+}
+
+/// Read gpg's machine-readable status lines rather than its translated messages.
+fn gpg(data: &Path, signature: &Path, home: Option<&Path>) -> io::Result<Verdict> {
+    let mut command = Command::new("gpg");
+    if let Some(home) = home {
+        command.env("GNUPGHOME", home);
+    }
+    let output = command
+        .args(["--batch", "--no-tty", "--status-fd", "1", "--verify"])
+        .arg(signature)
+        .arg(data)
+        .stdin(Stdio::null())
+        .output()?;
+    let status = String::from_utf8_lossy(&output.stdout);
+    let mut verdict = None;
+    let mut trusted = true;
+    for line in status.lines() {
+        let Some(line) = line.strip_prefix("[GNUPG:] ") else {
+            continue;
+        };
+        let (keyword, rest) = line.split_once(' ').unwrap_or((line, ""));
+        // the key id comes before the user id
+        let signer = || {
+            rest.split_once(' ')
+                .map_or(rest, |(_, uid)| uid)
+                .to_string()
+        };
+        match keyword {
+            "GOODSIG" | "EXPSIG" | "EXPKEYSIG" | "REVKEYSIG" => {
+                trusted &= keyword == "GOODSIG";
+                verdict = Some(Verdict::Good {
+                    signer: signer(),
+                    trusted,
+                });
+            }
+            "BADSIG" => verdict = Some(Verdict::Bad { signer: signer() }),
+            "NO_PUBKEY" => {
+                verdict.get_or_insert(Verdict::UnknownKey(rest.trim().to_string()));
+            }
+            "TRUST_UNDEFINED" | "TRUST_NEVER" => trusted = false,
+            _ => {}
+        }
+    }
+    Ok(match verdict {
+        Some(Verdict::Good { signer, .. }) => Verdict::Good { signer, trusted },
+        Some(verdict) => verdict,
+        None => Verdict::Error(first_line(&output.stderr, "gpg found no signature")),
+    })
+}
+
+fn sq(data: &Path, signature: &Path) -> io::Result<Verdict> {
+    let output = Command::new("sq")
+        .args(["verify", "--signature-file"])
+        .arg(signature)
+        .arg(data)
+        .stdin(Stdio::null())
+        .output()?;
+    let message = String::from_utf8_lossy(&output.stderr);
+    // e.g. "Good signature from 1234ABCD "Name <mail>""
+    let signer = message
+        .lines()
+        .find_map(|l| l.split_once("signature from ").map(|(_, s)| s.trim()))
+        .unwrap_or("an unknown signer")
+        .to_string();
+    Ok(if output.status.success() {
+        Verdict::Good {
+            signer,
+            trusted: true,
+        }
+    } else if message.contains("Missing key") || message.contains("No key to verify") {
+        Verdict::UnknownKey(first_line(&output.stderr, "unknown"))
+    } else if message.to_lowercase().contains("bad signature") {
+        Verdict::Bad { signer }
+    } else {
+        Verdict::Error(first_line(&output.stderr, "sq failed"))
+    })
+}
+
+fn first_line(text: &[u8], fallback: &str) -> String {
+    String::from_utf8_lossy(text)
+        .lines()
+        .map(str::trim)
+        .find(|l| !l.is_empty())
+        .unwrap_or(fallback)
+        .to_string()
+}
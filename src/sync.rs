@@ -0,0 +1,107 @@
+use std::{
+    collections::HashMap,
+    ffi::OsString,
+    fs::Metadata,
+    path::PathBuf,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use crate::app::Pane;
+use crate::vfs::Entry;
+
+/// Modification times closer than this are treated as equal (FAT stores 2-second times).
+const MTIME_TOLERANCE: Duration = Duration::from_secs(2);
+
+/// How an entry compares with the entry of the same name in the other pane.
+#[derive(Clone, Copy, PartialEq)]
+pub enum CompareStatus {
+    /// No entry of this name on the other side
+    Only,
+    /// Both sides have it and this one was modified more recently
+    Newer,
+    /// Both sides have it and the other one was modified more recently
+    Older,
+    /// Same modification time but different size or type; left alone by sync
+    Differs,
+    /// Same size and modification time (or both are directories)
+    Same,
+}
+
+/// Files to copy in each direction to bring the two panes in sync.
+pub struct SyncPlan {
+    pub to_right: Vec<PathBuf>,
+    pub to_left: Vec<PathBuf>,
+}
+
+/// Compare the two panes by name, size and modification time,
+/// storing each pane's view of the result in its `comparison` map.
+pub fn compare_panes(left: &mut Pane, right: &mut Pane) {
+    let l = index(&left.items);
+    let r = index(&right.items);
+    left.comparison = statuses(&l, &r);
+    right.comparison = statuses(&r, &l);
+}
+
+/// Entries that should be copied across: those only on one side, and files newer on one side.
+pub fn plan(left: &Pane, right: &Pane) -> SyncPlan {
+    let pick = |pane: &Pane| {
+        pane.items
+            .iter()
+            .filter(|e| {
+                matches!(
+                    pane.comparison.get(&e.file_name()),
+                    Some(CompareStatus::Only | CompareStatus::Newer)
+                )
+            })
+            .map(|e| e.path())
+            .collect()
+    };
+    SyncPlan {
+        to_right: pick(left),
+        to_left: pick(right),
+    }
+}
+
+fn index(items: &[Entry]) -> HashMap<OsString, Option<Metadata>> {
+    items
+        .iter()
+        .map(|e| (e.file_name(), e.path().metadata().ok()))
+        .collect()
+}
+
+fn statuses(
+    this: &HashMap<OsString, Option<Metadata>>,
+    other: &HashMap<OsString, Option<Metadata>>,
+) -> HashMap<OsString, CompareStatus> {
+    this.iter()
+        .map(|(name, meta)| {
+            let status = match other.get(name) {
+                None => CompareStatus::Only,
+                Some(other_meta) => compare(meta.as_ref(), other_meta.as_ref()),
+            };
+            (name.clone(), status)
+        })
+        .collect()
+}
+
+fn compare(a: Option<&Metadata>, b: Option<&Metadata>) -> CompareStatus {
+    let (Some(a), Some(b)) = (a, b) else {
+        return CompareStatus::Same;
+    };
+    if a.is_dir() && b.is_dir() {
+        return CompareStatus::Same;
+    }
+    let mtime = |m: &Metadata| m.modified().unwrap_or(UNIX_EPOCH);
+    let (ta, tb) = (mtime(a), mtime(b));
+    let apart =
+        |x: SystemTime, y: SystemTime| x.duration_since(y).is_ok_and(|d| d > MTIME_TOLERANCE);
+    if apart(ta, tb) {
+        CompareStatus::Newer
+    } else if apart(tb, ta) {
+        CompareStatus::Older
+    } else if a.len() != b.len() || a.is_dir() != b.is_dir() {
+        CompareStatus::Differs
+    } else {
+        CompareStatus::Same
+    }
+}
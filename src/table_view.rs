@@ -0,0 +1,158 @@
+use std::{fs, path::Path};
+
+use unicode_width::UnicodeWidthStr;
+
+/// Columns are never drawn wider than this; longer cells are cut.
+pub const MAX_COLUMN_WIDTH: usize = 40;
+
+/// Delimiters tried when sniffing a file, in order of preference on a tie.
+const DELIMITERS: [char; 4] = [',', '\t', ';', '|'];
+
+/// A CSV/TSV file shown as an aligned table.
+pub struct TableView {
+    pub title: String,
+    pub delimiter: char,
+    pub header: Vec<String>,
+    pub rows: Vec<Vec<String>>,
+    /// Display width of each column, capped at `MAX_COLUMN_WIDTH`
+    pub widths: Vec<usize>,
+    /// First data row shown
+    pub row_offset: usize,
+    /// First column shown
+    pub column_offset: usize,
+}
+
+impl TableView {
+    pub fn open(path: &Path) -> Option<Self> {
+        let text = fs::read_to_string(path).ok()?;
+        let tsv = path
+            .extension()
+            .is_some_and(|e| e.eq_ignore_ascii_case("tsv"));
+        let delimiter = sniff_delimiter(&text, tsv);
+        let mut records = parse(&text, delimiter).into_iter();
+        let header = records.next().unwrap_or_default();
+        let rows: Vec<Vec<String>> = records.collect();
+        let columns = rows
+            .iter()
+            .map(Vec::len)
+            .chain([header.len()])
+            .max()
+            .unwrap_or(0);
+        let mut widths = vec![0; columns];
+        for record in rows.iter().chain([&header]) {
+            for (w, cell) in widths.iter_mut().zip(record) {
+                *w = (*w).max(cell.width()).min(MAX_COLUMN_WIDTH);
+            }
+        }
+        let title = path
+            .file_name()
+            .map(|os_str| os_str.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        Some(Self {
+            title,
+            delimiter,
+            header,
+            rows,
+            widths,
+            row_offset: 0,
+            column_offset: 0,
+        })
+    }
+
+    pub fn down(&mut self, count: usize) {
+        self.row_offset = (self.row_offset + count).min(self.rows.len().saturating_sub(1));
+    }
+
+    pub fn up(&mut self, count: usize) {
+        self.row_offset = self.row_offset.saturating_sub(count);
+    }
+
+    pub fn right(&mut self, count: usize) {
+        self.column_offset = (self.column_offset + count).min(self.widths.len().saturating_sub(1));
+    }
+
+    pub fn left(&mut self, count: usize) {
+        self.column_offset = self.column_offset.saturating_sub(count);
+    }
+
+    pub fn go_top(&mut self) {
+        self.row_offset = 0;
+    }
+
+    pub fn go_bottom(&mut self) {
+        self.row_offset = self.rows.len().saturating_sub(1);
+    }
+}
+
+/// `.csv` and `.tsv` files.
+pub fn is_table(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| e.eq_ignore_ascii_case("csv") || e.eq_ignore_ascii_case("tsv"))
+}
+
+/// Pick the delimiter that splits the first lines into the same number (> 1) of fields most
+/// consistently; tabs win ties for `.tsv` files.
+fn sniff_delimiter(text: &str, tsv: bool) -> char {
+    let sample: Vec<&str> = text.lines().filter(|l| !l.is_empty()).take(20).collect();
+    let score = |d: char| {
+        let counts: Vec<usize> = sample.iter().map(|l| l.matches(d).count()).collect();
+        let first = counts.first().copied().unwrap_or(0);
+        if first == 0 {
+            return 0;
+        }
+        // lines agreeing with the first line's field count, weighted by that count
+        counts.iter().filter(|&&c| c == first).count() * 1000 + first
+    };
+    let mut order = DELIMITERS;
+    if tsv {
+        order.swap(0, 1);
+    }
+    order
+        .into_iter()
+        .fold((order[0], 0), |best, d| {
+            let s = score(d);
+            if s > best.1 {
+                (d, s)
+            } else {
+                best
+            }
+        })
+        .0
+}
+
+/// Split `text` into records, honoring double-quoted fields (which may contain the delimiter,
+/// newlines and `""` escapes).
+fn parse(text: &str, delimiter: char) -> Vec<Vec<String>> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted => {
+                if chars.peek() == Some(&'"') {
+                    chars.next();
+                    field.push('"');
+                } else {
+                    quoted = false;
+                }
+            }
+            '"' if field.is_empty() => quoted = true,
+            c if quoted => field.push(c),
+            c if c == delimiter => record.push(std::mem::take(&mut field)),
+            '\r' if chars.peek() == Some(&'\n') => {}
+            '\n' => {
+                record.push(std::mem::take(&mut field));
+                records.push(std::mem::take(&mut record));
+            }
+            c => field.push(c),
+        }
+    }
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push(record);
+    }
+    records
+}
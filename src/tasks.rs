@@ -0,0 +1,837 @@
+use std::{
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver, Sender},
+        Arc,
+    },
+    thread,
+};
+
+use tracing::{debug, info, warn};
+
+use crate::archive::{extract, is_locked, Extraction};
+use crate::checksum::{self, Check, Listed, Outcome};
+use crate::config::Config;
+use crate::elevate::{as_denied, denied, Privileged};
+use crate::fs_utils::{
+    copy_dir_recursively, copy_dir_with, copy_file, long_path, make_link, same_entry,
+    unique_copy_path, CopyOptions,
+};
+use crate::image_ops::ImageOp;
+use crate::resume::Manifest;
+use crate::rsync::{self, Transfer};
+use crate::runner::{fill_template, shell_command};
+use crate::shred::shred_file;
+use crate::trash;
+
+/// A unit of work executed by the background worker.
+pub enum JobKind {
+    Copy {
+        sources: Vec<PathBuf>,
+        dest: PathBuf,
+    },
+    Move {
+        sources: Vec<PathBuf>,
+        dest: PathBuf,
+    },
+    /// Copy `source` to the path `target`, under a new name
+    CopyAs {
+        source: PathBuf,
+        target: PathBuf,
+    },
+    /// Create links in `dest` to `sources`: hard links, or symbolic links to their absolute
+    /// paths
+    Link {
+        sources: Vec<PathBuf>,
+        dest: PathBuf,
+        hard: bool,
+    },
+    Delete {
+        paths: Vec<PathBuf>,
+    },
+    /// Overwrite the files under `paths` `passes` times before deleting them
+    Shred {
+        paths: Vec<PathBuf>,
+        passes: usize,
+    },
+    /// Move `paths` to the trash, or the Recycle Bin on Windows
+    Trash {
+        paths: Vec<PathBuf>,
+    },
+    /// Pack `sources` into the gzipped tarball `dest`
+    Archive {
+        sources: Vec<PathBuf>,
+        dest: PathBuf,
+    },
+    /// Unpack an archive into a new directory
+    Extract(Extraction),
+    /// Hash the files named in the checksum list `list` and compare them with `entries`,
+    /// sending each outcome to `results`
+    VerifySums {
+        list: PathBuf,
+        entries: Vec<Listed>,
+        results: Sender<Check>,
+    },
+    /// Convert, resize or strip the image files `paths`, one by one
+    Images {
+        paths: Vec<PathBuf>,
+        op: ImageOp,
+    },
+    /// Run `template` filled in for each of `paths` (see `fill_template`) by the shell in
+    /// `dir`, writing each command line, its output and exit status to `report`; `name` is
+    /// that of the share target it runs, if any
+    ForEach {
+        name: Option<String>,
+        paths: Vec<PathBuf>,
+        template: String,
+        dir: PathBuf,
+        report: PathBuf,
+    },
+}
+
+impl JobKind {
+    /// One-line summary shown in the queue popup.
+    pub fn describe(&self) -> String {
+        match self {
+            JobKind::Copy { sources, dest } => {
+                format!("copy {} item(s) to {}", sources.len(), dest.display())
+            }
+            JobKind::Move { sources, dest } => {
+                format!("move {} item(s) to {}", sources.len(), dest.display())
+            }
+            JobKind::CopyAs { source, target } => {
+                format!("copy {} to {}", source.display(), target.display())
+            }
+            JobKind::Link {
+                sources,
+                dest,
+                hard,
+            } => format!(
+                "{} link {} item(s) in {}",
+                if *hard { "hard" } else { "symbolic" },
+                sources.len(),
+                dest.display()
+            ),
+            JobKind::Delete { paths } => format!("delete {} item(s)", paths.len()),
+            JobKind::Shred { paths, passes } => {
+                format!("shred {} item(s) ({passes} passes)", paths.len())
+            }
+            JobKind::Trash { paths } => format!("move {} item(s) to the trash", paths.len()),
+            JobKind::Archive { sources, dest } => {
+                format!("archive {} item(s) into {}", sources.len(), dest.display())
+            }
+            JobKind::Extract(Extraction { archive, dest, .. }) => format!(
+                "extract {} into {}",
+                archive.file_name().unwrap_or_default().to_string_lossy(),
+                dest.display()
+            ),
+            JobKind::VerifySums { list, entries, .. } => format!(
+                "verify {} file(s) from {}",
+                entries.len(),
+                list.file_name().unwrap_or_default().to_string_lossy()
+            ),
+            JobKind::Images { paths, op } => op.describe(paths.len()),
+            JobKind::ForEach {
+                name: Some(name),
+                paths,
+                ..
+            } => format!("share {} item(s) via {name}", paths.len()),
+            JobKind::ForEach {
+                paths, template, ..
+            } => format!("run `{template}` for {} item(s)", paths.len()),
+        }
+    }
+
+    /// Number of items the job's progress counts up to, when it reports one per item.
+    fn total(&self) -> Option<usize> {
+        match self {
+            JobKind::Images { paths, .. } | JobKind::ForEach { paths, .. } => Some(paths.len()),
+            JobKind::VerifySums { entries, .. } => Some(entries.len()),
+            _ => None,
+        }
+    }
+
+    /// File the job writes its results to, for jobs that write one.
+    fn report(&self) -> Option<PathBuf> {
+        match self {
+            JobKind::ForEach { report, .. } => Some(report.clone()),
+            _ => None,
+        }
+    }
+
+    /// Whether a notification tells how the job ended, for jobs the user waits on.
+    fn announced(&self) -> bool {
+        matches!(
+            self,
+            JobKind::ForEach { .. } | JobKind::Extract(_) | JobKind::VerifySums { .. }
+        )
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum JobStatus {
+    Pending,
+    /// Started; the number is how many entries it has processed, for jobs that count them
+    Running(usize),
+    /// Started, copying through rsync, which reports the share of the bytes done
+    Transferring(Transfer),
+    Done,
+    Failed(String),
+    /// Stopped by an archive's encryption, waiting for a password to retry with
+    Locked,
+    /// Refused for lack of permission; `retry` is what was left to do, to offer running as
+    /// root
+    Denied {
+        retry: Privileged,
+        reason: String,
+    },
+    Cancelled,
+}
+
+impl JobStatus {
+    pub fn is_finished(&self) -> bool {
+        !matches!(
+            self,
+            JobStatus::Pending | JobStatus::Running(_) | JobStatus::Transferring(_)
+        )
+    }
+}
+
+/// A queued job as seen by the UI.
+pub struct Job {
+    pub id: usize,
+    pub description: String,
+    pub status: JobStatus,
+    /// What the count of a running job goes up to, if known
+    pub total: Option<usize>,
+    /// File with the results of each item, for jobs that write one
+    pub report: Option<PathBuf>,
+    announced: bool,
+    /// What an extraction job unpacks, to retry it with a password
+    extraction: Option<Extraction>,
+    /// What a copy job copies where, to resume it after it failed or was cancelled
+    copy: Option<(Vec<PathBuf>, PathBuf)>,
+    cancel: Arc<AtomicBool>,
+}
+
+struct Request {
+    id: usize,
+    kind: JobKind,
+    /// Resuming a copy that failed or was cancelled
+    resume: bool,
+    cancel: Arc<AtomicBool>,
+}
+
+/// Sequential job queue backed by a single worker thread.
+pub struct TaskQueue {
+    pub jobs: Vec<Job>,
+    next_id: usize,
+    requests: Sender<Request>,
+    updates: Receiver<(usize, JobStatus)>,
+    /// Announced jobs that finished since last taken
+    reported: Vec<(String, JobStatus)>,
+    /// Extractions stopped by the archive's encryption, oldest first, to ask passwords for
+    locked: Vec<Extraction>,
+    /// What jobs refused for lack of permission had left to do, and why, oldest first
+    denied: Vec<(Privileged, String)>,
+}
+
+impl TaskQueue {
+    /// A queue copying as `config` says.
+    pub fn new(config: &Config) -> Self {
+        let options = config.copy_options();
+        let rsync_program = config.rsync_program.clone();
+        let data_dir = config.data_dir.clone();
+        let (requests, request_rx) = mpsc::channel::<Request>();
+        let (update_tx, updates) = mpsc::channel();
+        thread::spawn(move || {
+            for request in request_rx {
+                if request.cancel.load(Ordering::Relaxed) {
+                    info!(job = request.id, "cancelled before starting");
+                    let _ = update_tx.send((request.id, JobStatus::Cancelled));
+                    continue;
+                }
+                info!(job = request.id, "started");
+                let _ = update_tx.send((request.id, JobStatus::Running(0)));
+                let report = |status| {
+                    let _ = update_tx.send((request.id, status));
+                };
+                let context = JobContext {
+                    options,
+                    rsync_program: &rsync_program,
+                    data_dir: data_dir.as_deref(),
+                };
+                let status = match run_job(&request, &context, &report) {
+                    Ok(()) if request.cancel.load(Ordering::Relaxed) => JobStatus::Cancelled,
+                    Ok(()) => JobStatus::Done,
+                    Err(e) if is_locked(&e) => JobStatus::Locked,
+                    Err(e) => match as_denied(&e) {
+                        Some(denied) => JobStatus::Denied {
+                            retry: denied.retry.clone(),
+                            reason: denied.reason.clone(),
+                        },
+                        None => JobStatus::Failed(e.to_string()),
+                    },
+                };
+                match &status {
+                    JobStatus::Failed(e) => warn!(job = request.id, "failed: {e}"),
+                    _ => info!(job = request.id, status = ?status, "finished"),
+                }
+                let _ = update_tx.send((request.id, status));
+            }
+        });
+        Self {
+            jobs: Vec::new(),
+            next_id: 0,
+            requests,
+            updates,
+            reported: Vec::new(),
+            locked: Vec::new(),
+            denied: Vec::new(),
+        }
+    }
+
+    /// Add a job to the end of the queue and return its id.
+    pub fn enqueue(&mut self, kind: JobKind) -> usize {
+        self.push(kind, false)
+    }
+
+    fn push(&mut self, kind: JobKind, resume: bool) -> usize {
+        let id = self.next_id;
+        self.next_id += 1;
+        let cancel = Arc::new(AtomicBool::new(false));
+        let description = kind.describe();
+        info!(job = id, "queued: {description}");
+        self.jobs.push(Job {
+            id,
+            description,
+            status: JobStatus::Pending,
+            total: kind.total(),
+            report: kind.report(),
+            announced: kind.announced(),
+            extraction: match &kind {
+                JobKind::Extract(extraction) => Some(extraction.clone()),
+                _ => None,
+            },
+            copy: match &kind {
+                JobKind::Copy { sources, dest } => Some((sources.clone(), dest.clone())),
+                _ => None,
+            },
+            cancel: cancel.clone(),
+        });
+        let _ = self.requests.send(Request {
+            id,
+            kind,
+            resume,
+            cancel,
+        });
+        id
+    }
+
+    /// Request cancellation; a running job stops after the item it is working on.
+    pub fn cancel(&mut self, id: usize) {
+        if let Some(job) = self.jobs.iter().find(|j| j.id == id) {
+            if !job.status.is_finished() {
+                job.cancel.store(true, Ordering::Relaxed);
+            }
+        }
+    }
+
+    /// Queue a failed or cancelled copy again, returning the new job's id. Copies through
+    /// rsync pick up where they stopped; others start over.
+    pub fn resume(&mut self, id: usize) -> Option<usize> {
+        let job = self.jobs.iter().find(|j| j.id == id)?;
+        if !matches!(job.status, JobStatus::Failed(_) | JobStatus::Cancelled) {
+            return None;
+        }
+        let (sources, dest) = job.copy.clone()?;
+        Some(self.push(JobKind::Copy { sources, dest }, true))
+    }
+
+    /// Whether a copy of `sources` into `dest` is queued or running.
+    pub fn is_copying(&self, sources: &[PathBuf], dest: &Path) -> bool {
+        self.jobs.iter().any(|j| {
+            !j.status.is_finished()
+                && j.copy
+                    .as_ref()
+                    .is_some_and(|(s, d)| s.as_slice() == sources && d == dest)
+        })
+    }
+
+    /// Announced jobs that finished since the last call, with how they ended.
+    pub fn take_reported(&mut self) -> Vec<(String, JobStatus)> {
+        std::mem::take(&mut self.reported)
+    }
+
+    /// The oldest extraction waiting for a password, taken off the list.
+    pub fn next_locked(&mut self) -> Option<Extraction> {
+        (!self.locked.is_empty()).then(|| self.locked.remove(0))
+    }
+
+    /// The oldest operation refused for lack of permission, with the reason, taken off the
+    /// list.
+    pub fn next_denied(&mut self) -> Option<(Privileged, String)> {
+        (!self.denied.is_empty()).then(|| self.denied.remove(0))
+    }
+
+    /// Drop finished jobs from the list.
+    pub fn clear_finished(&mut self) {
+        self.jobs.retain(|j| !j.status.is_finished());
+    }
+
+    /// Apply status updates from the worker; returns true if any job finished.
+    pub fn poll(&mut self) -> bool {
+        let mut finished = false;
+        while let Ok((id, status)) = self.updates.try_recv() {
+            if let Some(job) = self.jobs.iter_mut().find(|j| j.id == id) {
+                finished |= status.is_finished();
+                if status == JobStatus::Locked {
+                    self.locked.extend(job.extraction.clone());
+                } else if let JobStatus::Denied { retry, reason } = &status {
+                    self.denied.push((retry.clone(), reason.clone()));
+                } else if status.is_finished() && job.announced {
+                    self.reported
+                        .push((job.description.clone(), status.clone()));
+                }
+                job.status = status;
+            }
+        }
+        finished
+    }
+}
+
+/// Entries removed between two progress reports of a deletion.
+const REPORT_EVERY: usize = 100;
+
+/// What jobs are run with, from the config.
+struct JobContext<'a> {
+    options: CopyOptions,
+    rsync_program: &'a Path,
+    /// Where copies record their progress
+    data_dir: Option<&'a Path>,
+}
+
+fn run_job(request: &Request, context: &JobContext, report: &dyn Fn(JobStatus)) -> io::Result<()> {
+    let options = context.options;
+    let (kind, cancel) = (&request.kind, request.cancel.as_ref());
+    let progress = |done| report(JobStatus::Running(done));
+    let progress: &dyn Fn(usize) = &progress;
+    match kind {
+        JobKind::Copy { sources, dest } => {
+            // a copy next to its source is renamed, which rsync does not do
+            let beside = sources.iter().any(|src| {
+                src.file_name()
+                    .is_some_and(|name| same_entry(src, &dest.join(name)))
+            });
+            if options.rsync.applies_to(dest) && !beside {
+                let transfer = |t| report(JobStatus::Transferring(t));
+                debug!(dest = %dest.display(), "copy through rsync");
+                match rsync::copy(
+                    context.rsync_program,
+                    sources,
+                    dest,
+                    options.preserve,
+                    request.resume,
+                    cancel,
+                    &transfer,
+                ) {
+                    // not installed, or too old: copy without it
+                    Err(e)
+                        if matches!(
+                            e.kind(),
+                            io::ErrorKind::NotFound | io::ErrorKind::Unsupported
+                        ) =>
+                    {
+                        warn!("rsync unusable, copying without it: {e}");
+                    }
+                    result => {
+                        return result.map_err(|e| {
+                            denied(e, dest, || Privileged::Copy {
+                                sources: sources.clone(),
+                                dest: dest.clone(),
+                            })
+                        })
+                    }
+                }
+            }
+            let mut manifest = Manifest::open(context.data_dir, sources, dest);
+            let mut copy_one = |src: &Path, dst: &Path| {
+                if cancel.load(Ordering::Relaxed) {
+                    return Err(io::Error::new(io::ErrorKind::Interrupted, "cancelled"));
+                }
+                // finished by an earlier, interrupted run of the same copy
+                if manifest.is_done(src, dst) {
+                    return Ok(());
+                }
+                copy_file(src, dst, options)?;
+                manifest.record(src, dst);
+                Ok(())
+            };
+            for (i, src) in sources.iter().enumerate() {
+                debug!(src = %src.display(), dest = %dest.display(), "copy");
+                match copy_item(&long_path(src), &long_path(dest), options, &mut copy_one) {
+                    Err(e)
+                        if e.kind() == io::ErrorKind::Interrupted
+                            && cancel.load(Ordering::Relaxed) =>
+                    {
+                        break
+                    }
+                    result => result.map_err(|e| {
+                        denied(e, src, || Privileged::Copy {
+                            sources: sources[i..].to_vec(),
+                            dest: dest.clone(),
+                        })
+                    })?,
+                }
+            }
+            if !cancel.load(Ordering::Relaxed) {
+                manifest.finish();
+            }
+        }
+        JobKind::Move { sources, dest } => {
+            for (i, src) in sources.iter().enumerate() {
+                if cancel.load(Ordering::Relaxed) {
+                    break;
+                }
+                debug!(src = %src.display(), dest = %dest.display(), "move");
+                move_item(&long_path(src), &long_path(dest), options).map_err(|e| {
+                    denied(e, src, || Privileged::Move {
+                        sources: sources[i..].to_vec(),
+                        dest: dest.clone(),
+                    })
+                })?;
+            }
+        }
+        JobKind::CopyAs { source, target } => {
+            debug!(src = %source.display(), dst = %target.display(), "copy as");
+            // a typed target may lie in directories that do not exist yet
+            let copy = || {
+                let (source, target) = (long_path(source), long_path(target));
+                if let Some(parent) = target.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                if source.is_dir() {
+                    copy_dir_recursively(&source, &target, options)
+                } else {
+                    copy_file(&source, &target, options)
+                }
+            };
+            copy().map_err(|e| {
+                denied(e, source, || Privileged::CopyAs {
+                    source: source.clone(),
+                    target: target.clone(),
+                })
+            })?;
+        }
+        JobKind::Link {
+            sources,
+            dest,
+            hard,
+        } => {
+            for src in sources {
+                if cancel.load(Ordering::Relaxed) {
+                    break;
+                }
+                let Some(name) = src.file_name() else {
+                    continue;
+                };
+                let mut link = dest.join(name);
+                if same_entry(src, &link) {
+                    link = unique_copy_path(src, dest, options.names);
+                }
+                debug!(src = %src.display(), link = %link.display(), hard, "link");
+                make_link(src, &link, *hard)
+                    .map_err(|e| io::Error::new(e.kind(), format!("{}: {e}", src.display())))?;
+            }
+        }
+        JobKind::Delete { paths } => {
+            let mut removed = 0;
+            for (i, path) in paths.iter().enumerate() {
+                if cancel.load(Ordering::Relaxed) {
+                    break;
+                }
+                debug!(path = %path.display(), "delete");
+                remove_tree(&long_path(path), cancel, &mut removed, progress).map_err(|e| {
+                    denied(e, path, || Privileged::Delete {
+                        paths: paths[i..].to_vec(),
+                    })
+                })?;
+            }
+            progress(removed);
+        }
+        JobKind::Shred { paths, passes } => {
+            let mut shredded = 0;
+            for path in paths {
+                if cancel.load(Ordering::Relaxed) {
+                    break;
+                }
+                debug!(path = %path.display(), "shred");
+                shred_tree(&long_path(path), *passes, cancel, &mut shredded, progress)?;
+            }
+        }
+        JobKind::Trash { paths } => {
+            for path in paths {
+                if cancel.load(Ordering::Relaxed) {
+                    break;
+                }
+                debug!(path = %path.display(), "trash");
+                trash::trash(path)?;
+            }
+        }
+        JobKind::Archive { sources, dest } => archive(sources, dest)?,
+        JobKind::Extract(Extraction {
+            archive,
+            format,
+            dest,
+            password,
+        }) => {
+            debug!(archive = %archive.display(), dest = %dest.display(), "extract");
+            fs::create_dir(dest)?;
+            if let Err(e) = extract(archive, *format, dest, password.as_deref()) {
+                // the directory is ours: leave nothing half extracted behind
+                let _ = fs::remove_dir_all(dest);
+                return Err(e);
+            }
+        }
+        JobKind::VerifySums {
+            list,
+            entries,
+            results,
+        } => verify_sums(list, entries, results, cancel, progress)?,
+        JobKind::Images { paths, op } => {
+            for (done, path) in paths.iter().enumerate() {
+                if cancel.load(Ordering::Relaxed) {
+                    break;
+                }
+                debug!(path = %path.display(), "{}", op.describe(1));
+                op.apply(path)
+                    .map_err(|e| io::Error::new(e.kind(), format!("{}: {e}", path.display())))?;
+                progress(done + 1);
+            }
+        }
+        JobKind::ForEach {
+            paths,
+            template,
+            dir,
+            report,
+            ..
+        } => for_each(paths, template, dir, report, cancel, progress)?,
+    }
+    Ok(())
+}
+
+/// Run the command for every path in turn, on through failures, and fail at the end with a
+/// count of the commands that did.
+fn for_each(
+    paths: &[PathBuf],
+    template: &str,
+    dir: &Path,
+    report: &Path,
+    cancel: &AtomicBool,
+    progress: &dyn Fn(usize),
+) -> io::Result<()> {
+    let mut log = fs::File::create(report)?;
+    let mut failed = Vec::new();
+    let mut ran = 0;
+    for path in paths {
+        if cancel.load(Ordering::Relaxed) {
+            break;
+        }
+        let line = fill_template(template, path);
+        debug!(line, "for each");
+        writeln!(log, "$ {line}")?;
+        let result = match shell_command(&line)
+            .current_dir(dir)
+            .stdin(Stdio::null())
+            .output()
+        {
+            Ok(output) => {
+                log.write_all(&output.stdout)?;
+                log.write_all(&output.stderr)?;
+                match output.status.code() {
+                    Some(0) => Ok(()),
+                    Some(code) => Err(format!("exit code {code}")),
+                    None => Err(output.status.to_string()),
+                }
+            }
+            Err(e) => Err(e.to_string()),
+        };
+        match result {
+            Ok(()) => writeln!(log, "[ok]\n")?,
+            Err(e) => {
+                writeln!(log, "[{e}]\n")?;
+                let name = path.file_name().unwrap_or(path.as_os_str());
+                failed.push(name.to_string_lossy().into_owned());
+            }
+        }
+        ran += 1;
+        progress(ran);
+    }
+    let summary = format!("{} of {ran} command(s) failed", failed.len());
+    writeln!(log, "{summary}")?;
+    if failed.is_empty() {
+        Ok(())
+    } else {
+        Err(io::Error::other(format!(
+            "{summary}: {}",
+            failed.join(", ")
+        )))
+    }
+}
+
+/// Check every file of a checksum list, on through failures, and fail at the end with a count
+/// of those that did not match.
+fn verify_sums(
+    list: &Path,
+    entries: &[Listed],
+    results: &Sender<Check>,
+    cancel: &AtomicBool,
+    progress: &dyn Fn(usize),
+) -> io::Result<()> {
+    let dir = list.parent().unwrap_or(Path::new("."));
+    let mut failed = 0;
+    for (index, entry) in entries.iter().enumerate() {
+        if cancel.load(Ordering::Relaxed) {
+            break;
+        }
+        debug!(name = entry.name, "verify checksum");
+        let outcome = checksum::check(dir, entry);
+        if outcome != Outcome::Match {
+            failed += 1;
+        }
+        // the report popup may have been closed
+        let _ = results.send(Check { index, outcome });
+        progress(index + 1);
+    }
+    if failed == 0 {
+        Ok(())
+    } else {
+        Err(io::Error::other(format!(
+            "{failed} of {} file(s) did not match or are missing",
+            entries.len()
+        )))
+    }
+}
+
+/// Remove `path` and, for a directory, everything in it, counting removed entries in
+/// `removed`. Symlinks are removed, not followed. Stops early when cancelled.
+fn remove_tree(
+    path: &Path,
+    cancel: &AtomicBool,
+    removed: &mut usize,
+    progress: &dyn Fn(usize),
+) -> io::Result<()> {
+    if fs::symlink_metadata(path)?.is_dir() {
+        for entry in fs::read_dir(path)? {
+            if cancel.load(Ordering::Relaxed) {
+                return Ok(());
+            }
+            remove_tree(&entry?.path(), cancel, removed, progress)?;
+        }
+        fs::remove_dir(path)?;
+    } else {
+        fs::remove_file(path)?;
+    }
+    *removed += 1;
+    if (*removed).is_multiple_of(REPORT_EVERY) {
+        progress(*removed);
+    }
+    Ok(())
+}
+
+/// Like `remove_tree`, but overwriting each file first and reporting every one, as that is
+/// slow. Symbolic links are removed without touching what they point to.
+fn shred_tree(
+    path: &Path,
+    passes: usize,
+    cancel: &AtomicBool,
+    shredded: &mut usize,
+    progress: &dyn Fn(usize),
+) -> io::Result<()> {
+    let kind = fs::symlink_metadata(path)?.file_type();
+    if kind.is_dir() {
+        for entry in fs::read_dir(path)? {
+            if cancel.load(Ordering::Relaxed) {
+                return Ok(());
+            }
+            shred_tree(&entry?.path(), passes, cancel, shredded, progress)?;
+        }
+        return fs::remove_dir(path);
+    }
+    if kind.is_file() {
+        shred_file(path, passes)
+            .map_err(|e| io::Error::new(e.kind(), format!("{}: {e}", path.display())))?;
+    } else {
+        fs::remove_file(path)?;
+    }
+    *shredded += 1;
+    progress(*shredded);
+    Ok(())
+}
+
+/// Copy `src` into `dest_dir`, handing each file to `copy_one`.
+fn copy_item(
+    src: &Path,
+    dest_dir: &Path,
+    options: CopyOptions,
+    copy_one: &mut dyn FnMut(&Path, &Path) -> io::Result<()>,
+) -> io::Result<()> {
+    let Some(file_name) = src.file_name() else {
+        return Ok(());
+    };
+    let mut dst = dest_dir.join(file_name);
+    // pasted next to itself: a copy under a new name rather than over the source
+    if same_entry(src, &dst) {
+        dst = unique_copy_path(src, dest_dir, options.names);
+    }
+    if src.is_dir() {
+        copy_dir_with(src, &dst, options, copy_one)
+    } else {
+        copy_one(src, &dst)
+    }
+}
+
+fn move_item(src: &Path, dest_dir: &Path, options: CopyOptions) -> io::Result<()> {
+    let Some(file_name) = src.file_name() else {
+        return Ok(());
+    };
+    let dst = dest_dir.join(file_name);
+    match fs::rename(src, &dst) {
+        Ok(()) => return Ok(()),
+        // rename fails across filesystems: fall back to copy and delete
+        Err(e) if e.kind() == io::ErrorKind::CrossesDevices => {}
+        // anything else, such as a non-empty directory in the way, would only get worse by
+        // copying over it
+        Err(e) => return Err(e),
+    }
+    copy_item(src, dest_dir, options, &mut |src, dst| {
+        copy_file(src, dst, options)
+    })?;
+    if src.is_dir() {
+        fs::remove_dir_all(src)
+    } else {
+        fs::remove_file(src)
+    }
+}
+
+fn archive(sources: &[PathBuf], dest: &Path) -> io::Result<()> {
+    let mut cmd = Command::new("tar");
+    cmd.arg("-czf").arg(dest);
+    for src in sources {
+        if let (Some(parent), Some(name)) = (src.parent(), src.file_name()) {
+            cmd.arg("-C").arg(parent).arg(name);
+        }
+    }
+    let output = cmd.output()?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(io::Error::other(
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ))
+    }
+}
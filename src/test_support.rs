@@ -0,0 +1,228 @@
+//! Drives the whole TUI in tests: `run_app` draws on ratatui's `TestBackend` and reads
+//! scripted key presses, against a fixture in a temporary directory.
+
+use std::{
+    collections::VecDeque,
+    fs,
+    io::{self, Write},
+    path::PathBuf,
+    thread,
+    time::{Duration, Instant},
+};
+
+use crossterm::event::{
+    Event, KeyCode, KeyEvent, KeyModifiers, MouseButton, MouseEvent, MouseEventKind,
+};
+use kura_core::{
+    app::App, config::Config, dir_views::DirViews, frecency::History, scripting::Scripts,
+};
+use ratatui::{
+    backend::{Backend, TestBackend},
+    buffer::Cell,
+    layout::Rect,
+    Terminal,
+};
+use tempfile::TempDir;
+
+use crate::input::{run_app, InputSource};
+
+/// Key presses and clicks fed to `run_app` one by one; running out of them is an error, so a
+/// test that never gets back to quitting fails instead of hanging.
+pub struct ScriptedInput {
+    events: VecDeque<Event>,
+}
+
+impl ScriptedInput {
+    pub fn new(events: Vec<Event>) -> Self {
+        Self {
+            events: events.into(),
+        }
+    }
+}
+
+impl InputSource for ScriptedInput {
+    fn next_event(&mut self, _timeout: Duration) -> io::Result<Option<Event>> {
+        match self.events.pop_front() {
+            Some(event) => Ok(Some(event)),
+            None => Err(io::Error::other("ran out of scripted input")),
+        }
+    }
+}
+
+/// `TestBackend` with the `Write` that `run_app` needs to suspend the screen; what is
+/// written is dropped.
+pub struct TestScreen(pub TestBackend);
+
+impl Backend for TestScreen {
+    fn draw<'a, I>(&mut self, content: I) -> io::Result<()>
+    where
+        I: Iterator<Item = (u16, u16, &'a Cell)>,
+    {
+        self.0.draw(content)
+    }
+
+    fn hide_cursor(&mut self) -> io::Result<()> {
+        self.0.hide_cursor()
+    }
+
+    fn show_cursor(&mut self) -> io::Result<()> {
+        self.0.show_cursor()
+    }
+
+    fn get_cursor(&mut self) -> io::Result<(u16, u16)> {
+        self.0.get_cursor()
+    }
+
+    fn set_cursor(&mut self, x: u16, y: u16) -> io::Result<()> {
+        self.0.set_cursor(x, y)
+    }
+
+    fn clear(&mut self) -> io::Result<()> {
+        self.0.clear()
+    }
+
+    fn size(&self) -> io::Result<Rect> {
+        self.0.size()
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Backend::flush(&mut self.0)
+    }
+}
+
+impl Write for TestScreen {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// An app with both panes in a fresh temporary directory, on a 100x30 test screen, with the
+/// default config, no plugins, and a history and view settings that are not saved.
+pub struct Harness {
+    pub dir: TempDir,
+    /// Stands in for the data directory, so that tests leave the real one alone; kept until
+    /// the harness is dropped
+    _data: TempDir,
+    pub app: App,
+    terminal: Terminal<TestScreen>,
+}
+
+impl Harness {
+    /// Create `files` in the fixture directory first; names ending in `/` are directories,
+    /// and files hold their own name.
+    pub fn new(files: &[&str]) -> Self {
+        let dir = tempfile::tempdir().expect("temporary directory");
+        for name in files {
+            let path = dir.path().join(name);
+            if name.ends_with('/') {
+                fs::create_dir_all(&path).expect("fixture directory");
+            } else {
+                if let Some(parent) = path.parent() {
+                    fs::create_dir_all(parent).expect("fixture directory");
+                }
+                fs::write(&path, name).expect("fixture file");
+            }
+        }
+        let data = tempfile::tempdir().expect("data directory");
+        let config = Config {
+            data_dir: Some(data.path().to_path_buf()),
+            ..Config::default()
+        };
+        let app = App::open(
+            dir.path().to_path_buf(),
+            config,
+            History::default(),
+            DirViews::default(),
+            Scripts::load_from(None),
+        )
+        .expect("app");
+        let terminal = Terminal::new(TestScreen(TestBackend::new(100, 30))).expect("terminal");
+        Self {
+            dir,
+            _data: data,
+            app,
+            terminal,
+        }
+    }
+
+    /// Path of `name` in the fixture directory.
+    pub fn path(&self, name: &str) -> PathBuf {
+        self.dir.path().join(name)
+    }
+
+    /// Type `keys`, then `q` to leave `run_app`. `\n` stands for Enter, `\t` for Tab, `\x08`
+    /// for Backspace and `\x1b` for Esc.
+    pub fn press(&mut self, keys: &str) {
+        let mut events: Vec<KeyEvent> = keys
+            .chars()
+            .map(|c| match c {
+                '\n' => KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE),
+                '\t' => KeyEvent::new(KeyCode::Tab, KeyModifiers::NONE),
+                '\x08' => KeyEvent::new(KeyCode::Backspace, KeyModifiers::NONE),
+                '\x1b' => KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE),
+                c => KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE),
+            })
+            .collect();
+        events.push(KeyEvent::new(KeyCode::Char('q'), KeyModifiers::NONE));
+        self.run(events);
+    }
+
+    /// Feed `keys` to `run_app`; they must end with a key that quits.
+    pub fn run(&mut self, keys: Vec<KeyEvent>) {
+        self.run_events(keys.into_iter().map(Event::Key).collect());
+    }
+
+    /// Feed `events` to `run_app`; they must end with a key that quits.
+    pub fn run_events(&mut self, events: Vec<Event>) {
+        run_app(
+            &mut self.terminal,
+            &mut self.app,
+            &mut ScriptedInput::new(events),
+        )
+        .expect("run_app");
+    }
+
+    /// Click the left mouse button at `column`, `row` of the screen.
+    pub fn click(&mut self, column: u16, row: u16) {
+        let click = MouseEvent {
+            kind: MouseEventKind::Down(MouseButton::Left),
+            column,
+            row,
+            modifiers: KeyModifiers::NONE,
+        };
+        let quit = KeyEvent::new(KeyCode::Char('q'), KeyModifiers::NONE);
+        self.run_events(vec![Event::Mouse(click), Event::Key(quit)]);
+    }
+
+    /// Wait until every queued job has finished, and reload the panes as the main loop would.
+    pub fn wait_for_jobs(&mut self) {
+        let start = Instant::now();
+        loop {
+            self.app.tasks.poll();
+            if self.app.tasks.jobs.iter().all(|j| j.status.is_finished()) {
+                break;
+            }
+            assert!(
+                start.elapsed() < Duration::from_secs(10),
+                "jobs did not finish"
+            );
+            thread::sleep(Duration::from_millis(10));
+        }
+        self.app.refresh_panes();
+    }
+
+    /// The last frame drawn, one line of text per row.
+    pub fn screen(&self) -> String {
+        let buffer = self.terminal.backend().0.buffer();
+        let width = buffer.area.width as usize;
+        buffer
+            .content
+            .chunks(width)
+            .map(|row| row.iter().map(|c| c.symbol.as_str()).collect::<String>() + "\n")
+            .collect()
+    }
+}
@@ -0,0 +1,1813 @@
+//! End-to-end tests of the TUI through `test_support::Harness`.
+
+use std::{
+    fs,
+    io::{self, BufRead, BufReader, Read, Write},
+    net::{TcpListener, TcpStream},
+    path::{Path, PathBuf},
+    rc::Rc,
+    thread,
+    time::{Duration, Instant, SystemTime},
+};
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use kura_core::{
+    actions::{extract_selection, run_for_each},
+    checksum::Outcome,
+    config::{ShareTarget, UserCommand},
+    diff::{DiffView, LineKind},
+    elevate::Privileged,
+    file_index::FileIndex,
+    fs_utils::SizeDisplay,
+    heat::Heat,
+    iso::IsoFs,
+    keys::KeyCombo,
+    mode::Mode,
+    resume::{self, Manifest},
+    rsync::UseRsync,
+    s3::{Credentials, S3Fs},
+    signature::Verdict,
+    tasks::{JobKind, JobStatus, TaskQueue},
+    vfs::{Entry, RemoteEntry, VfsProvider},
+    xattr,
+};
+
+use crate::test_support::Harness;
+
+#[test]
+fn lists_the_directory() {
+    let mut h = Harness::new(&["a.txt", "b.txt", "sub/"]);
+    h.press("");
+    let screen = h.screen();
+    for name in ["a.txt", "b.txt", "sub"] {
+        assert!(screen.contains(name), "{name} missing from\n{screen}");
+    }
+    assert!(screen.contains("item 1/3"), "{screen}");
+}
+
+#[test]
+fn moves_the_cursor() {
+    let mut h = Harness::new(&["a.txt", "b.txt", "c.txt"]);
+    h.press("jj");
+    assert_eq!(h.app.current_pane().selected, 2);
+    assert!(h.screen().contains("item 3/3"));
+}
+
+#[test]
+fn copies_and_pastes_into_a_subdirectory() {
+    let mut h = Harness::new(&["a.txt", "sub/"]);
+    // copy a.txt, enter sub, paste
+    h.press("yyj\np");
+    h.wait_for_jobs();
+    assert_eq!(fs::read_to_string(h.path("sub/a.txt")).unwrap(), "a.txt");
+    assert!(h.path("a.txt").exists());
+}
+
+#[test]
+fn deletes_after_confirming() {
+    let mut h = Harness::new(&["a.txt", "b.txt"]);
+    h.press("xy");
+    h.wait_for_jobs();
+    assert!(!h.path("a.txt").exists());
+    assert!(h.path("b.txt").exists());
+}
+
+#[test]
+fn cancelling_a_delete_keeps_the_file() {
+    let mut h = Harness::new(&["a.txt"]);
+    h.press("x\x1b");
+    h.wait_for_jobs();
+    assert!(h.path("a.txt").exists());
+}
+
+#[test]
+fn renames_inline() {
+    let mut h = Harness::new(&["a.txt"]);
+    h.press("r\x08\x08\x08\x08\x08new.txt\n");
+    assert!(!h.path("a.txt").exists());
+    assert_eq!(fs::read_to_string(h.path("new.txt")).unwrap(), "a.txt");
+    assert!(h.screen().contains("new.txt"));
+}
+
+#[test]
+fn dry_run_leaves_files_alone() {
+    let mut h = Harness::new(&["a.txt"]);
+    h.press("#X");
+    h.wait_for_jobs();
+    assert!(h.path("a.txt").exists());
+    assert!(h.screen().contains("Dry run"), "{}", h.screen());
+}
+
+#[test]
+fn jumps_to_the_ends_with_g_sequences() {
+    let mut h = Harness::new(&["a.txt", "b.txt", "c.txt"]);
+    h.press("G");
+    assert_eq!(h.app.current_pane().selected, 2);
+    h.press("gg");
+    assert_eq!(h.app.current_pane().selected, 0);
+}
+
+#[test]
+fn a_broken_sequence_is_dropped() {
+    let mut h = Harness::new(&["a.txt", "b.txt"]);
+    h.press("gj");
+    assert_eq!(h.app.current_pane().selected, 0);
+    h.press("j");
+    assert_eq!(h.app.current_pane().selected, 1);
+}
+
+#[test]
+fn runs_a_command_bound_to_a_ctrl_chord() {
+    let mut h = Harness::new(&["a.txt"]);
+    h.app.config.commands.push(UserCommand {
+        name: "greet".into(),
+        keys: KeyCombo::parse_sequence("<C-t>x").unwrap(),
+        run: "echo hello from the chord".into(),
+        refresh: false,
+        output: true,
+    });
+    let t = KeyEvent::new(KeyCode::Char('t'), KeyModifiers::CONTROL);
+    let x = KeyEvent::new(KeyCode::Char('x'), KeyModifiers::NONE);
+    let q = KeyEvent::new(KeyCode::Char('q'), KeyModifiers::NONE);
+    h.run(vec![t, x, q]);
+    assert!(matches!(h.app.mode, Mode::Viewer(_)));
+    assert!(
+        h.screen().contains("hello from the chord"),
+        "{}",
+        h.screen()
+    );
+    // close the output, then `x` alone still asks before deleting
+    h.press("\nx");
+    assert!(matches!(h.app.mode, Mode::ConfirmDelete { .. }));
+}
+
+#[test]
+fn pastes_from_a_named_register() {
+    let mut h = Harness::new(&["a.txt", "b.txt", "sub/"]);
+    h.press("\"ayyjyyj\n\"ap");
+    h.wait_for_jobs();
+    assert!(h.path("sub/a.txt").exists());
+    assert!(!h.path("sub/b.txt").exists());
+    h.press("p");
+    h.wait_for_jobs();
+    assert!(h.path("sub/b.txt").exists());
+    let names: Vec<char> = h.app.registers.list().iter().map(|(c, _)| *c).collect();
+    assert_eq!(names, ['0', '1', 'a']);
+}
+
+#[test]
+fn pastes_a_backup_under_a_new_name() {
+    let mut h = Harness::new(&["config.toml"]);
+    h.press("yygp.bak\n");
+    h.wait_for_jobs();
+    assert_eq!(
+        fs::read_to_string(h.path("config.toml.bak")).unwrap(),
+        "config.toml"
+    );
+}
+
+#[test]
+fn paste_as_asks_before_overwriting() {
+    let mut h = Harness::new(&["a.txt", "b.txt"]);
+    // the first Enter only asks; Esc leaves the prompt
+    h.press("yygp\x08\x08\x08\x08\x08b.txt\n\x1b");
+    h.wait_for_jobs();
+    assert_eq!(fs::read_to_string(h.path("b.txt")).unwrap(), "b.txt");
+    h.press("gp\x08\x08\x08\x08\x08b.txt\n\n");
+    h.wait_for_jobs();
+    assert_eq!(fs::read_to_string(h.path("b.txt")).unwrap(), "a.txt");
+}
+
+#[cfg(unix)]
+#[test]
+fn pastes_symbolic_and_hard_links() {
+    let mut h = Harness::new(&["a.txt", "sub/"]);
+    h.press("yyj\ngl");
+    h.wait_for_jobs();
+    let link = h.path("sub/a.txt");
+    assert!(link.symlink_metadata().unwrap().is_symlink());
+    assert_eq!(
+        fs::read_link(&link).unwrap(),
+        h.path("a.txt").canonicalize().unwrap()
+    );
+    fs::remove_file(&link).unwrap();
+    h.press("gL");
+    h.wait_for_jobs();
+    assert!(!link.symlink_metadata().unwrap().is_symlink());
+    assert_eq!(fs::read_to_string(&link).unwrap(), "a.txt");
+}
+
+#[test]
+fn pasting_next_to_the_source_makes_numbered_copies() {
+    let mut h = Harness::new(&["notes.txt", "dir/inner.txt"]);
+    h.press("jyypp");
+    h.wait_for_jobs();
+    assert_eq!(
+        fs::read_to_string(h.path("notes (copy).txt")).unwrap(),
+        "notes.txt"
+    );
+    assert!(h.path("notes (copy 2).txt").exists());
+    assert_eq!(
+        fs::read_to_string(h.path("notes.txt")).unwrap(),
+        "notes.txt"
+    );
+    h.press("ggyyp");
+    h.wait_for_jobs();
+    assert!(h.path("dir (copy)/inner.txt").exists());
+}
+
+#[test]
+fn directory_counts_follow_changes() {
+    let mut h = Harness::new(&["dir/a.txt", "dir/b.txt"]);
+    let wait_for_counts = |h: &mut Harness| {
+        let start = Instant::now();
+        while h
+            .app
+            .left
+            .dir_stats(&h.path("dir"))
+            .unwrap()
+            .entries
+            .is_none()
+        {
+            h.app.left.poll_counts();
+            assert!(start.elapsed() < Duration::from_secs(10), "no count");
+            thread::sleep(Duration::from_millis(10));
+        }
+    };
+    h.press("gc");
+    wait_for_counts(&mut h);
+    h.press("");
+    assert!(h.screen().contains("dir  2 items"));
+    fs::write(h.path("dir/c.txt"), "c").unwrap();
+    h.app.refresh_panes();
+    wait_for_counts(&mut h);
+    h.press("");
+    assert!(h.screen().contains("dir  3 items"));
+}
+
+#[test]
+fn sorts_by_two_keys_and_inverts_with_the_sort_key() {
+    let mut h = Harness::new(&["c.txt", "bb.txt", "a.txt"]);
+    let names = |h: &Harness| -> Vec<String> {
+        h.app
+            .left
+            .items
+            .iter()
+            .map(|e| e.file_name().to_string_lossy().into_owned())
+            .collect()
+    };
+    // size first, then name for the two files of the same size
+    h.press("sk\nsj\t\x1b");
+    assert_eq!(names(&h), ["bb.txt", "a.txt", "c.txt"]);
+    assert!(h.screen().contains("[size↓,name↑"), "{}", h.screen());
+    h.press("ss");
+    assert_eq!(names(&h), ["a.txt", "c.txt", "bb.txt"]);
+    assert!(h.screen().contains("[size↑,name↑"), "{}", h.screen());
+}
+
+#[test]
+fn rename_notices_the_entry_changing_under_the_prompt() {
+    let mut h = Harness::new(&["a.txt", "b.txt"]);
+    h.app.mode = Mode::rename(&h.app.left.items[0]);
+    fs::remove_file(h.path("a.txt")).unwrap();
+    // the first Enter only warns
+    h.press("\x08\x08\x08\x08\x08c.txt\n\x1b");
+    assert!(!h.path("c.txt").exists());
+    assert_eq!(fs::read_to_string(h.path("b.txt")).unwrap(), "b.txt");
+
+    fs::write(h.path("a.txt"), "a.txt").unwrap();
+    h.app.refresh_panes();
+    h.app.mode = Mode::rename(&h.app.left.items[0]);
+    fs::write(h.path("a.txt"), "replaced").unwrap();
+    h.press("\x08\x08\x08\x08\x08c.txt\n\n");
+    assert_eq!(fs::read_to_string(h.path("c.txt")).unwrap(), "replaced");
+}
+
+#[test]
+fn operators_act_on_the_entries_their_motion_covers() {
+    let mut h = Harness::new(&["a.txt", "b.txt", "c.txt", "d.txt", "e.txt"]);
+    h.press("jy2j");
+    assert_eq!(
+        h.app.registers.get(None),
+        [h.path("b.txt"), h.path("c.txt"), h.path("d.txt")]
+    );
+    assert_eq!(h.app.left.selected, 1);
+    h.press("Gykk");
+    assert_eq!(
+        h.app.registers.get(None),
+        [h.path("d.txt"), h.path("e.txt")]
+    );
+    // the same key again takes `count` entries from the cursor
+    h.press("gg2yy");
+    assert_eq!(
+        h.app.registers.get(None),
+        [h.path("a.txt"), h.path("b.txt")]
+    );
+    // a key that is not a motion cancels the operator
+    h.press("ydyG");
+    assert_eq!(h.app.registers.get(None).len(), 5);
+}
+
+#[test]
+fn walks_the_jump_list_back_and_forth() {
+    let mut h = Harness::new(&["a.txt", "sub/x.txt", "sub/y.txt"]);
+    h.press("j\nj");
+    h.press("h");
+    let ctrl_o = KeyEvent::new(KeyCode::Char('o'), KeyModifiers::CONTROL);
+    let quit = KeyEvent::new(KeyCode::Char('q'), KeyModifiers::NONE);
+    h.run(vec![ctrl_o, quit]);
+    assert_eq!(h.app.left.current_dir, h.path("sub"));
+    assert_eq!(
+        h.app.left.items[h.app.left.selected].path(),
+        h.path("sub/y.txt")
+    );
+    h.run(vec![ctrl_o, quit]);
+    assert_eq!(h.app.left.current_dir, h.dir.path());
+    assert_eq!(h.app.left.items[h.app.left.selected].path(), h.path("sub"));
+    // Tab, which terminals send for Ctrl-i
+    h.press("\t");
+    assert_eq!(h.app.left.current_dir, h.path("sub"));
+    assert_eq!(
+        h.app.left.items[h.app.left.selected].path(),
+        h.path("sub/y.txt")
+    );
+}
+
+#[test]
+fn goes_up_the_path_by_its_breadcrumbs() {
+    let mut h = Harness::new(&["sub/deeper/"]);
+    h.press("\n\ngb\n");
+    assert_eq!(h.app.left.current_dir, h.path("sub"));
+
+    // a click on the fixture directory's name in the left pane's title
+    h.press("\n");
+    let name = h.dir.path().file_name().unwrap().to_string_lossy();
+    let title = h.screen().lines().nth(1).unwrap().to_string();
+    let column = title
+        .find(&*name)
+        .map(|i| title[..i].chars().count())
+        .unwrap();
+    h.click(column as u16, 1);
+    assert_eq!(h.app.left.current_dir, h.dir.path());
+}
+
+#[test]
+fn creates_the_missing_directories_of_a_typed_path() {
+    let mut h = Harness::new(&["a.txt"]);
+    // the first Enter only says what would be created
+    h.press("yygp\x08\x08\x08\x08\x08backup/2024/a.txt\n\x1b");
+    h.wait_for_jobs();
+    assert!(!h.path("backup").exists());
+    h.press("gp\x08\x08\x08\x08\x08backup/2024/a.txt\n\n");
+    h.wait_for_jobs();
+    assert_eq!(
+        fs::read_to_string(h.path("backup/2024/a.txt")).unwrap(),
+        "a.txt"
+    );
+
+    h.press("gnnotes/todo.md\n\ngnlogs/\n");
+    assert!(h.path("notes/todo.md").is_file());
+    assert!(h.path("logs").is_dir());
+    // an existing entry is never replaced
+    h.press("gna.txt\n\x1b");
+    assert_eq!(fs::read_to_string(h.path("a.txt")).unwrap(), "a.txt");
+}
+
+#[test]
+fn deleting_in_a_protected_directory_needs_its_name() {
+    let mut h = Harness::new(&["a.txt", "b.txt"]);
+    h.app.config.protected_paths = vec![h.dir.path().to_path_buf()];
+    let name = h
+        .dir
+        .path()
+        .file_name()
+        .unwrap()
+        .to_string_lossy()
+        .to_string();
+    // `X` asks too, and neither `y` nor a wrong name goes ahead
+    h.press("Xy\nwrong\n\x1b");
+    h.wait_for_jobs();
+    assert!(h.path("a.txt").exists());
+    h.press(&format!("X{name}\n"));
+    h.wait_for_jobs();
+    assert!(!h.path("a.txt").exists());
+
+    // outside protected directories, `X` asks only above `confirm_over`
+    h.app.config.protected_paths.clear();
+    h.app.config.confirm_over = Some(0);
+    h.press("Xn");
+    h.wait_for_jobs();
+    assert!(h.path("b.txt").exists());
+}
+
+#[test]
+fn remembers_view_settings_per_directory() {
+    let mut h = Harness::new(&["downloads/.cache", "projects/"]);
+    // hide dotfiles in downloads only
+    h.press("\n.gv");
+    assert!(h
+        .app
+        .left
+        .views
+        .borrow()
+        .get(&h.path("downloads"))
+        .is_some());
+    h.press("hj\n");
+    assert_eq!(h.app.left.current_dir, h.path("projects"));
+    assert!(h.app.left.show_hidden);
+    h.press("hk\nL");
+    assert_eq!(h.app.left.current_dir, h.path("downloads"));
+    assert!(!h.app.left.show_hidden);
+    // changes made there are remembered too, and `gv` forgets them all
+    h.press("hk\n");
+    assert!(h.app.left.size_display == SizeDisplay::Human);
+    h.press("gvhk\n");
+    assert!(h
+        .app
+        .left
+        .views
+        .borrow()
+        .get(&h.path("downloads"))
+        .is_none());
+}
+
+#[test]
+fn tints_names_by_size_then_age() {
+    let mut h = Harness::new(&["a.txt", "sub/"]);
+    fs::write(h.path("big.bin"), vec![0; 100_000]).unwrap();
+    h.app.left.refresh().unwrap();
+    h.press("gh");
+    assert!(h.screen().contains("heat:size"), "{}", h.screen());
+    let now = SystemTime::now();
+    let levels: Vec<Option<f64>> = h
+        .app
+        .left
+        .items
+        .iter()
+        .map(|e| Heat::Size.level(e, 100_000, now))
+        .collect();
+    // a.txt, big.bin, sub/
+    assert!(levels[0].unwrap() < 0.5);
+    assert_eq!(levels[1], Some(1.0));
+    assert_eq!(levels[2], None);
+
+    h.press("gh");
+    assert!(h.screen().contains("heat:age"));
+    assert!(Heat::Age.level(&h.app.left.items[0], 0, now).unwrap() > 0.9);
+    h.press("gh");
+    assert!(!h.screen().contains("heat:"));
+}
+
+#[test]
+fn column_layout_shows_the_parent_and_a_preview() {
+    let mut h = Harness::new(&["docs/", "src/main.rs"]);
+    // the cursor on `src` previews its entries
+    h.press("gmj");
+    assert!(h.app.layout.columns);
+    assert!(h.screen().contains("main.rs"), "{}", h.screen());
+    // `l` goes in, where the parent column lists `docs` and the preview is the file's text
+    h.press("l");
+    assert_eq!(h.app.left.current_dir, h.path("src"));
+    let screen = h.screen();
+    assert!(screen.contains("docs"), "{screen}");
+    assert!(screen.contains("src/main.rs"), "{screen}");
+    h.press("h");
+    assert_eq!(h.app.left.current_dir, h.dir.path());
+    h.press("gm");
+    assert!(!h.app.layout.columns);
+}
+
+#[test]
+fn marks_the_entries_meeting_a_typed_condition() {
+    let mut h = Harness::new(&["new.jpg", "old.jpg", "old.png", "notes.txt"]);
+    let month_ago = SystemTime::now() - Duration::from_secs(30 * 24 * 3600);
+    for name in ["old.jpg", "old.png"] {
+        let file = fs::File::options().write(true).open(h.path(name)).unwrap();
+        file.set_modified(month_ago).unwrap();
+    }
+    h.app.left.refresh().unwrap();
+    // Enter on a query that does not parse leaves the prompt open to finish it
+    h.press("g+size<\n1k\n");
+    assert_eq!(h.app.left.marked.len(), 4);
+    h.press("g-type=file\n");
+    assert!(h.app.left.marked.is_empty());
+    h.press("g+modified>7d and (ext=jpg or ext=PNG)\n");
+    let marked = &h.app.left.marked;
+    assert_eq!(marked.len(), 2);
+    assert!(marked.contains(&h.path("old.jpg")) && marked.contains(&h.path("old.png")));
+    // conditions side by side must all hold
+    h.press("g-ext=png name=old*\n");
+    assert_eq!(h.app.left.marked.len(), 1);
+    assert!(h.app.left.marked.contains(&h.path("old.jpg")));
+}
+
+#[cfg(unix)]
+#[test]
+fn runs_a_templated_command_for_each_marked_file() {
+    let mut h = Harness::new(&["a.txt", "b.md", "skip.log"]);
+    h.press("g+ext=txt,md\ng!cp {path} {dir}/{stem}.bak\n");
+    h.wait_for_jobs();
+    assert_eq!(fs::read_to_string(h.path("a.bak")).unwrap(), "a.txt");
+    assert_eq!(fs::read_to_string(h.path("b.bak")).unwrap(), "b.md");
+    assert!(!h.path("skip.bak").exists());
+
+    // a failing command does not stop the rest, and the job reports how many failed
+    h.press("g!test {ext} = txt\n");
+    h.wait_for_jobs();
+    let job = h.app.tasks.jobs.last().unwrap();
+    assert_eq!(
+        job.status,
+        JobStatus::Failed("1 of 2 command(s) failed: b.md".into())
+    );
+    let report = fs::read_to_string(job.report.as_ref().unwrap()).unwrap();
+    assert!(report.contains("$ test txt = txt\n[ok]"), "{report}");
+    assert!(
+        report.contains("$ test md = txt\n[exit code 1]"),
+        "{report}"
+    );
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn finds_files_through_a_watched_index() {
+    let mut h = Harness::new(&["src/deep/main.rs", "docs/readme.md"]);
+    let wait_until = |done: &dyn Fn() -> bool| {
+        let start = Instant::now();
+        while !done() {
+            assert!(start.elapsed() < Duration::from_secs(10), "timed out");
+            thread::sleep(Duration::from_millis(10));
+        }
+    };
+    let cache = tempfile::tempdir().unwrap();
+    let file = cache.path().join("index");
+    let index = FileIndex::open_at(h.dir.path().to_path_buf(), Some(file.clone()));
+    wait_until(&|| !index.is_scanning());
+    h.app.index = Some(index.clone());
+
+    // a file created after the scan comes in through inotify
+    let lib = h.path("src/deep/lib.rs");
+    fs::write(&lib, "").unwrap();
+    wait_until(&|| index.contains(&lib));
+    h.press("gfdplib\n");
+    let pane = &h.app.left;
+    assert_eq!(pane.current_dir, h.path("src/deep"));
+    assert_eq!(pane.items[pane.selected].path(), lib);
+
+    fs::remove_dir_all(h.path("src")).unwrap();
+    wait_until(&|| !index.contains(&h.path("src")));
+    assert!(!index.contains(&lib));
+    // the saved index is read back at once
+    wait_until(&|| fs::read_to_string(&file).is_ok_and(|t| !t.contains("src")));
+    let reopened = FileIndex::open_at(h.dir.path().to_path_buf(), Some(file));
+    assert!(reopened.contains(&h.path("docs/readme.md")));
+    assert!(!reopened.contains(&h.path("src")));
+}
+
+#[cfg(unix)]
+#[test]
+fn shares_the_selection_through_a_configured_target() {
+    let mut h = Harness::new(&["a.txt", "b.txt", "outbox/"]);
+    for (name, run) in [
+        ("print", "lp {path}"),
+        ("outbox", "cp {path} outbox/{stem}.sent"),
+    ] {
+        h.app.config.shares.push(ShareTarget {
+            name: name.into(),
+            run: run.into(),
+        });
+    }
+    h.press("g+ext=txt\ngsj\n");
+    h.wait_for_jobs();
+    assert_eq!(
+        fs::read_to_string(h.path("outbox/a.sent")).unwrap(),
+        "a.txt"
+    );
+    assert!(h.path("outbox/b.sent").exists());
+    // the result is announced once the job finishes, which may be before the keys run out
+    h.press("");
+    assert!(h
+        .app
+        .notifications
+        .history()
+        .any(|n| n.message == "share 2 item(s) via outbox: done"));
+}
+
+/// A minimal ISO9660 image holding `README.TXT` and `DOCS/A.TXT`; with `looped`, also
+/// `DOCS/LOOP`, a corrupt directory whose data is the root's.
+fn iso_image(looped: bool) -> Vec<u8> {
+    const SECTOR: usize = 2048;
+    let record = |extent: u32, len: u32, dir: bool, name: &[u8]| {
+        let size = (33 + name.len() + 1) & !1;
+        let mut r = vec![0u8; size];
+        r[0] = size as u8;
+        r[2..6].copy_from_slice(&extent.to_le_bytes());
+        r[6..10].copy_from_slice(&extent.to_be_bytes());
+        r[10..14].copy_from_slice(&len.to_le_bytes());
+        r[14..18].copy_from_slice(&len.to_be_bytes());
+        r[18..25].copy_from_slice(&[124, 1, 2, 3, 4, 5, 0]);
+        r[25] = if dir { 2 } else { 0 };
+        r[32] = name.len() as u8;
+        r[33..33 + name.len()].copy_from_slice(name);
+        r
+    };
+    let mut image = vec![0u8; 22 * SECTOR];
+    let mut put = |sector: usize, bytes: &[u8]| {
+        image[sector * SECTOR..sector * SECTOR + bytes.len()].copy_from_slice(bytes);
+    };
+    let root = record(18, SECTOR as u32, true, &[0]);
+    let mut pvd = vec![1, b'C', b'D', b'0', b'0', b'1', 1];
+    pvd.resize(156, 0);
+    pvd.extend(&root);
+    put(16, &pvd);
+    put(17, &[255, b'C', b'D', b'0', b'0', b'1', 1]);
+    let dir = |own: u32, parent: u32, entries: &[Vec<u8>]| {
+        let mut d = record(own, SECTOR as u32, true, &[0]);
+        d.extend(record(parent, SECTOR as u32, true, &[1]));
+        d.extend(entries.concat());
+        d
+    };
+    put(
+        18,
+        &dir(
+            18,
+            18,
+            &[
+                record(19, SECTOR as u32, true, b"DOCS"),
+                record(20, 6, false, b"README.TXT;1"),
+            ],
+        ),
+    );
+    let mut docs = vec![record(21, 5, false, b"A.TXT;1")];
+    if looped {
+        docs.push(record(18, SECTOR as u32, true, b"LOOP"));
+    }
+    put(19, &dir(19, 18, &docs));
+    put(20, b"readme");
+    put(21, b"hello");
+    image
+}
+
+#[test]
+fn browses_and_extracts_from_an_iso_image() {
+    let mut h = Harness::new(&["out/"]);
+    fs::write(h.path("disc.iso"), iso_image(false)).unwrap();
+    h.app.left.refresh().unwrap();
+    let image = h.path("disc.iso");
+    h.app.left.selected = h
+        .app
+        .left
+        .items
+        .iter()
+        .position(|e| e.path() == image)
+        .unwrap();
+    assert!(h.app.right.change_dir(h.path("out")));
+    h.press("\n");
+    assert_eq!(h.app.left.current_dir, PathBuf::from("/"));
+    let screen = h.screen();
+    assert!(screen.contains("iso:"), "{screen}");
+    assert!(screen.contains("README.TXT"), "{screen}");
+
+    // extract a file from a subdirectory into the other pane
+    h.app.left.selected = 0;
+    h.press("\n");
+    assert_eq!(h.app.left.current_dir, PathBuf::from("/DOCS"));
+    h.run(vec![
+        KeyEvent::new(KeyCode::F(5), KeyModifiers::NONE),
+        KeyEvent::new(KeyCode::Char('y'), KeyModifiers::NONE),
+        KeyEvent::new(KeyCode::Char('q'), KeyModifiers::NONE),
+    ]);
+    h.wait_for_jobs();
+    assert_eq!(fs::read_to_string(h.path("out/A.TXT")).unwrap(), "hello");
+
+    // up from the root of the image is back beside it
+    h.press("hh");
+    assert_eq!(h.app.left.current_dir, h.dir.path());
+    assert_eq!(h.app.left.items[h.app.left.selected].path(), image);
+    assert!(h.app.left.is_local());
+}
+
+#[test]
+fn opens_an_iso_image_whose_directory_loops_back_to_the_root() {
+    let dir = tempfile::tempdir().unwrap();
+    let image = dir.path().join("looped.iso");
+    fs::write(&image, iso_image(true)).unwrap();
+    let iso = IsoFs::open(&image).unwrap();
+    let names: Vec<String> = iso
+        .read_dir(Path::new("/DOCS"))
+        .unwrap()
+        .map(|e| e.unwrap().file_name().to_string_lossy().into_owned())
+        .collect();
+    assert!(names.contains(&"LOOP".to_string()), "{names:?}");
+    // the root is listed once, not again below the loop
+    assert!(iso.read_dir(Path::new("/DOCS/LOOP")).is_err());
+}
+
+#[test]
+fn extracts_archives_into_directories_named_after_them() {
+    let mut h = Harness::new(&["src/a.txt", "src/sub/b.txt", "pack/"]);
+    sevenz_rust::compress_to_path(h.path("src"), h.path("pack.7z")).unwrap();
+    let tar = std::process::Command::new("tar")
+        .arg("-czf")
+        .arg(h.path("bundle.tar.gz"))
+        .arg("-C")
+        .arg(h.path("src"))
+        .arg(".")
+        .status()
+        .unwrap();
+    assert!(tar.success());
+    h.app.left.refresh().unwrap();
+    h.press("g+ext=7z | ext=gz\ngx");
+    h.wait_for_jobs();
+    // `pack` is taken, so the 7z archive goes to `pack (2)`
+    assert_eq!(
+        fs::read_to_string(h.path("pack (2)/sub/b.txt")).unwrap(),
+        "src/sub/b.txt"
+    );
+    assert_eq!(
+        fs::read_to_string(h.path("bundle/a.txt")).unwrap(),
+        "src/a.txt"
+    );
+
+    // a broken archive leaves no directory behind
+    fs::write(h.path("broken.7z"), "not an archive").unwrap();
+    h.app.left.refresh().unwrap();
+    let broken = h.path("broken.7z");
+    h.app.left.selected = h
+        .app
+        .left
+        .items
+        .iter()
+        .position(|e| e.path() == broken)
+        .unwrap();
+    h.press("gx");
+    h.wait_for_jobs();
+    assert!(matches!(
+        h.app.tasks.jobs.last().unwrap().status,
+        JobStatus::Failed(_)
+    ));
+    assert!(!h.path("broken").exists());
+}
+
+#[cfg(unix)]
+#[test]
+fn asks_for_the_password_of_an_encrypted_archive() {
+    let mut h = Harness::new(&["src/a.txt"]);
+    sevenz_rust::compress_to_path_encrypted(h.path("src"), h.path("locked.7z"), "secret".into())
+        .unwrap();
+    h.app.left.refresh().unwrap();
+    h.press("g+ext=7z\n");
+    extract_selection(&mut h.app);
+    h.wait_for_jobs();
+    assert_eq!(h.app.tasks.jobs[0].status, JobStatus::Locked);
+    assert!(!h.path("locked").exists());
+
+    // the prompt opens once the job stops; a wrong password asks again. The retry waits
+    // behind a slow job so the prompt cannot come back before the keys run out
+    let src = h.path("src");
+    run_for_each(&mut h.app, vec![src], "sleep 0.3".into(), None);
+    h.press("wrong\n");
+    h.wait_for_jobs();
+    h.app.poll_tasks();
+    let Mode::ArchivePassword { extraction, .. } = &h.app.mode else {
+        panic!("no password prompt");
+    };
+    assert_eq!(extraction.password.as_deref(), Some("wrong"));
+    h.press("secret\n");
+    h.wait_for_jobs();
+    assert_eq!(
+        fs::read_to_string(h.path("locked/a.txt")).unwrap(),
+        "src/a.txt"
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn shreds_files_after_asking() {
+    let mut h = Harness::new(&["secret.txt", "dir/a.txt", "dir/b.txt", "keep.txt"]);
+    // another link to the file sees its data overwritten, which deleting would not do
+    fs::hard_link(h.path("secret.txt"), h.path("other-link")).unwrap();
+    h.app.left.refresh().unwrap();
+    for name in ["secret.txt", "dir"] {
+        h.app.left.marked.insert(h.path(name));
+    }
+    h.press("gwn");
+    assert!(h.path("secret.txt").exists());
+    h.press("gw");
+    assert!(matches!(h.app.mode, Mode::ConfirmShred { .. }));
+    h.press("y");
+    h.wait_for_jobs();
+    assert!(!h.path("secret.txt").exists());
+    assert!(!h.path("dir").exists());
+    assert_eq!(fs::read(h.path("other-link")).unwrap(), b"");
+    assert!(h.path("keep.txt").exists());
+}
+
+#[test]
+fn verifies_a_detached_signature() {
+    let gpg = |args: &[&str], home: &std::path::Path| {
+        std::process::Command::new("gpg")
+            .args(["--batch", "--passphrase", "", "--homedir"])
+            .arg(home)
+            .args(args)
+            .output()
+    };
+    let home = tempfile::tempdir().unwrap();
+    // skipped where gpg is not installed
+    let Ok(made) = gpg(
+        &[
+            "--quick-gen-key",
+            "Kura Test <test@example.com>",
+            "ed25519",
+            "sign",
+            "never",
+        ],
+        home.path(),
+    ) else {
+        return;
+    };
+    assert!(made.status.success());
+    let mut h = Harness::new(&["release.tar"]);
+    h.app.config.gnupg_home = Some(home.path().to_path_buf());
+    let release = h.path("release.tar");
+    let signed = gpg(&["--detach-sign", release.to_str().unwrap()], home.path()).unwrap();
+    assert!(signed.status.success());
+    h.app.left.refresh().unwrap();
+    let verify = |h: &mut Harness| {
+        h.app.signatures.clear();
+        h.press("gV");
+        let start = Instant::now();
+        while !h.app.signatures.contains_key(&release) {
+            assert!(start.elapsed() < Duration::from_secs(10), "timed out");
+            thread::sleep(Duration::from_millis(10));
+            h.app.poll_verification();
+        }
+    };
+    verify(&mut h);
+    assert_eq!(
+        h.app.notifications.history().next().unwrap().message,
+        "release.tar: good signature from Kura Test <test@example.com>"
+    );
+    h.press("I");
+    assert!(h
+        .screen()
+        .contains("Signature: release.tar.sig, good signature from Kura Test"));
+
+    fs::write(&release, "changed").unwrap();
+    h.press("\x1b");
+    verify(&mut h);
+    assert!(matches!(h.app.signatures[&release], Verdict::Bad { .. }));
+    let _ = std::process::Command::new("gpgconf")
+        .args(["--kill", "gpg-agent"])
+        .env("GNUPGHOME", home.path())
+        .status();
+}
+
+#[test]
+fn verifies_the_files_of_a_checksum_list() {
+    let mut h = Harness::new(&["a.txt", "b.txt", "sub/c.txt"]);
+    fs::write(
+        h.path("SHA256SUMS"),
+        "# release\n\
+         18b7cb099a9ea3f50ba899b5ba81e0d377a5f3b16f8f6eeb8b3e58cd4692b993  a.txt\n\
+         MD5 (b.txt) = ce506ace22f28ac2bc4f933d4cf989fd\n\
+         0000000000000000000000000000000000000000000000000000000000000000 *sub/c.txt\n\
+         18b7cb099a9ea3f50ba899b5ba81e0d377a5f3b16f8f6eeb8b3e58cd4692b993  gone.txt\n",
+    )
+    .unwrap();
+    h.app.left.refresh().unwrap();
+    let list = h.path("SHA256SUMS");
+    h.app.left.selected = h
+        .app
+        .left
+        .items
+        .iter()
+        .position(|e| e.path() == list)
+        .unwrap();
+    h.press("\n");
+    h.wait_for_jobs();
+    let Mode::Checksums(report) = &mut h.app.mode else {
+        panic!("no report: {}", h.app.mode.name());
+    };
+    report.refresh();
+    assert_eq!(
+        report.outcomes,
+        vec![
+            Some(Outcome::Match),
+            Some(Outcome::Match),
+            Some(Outcome::Mismatch),
+            Some(Outcome::Missing)
+        ]
+    );
+    h.press("jj");
+    assert_eq!(
+        h.app.notifications.history().next().unwrap().message,
+        "verify 4 file(s) from SHA256SUMS: 2 of 4 file(s) did not match or are missing"
+    );
+    let screen = h.screen();
+    assert!(screen.contains("SHA256SUMS: 4/4 checked, 2 failed"));
+    assert!(screen.contains("FAILED  sub/c.txt"));
+    assert!(screen.contains("MISSING gone.txt"));
+
+    h.press("\n");
+    assert_eq!(h.app.left.current_dir, h.path("sub"));
+    assert_eq!(h.app.left.items[h.app.left.selected].file_name(), "c.txt");
+}
+
+#[test]
+fn runs_a_command_when_a_watched_directory_changes() {
+    let mut h = Harness::new(&["src/main.c", "notes.txt"]);
+    let dir = h.path("src");
+    h.app.left.selected = h
+        .app
+        .left
+        .items
+        .iter()
+        .position(|e| e.path() == dir)
+        .unwrap();
+    h.press("gWecho built {name}\n");
+    let log = h.app.watching.as_ref().expect("watching").log.clone();
+    assert!(h.screen().contains("watching"));
+    let wait_for = |h: &mut Harness, text: &str| {
+        let start = Instant::now();
+        while !fs::read_to_string(&log).unwrap().contains(text) {
+            assert!(
+                start.elapsed() < Duration::from_secs(10),
+                "no {text:?} in the log"
+            );
+            thread::sleep(Duration::from_millis(10));
+            h.app.poll_watch();
+        }
+    };
+    wait_for(&mut h, "built src\n[exit code 0]");
+
+    // changes outside the directory are not watched
+    fs::write(h.path("notes.txt"), "more").unwrap();
+    thread::sleep(Duration::from_millis(300));
+    fs::write(dir.join("util.c"), "int x;").unwrap();
+    wait_for(&mut h, "$ echo built util.c\nbuilt util.c\n[exit code 0]");
+    assert!(!fs::read_to_string(&log).unwrap().contains("notes.txt"));
+
+    h.press("\ngW");
+    assert!(h.app.watching.is_none());
+    assert_eq!(
+        h.app.notifications.history().next().unwrap().message,
+        format!("stopped watching {}", dir.display())
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn follows_link_chains_and_cleans_up_broken_links() {
+    use std::os::unix::fs::symlink;
+    let mut h = Harness::new(&["c.txt", "sub/"]);
+    symlink("b", h.path("a")).unwrap();
+    symlink("c.txt", h.path("b")).unwrap();
+    symlink("nowhere", h.path("dangling")).unwrap();
+    symlink("../gone", h.path("sub/dangling")).unwrap();
+    symlink("loop2", h.path("loop1")).unwrap();
+    symlink("loop1", h.path("loop2")).unwrap();
+    h.app.left.refresh().unwrap();
+    let select = |h: &mut Harness, name: &str| {
+        let path = h.path(name);
+        h.app.left.selected = h
+            .app
+            .left
+            .items
+            .iter()
+            .position(|e| e.path() == path)
+            .unwrap();
+    };
+    select(&mut h, "a");
+    h.press("I");
+    assert!(h.screen().contains("Links:    a → b → c.txt"));
+    select(&mut h, "dangling");
+    h.press("\x1bI");
+    let screen = h.screen();
+    assert!(screen.contains("symlink (broken)"));
+    assert!(screen.contains("Links:    dangling → nowhere (missing)"));
+    select(&mut h, "loop1");
+    h.press("\x1bI");
+    assert!(h.screen().contains("loop1 → loop2 → loop1 (loop)"));
+
+    h.press("\x1bgB");
+    let start = Instant::now();
+    loop {
+        let Mode::Links(links) = &mut h.app.mode else {
+            panic!("no broken links popup");
+        };
+        links.refresh();
+        if !links.is_scanning() {
+            break;
+        }
+        assert!(start.elapsed() < Duration::from_secs(10), "timed out");
+        thread::sleep(Duration::from_millis(10));
+    }
+    h.press("");
+    let screen = h.screen();
+    assert!(screen.contains("4 found"));
+    assert!(screen.contains("sub/dangling → ../gone"));
+
+    h.press("Dy");
+    h.wait_for_jobs();
+    for name in ["dangling", "sub/dangling", "loop1", "loop2"] {
+        assert!(h.path(name).symlink_metadata().is_err(), "{name} is left");
+    }
+    assert!(h.path("a").exists());
+}
+
+#[cfg(unix)]
+#[test]
+fn finds_hard_links_and_offers_them_for_copies() {
+    let mut h = Harness::new(&["data.bin", "sub/", "other/", "out/"]);
+    fs::hard_link(h.path("data.bin"), h.path("sub/twin")).unwrap();
+    fs::hard_link(h.path("data.bin"), h.path("other/copy")).unwrap();
+    h.app.left.refresh().unwrap();
+    let data = h.path("data.bin");
+    h.app.left.selected = h
+        .app
+        .left
+        .items
+        .iter()
+        .position(|e| e.path() == data)
+        .unwrap();
+    h.press("I");
+    assert!(h.screen().contains("Hard links: 3 (gi: find the others)"));
+
+    h.press("\x1bgi\n");
+    let start = Instant::now();
+    let links = loop {
+        let Mode::Links(links) = &mut h.app.mode else {
+            panic!("no hard links popup");
+        };
+        links.refresh();
+        if !links.is_scanning() {
+            break links.links.clone();
+        }
+        assert!(start.elapsed() < Duration::from_secs(10), "timed out");
+        thread::sleep(Duration::from_millis(10));
+    };
+    assert_eq!(links, vec![h.path("other/copy"), h.path("sub/twin")]);
+    h.press("");
+    assert!(h.screen().contains("2 of 2 other name(s) found"));
+
+    // copying to the other pane may hard link instead
+    h.press("\x1b");
+    assert!(h.app.right.change_dir(h.path("out")));
+    h.run(vec![
+        KeyEvent::new(KeyCode::F(5), KeyModifiers::NONE),
+        KeyEvent::new(KeyCode::Char('q'), KeyModifiers::NONE),
+    ]);
+    assert!(h.screen().contains("h: hard link instead"));
+    h.press("h");
+    h.wait_for_jobs();
+    use std::os::unix::fs::MetadataExt;
+    assert_eq!(fs::metadata(&data).unwrap().nlink(), 4);
+    assert_eq!(
+        fs::metadata(h.path("out/data.bin")).unwrap().ino(),
+        fs::metadata(&data).unwrap().ino()
+    );
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn shows_and_edits_extended_attributes() {
+    let mut h = Harness::new(&["photo.jpg"]);
+    let photo = h.path("photo.jpg");
+    if let Err(e) = xattr::set(&photo, "user.origin", b"camera") {
+        // e.g. tmpfs before Linux 6.6
+        assert_eq!(e.kind(), std::io::ErrorKind::Unsupported);
+        return;
+    }
+    h.press("I");
+    assert!(h.screen().contains("user.origin = camera"));
+
+    // a name without a namespace goes in user.
+    h.press("arating=5\n");
+    assert!(h.screen().contains("user.rating = 5"));
+    h.press("a-user.origin\n");
+    assert!(matches!(h.app.mode, Mode::Inspect { .. }));
+    assert_eq!(
+        xattr::list(&photo).unwrap(),
+        vec![("user.rating".to_string(), b"5".to_vec())]
+    );
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn offers_to_retry_a_refused_deletion_as_root() {
+    let mut h = Harness::new(&["notes.txt"]);
+    // not even root may delete files of /proc
+    let refused = PathBuf::from("/proc/self/status");
+    let notes = h.path("notes.txt");
+    h.app.config.elevate_with = "sudo -k".into();
+    h.app.tasks.enqueue(JobKind::Delete {
+        paths: vec![refused.clone(), notes.clone()],
+    });
+    h.wait_for_jobs();
+    assert!(matches!(
+        h.app.tasks.jobs[0].status,
+        JobStatus::Denied { .. }
+    ));
+    h.app.poll_tasks();
+    let Mode::ConfirmElevate { retry, reason } = &h.app.mode else {
+        panic!("no offer to retry as root");
+    };
+    // the rest of the job is retried
+    assert_eq!(
+        *retry,
+        Privileged::Delete {
+            paths: vec![refused, notes.clone()]
+        }
+    );
+    assert!(reason.starts_with("/proc/self/status: Operation not permitted"));
+    h.press("");
+    assert!(h.screen().contains("sudo -k rm -rf -- /proc/self/status"));
+
+    h.press("n");
+    assert!(matches!(h.app.mode, Mode::Filer));
+    assert!(notes.exists());
+}
+
+/// Serve `root` over WebDAV on a local port for one test: enough of PROPFIND, GET, PUT,
+/// DELETE and MKCOL for plain names. Returns the port.
+fn serve_webdav(root: PathBuf) -> u16 {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let _ = answer_webdav(&root, stream);
+        }
+    });
+    port
+}
+
+fn answer_webdav(root: &Path, mut stream: TcpStream) -> io::Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut request = String::new();
+    reader.read_line(&mut request)?;
+    let mut words = request.split_whitespace();
+    let (method, target) = (words.next().unwrap_or(""), words.next().unwrap_or(""));
+    let mut len = 0;
+    loop {
+        let mut header = String::new();
+        reader.read_line(&mut header)?;
+        match header.split_once(':') {
+            Some((name, value)) if name.eq_ignore_ascii_case("content-length") => {
+                len = value.trim().parse().unwrap_or(0);
+            }
+            Some(_) => {}
+            None => break,
+        }
+    }
+    let mut body = vec![0; len];
+    reader.read_exact(&mut body)?;
+    let path = root.join(target.trim_matches('/'));
+    let (status, content) = match method {
+        "PROPFIND" if path.exists() => {
+            let mut paths = vec![path.clone()];
+            if path.is_dir() {
+                paths.extend(fs::read_dir(&path)?.flatten().map(|e| e.path()));
+            }
+            let responses: String = paths
+                .iter()
+                .map(|p| {
+                    let href = format!("/{}", p.strip_prefix(root).unwrap().display());
+                    let meta = fs::metadata(p).unwrap();
+                    let (href, props) = if meta.is_dir() {
+                        (
+                            href + "/",
+                            "<d:resourcetype><d:collection/></d:resourcetype>".into(),
+                        )
+                    } else {
+                        let len = meta.len();
+                        (
+                            href,
+                            format!(
+                                "<d:resourcetype/><d:getcontentlength>{len}</d:getcontentlength>\
+                             <d:getlastmodified>Mon, 12 Jan 2026 10:00:00 GMT</d:getlastmodified>"
+                            ),
+                        )
+                    };
+                    format!(
+                        "<d:response><d:href>{href}</d:href><d:propstat><d:prop>{props}\
+                         </d:prop></d:propstat></d:response>"
+                    )
+                })
+                .collect();
+            let xml = format!(
+                r#"<?xml version="1.0"?><d:multistatus xmlns:d="DAV:">{responses}</d:multistatus>"#
+            );
+            ("207 Multi-Status", xml.into_bytes())
+        }
+        "GET" if path.is_file() => ("200 OK", fs::read(&path)?),
+        "PUT" => {
+            fs::write(&path, &body)?;
+            ("201 Created", Vec::new())
+        }
+        "DELETE" if path.is_dir() => {
+            fs::remove_dir_all(&path)?;
+            ("204 No Content", Vec::new())
+        }
+        "DELETE" if path.exists() => {
+            fs::remove_file(&path)?;
+            ("204 No Content", Vec::new())
+        }
+        "MKCOL" => {
+            fs::create_dir(&path)?;
+            ("201 Created", Vec::new())
+        }
+        _ => ("404 Not Found", Vec::new()),
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {status}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        content.len()
+    )?;
+    stream.write_all(&content)
+}
+
+#[test]
+fn browses_uploads_to_and_deletes_from_a_webdav_share() {
+    let mut h = Harness::new(&["upload.txt"]);
+    let server = tempfile::tempdir().unwrap();
+    let share = server.path().join("share");
+    fs::create_dir_all(share.join("docs")).unwrap();
+    fs::write(share.join("photo.jpg"), "jpeg").unwrap();
+    fs::write(share.join("docs/a.txt"), "a").unwrap();
+    let port = serve_webdav(server.path().to_path_buf());
+
+    h.app.switch_pane();
+    h.app
+        .open_location(&format!("dav://127.0.0.1:{port}/share"));
+    h.press("");
+    let screen = h.screen();
+    assert!(
+        screen.contains(&format!("dav://127.0.0.1:{port}")),
+        "{screen}"
+    );
+    assert!(
+        screen.contains("photo.jpg") && screen.contains("docs"),
+        "{screen}"
+    );
+
+    // F5 from the local pane uploads
+    h.app.switch_pane();
+    let upload = h.path("upload.txt");
+    h.app.left.selected = h
+        .app
+        .left
+        .items
+        .iter()
+        .position(|e| e.path() == upload)
+        .unwrap();
+    h.run(vec![
+        KeyEvent::new(KeyCode::F(5), KeyModifiers::NONE),
+        KeyEvent::new(KeyCode::Char('y'), KeyModifiers::NONE),
+        KeyEvent::new(KeyCode::Char('q'), KeyModifiers::NONE),
+    ]);
+    assert_eq!(
+        fs::read_to_string(share.join("upload.txt")).unwrap(),
+        "upload.txt"
+    );
+
+    // files and whole directories can be deleted
+    h.app.switch_pane();
+    for name in ["photo.jpg", "docs"] {
+        let path = PathBuf::from("/share").join(name);
+        h.app.right.selected = h
+            .app
+            .right
+            .items
+            .iter()
+            .position(|e| e.path() == path)
+            .unwrap();
+        h.press("X");
+        assert!(!share.join(name).exists(), "{name} is left");
+    }
+    assert_eq!(h.app.right.items.len(), 1);
+}
+
+/// Serve each directory of `root` as an S3 bucket on a local port for one test: enough of
+/// ListBuckets, ListObjectsV2, GET, PUT and DELETE for plain names, from signed requests
+/// only. Returns the port.
+fn serve_s3(root: PathBuf) -> u16 {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let _ = answer_s3(&root, stream);
+        }
+    });
+    port
+}
+
+fn answer_s3(root: &Path, mut stream: TcpStream) -> io::Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut request = String::new();
+    reader.read_line(&mut request)?;
+    let mut words = request.split_whitespace();
+    let (method, target) = (words.next().unwrap_or(""), words.next().unwrap_or(""));
+    let (mut len, mut signed) = (0, false);
+    loop {
+        let mut header = String::new();
+        reader.read_line(&mut header)?;
+        match header.split_once(':') {
+            Some((name, value)) if name.eq_ignore_ascii_case("content-length") => {
+                len = value.trim().parse().unwrap_or(0);
+            }
+            Some((name, value)) if name.eq_ignore_ascii_case("authorization") => {
+                signed = value
+                    .trim()
+                    .starts_with("AWS4-HMAC-SHA256 Credential=test-key/");
+            }
+            Some(_) => {}
+            None => break,
+        }
+    }
+    let mut body = vec![0; len];
+    reader.read_exact(&mut body)?;
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let path = root.join(path.trim_start_matches('/'));
+    let prefix = query
+        .split('&')
+        .find_map(|pair| pair.strip_prefix("prefix="))
+        .unwrap_or("")
+        .replace("%2F", "/");
+    let (status, content) = match method {
+        _ if !signed => ("403 Forbidden", Vec::new()),
+        "GET" if path == root => {
+            let buckets: String = fs::read_dir(root)?
+                .flatten()
+                .map(|e| {
+                    format!(
+                        "<Bucket><Name>{}</Name><CreationDate>2026-01-12T10:00:00.000Z\
+                         </CreationDate></Bucket>",
+                        e.file_name().to_string_lossy()
+                    )
+                })
+                .collect();
+            let xml = format!(
+                "<ListAllMyBucketsResult><Buckets>{buckets}</Buckets></ListAllMyBucketsResult>"
+            );
+            ("200 OK", xml.into_bytes())
+        }
+        "GET" if query.contains("list-type=2") => {
+            let mut entries = String::new();
+            for entry in fs::read_dir(path.join(&prefix))?.flatten() {
+                let key = format!("{prefix}{}", entry.file_name().to_string_lossy());
+                let meta = entry.metadata()?;
+                entries += &if meta.is_dir() {
+                    format!("<CommonPrefixes><Prefix>{key}/</Prefix></CommonPrefixes>")
+                } else {
+                    format!(
+                        "<Contents><Key>{key}</Key><LastModified>2026-01-12T10:00:00.000Z\
+                         </LastModified><Size>{}</Size></Contents>",
+                        meta.len()
+                    )
+                };
+            }
+            let xml = format!(
+                "<ListBucketResult><IsTruncated>false</IsTruncated>{entries}</ListBucketResult>"
+            );
+            ("200 OK", xml.into_bytes())
+        }
+        "GET" if path.is_file() => ("200 OK", fs::read(&path)?),
+        "PUT" => {
+            fs::write(&path, &body)?;
+            ("200 OK", Vec::new())
+        }
+        "DELETE" => {
+            let _ = fs::remove_file(&path);
+            ("204 No Content", Vec::new())
+        }
+        _ => ("404 Not Found", Vec::new()),
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {status}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        content.len()
+    )?;
+    stream.write_all(&content)
+}
+
+#[test]
+fn browses_previews_and_uploads_to_an_s3_bucket() {
+    let mut h = Harness::new(&["upload.txt"]);
+    let server = tempfile::tempdir().unwrap();
+    let bucket = server.path().join("media");
+    fs::create_dir_all(bucket.join("photos")).unwrap();
+    fs::write(bucket.join("notes.txt"), "kept in the bucket").unwrap();
+    let port = serve_s3(server.path().to_path_buf());
+    let credentials = Credentials {
+        access_key: "test-key".into(),
+        secret_key: "test-secret".into(),
+        session_token: None,
+    };
+    let endpoint = format!("http://127.0.0.1:{port}");
+    let s3 = S3Fs::new(credentials, "us-east-1".into(), Some(endpoint));
+
+    h.app.switch_pane();
+    h.app
+        .right
+        .set_location(Rc::new(s3), PathBuf::from("/"))
+        .unwrap();
+    h.press("");
+    assert!(h.screen().contains("media"), "{}", h.screen());
+
+    assert!(h.app.right.change_dir(PathBuf::from("/media")));
+    h.press("");
+    let screen = h.screen();
+    assert!(
+        screen.contains("notes.txt") && screen.contains("photos"),
+        "{screen}"
+    );
+
+    // objects are downloaded to be viewed
+    let notes = PathBuf::from("/media/notes.txt");
+    h.app.right.selected = h
+        .app
+        .right
+        .items
+        .iter()
+        .position(|e| e.path() == notes)
+        .unwrap();
+    h.app.on_enter();
+    assert!(matches!(h.app.mode, Mode::Viewer(_)));
+    h.app.mode = Mode::Filer;
+
+    // F5 from the local pane uploads
+    h.app.switch_pane();
+    let upload = h.path("upload.txt");
+    h.app.left.selected = h
+        .app
+        .left
+        .items
+        .iter()
+        .position(|e| e.path() == upload)
+        .unwrap();
+    h.run(vec![
+        KeyEvent::new(KeyCode::F(5), KeyModifiers::NONE),
+        KeyEvent::new(KeyCode::Char('y'), KeyModifiers::NONE),
+        KeyEvent::new(KeyCode::Char('q'), KeyModifiers::NONE),
+    ]);
+    assert_eq!(
+        fs::read_to_string(bucket.join("upload.txt")).unwrap(),
+        "upload.txt"
+    );
+    assert!(h
+        .app
+        .right
+        .items
+        .iter()
+        .any(|e| e.path() == Path::new("/media/upload.txt")));
+}
+
+#[test]
+fn lists_and_opens_phones_mounted_through_gvfs() {
+    let mut h = Harness::new(&["a.txt"]);
+    let runtime = tempfile::tempdir().unwrap();
+    let phone = runtime.path().join("gvfs/mtp:host=Google_Pixel_7_ABC123");
+    let dcim = phone.join("Internal shared storage/DCIM");
+    fs::create_dir_all(&dcim).unwrap();
+    fs::write(dcim.join("IMG_0001.jpg"), "jpeg").unwrap();
+    h.app.config.gvfs_root = Some(runtime.path().join("gvfs"));
+
+    h.app.open_mounts();
+    h.press("");
+    assert!(
+        h.screen().contains("Google Pixel 7 ABC123"),
+        "{}",
+        h.screen()
+    );
+    let Mode::Mounts { mounts, selected } = &mut h.app.mode else {
+        panic!("mounts not shown");
+    };
+    *selected = mounts.iter().position(|m| m.fs_type == "mtp").unwrap();
+    h.press("\n");
+    assert_eq!(h.app.left.current_dir, phone);
+
+    h.app
+        .open_location("mtp://Google_Pixel_7_ABC123/Internal%20shared%20storage/DCIM");
+    assert_eq!(h.app.left.current_dir, dcim);
+    assert!(h
+        .app
+        .left
+        .items
+        .iter()
+        .any(|e| e.path() == dcim.join("IMG_0001.jpg")));
+}
+
+#[cfg(unix)]
+#[test]
+fn copies_through_rsync_and_resumes_a_failed_copy() {
+    let mut h = Harness::new(&["album/one.jpg", "album/two.jpg", "target/"]);
+    // a stand-in for rsync: fails once if asked to, then copies like `cp -R`
+    let bin = tempfile::tempdir().unwrap();
+    let script = bin.path().join("rsync");
+    fs::write(
+        &script,
+        "#!/bin/sh\n\
+         dir=$(dirname \"$0\")\n\
+         echo \"$*\" > \"$dir/args\"\n\
+         if [ -e \"$dir/fail\" ]; then rm \"$dir/fail\"; echo 'rsync: connection unexpectedly closed' >&2; exit 12; fi\n\
+         while [ \"$1\" != -- ]; do shift; done; shift\n\
+         for dest; do :; done\n\
+         printf '      4,096  50%%    1.00MB/s    0:00:01 (xfr#1, to-chk=1/2)\\r'\n\
+         while [ $# -gt 1 ]; do cp -R \"$1\" \"$dest\"; shift; done\n",
+    )
+    .unwrap();
+    use std::os::unix::fs::PermissionsExt;
+    fs::set_permissions(&script, fs::Permissions::from_mode(0o755)).unwrap();
+    fs::write(bin.path().join("fail"), "").unwrap();
+    h.app.config.copy_with_rsync = UseRsync::Always;
+    h.app.config.rsync_program = script;
+    h.app.tasks = TaskQueue::new(&h.app.config);
+
+    assert!(h.app.right.change_dir(h.path("target")));
+    let album = h.path("album");
+    h.app.left.selected = h
+        .app
+        .left
+        .items
+        .iter()
+        .position(|e| e.path() == album)
+        .unwrap();
+    h.run(vec![
+        KeyEvent::new(KeyCode::F(5), KeyModifiers::NONE),
+        KeyEvent::new(KeyCode::Char('y'), KeyModifiers::NONE),
+        KeyEvent::new(KeyCode::Char('q'), KeyModifiers::NONE),
+    ]);
+    h.wait_for_jobs();
+    assert_eq!(
+        h.app.tasks.jobs[0].status,
+        JobStatus::Failed("rsync: connection unexpectedly closed".into())
+    );
+    let args = fs::read_to_string(bin.path().join("args")).unwrap();
+    assert!(
+        args.contains("--partial") && args.contains("--info=progress2"),
+        "{args}"
+    );
+    // a fresh copy replaces differing files rather than appending to them
+    assert!(!args.contains("--append"), "{args}");
+
+    // `r` in the queue runs the copy again, completing the files it cut off
+    h.app.mode = Mode::Tasks { selected: 0 };
+    h.press("r\x1b");
+    h.wait_for_jobs();
+    assert_eq!(h.app.tasks.jobs[1].status, JobStatus::Done);
+    let args = fs::read_to_string(bin.path().join("args")).unwrap();
+    assert!(args.contains("--append-verify"), "{args}");
+    assert_eq!(
+        fs::read_to_string(h.path("target/album/two.jpg")).unwrap(),
+        "album/two.jpg"
+    );
+}
+
+#[test]
+fn resumes_an_interrupted_copy_skipping_copied_files() {
+    let mut h = Harness::new(&["album/one.jpg", "album/two.jpg", "target/"]);
+    let (album, target) = (h.path("album"), h.path("target"));
+    // as left by a run cancelled after the first file
+    let sources = vec![album.clone()];
+    fs::create_dir(target.join("album")).unwrap();
+    fs::write(target.join("album/one.jpg"), "copied before").unwrap();
+    let data_dir = h.app.config.data_dir.clone();
+    Manifest::open(data_dir.as_deref(), &sources, &target)
+        .record(&album.join("one.jpg"), &target.join("album/one.jpg"));
+
+    assert!(h.app.right.change_dir(target.clone()));
+    h.app.left.selected = h
+        .app
+        .left
+        .items
+        .iter()
+        .position(|e| e.path() == album)
+        .unwrap();
+    h.run(vec![
+        KeyEvent::new(KeyCode::F(5), KeyModifiers::NONE),
+        KeyEvent::new(KeyCode::Char('y'), KeyModifiers::NONE),
+        KeyEvent::new(KeyCode::Char('q'), KeyModifiers::NONE),
+    ]);
+    assert!(h.screen().contains("interrupted"), "{}", h.screen());
+    assert!(matches!(h.app.mode, Mode::ConfirmResume { done: 1, .. }));
+
+    h.press("y");
+    h.wait_for_jobs();
+    assert_eq!(h.app.tasks.jobs[0].status, JobStatus::Done);
+    assert_eq!(
+        fs::read_to_string(target.join("album/one.jpg")).unwrap(),
+        "copied before"
+    );
+    assert_eq!(
+        fs::read_to_string(target.join("album/two.jpg")).unwrap(),
+        "album/two.jpg"
+    );
+    // a complete copy leaves nothing to resume
+    assert_eq!(
+        resume::interrupted(data_dir.as_deref(), &sources, &target),
+        None
+    );
+    // nor is the record of a copy still on the queue taken for an interrupted one
+    let copy = JobKind::Copy {
+        sources: sources.clone(),
+        dest: target.clone(),
+    };
+    h.app.tasks.enqueue(copy);
+    assert!(h.app.tasks.is_copying(&sources, &target));
+    h.wait_for_jobs();
+    assert!(!h.app.tasks.is_copying(&sources, &target));
+}
+
+#[test]
+fn scrolls_a_date_grouped_listing_by_its_rows() {
+    let names: Vec<String> = (0..60).map(|i| format!("f{i:02}.txt")).collect();
+    let mut h = Harness::new(&names.iter().map(String::as_str).collect::<Vec<_>>());
+    let month_ago = SystemTime::now() - Duration::from_secs(30 * 24 * 3600);
+    for name in &names[..30] {
+        let file = fs::File::options().write(true).open(h.path(name)).unwrap();
+        file.set_modified(month_ago).unwrap();
+    }
+    h.app.left.refresh().unwrap();
+    // the cursor stays on its entry, further down than the view reaches
+    h.press("t");
+    let screen = h.screen();
+    assert!(screen.contains(">>   f00.txt"), "{screen}");
+    assert!(!screen.contains("Today"), "{screen}");
+    h.press("gg");
+    let screen = h.screen();
+    assert!(screen.contains(">>   f59.txt"), "{screen}");
+    assert!(!screen.contains("Older"), "{screen}");
+    h.press("G");
+    let screen = h.screen();
+    assert!(!screen.contains("Today"), "{screen}");
+    assert!(
+        screen.contains(&format!(
+            ">>   {}",
+            h.app.left.items[59].file_name().to_string_lossy()
+        )),
+        "{screen}"
+    );
+    h.press("\t");
+    let screen = h.screen();
+    assert!(screen.contains(">> ▸ Older (30)"), "{screen}");
+    assert!(screen.contains("item 31/60"), "{screen}");
+}
+
+#[test]
+fn fits_long_and_wide_names_into_the_pane() {
+    let mut h = Harness::new(&[
+        "quarterly_financial_report_for_the_board_meeting_2024_final.pdf",
+        "写真アルバム_二〇二四年_夏休み_家族旅行_北海道.jpg",
+        "a_directory_whose_name_goes_on_and_on_and_on_and_on/",
+    ]);
+    h.press("");
+    let screen = h.screen();
+    let rows: Vec<&str> = screen.lines().skip(2).take(3).collect();
+    // wide characters fill two cells, the second blank in the test screen
+    for row in &rows {
+        assert_eq!(row.chars().nth(49), Some('│'), "{screen}");
+    }
+    let left = |row: &str| {
+        row.chars()
+            .take(49)
+            .filter(|&c| c != ' ')
+            .collect::<String>()
+    };
+    assert!(left(rows[0]).ends_with("_and_…"), "{screen}");
+    assert!(
+        left(rows[1]).contains("quarterly_financial…_meeting_2024_final.pdf"),
+        "{screen}"
+    );
+    assert!(left(rows[2]).starts_with("│写真アルバム_"), "{screen}");
+    assert!(
+        left(rows[2]).ends_with("…み_家族旅行_北海道.jpg"),
+        "{screen}"
+    );
+
+    // a long name makes room for the entry count after it
+    h.press("gc");
+    let dir = h.path("a_directory_whose_name_goes_on_and_on_and_on_and_on");
+    let start = Instant::now();
+    while h.app.left.dir_stats(&dir).unwrap().entries.is_none() {
+        h.app.left.poll_counts();
+        assert!(start.elapsed() < Duration::from_secs(10), "no count");
+        thread::sleep(Duration::from_millis(10));
+    }
+    h.press("");
+    let screen = h.screen();
+    let row = screen.lines().nth(2).unwrap();
+    assert!(left(row).ends_with("…0items"), "{screen}");
+    assert_eq!(row.chars().nth(49), Some('│'), "{screen}");
+}
+
+#[test]
+fn diffs_changed_lines_and_files_too_different_to_search() {
+    let view = DiffView::new(
+        Path::new("a"),
+        Path::new("b"),
+        "one\ntwo\nthree",
+        "one\n2\nthree",
+    );
+    let lines: Vec<&str> = view.lines.iter().map(|l| l.text.as_str()).collect();
+    assert_eq!(lines, ["@@ -1,3 +1,3 @@", " one", "-two", "+2", " three"]);
+
+    let old: String = (0..40_000).map(|i| format!("old {i}\n")).collect();
+    let new: String = (0..40_000).map(|i| format!("new {i}\n")).collect();
+    let mut view = DiffView::new(Path::new("a"), Path::new("b"), &old, &new);
+    assert_eq!(view.lines.len(), 80_001);
+    assert_eq!(view.lines[0].text, "@@ -1,40000 +1,40000 @@");
+    assert!(view.lines[1].kind == LineKind::Removed);
+    assert!(view.lines[40_001].kind == LineKind::Added);
+    view.go_bottom();
+    assert_eq!(view.offset, 80_000);
+}
+
+/// A remote provider over a local directory that, like SFTP, reports symlinks to directories
+/// as directories.
+struct Mirror;
+
+impl VfsProvider for Mirror {
+    fn label(&self) -> String {
+        "mirror:".into()
+    }
+
+    fn read_dir(&self, dir: &Path) -> io::Result<Box<dyn Iterator<Item = io::Result<Entry>> + '_>> {
+        Ok(Box::new(fs::read_dir(dir)?.map(|e| {
+            let path = e?.path();
+            Ok(Entry::Remote(RemoteEntry {
+                is_dir: path.is_dir(),
+                is_symlink: path.symlink_metadata()?.is_symlink(),
+                len: 0,
+                modified: None,
+                mode: None,
+                path,
+            }))
+        })))
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> io::Result<()> {
+        fs::rename(from, to)
+    }
+
+    fn create_dir(&self, path: &Path) -> io::Result<()> {
+        fs::create_dir(path)
+    }
+
+    fn remove_file(&self, path: &Path) -> io::Result<()> {
+        fs::remove_file(path)
+    }
+
+    fn remove_dir(&self, path: &Path) -> io::Result<()> {
+        fs::remove_dir(path)
+    }
+
+    fn download(&self, path: &Path, dest: &Path) -> io::Result<()> {
+        fs::copy(path, dest).map(|_| ())
+    }
+
+    fn upload(&self, source: &Path, path: &Path) -> io::Result<()> {
+        fs::copy(source, path).map(|_| ())
+    }
+}
+
+#[cfg(unix)]
+#[test]
+fn deletes_remote_links_to_directories_without_following_them() {
+    use std::os::unix::fs::symlink;
+    let mut h = Harness::new(&["shared/keep.txt", "remote/box/"]);
+    symlink(h.path("shared"), h.path("remote/box/link")).unwrap();
+    symlink(h.path("shared"), h.path("remote/link")).unwrap();
+    let remote = h.path("remote");
+    h.app
+        .left
+        .set_location(Rc::new(Mirror), remote.clone())
+        .unwrap();
+    // both entries, the directory holding a link first, then the link itself
+    h.press("xy");
+    assert!(!h.path("remote/box").exists());
+    h.press("xy");
+    assert!(h.path("remote/link").symlink_metadata().is_err());
+    assert_eq!(
+        fs::read_to_string(h.path("shared/keep.txt")).unwrap(),
+        "shared/keep.txt"
+    );
+}
+
+#[test]
+fn a_move_blocked_by_a_directory_in_the_way_fails_without_merging() {
+    let mut h = Harness::new(&["notes.txt", "photos/a.jpg", "target/photos/b.jpg"]);
+    let (photos, target) = (h.path("photos"), h.path("target"));
+    h.app.tasks.enqueue(JobKind::Move {
+        sources: vec![h.path("notes.txt"), photos.clone()],
+        dest: target.clone(),
+    });
+    h.wait_for_jobs();
+    assert!(matches!(h.app.tasks.jobs[0].status, JobStatus::Failed(_)));
+    assert_eq!(
+        fs::read_to_string(target.join("notes.txt")).unwrap(),
+        "notes.txt"
+    );
+    // the directory stays where it was, and the one in the way keeps only its own files
+    assert!(photos.join("a.jpg").exists());
+    assert!(!target.join("photos/a.jpg").exists());
+}
@@ -0,0 +1,157 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::mpsc::{self, Receiver, TryRecvError},
+    thread,
+};
+
+/// Markers the scanner looks for, matched as whole upper-case words.
+pub const MARKERS: &[&str] = &["TODO", "FIXME", "HACK"];
+
+/// Directories not worth descending into: VCS metadata and build output.
+const SKIPPED_DIRS: &[&str] = &[".git", ".hg", ".svn", "node_modules", "target"];
+
+/// Files larger than this are assumed not to be source code.
+const MAX_FILE_SIZE: u64 = 2 * 1024 * 1024;
+
+pub struct TodoMatch {
+    pub path: PathBuf,
+    /// 1-based line number
+    pub line: usize,
+    pub marker: &'static str,
+    pub text: String,
+}
+
+/// Results of a marker scan under `root`, filled in by a background thread.
+pub struct TodoList {
+    pub root: PathBuf,
+    pub matches: Vec<TodoMatch>,
+    pub cursor: usize,
+    pending: Option<Receiver<Vec<TodoMatch>>>,
+}
+
+impl TodoList {
+    /// Start scanning `root`.
+    pub fn scan(root: PathBuf) -> Self {
+        let (tx, rx) = mpsc::channel();
+        let dir = root.clone();
+        thread::spawn(move || {
+            let _ = tx.send(scan_tree(&dir));
+        });
+        Self {
+            root,
+            matches: Vec::new(),
+            cursor: 0,
+            pending: Some(rx),
+        }
+    }
+
+    pub fn is_scanning(&self) -> bool {
+        self.pending.is_some()
+    }
+
+    /// Pick up the scan results; returns true once they arrive.
+    pub fn poll(&mut self) -> bool {
+        let Some(rx) = &self.pending else {
+            return false;
+        };
+        match rx.try_recv() {
+            Ok(matches) => {
+                self.matches = matches;
+                self.cursor = 0;
+                self.pending = None;
+                true
+            }
+            Err(TryRecvError::Disconnected) => {
+                self.pending = None;
+                false
+            }
+            Err(TryRecvError::Empty) => false,
+        }
+    }
+
+    pub fn current(&self) -> Option<&TodoMatch> {
+        self.matches.get(self.cursor)
+    }
+
+    /// Whether match `i` is the first one of its file.
+    pub fn starts_file(&self, i: usize) -> bool {
+        i == 0 || self.matches[i - 1].path != self.matches[i].path
+    }
+
+    pub fn down(&mut self, count: usize) {
+        self.cursor = (self.cursor + count).min(self.matches.len().saturating_sub(1));
+    }
+
+    pub fn up(&mut self, count: usize) {
+        self.cursor = self.cursor.saturating_sub(count);
+    }
+
+    pub fn go_top(&mut self) {
+        self.cursor = 0;
+    }
+
+    pub fn go_bottom(&mut self) {
+        self.cursor = self.matches.len().saturating_sub(1);
+    }
+}
+
+/// Every marker line in the text files under `root`, ordered by path and line.
+fn scan_tree(root: &Path) -> Vec<TodoMatch> {
+    let mut matches = Vec::new();
+    let mut stack = vec![root.to_path_buf()];
+    while let Some(dir) = stack.pop() {
+        let Ok(entries) = fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let Ok(file_type) = entry.file_type() else {
+                continue;
+            };
+            let path = entry.path();
+            if file_type.is_dir() {
+                let name = entry.file_name();
+                if !SKIPPED_DIRS.iter().any(|s| name == *s) {
+                    stack.push(path);
+                }
+            } else if file_type.is_file()
+                && entry.metadata().is_ok_and(|m| m.len() <= MAX_FILE_SIZE)
+            {
+                scan_file(&path, &mut matches);
+            }
+        }
+    }
+    matches.sort_by(|a, b| a.path.cmp(&b.path).then(a.line.cmp(&b.line)));
+    matches
+}
+
+fn scan_file(path: &Path, matches: &mut Vec<TodoMatch>) {
+    let Ok(bytes) = fs::read(path) else {
+        return;
+    };
+    // a NUL byte near the start means a binary file
+    if bytes.iter().take(8192).any(|&b| b == 0) {
+        return;
+    }
+    let text = String::from_utf8_lossy(&bytes);
+    for (i, line) in text.lines().enumerate() {
+        if let Some(marker) = MARKERS.iter().copied().find(|m| contains_word(line, m)) {
+            matches.push(TodoMatch {
+                path: path.to_path_buf(),
+                line: i + 1,
+                marker,
+                text: line.trim().to_string(),
+            });
+        }
+    }
+}
+
+/// Whether `word` occurs in `line` not surrounded by other identifier characters.
+fn contains_word(line: &str, word: &str) -> bool {
+    let is_ident = |c: char| c.is_alphanumeric() || c == '_';
+    line.match_indices(word).any(|(at, _)| {
+        let before = line[..at].chars().next_back();
+        let after = line[at + word.len()..].chars().next();
+        !before.is_some_and(is_ident) && !after.is_some_and(is_ident)
+    })
+}
@@ -0,0 +1,97 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use crate::fs_utils::percent_encode;
+
+/// Move `path` to the desktop's trash (the Recycle Bin on Windows), where it can be restored.
+pub fn trash(path: &Path) -> io::Result<()> {
+    let path = std::path::absolute(path)?;
+    if cfg!(windows) {
+        // the path goes through the environment, so no quoting is needed
+        let script = "Add-Type -AssemblyName Microsoft.VisualBasic; \
+            $p = $env:KURA_TRASH_PATH; \
+            if (Test-Path -LiteralPath $p -PathType Container) { \
+            [Microsoft.VisualBasic.FileIO.FileSystem]::DeleteDirectory($p, 'OnlyErrorDialogs', 'SendToRecycleBin') \
+            } else { \
+            [Microsoft.VisualBasic.FileIO.FileSystem]::DeleteFile($p, 'OnlyErrorDialogs', 'SendToRecycleBin') }";
+        run(Command::new("powershell")
+            .args(["-NoProfile", "-NonInteractive", "-Command", script])
+            .env("KURA_TRASH_PATH", &path))
+    } else if cfg!(target_os = "macos") {
+        run(Command::new("osascript")
+            .args([
+                "-e",
+                "tell application \"Finder\" to delete (POSIX file (system attribute \"KURA_TRASH_PATH\"))",
+            ])
+            .env("KURA_TRASH_PATH", &path))
+    } else {
+        freedesktop_trash(&path)
+    }
+}
+
+fn run(command: &mut Command) -> io::Result<()> {
+    let output = command.output()?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(io::Error::other(
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ))
+    }
+}
+
+/// Follow the freedesktop.org trash specification: move the file into `Trash/files` and
+/// record where it came from in `Trash/info`.
+fn freedesktop_trash(path: &Path) -> io::Result<()> {
+    let trash = std::env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|h| PathBuf::from(h).join(".local/share")))
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no home directory"))?
+        .join("Trash");
+    let (files, info) = (trash.join("files"), trash.join("info"));
+    fs::create_dir_all(&files)?;
+    fs::create_dir_all(&info)?;
+
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let mut stored = name.to_string();
+    let mut n = 1;
+    // reserving the info file first claims the name against other programs trashing at once
+    let info_file = loop {
+        let candidate = info.join(format!("{stored}.trashinfo"));
+        match fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&candidate)
+        {
+            Ok(_) if !files.join(&stored).exists() => break candidate,
+            Ok(_) => {
+                let _ = fs::remove_file(&candidate);
+            }
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {}
+            Err(e) => return Err(e),
+        }
+        n += 1;
+        stored = format!("{name}.{n}");
+    };
+    let record = format!(
+        "[Trash Info]\nPath={}\nDeletionDate={}\n",
+        percent_encode(&path.to_string_lossy()),
+        chrono::Local::now().format("%Y-%m-%dT%H:%M:%S")
+    );
+    let result = fs::write(&info_file, record).and_then(|()| {
+        fs::rename(path, files.join(&stored)).map_err(|e| {
+            if e.kind() == io::ErrorKind::CrossesDevices {
+                io::Error::other("the trash is on another file system")
+            } else {
+                e
+            }
+        })
+    });
+    if result.is_err() {
+        let _ = fs::remove_file(&info_file);
+    }
+    result
+}
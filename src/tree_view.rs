@@ -0,0 +1,218 @@
+use std::{collections::HashSet, fs, path::Path};
+
+use serde_json::Value;
+
+/// Kind of scalar, used for coloring.
+#[derive(Clone, Copy, PartialEq)]
+pub enum ScalarKind {
+    String,
+    Number,
+    /// `true`, `false` and `null`
+    Literal,
+}
+
+pub enum NodeKind {
+    /// Start of an object or array; `close` is the index of its closing node
+    Open {
+        bracket: char,
+        close: usize,
+        len: usize,
+    },
+    Close(char),
+    Scalar(String, ScalarKind),
+}
+
+/// One line of the expanded document.
+pub struct Node {
+    pub depth: usize,
+    pub key: Option<String>,
+    pub kind: NodeKind,
+    /// Followed by a sibling, so printed with a trailing comma
+    pub comma: bool,
+}
+
+/// A JSON or YAML document pretty-printed as a tree whose objects and arrays can be folded.
+pub struct TreeView {
+    pub title: String,
+    pub nodes: Vec<Node>,
+    /// Indices of the `Open` nodes that are folded
+    pub folded: HashSet<usize>,
+    /// Indices into `nodes` of the lines currently shown
+    pub visible: Vec<usize>,
+    /// Position of the cursor within `visible`
+    pub cursor: usize,
+    /// `z` was pressed and the fold command letter is expected next
+    pub pending_z: bool,
+}
+
+impl TreeView {
+    /// Parse `path` as JSON or YAML by its extension; `None` if it does not parse.
+    pub fn open(path: &Path) -> Option<Self> {
+        let text = fs::read_to_string(path).ok()?;
+        let value: Value = if is_yaml(path) {
+            serde_yaml::from_str(&text).ok()?
+        } else {
+            serde_json::from_str(&text).ok()?
+        };
+        let mut nodes = Vec::new();
+        flatten(&value, None, 0, false, &mut nodes);
+        let title = path
+            .file_name()
+            .map(|os_str| os_str.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let mut view = Self {
+            title,
+            nodes,
+            folded: HashSet::new(),
+            visible: Vec::new(),
+            cursor: 0,
+            pending_z: false,
+        };
+        view.update_visible();
+        Some(view)
+    }
+
+    /// Handle the letter following `z`: `a` toggles, `o` opens and `c` closes the fold under
+    /// the cursor; `R` opens and `M` closes every fold.
+    pub fn fold_command(&mut self, c: char) {
+        let Some(&node) = self.visible.get(self.cursor) else {
+            return;
+        };
+        // on a closing line, act on the object or array it closes
+        let node = match self.nodes[node].kind {
+            NodeKind::Close(_) => self.opening_of(node).unwrap_or(node),
+            _ => node,
+        };
+        let is_open = matches!(self.nodes[node].kind, NodeKind::Open { .. });
+        match c {
+            'a' if is_open => {
+                if !self.folded.remove(&node) {
+                    self.folded.insert(node);
+                }
+            }
+            'o' => {
+                self.folded.remove(&node);
+            }
+            'c' if is_open => {
+                self.folded.insert(node);
+            }
+            'R' => self.folded.clear(),
+            // everything below the root
+            'M' => self.folded = (1..self.nodes.len()).filter(|&i| self.is_open(i)).collect(),
+            _ => return,
+        }
+        self.update_visible();
+        self.cursor = self
+            .visible
+            .iter()
+            .position(|&i| i >= node)
+            .unwrap_or(0)
+            .min(self.visible.len().saturating_sub(1));
+        // folding all may hide the cursor's node: stay on the closest line above
+        if self.visible.get(self.cursor).is_some_and(|&i| i > node) {
+            self.cursor = self.cursor.saturating_sub(1);
+        }
+    }
+
+    fn is_open(&self, i: usize) -> bool {
+        matches!(self.nodes[i].kind, NodeKind::Open { .. })
+    }
+
+    fn opening_of(&self, close: usize) -> Option<usize> {
+        (0..close)
+            .rev()
+            .find(|&i| matches!(self.nodes[i].kind, NodeKind::Open { close: c, .. } if c == close))
+    }
+
+    fn update_visible(&mut self) {
+        self.visible.clear();
+        let mut i = 0;
+        while i < self.nodes.len() {
+            self.visible.push(i);
+            i = match self.nodes[i].kind {
+                NodeKind::Open { close, .. } if self.folded.contains(&i) => close + 1,
+                _ => i + 1,
+            };
+        }
+    }
+
+    pub fn down(&mut self, count: usize) {
+        self.cursor = (self.cursor + count).min(self.visible.len().saturating_sub(1));
+    }
+
+    pub fn up(&mut self, count: usize) {
+        self.cursor = self.cursor.saturating_sub(count);
+    }
+
+    pub fn go_top(&mut self) {
+        self.cursor = 0;
+    }
+
+    pub fn go_bottom(&mut self) {
+        self.cursor = self.visible.len().saturating_sub(1);
+    }
+}
+
+fn flatten(value: &Value, key: Option<String>, depth: usize, comma: bool, nodes: &mut Vec<Node>) {
+    let (bracket, closing, children): (char, char, Vec<(Option<String>, &Value)>) = match value {
+        Value::Object(map) => (
+            '{',
+            '}',
+            map.iter().map(|(k, v)| (Some(k.clone()), v)).collect(),
+        ),
+        Value::Array(items) => ('[', ']', items.iter().map(|v| (None, v)).collect()),
+        scalar => {
+            let (text, kind) = match scalar {
+                Value::String(s) => (Value::String(s.clone()).to_string(), ScalarKind::String),
+                Value::Number(n) => (n.to_string(), ScalarKind::Number),
+                other => (other.to_string(), ScalarKind::Literal),
+            };
+            nodes.push(Node {
+                depth,
+                key,
+                kind: NodeKind::Scalar(text, kind),
+                comma,
+            });
+            return;
+        }
+    };
+    let open = nodes.len();
+    nodes.push(Node {
+        depth,
+        key,
+        kind: NodeKind::Open {
+            bracket,
+            close: 0,
+            len: children.len(),
+        },
+        comma,
+    });
+    let last = children.len().saturating_sub(1);
+    for (i, (k, v)) in children.into_iter().enumerate() {
+        flatten(v, k, depth + 1, i < last, nodes);
+    }
+    let close = nodes.len();
+    nodes.push(Node {
+        depth,
+        key: None,
+        kind: NodeKind::Close(closing),
+        comma,
+    });
+    if let NodeKind::Open { close: c, .. } = &mut nodes[open].kind {
+        *c = close;
+    }
+}
+
+fn is_yaml(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| e.eq_ignore_ascii_case("yaml") || e.eq_ignore_ascii_case("yml"))
+}
+
+/// `.json`, `.yaml` and `.yml` files.
+pub fn is_structured(path: &Path) -> bool {
+    is_yaml(path)
+        || path
+            .extension()
+            .is_some_and(|e| e.eq_ignore_ascii_case("json"))
+}
@@ -3,11 +3,44 @@ use ratatui::{
     layout::{Alignment, Constraint, Direction, Layout, Rect},
     style::{Color, Modifier, Style},
     text::{Span, Spans},
-    widgets::{Block, Borders, Clear, List, ListItem, ListState, Paragraph},
+    widgets::{Block, Borders, Clear, List, ListItem, ListState, Paragraph, Wrap},
     Frame,
 };
 
-use crate::{app::App, app::Pane, mode::Mode, mode::PaneType};
+use kura_core::{
+    actions::validate_new_entry,
+    app::App,
+    app::{Pane, PAGE_SIZE},
+    checksum::Outcome,
+    columns::Preview,
+    diff::{DiffView, LineKind},
+    format::{human_size, thousands},
+    fs_utils::{validate_name, validate_path, NameProblem, SizeDisplay, SortBy},
+    git::GitStatus,
+    heat::{blend, Heat},
+    image_ops::IMAGE_ACTIONS,
+    links::LinkSearch,
+    log_view::{Level, LogViewer},
+    mode::PaneType,
+    mode::{Mode, TypeToConfirm},
+    notification::Severity,
+    predicate::Predicate,
+    rsync::Transfer,
+    runner::fill_template,
+    sync::CompareStatus,
+    table_view::TableView,
+    tasks::JobStatus,
+    todo_scan::TodoList,
+    tree_view::{NodeKind, ScalarKind, TreeView},
+    vfs::{Entry, EntryChange},
+    viewer::Viewer,
+};
+use serde_json::Value;
+use std::{
+    fs,
+    path::{Component, PathBuf, MAIN_SEPARATOR},
+    time::SystemTime,
+};
 use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
 
 pub fn ui<B: Backend>(f: &mut Frame<B>, app: &App) {
@@ -17,7 +50,7 @@ pub fn ui<B: Backend>(f: &mut Frame<B>, app: &App) {
         .constraints([Constraint::Length(1), Constraint::Min(0)])
         .split(size);
 
-    let header = Paragraph::new(Spans::from(vec![
+    let mut title = vec![
         Span::styled(
             "蔵",
             Style::default()
@@ -26,101 +59,258 @@ pub fn ui<B: Backend>(f: &mut Frame<B>, app: &App) {
         ),
         Span::raw(" "),
         Span::styled("kura", Style::default().add_modifier(Modifier::BOLD)),
-    ]))
-    .alignment(Alignment::Center);
+    ];
+    if app.dry_run {
+        title.push(Span::styled(
+            " [dry run]",
+            Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+        ));
+    }
+    let header = Paragraph::new(Spans::from(title)).alignment(Alignment::Center);
     f.render_widget(header, chunks[0]);
-
-    let (content_area, footer_area) =
-        if matches!(app.mode, Mode::Search { .. } | Mode::Rename { .. }) {
-            let v = Layout::default()
-                .direction(Direction::Vertical)
-                .constraints([Constraint::Min(0), Constraint::Length(1)])
-                .split(chunks[1]);
-            (v[0], Some(v[1]))
-        } else {
-            (chunks[1], None)
-        };
-
-    if let Mode::Viewer {
-        content,
-        title,
-        offset,
-    } = &app.mode
+    if let Some(job) = app
+        .tasks
+        .jobs
+        .iter()
+        .find(|j| matches!(j.status, JobStatus::Running(_) | JobStatus::Transferring(_)))
     {
-        let block = Block::default().borders(Borders::ALL).title(title.as_str());
-        // available rows and margin width
-        let inner_height = content_area.height.saturating_sub(2) as usize;
-        let number_width = inner_height.to_string().len().max(1);
-        // wrap each content line into display rows of at most (width - margin) cols
-        let text_width = content_area.width.saturating_sub((number_width + 1) as u16) as usize;
-        let mut rows: Vec<String> = Vec::new();
-        for line in content.lines() {
-            // if the line fits, push as-is
-            if UnicodeWidthStr::width(line) <= text_width {
-                rows.push(line.to_string());
-            } else {
-                let mut s = line;
-                // break into segments that fit
-                while UnicodeWidthStr::width(s) > text_width {
-                    let mut w = 0;
-                    let mut end = 0;
-                    for (i, ch) in s.char_indices() {
-                        let cw = ch.width().unwrap_or(0);
-                        if w + cw > text_width {
-                            break;
-                        }
-                        w += cw;
-                        end = i + ch.len_utf8();
-                    }
-                    if end == 0 {
-                        let first = s.chars().next().unwrap();
-                        let len = first.len_utf8();
-                        rows.push(s[..len].to_string());
-                        s = &s[len..];
-                    } else {
-                        rows.push(s[..end].to_string());
-                        s = &s[end..];
-                    }
-                }
-                if !s.is_empty() {
-                    rows.push(s.to_string());
-                }
-            }
-        }
-        let total_rows = rows.len();
-        let max_off = total_rows.saturating_sub(inner_height);
-        let start = (*offset as usize).min(max_off) as usize;
-        let numbered: Vec<Spans> = rows
-            .iter()
-            .skip(start)
-            .take(inner_height)
-            .enumerate()
-            .map(|(i, row)| {
-                let num = format!("{:>width$} ", i, width = number_width);
-                Spans::from(vec![
-                    Span::styled(num, Style::default().fg(Color::DarkGray)),
-                    Span::raw(row),
-                ])
+        let (label, color) = status_label(&job.status, job.total);
+        let running = Paragraph::new(Spans::from(vec![
+            Span::raw(format!(" {} ", job.description)),
+            Span::styled(format!("[{label}]"), Style::default().fg(color)),
+        ]));
+        f.render_widget(running, chunks[0]);
+    }
+    if let Some(space) = app.current_pane().space {
+        let free = Paragraph::new(format!(
+            "{} free of {} ",
+            human_size(space.free),
+            human_size(space.total)
+        ))
+        .style(Style::default().fg(Color::DarkGray))
+        .alignment(Alignment::Right);
+        f.render_widget(free, chunks[0]);
+    }
+
+    let (content_area, footer_area) = if matches!(
+        app.mode,
+        Mode::Search { .. }
+            | Mode::Rename { .. }
+            | Mode::PasteAs { .. }
+            | Mode::Create { .. }
+            | Mode::MarkPattern { .. }
+            | Mode::MarkWhere { .. }
+            | Mode::Filter { .. }
+            | Mode::Location { .. }
+            | Mode::Run { .. }
+            | Mode::ForEach { .. }
+            | Mode::Watch { .. }
+            | Mode::HardLinkScope { .. }
+            | Mode::Xattr { .. }
+            | Mode::ArchivePassword { .. }
+            | Mode::ResizeImages { .. }
+            | Mode::Viewer(Viewer {
+                editing: Some(_),
+                ..
             })
-            .collect();
-        let paragraph = Paragraph::new(numbered).block(block);
-        f.render_widget(paragraph, content_area);
+    ) || app.notifications.current().is_some()
+    {
+        let v = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(0), Constraint::Length(1)])
+            .split(chunks[1]);
+        (v[0], Some(v[1]))
     } else {
-        let panes = Layout::default()
+        (chunks[1], None)
+    };
+
+    if let Mode::Viewer(viewer) = &app.mode {
+        draw_viewer(f, content_area, viewer, false);
+    } else if let Mode::Diff(diff) = &app.mode {
+        draw_diff(f, content_area, diff);
+    } else if let Mode::LogViewer(log) = &app.mode {
+        draw_log_viewer(f, content_area, log);
+    } else if let Mode::Tree(tree) = &app.mode {
+        draw_tree(f, content_area, tree);
+    } else if let Mode::Table(table) = &app.mode {
+        draw_table(f, content_area, table);
+    } else if let Mode::Todos(list) = &app.mode {
+        draw_todos(f, content_area, list);
+    } else if let Mode::Compare { left, right, sync } = &app.mode {
+        let halves = Layout::default()
             .direction(Direction::Horizontal)
             .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
             .split(content_area);
-        draw_pane(f, panes[0], &app.left, app.active == PaneType::Left);
-        draw_pane(f, panes[1], &app.right, app.active == PaneType::Right);
+        for (area, viewer, side) in [
+            (halves[0], left, PaneType::Left),
+            (halves[1], right, PaneType::Right),
+        ] {
+            let focused = *sync || app.active == side;
+            draw_viewer(f, area, viewer, focused);
+        }
+    } else {
+        let layout = app.layout;
+        // a pane that is not drawn has no breadcrumbs to click
+        app.left.title_area.set(None);
+        app.right.title_area.set(None);
+        if layout.columns {
+            let columns = Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints([
+                    Constraint::Percentage(20),
+                    Constraint::Percentage(40),
+                    Constraint::Percentage(40),
+                ])
+                .split(content_area);
+            draw_parent_column(f, columns[0], app);
+            draw_pane(f, columns[1], app, app.current_pane(), true);
+            draw_preview_column(f, columns[2], app);
+        } else if layout.single {
+            draw_pane(f, content_area, app, app.current_pane(), true);
+        } else {
+            let panes = Layout::default()
+                .direction(if layout.stacked {
+                    Direction::Vertical
+                } else {
+                    Direction::Horizontal
+                })
+                .constraints([
+                    Constraint::Percentage(layout.ratio),
+                    Constraint::Percentage(100 - layout.ratio),
+                ])
+                .split(content_area);
+            draw_pane(f, panes[0], app, &app.left, app.active == PaneType::Left);
+            draw_pane(f, panes[1], app, &app.right, app.active == PaneType::Right);
+        }
     }
 
-    if let Mode::ConfirmDelete { items } = &app.mode {
+    if let Mode::ConfirmDelete { items, protected } = &app.mode {
         let popup = centered_rect(40, 20, f.size());
         let block = Block::default()
             .title("Confirm Deletion")
             .borders(Borders::ALL);
-        let prompt = format!("Delete {} item(s)? (y/N)", items.len());
-        let paragraph = Paragraph::new(prompt)
+        let mut lines = vec![Spans::from(format!("Delete {} item(s)?", items.len()))];
+        lines.extend(confirm_prompt(protected));
+        let paragraph = Paragraph::new(lines)
+            .block(block)
+            .alignment(Alignment::Center);
+        f.render_widget(Clear, popup);
+        f.render_widget(paragraph, popup);
+    }
+
+    if let Mode::ConfirmShred { items, protected } = &app.mode {
+        let popup = centered_rect(50, 25, f.size());
+        let block = Block::default()
+            .title("Confirm Secure Wipe")
+            .borders(Borders::ALL);
+        let mut lines = vec![
+            Spans::from(format!(
+                "Overwrite {} item(s) {} times and delete them?",
+                items.len(),
+                app.config.shred_passes
+            )),
+            Spans::from(Span::styled(
+                "They cannot be restored, not even from the trash.",
+                Style::default().fg(Color::Red),
+            )),
+            Spans::from(Span::styled(
+                "SSDs and copy-on-write file systems may keep old copies.",
+                Style::default().fg(Color::DarkGray),
+            )),
+        ];
+        lines.extend(confirm_prompt(protected));
+        let paragraph = Paragraph::new(lines)
+            .block(block)
+            .alignment(Alignment::Center);
+        f.render_widget(Clear, popup);
+        f.render_widget(paragraph, popup);
+    }
+
+    if let Mode::ConfirmElevate { retry, reason } = &app.mode {
+        let popup = centered_rect(60, 25, f.size());
+        let block = Block::default()
+            .title("Permission Denied")
+            .borders(Borders::ALL);
+        let lines = vec![
+            Spans::from(format!("Cannot {}:", retry.describe())),
+            Spans::from(Span::styled(
+                reason.as_str(),
+                Style::default().fg(Color::Red),
+            )),
+            Spans::from(""),
+            Spans::from("Retry as root with"),
+            Spans::from(retry.command_line(&app.config.elevate_with)),
+            Spans::from("(y/N)"),
+        ];
+        let paragraph = Paragraph::new(lines)
+            .block(block)
+            .alignment(Alignment::Center)
+            .wrap(Wrap { trim: false });
+        f.render_widget(Clear, popup);
+        f.render_widget(paragraph, popup);
+    }
+
+    if let Mode::ConfirmResume {
+        sources,
+        dest,
+        done,
+    } = &app.mode
+    {
+        let popup = centered_rect(60, 25, f.size());
+        let block = Block::default().title("Resume Copy").borders(Borders::ALL);
+        let lines = vec![
+            Spans::from(format!(
+                "Copying {} item(s) to {} was interrupted",
+                sources.len(),
+                dest.display()
+            )),
+            Spans::from(format!("after {done} file(s).")),
+            Spans::from(""),
+            Spans::from("y: resume, skipping the files copied unchanged"),
+            Spans::from("n: copy everything again"),
+            Spans::from("Esc: cancel"),
+        ];
+        let paragraph = Paragraph::new(lines)
+            .block(block)
+            .alignment(Alignment::Center)
+            .wrap(Wrap { trim: false });
+        f.render_widget(Clear, popup);
+        f.render_widget(paragraph, popup);
+    }
+
+    if let Mode::ConfirmTransfer {
+        sources,
+        dest,
+        move_items,
+        conflicts,
+        protected,
+        hard_links,
+        ..
+    } = &app.mode
+    {
+        let popup = centered_rect(50, 25, f.size());
+        let verb = if *move_items { "Move" } else { "Copy" };
+        let block = Block::default()
+            .title(format!("Confirm {verb}"))
+            .borders(Borders::ALL);
+        let mut lines = vec![
+            Spans::from(format!("{verb} {} item(s) to", sources.len())),
+            Spans::from(dest.display().to_string()),
+        ];
+        if *conflicts > 0 {
+            lines.push(Spans::from(Span::styled(
+                format!("{conflicts} existing item(s) will be overwritten"),
+                Style::default().fg(Color::Red),
+            )));
+        }
+        lines.extend(confirm_prompt(protected));
+        if *hard_links {
+            lines.push(Spans::from(Span::styled(
+                "h: hard link instead, on the same file system",
+                Style::default().fg(Color::DarkGray),
+            )));
+        }
+        let paragraph = Paragraph::new(lines)
             .block(block)
             .alignment(Alignment::Center);
         f.render_widget(Clear, popup);
@@ -129,17 +319,491 @@ pub fn ui<B: Backend>(f: &mut Frame<B>, app: &App) {
 
     if let Mode::Sort { selected } = &app.mode {
         let popup = centered_rect(40, 20, f.size());
-        let block = Block::default().title("Sort By").borders(Borders::ALL);
-        let items: Vec<ListItem> = crate::fs_utils::SORT_OPTIONS
+        let block = Block::default()
+            .title("Sort By (Tab: then by)")
+            .borders(Borders::ALL);
+        let spec = app.current_pane().sort_by.unwrap_or_default();
+        let items: Vec<ListItem> = kura_core::fs_utils::SORT_OPTIONS
             .iter()
+            .zip(SortBy::ALL)
             .enumerate()
-            .map(|(i, option)| {
+            .map(|(i, (option, by))| {
                 let style = if i == *selected {
                     Style::default().add_modifier(Modifier::REVERSED)
                 } else {
                     Style::default()
                 };
-                ListItem::new(Spans::from(Span::styled(*option, style)))
+                // the keys in use, first and second
+                let rank = if by == spec.primary {
+                    "1 "
+                } else if spec.secondary == Some(by) {
+                    "2 "
+                } else {
+                    "  "
+                };
+                ListItem::new(Spans::from(vec![
+                    Span::styled(rank, Style::default().fg(Color::Cyan)),
+                    Span::styled(*option, style),
+                ]))
+            })
+            .collect();
+        let list = List::new(items).block(block);
+        f.render_widget(Clear, popup);
+        f.render_widget(list, popup);
+    }
+
+    if let Mode::ImageActions { paths, selected } = &app.mode {
+        let popup = centered_rect(40, 30, f.size());
+        let block = Block::default()
+            .title(format!("{} image(s)", paths.len()))
+            .borders(Borders::ALL);
+        let items: Vec<ListItem> = IMAGE_ACTIONS
+            .iter()
+            .map(|action| ListItem::new(*action))
+            .collect();
+        let mut state = ListState::default();
+        state.select(Some(*selected));
+        let list = List::new(items)
+            .block(block)
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+        f.render_widget(Clear, popup);
+        f.render_stateful_widget(list, popup, &mut state);
+    }
+
+    if let Mode::Share { paths, selected } = &app.mode {
+        let popup = centered_rect(60, 40, f.size());
+        let block = Block::default()
+            .title(format!(
+                "Share {} item(s)  Enter/1-9: send, Esc: close",
+                paths.len()
+            ))
+            .borders(Borders::ALL);
+        let items: Vec<ListItem> = app
+            .config
+            .shares
+            .iter()
+            .enumerate()
+            .map(|(i, share)| {
+                let number = if i < 9 {
+                    format!("{} ", i + 1)
+                } else {
+                    "  ".to_string()
+                };
+                ListItem::new(Spans::from(vec![
+                    Span::styled(number, Style::default().fg(Color::Yellow)),
+                    Span::raw(format!("{}  ", share.name)),
+                    Span::styled(share.run.as_str(), Style::default().fg(Color::DarkGray)),
+                ]))
+            })
+            .collect();
+        let mut state = ListState::default();
+        state.select(Some(*selected));
+        let list = List::new(items)
+            .block(block)
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+        f.render_widget(Clear, popup);
+        f.render_stateful_widget(list, popup, &mut state);
+    }
+
+    if let Mode::Mounts { mounts, selected } = &app.mode {
+        let popup = centered_rect(70, 50, f.size());
+        let block = Block::default()
+            .title("Mounts  Enter: go, Esc: close")
+            .borders(Borders::ALL);
+        let width = mounts.iter().map(|m| m.label().width()).max().unwrap_or(0);
+        let items: Vec<ListItem> = mounts
+            .iter()
+            .map(|m| {
+                let label = m.label();
+                let pad = " ".repeat(width - label.width());
+                let space = match m.space {
+                    _ if m.mount_with.is_some() => format!("{:>25}", "Enter: mount"),
+                    Some(s) => format!(
+                        "{:>9} free of {:>9}",
+                        human_size(s.free),
+                        human_size(s.total)
+                    ),
+                    None => format!("{:>25}", "?"),
+                };
+                ListItem::new(Spans::from(vec![
+                    Span::raw(format!("{label}{pad}  ")),
+                    Span::styled(space, Style::default().fg(Color::Cyan)),
+                    Span::styled(
+                        if m.is_device() {
+                            format!("  {}", m.fs_type)
+                        } else {
+                            format!("  {} {}", m.fs_type, m.device)
+                        },
+                        Style::default().fg(Color::DarkGray),
+                    ),
+                ]))
+            })
+            .collect();
+        let mut state = ListState::default();
+        state.select(Some(*selected));
+        let list = List::new(items)
+            .block(block)
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+        f.render_widget(Clear, popup);
+        f.render_stateful_widget(list, popup, &mut state);
+    }
+
+    if let Mode::Basket { selected } = &app.mode {
+        let popup = centered_rect(70, 50, f.size());
+        let block = Block::default()
+            .title(format!(
+                "Basket ({})  p: copy here, P: move here, x: delete, d: trash, Del: remove, c: clear, Enter: go",
+                app.basket.len()
+            ))
+            .borders(Borders::ALL);
+        let items: Vec<ListItem> = app
+            .basket
+            .iter()
+            .map(|p| ListItem::new(p.display().to_string()))
+            .collect();
+        let mut state = ListState::default();
+        state.select(Some(*selected));
+        let list = List::new(items)
+            .block(block)
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+        f.render_widget(Clear, popup);
+        f.render_stateful_widget(list, popup, &mut state);
+    }
+
+    if let Mode::Sync { to_right, to_left } = &app.mode {
+        let popup = centered_rect(60, 50, f.size());
+        let block = Block::default()
+            .title(format!(
+                "Sync (dry run): {} to right, {} to left  y: copy, Esc: cancel",
+                to_right.len(),
+                to_left.len()
+            ))
+            .borders(Borders::ALL);
+        let name = |p: &std::path::PathBuf| {
+            p.file_name()
+                .map(|n| n.to_string_lossy().into_owned())
+                .unwrap_or_default()
+        };
+        let mut items: Vec<ListItem> = to_right
+            .iter()
+            .map(|p| ListItem::new(format!("→ {}", name(p))))
+            .collect();
+        items.extend(
+            to_left
+                .iter()
+                .map(|p| ListItem::new(format!("← {}", name(p)))),
+        );
+        if items.is_empty() {
+            items.push(ListItem::new("Directories are in sync"));
+        }
+        let list = List::new(items).block(block);
+        f.render_widget(Clear, popup);
+        f.render_widget(list, popup);
+    }
+
+    if let Mode::Jump { query, selected } = &app.mode {
+        let popup = centered_rect(60, 50, f.size());
+        let block = Block::default()
+            .title(format!("Jump: {query}"))
+            .borders(Borders::ALL);
+        let cursor = "Jump: ".width() + query.cursor_width();
+        f.set_cursor(popup.x + 1 + cursor as u16, popup.y);
+        let found = app.history.query(query);
+        let items: Vec<ListItem> = if found.is_empty() {
+            vec![ListItem::new("No matching directories")]
+        } else {
+            found
+                .iter()
+                .take(popup.height.saturating_sub(2) as usize)
+                .map(|p| ListItem::new(p.display().to_string()))
+                .collect()
+        };
+        let mut state = ListState::default();
+        if !found.is_empty() {
+            state.select(Some((*selected).min(found.len() - 1)));
+        }
+        let list = List::new(items)
+            .block(block)
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+        f.render_widget(Clear, popup);
+        f.render_stateful_widget(list, popup, &mut state);
+    }
+
+    if let Mode::Find(finder) = &app.mode {
+        let popup = centered_rect(70, 60, f.size());
+        let status = if finder.index.is_scanning() {
+            "scanning…".to_string()
+        } else {
+            format!("{} found", finder.results.len())
+        };
+        let prefix = format!("Find in {}: ", finder.dir.display());
+        let block = Block::default()
+            .title(format!("{prefix}{}", finder.query))
+            .title(Span::styled(
+                format!(" {status}, ^R: rescan "),
+                Style::default().fg(Color::DarkGray),
+            ))
+            .borders(Borders::ALL);
+        let cursor = prefix.width() + finder.query.cursor_width();
+        f.set_cursor(popup.x + 1 + cursor as u16, popup.y);
+        let items: Vec<ListItem> = if finder.results.is_empty() {
+            vec![ListItem::new("No matching files")]
+        } else {
+            finder
+                .results
+                .iter()
+                .map(|p| {
+                    let relative = p.strip_prefix(&finder.dir).unwrap_or(p);
+                    ListItem::new(relative.display().to_string())
+                })
+                .collect()
+        };
+        let mut state = ListState::default();
+        if !finder.results.is_empty() {
+            state.select(Some(finder.selected.min(finder.results.len() - 1)));
+        }
+        let list = List::new(items)
+            .block(block)
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+        f.render_widget(Clear, popup);
+        f.render_stateful_widget(list, popup, &mut state);
+    }
+
+    if let Mode::Checksums(report) = &app.mode {
+        let popup = centered_rect(70, 60, f.size());
+        let block = Block::default()
+            .title(format!(
+                "{}: {}/{} checked, {} failed (Enter: go to file, Esc: close)",
+                report
+                    .list
+                    .file_name()
+                    .unwrap_or_default()
+                    .to_string_lossy(),
+                report.checked(),
+                report.entries.len(),
+                report.failed()
+            ))
+            .borders(Borders::ALL);
+        let items: Vec<ListItem> = report
+            .entries
+            .iter()
+            .zip(&report.outcomes)
+            .map(|(entry, outcome)| {
+                let (label, color) = match outcome {
+                    None => ("…", Color::DarkGray),
+                    Some(o @ Outcome::Match) => (o.label(), Color::Green),
+                    Some(o @ Outcome::Missing) => (o.label(), Color::Yellow),
+                    Some(o) => (o.label(), Color::Red),
+                };
+                let mut spans = vec![
+                    Span::styled(format!("{label:<8}"), Style::default().fg(color)),
+                    Span::raw(entry.name.clone()),
+                ];
+                if let Some(Outcome::Unreadable(e)) = outcome {
+                    spans.push(Span::styled(
+                        format!("  {e}"),
+                        Style::default().fg(Color::DarkGray),
+                    ));
+                }
+                ListItem::new(Spans::from(spans))
+            })
+            .collect();
+        let mut state = ListState::default();
+        state.select(Some(report.selected));
+        let list = List::new(items)
+            .block(block)
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+        f.render_widget(Clear, popup);
+        f.render_stateful_widget(list, popup, &mut state);
+    }
+
+    if let Mode::Links(links) = &app.mode {
+        let popup = centered_rect(70, 60, f.size());
+        let found = match &links.search {
+            LinkSearch::Broken => format!("{} found", links.links.len()),
+            LinkSearch::HardLinks { count, .. } => {
+                format!("{} of {} other name(s) found", links.links.len(), count - 1)
+            }
+        };
+        let status = if links.is_scanning() {
+            format!("scanning, {found}")
+        } else {
+            found
+        };
+        let what = match &links.search {
+            LinkSearch::Broken => "Broken links".to_string(),
+            LinkSearch::HardLinks { of, .. } => format!(
+                "Hard links of {}",
+                of.file_name().unwrap_or_default().to_string_lossy()
+            ),
+        };
+        let block = Block::default()
+            .title(format!(
+                "{what} in {}: {status} (Enter: go to, d: delete, D: delete all)",
+                links.dir.file_name().map_or_else(
+                    || links.dir.display().to_string(),
+                    |n| n.to_string_lossy().into_owned()
+                )
+            ))
+            .borders(Borders::ALL);
+        let items: Vec<ListItem> = if links.links.is_empty() && !links.is_scanning() {
+            vec![ListItem::new(match links.search {
+                LinkSearch::Broken => "No broken links",
+                LinkSearch::HardLinks { .. } => "No other names in this directory",
+            })]
+        } else {
+            links
+                .links
+                .iter()
+                .map(|p| {
+                    let relative = p.strip_prefix(&links.dir).unwrap_or(p);
+                    let mut spans = vec![Span::raw(relative.display().to_string())];
+                    if links.search == LinkSearch::Broken {
+                        let target = fs::read_link(p).unwrap_or_default();
+                        spans.push(Span::styled(
+                            format!(" → {}", target.display()),
+                            Style::default().fg(Color::Magenta),
+                        ));
+                    }
+                    ListItem::new(Spans::from(spans))
+                })
+                .collect()
+        };
+        let mut state = ListState::default();
+        if !links.links.is_empty() {
+            state.select(Some(links.selected.min(links.links.len() - 1)));
+        }
+        let list = List::new(items)
+            .block(block)
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+        f.render_widget(Clear, popup);
+        f.render_stateful_widget(list, popup, &mut state);
+    }
+
+    if let Mode::Message { title, lines } = &app.mode {
+        let popup = centered_rect(60, 30, f.size());
+        let block = Block::default().title(title.as_str()).borders(Borders::ALL);
+        let text: Vec<Spans> = lines.iter().map(|l| Spans::from(l.as_str())).collect();
+        let paragraph = Paragraph::new(text).block(block);
+        f.render_widget(Clear, popup);
+        f.render_widget(paragraph, popup);
+    }
+
+    if let Mode::Inspect {
+        lines,
+        video,
+        quarantined,
+        ..
+    } = &app.mode
+    {
+        let popup = centered_rect(60, if lines.len() > 6 { 60 } else { 30 }, f.size());
+        let mut title = String::from("Inspect (y: copy exact size");
+        if video.is_some() {
+            title.push_str(", t: thumbnail");
+        }
+        title.push_str(", a: set attribute");
+        if *quarantined {
+            title.push_str(", Q: remove quarantine");
+        }
+        title.push(')');
+        let block = Block::default().title(title).borders(Borders::ALL);
+        let text: Vec<Spans> = lines.iter().map(|l| Spans::from(l.as_str())).collect();
+        let paragraph = Paragraph::new(text).block(block);
+        f.render_widget(Clear, popup);
+        f.render_widget(paragraph, popup);
+    }
+
+    if let Mode::Notifications = &app.mode {
+        let popup = centered_rect(70, 50, f.size());
+        let block = Block::default()
+            .title("Notifications")
+            .borders(Borders::ALL);
+        let items: Vec<ListItem> = app
+            .notifications
+            .history()
+            .map(|n| {
+                let time = chrono::DateTime::<chrono::Local>::from(n.time).format("%H:%M:%S");
+                ListItem::new(Spans::from(vec![
+                    Span::styled(format!("{time} "), Style::default().fg(Color::DarkGray)),
+                    Span::styled(
+                        format!("{:<8}", n.severity.label()),
+                        Style::default().fg(severity_color(n.severity)),
+                    ),
+                    Span::raw(n.message.as_str()),
+                ]))
+            })
+            .collect();
+        let list = if items.is_empty() {
+            List::new(vec![ListItem::new("(nothing yet)")])
+        } else {
+            List::new(items)
+        };
+        f.render_widget(Clear, popup);
+        f.render_widget(list.block(block), popup);
+    }
+
+    if let Mode::Registers { selected } = &app.mode {
+        let popup = centered_rect(70, 50, f.size());
+        let block = Block::default()
+            .title("Registers (Enter: paste, 0-9/a-z: use with the next y, p or P)")
+            .borders(Borders::ALL);
+        let items: Vec<ListItem> = app
+            .registers
+            .list()
+            .into_iter()
+            .enumerate()
+            .map(|(i, (name, paths))| {
+                let style = if i == *selected {
+                    Style::default().add_modifier(Modifier::REVERSED)
+                } else {
+                    Style::default()
+                };
+                let names: Vec<String> = paths
+                    .iter()
+                    .filter_map(|p| p.file_name())
+                    .map(|n| n.to_string_lossy().into_owned())
+                    .collect();
+                ListItem::new(Spans::from(vec![
+                    Span::styled(format!("\"{name} "), Style::default().fg(Color::Yellow)),
+                    Span::styled(
+                        format!("{:>4} ", paths.len()),
+                        Style::default().fg(Color::DarkGray),
+                    ),
+                    Span::styled(names.join(", "), style),
+                ]))
+            })
+            .collect();
+        let list = if items.is_empty() {
+            List::new(vec![ListItem::new("(nothing yanked yet)")])
+        } else {
+            List::new(items)
+        };
+        f.render_widget(Clear, popup);
+        f.render_widget(list.block(block), popup);
+    }
+
+    if let Mode::Tasks { selected } = &app.mode {
+        let popup = centered_rect(60, 40, f.size());
+        let block = Block::default()
+            .title("Tasks (c: cancel, r: resume copy, C: clear finished, v: view report)")
+            .borders(Borders::ALL);
+        let items: Vec<ListItem> = app
+            .tasks
+            .jobs
+            .iter()
+            .enumerate()
+            .map(|(i, job)| {
+                let (label, color) = status_label(&job.status, job.total);
+                let style = if i == *selected {
+                    Style::default().add_modifier(Modifier::REVERSED)
+                } else {
+                    Style::default()
+                };
+                ListItem::new(Spans::from(vec![
+                    Span::styled(job.description.clone(), style),
+                    Span::raw(" "),
+                    Span::styled(format!("[{label}]"), Style::default().fg(color)),
+                ]))
             })
             .collect();
         let list = List::new(items).block(block);
@@ -148,69 +812,1213 @@ pub fn ui<B: Backend>(f: &mut Frame<B>, app: &App) {
     }
 
     if let Some(footer) = footer_area {
-        let prompt = match &app.mode {
-            Mode::Search { query } => format!("/{query}"),
-            Mode::Rename { original, buffer } => format!("rename: {original} -> {buffer}"),
-            _ => String::new(),
+        // the prompt text, and where the cursor goes in it; otherwise the status message
+        let (prompt, cursor) = match &app.mode {
+            Mode::Search { query, regex } => {
+                let mut spans = vec![Span::raw(format!("/{query}"))];
+                if *regex {
+                    spans.push(Span::styled("  [regex]", Style::default().fg(Color::Cyan)));
+                }
+                (Spans::from(spans), 1 + query.cursor_width())
+            }
+            Mode::Viewer(Viewer {
+                editing: Some(query),
+                ..
+            }) => (Spans::from(format!("/{query}")), 1 + query.cursor_width()),
+            Mode::Rename {
+                original,
+                buffer,
+                overwrite,
+                conflict,
+                ..
+            } => {
+                let prefix = format!("rename: {original} -> ");
+                let cursor = prefix.width() + buffer.cursor_width();
+                let mut spans = vec![Span::raw(format!("{prefix}{buffer}"))];
+                let dir = &app.current_pane().current_dir;
+                let conflict = match conflict {
+                    Some(EntryChange::Removed) => Some(format!(
+                        "{original} was deleted meanwhile; Enter checks again, Esc cancels"
+                    )),
+                    Some(EntryChange::Replaced) => Some(format!(
+                        "{original} was replaced meanwhile; Enter renames it anyway, Esc cancels"
+                    )),
+                    None => None,
+                };
+                match conflict {
+                    Some(text) => spans.push(Span::styled(
+                        format!("  ⚠ {text}"),
+                        Style::default().fg(Color::Red),
+                    )),
+                    None => spans.extend(name_warning(
+                        validate_name(dir, original, buffer),
+                        *overwrite,
+                    )),
+                }
+                (Spans::from(spans), cursor)
+            }
+            Mode::PasteAs {
+                source,
+                buffer,
+                confirmed,
+            } => {
+                let name = source.file_name().unwrap_or_default().to_string_lossy();
+                let prefix = format!("paste {name} as: ");
+                let cursor = prefix.width() + buffer.cursor_width();
+                let mut spans = vec![Span::raw(format!("{prefix}{buffer}"))];
+                let dir = &app.current_pane().current_dir;
+                spans.extend(name_warning(validate_path(dir, buffer), *confirmed));
+                (Spans::from(spans), cursor)
+            }
+            Mode::Create { buffer, confirmed } => {
+                let prefix = "new (end with / for a directory): ";
+                let cursor = prefix.width() + buffer.cursor_width();
+                let mut spans = vec![Span::raw(format!("{prefix}{buffer}"))];
+                let dir = &app.current_pane().current_dir;
+                spans.extend(name_warning(validate_new_entry(dir, buffer), *confirmed));
+                (Spans::from(spans), cursor)
+            }
+            Mode::Filter { query } => {
+                let count = app.current_pane().total();
+                (
+                    Spans::from(vec![
+                        Span::raw(format!("filter: {query}")),
+                        Span::styled(
+                            format!(
+                                "  ({count} {})",
+                                if count == 1 { "entry" } else { "entries" }
+                            ),
+                            Style::default().fg(Color::Cyan),
+                        ),
+                    ]),
+                    "filter: ".width() + query.cursor_width(),
+                )
+            }
+            Mode::Location { buffer } => (
+                Spans::from(format!("open: {buffer}")),
+                "open: ".width() + buffer.cursor_width(),
+            ),
+            Mode::MarkPattern { query, mark } => {
+                let prefix = if *mark { "mark: " } else { "unmark: " };
+                (
+                    Spans::from(format!("{prefix}{query}")),
+                    prefix.width() + query.cursor_width(),
+                )
+            }
+            Mode::MarkWhere { query, mark } => {
+                let prefix = if *mark {
+                    "mark where: "
+                } else {
+                    "unmark where: "
+                };
+                let hint = match Predicate::parse(&query.to_string()) {
+                    _ if query.to_string().trim().is_empty() => Span::styled(
+                        "  (e.g. modified<7d and (ext=jpg,png or size>10M))",
+                        Style::default().fg(Color::DarkGray),
+                    ),
+                    Ok(predicate) => {
+                        let now = SystemTime::now();
+                        let pane = app.current_pane();
+                        let count = pane
+                            .items
+                            .iter()
+                            .filter(|e| predicate.matches(e, now))
+                            .count();
+                        Span::styled(
+                            format!(
+                                "  ({count} {})",
+                                if count == 1 { "entry" } else { "entries" }
+                            ),
+                            Style::default().fg(Color::Cyan),
+                        )
+                    }
+                    Err(e) => Span::styled(format!("  ({e})"), Style::default().fg(Color::Red)),
+                };
+                (
+                    Spans::from(vec![Span::raw(format!("{prefix}{query}")), hint]),
+                    prefix.width() + query.cursor_width(),
+                )
+            }
+            Mode::ResizeImages { paths, width } => {
+                let prefix = format!("resize {} image(s) to width (px): ", paths.len());
+                (
+                    Spans::from(format!("{prefix}{width}")),
+                    prefix.width() + width.cursor_width(),
+                )
+            }
+            Mode::Xattr { path, input } => {
+                let prefix = format!(
+                    "attribute of {} (name=value, or -name to remove): ",
+                    path.file_name().unwrap_or_default().to_string_lossy()
+                );
+                (
+                    Spans::from(format!("{prefix}{input}")),
+                    prefix.width() + input.cursor_width(),
+                )
+            }
+            Mode::HardLinkScope { path, scope } => {
+                let prefix = format!(
+                    "find other names of {} under: ",
+                    path.file_name().unwrap_or_default().to_string_lossy()
+                );
+                (
+                    Spans::from(format!("{prefix}{scope}")),
+                    prefix.width() + scope.cursor_width(),
+                )
+            }
+            Mode::Watch { dir, template } => {
+                let prefix = format!(
+                    "watch {}, on change run: ",
+                    dir.file_name().unwrap_or_default().to_string_lossy()
+                );
+                let hint = if template.is_empty() {
+                    "  ({path} {name} {stem} {ext} {dir} of the changed file)"
+                } else {
+                    ""
+                };
+                (
+                    Spans::from(vec![
+                        Span::raw(format!("{prefix}{template}")),
+                        Span::styled(hint, Style::default().fg(Color::DarkGray)),
+                    ]),
+                    prefix.width() + template.cursor_width(),
+                )
+            }
+            Mode::ForEach { paths, template } => {
+                let prefix = format!("for each of {}: ", paths.len());
+                let text = template.to_string();
+                let hint = match paths.first() {
+                    _ if text.trim().is_empty() => {
+                        "  ({path} {name} {stem} {ext} {dir})".to_string()
+                    }
+                    Some(first) => format!("  $ {}", fill_template(&text, first)),
+                    None => String::new(),
+                };
+                (
+                    Spans::from(vec![
+                        Span::raw(format!("{prefix}{text}")),
+                        Span::styled(hint, Style::default().fg(Color::DarkGray)),
+                    ]),
+                    prefix.width() + template.cursor_width(),
+                )
+            }
+            Mode::ArchivePassword {
+                extraction,
+                password,
+            } => {
+                let prefix = format!(
+                    "password for {}: ",
+                    extraction
+                        .archive
+                        .file_name()
+                        .unwrap_or_default()
+                        .to_string_lossy()
+                );
+                let (masked, cursor) = password.masked();
+                let hint = if extraction.password.is_some() {
+                    Span::styled(
+                        "  (wrong password, try again)",
+                        Style::default().fg(Color::Red),
+                    )
+                } else {
+                    Span::styled("  (Esc: give up)", Style::default().fg(Color::DarkGray))
+                };
+                (
+                    Spans::from(vec![Span::raw(format!("{prefix}{masked}")), hint]),
+                    prefix.width() + cursor,
+                )
+            }
+            Mode::Run { program, args } => {
+                let prefix = format!(
+                    "run: {} ",
+                    program.file_name().unwrap_or_default().to_string_lossy()
+                );
+                (
+                    Spans::from(format!("{prefix}{args}")),
+                    prefix.width() + args.cursor_width(),
+                )
+            }
+            _ => {
+                if let Some(notification) = app.notifications.current() {
+                    let style = Style::default().fg(severity_color(notification.severity));
+                    f.render_widget(
+                        Paragraph::new(notification.message.as_str()).style(style),
+                        footer,
+                    );
+                }
+                return;
+            }
         };
         let paragraph = Paragraph::new(prompt);
         f.render_widget(paragraph, footer);
+        f.set_cursor(footer.x + cursor as u16, footer.y);
+    }
+}
+
+/// Text and color describing a job's state; `total` is what its count goes up to, if known.
+fn status_label(status: &JobStatus, total: Option<usize>) -> (String, Color) {
+    match status {
+        JobStatus::Pending => ("pending".to_string(), Color::DarkGray),
+        JobStatus::Running(done) if total.is_some() => (
+            format!("running, {done}/{}", total.unwrap_or_default()),
+            Color::Yellow,
+        ),
+        JobStatus::Running(0) => ("running".to_string(), Color::Yellow),
+        JobStatus::Running(done) => (
+            format!("running, {} entries", thousands(*done as u64)),
+            Color::Yellow,
+        ),
+        JobStatus::Transferring(Transfer { percent, speed }) => {
+            // a ten-cell bar
+            let filled = usize::from(*percent).min(100) / 10;
+            let bar = format!("{}{}", "█".repeat(filled), "░".repeat(10 - filled));
+            (format!("running, {bar} {percent}%, {speed}"), Color::Yellow)
+        }
+        JobStatus::Done => ("done".to_string(), Color::Green),
+        JobStatus::Failed(e) => (format!("failed: {e}"), Color::Red),
+        JobStatus::Cancelled => ("cancelled".to_string(), Color::DarkGray),
+        JobStatus::Locked => ("needs a password".to_string(), Color::Magenta),
+        JobStatus::Denied { .. } => ("permission denied".to_string(), Color::Magenta),
+    }
+}
+
+fn draw_viewer<B: Backend>(f: &mut Frame<B>, area: Rect, viewer: &Viewer, focused: bool) {
+    let mut title = vec![if focused {
+        Span::styled(
+            viewer.title.as_str(),
+            Style::default()
+                .fg(Color::Yellow)
+                .add_modifier(Modifier::BOLD),
+        )
+    } else {
+        Span::raw(viewer.title.as_str())
+    }];
+    if !viewer.wrap {
+        title.push(Span::styled(
+            format!("  [nowrap, col {}]", viewer.column + 1),
+            Style::default().fg(Color::Cyan),
+        ));
+    }
+    if viewer.follow {
+        title.push(Span::styled(
+            "  [following]",
+            Style::default().fg(Color::Green),
+        ));
+    }
+    if !viewer.search.is_empty() {
+        let count = match viewer.current_match {
+            Some(i) => format!("{}/{}", i + 1, viewer.matches.len()),
+            None => viewer.matches.len().to_string(),
+        };
+        title.push(Span::styled(
+            format!("  /{} [{count}]", viewer.search),
+            Style::default().fg(Color::Cyan),
+        ));
+    }
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title(Spans::from(title));
+    // available rows and margin width
+    let inner_height = area.height.saturating_sub(2) as usize;
+    let number_width = inner_height.to_string().len().max(1);
+    // wrap each content line into display rows of at most (width - margin) cols
+    let text_width = area.width.saturating_sub((number_width + 1) as u16) as usize;
+    let row_ranges = |line: &str| {
+        if viewer.wrap {
+            wrap_ranges(line, text_width)
+        } else {
+            vec![clip_range(line, viewer.column, text_width)]
+        }
+    };
+    // only a window of the file is loaded; `i` below indexes it, `base + i` is the file line
+    let lines = viewer.window();
+    let base = viewer.window_start();
+    // the last line that can start a screen without leaving blank rows below
+    let mut last_start = lines.len();
+    let mut rows_below = 0;
+    while viewer.window_at_end() && last_start > 0 {
+        let rows = row_ranges(&lines[last_start - 1]).len();
+        if rows_below + rows > inner_height {
+            break;
+        }
+        rows_below += rows;
+        last_start -= 1;
+    }
+    let start = viewer.offset().saturating_sub(base).min(last_start);
+    let current_line = viewer.current_match.map(|i| viewer.matches[i]);
+    let mut numbered: Vec<Spans> = Vec::new();
+    let mut shown_to_end = viewer.window_at_end();
+    for (i, line) in lines.iter().enumerate().skip(start) {
+        if numbered.len() >= inner_height {
+            shown_to_end = false;
+            break;
+        }
+        let found: Vec<(usize, usize)> = if viewer.search.is_empty() {
+            Vec::new()
+        } else {
+            line.match_indices(viewer.search.as_str())
+                .map(|(at, m)| (at, at + m.len()))
+                .collect()
+        };
+        let highlight = if current_line == Some(base + i) {
+            Style::default().fg(Color::Black).bg(Color::LightRed)
+        } else {
+            Style::default().fg(Color::Black).bg(Color::Yellow)
+        };
+        for (row, (from, to)) in row_ranges(line).into_iter().enumerate() {
+            // relative line numbers on the first row of each line, blank on continuations
+            let num = if row == 0 {
+                format!("{:>width$} ", i - start, width = number_width)
+            } else {
+                " ".repeat(number_width + 1)
+            };
+            let mut spans = vec![Span::styled(num, Style::default().fg(Color::DarkGray))];
+            let mut pos = from;
+            for &(a, b) in &found {
+                let (a, b) = (a.max(pos), b.min(to));
+                if a >= b {
+                    continue;
+                }
+                spans.push(Span::raw(&line[pos..a]));
+                spans.push(Span::styled(&line[a..b], highlight));
+                pos = b;
+            }
+            spans.push(Span::raw(&line[pos..to]));
+            numbered.push(Spans::from(spans));
+        }
+    }
+    let paragraph = Paragraph::new(numbered).block(block);
+    f.render_widget(paragraph, area);
+
+    // like vim's ruler: where the top line is, and how far into the file
+    let line = base + start + 1;
+    let position = match viewer.total_lines() {
+        Some(total) => format!("line {line} of {total}"),
+        None => format!("line {line}"),
+    };
+    let share = match (base + start == 0, shown_to_end) {
+        (true, true) => "All".to_string(),
+        (true, false) => "Top".to_string(),
+        (false, true) => "Bot".to_string(),
+        (false, false) => format!("{}%", viewer.percent()),
+    };
+    draw_bottom_label(f, area, &format!(" {position}  {share} "));
+}
+
+/// Write `label` right-aligned into the bottom border of `area`.
+fn draw_bottom_label<B: Backend>(f: &mut Frame<B>, area: Rect, label: &str) {
+    if area.height < 2 || area.width < 4 {
+        return;
+    }
+    let border = Rect {
+        x: area.x + 1,
+        y: area.y + area.height - 1,
+        width: area.width - 2,
+        height: 1,
+    };
+    f.render_widget(
+        Paragraph::new(label.to_string()).alignment(Alignment::Right),
+        border,
+    );
+}
+
+/// Byte range of `line` visible when scrolled `column` display columns to the right.
+fn clip_range(line: &str, column: usize, width: usize) -> (usize, usize) {
+    let mut start = line.len();
+    let mut end = line.len();
+    let mut w = 0;
+    for (i, ch) in line.char_indices() {
+        if w >= column && start == line.len() {
+            start = i;
+        }
+        let cw = ch.width().unwrap_or(0);
+        if w + cw > column + width {
+            end = i;
+            break;
+        }
+        w += cw;
+    }
+    (start, end.max(start))
+}
+
+/// Byte ranges of `line` split into display rows of at most `width` columns.
+fn wrap_ranges(line: &str, width: usize) -> Vec<(usize, usize)> {
+    let mut rows = Vec::new();
+    let mut start = 0;
+    let mut w = 0;
+    for (i, ch) in line.char_indices() {
+        let cw = ch.width().unwrap_or(0);
+        // a character wider than the row still gets a row of its own
+        if w + cw > width && i > start {
+            rows.push((start, i));
+            start = i;
+            w = 0;
+        }
+        w += cw;
+    }
+    if start < line.len() || rows.is_empty() {
+        rows.push((start, line.len()));
+    }
+    rows
+}
+
+fn draw_diff<B: Backend>(f: &mut Frame<B>, area: Rect, diff: &DiffView) {
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title(diff.title.as_str());
+    let text: Vec<Spans> = if diff.lines.is_empty() {
+        vec![Spans::from("Files are identical")]
+    } else {
+        diff.lines
+            .iter()
+            .skip(diff.offset)
+            .take(area.height.saturating_sub(2) as usize)
+            .map(|line| {
+                let style = match line.kind {
+                    LineKind::Added => Style::default().fg(Color::Green),
+                    LineKind::Removed => Style::default().fg(Color::Red),
+                    LineKind::Hunk => Style::default().fg(Color::Cyan),
+                    LineKind::Context => Style::default(),
+                };
+                Spans::from(Span::styled(line.text.as_str(), style))
+            })
+            .collect()
+    };
+    f.render_widget(Paragraph::new(text).block(block), area);
+}
+
+fn draw_log_viewer<B: Backend>(f: &mut Frame<B>, area: Rect, log: &LogViewer) {
+    let v = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(0), Constraint::Length(1)])
+        .split(area);
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title(log.title.as_str());
+    let items: Vec<ListItem> = log
+        .visible
+        .iter()
+        .map(|&i| {
+            let record = &log.records[i];
+            let color = match record.level {
+                Level::Trace => Color::DarkGray,
+                Level::Debug => Color::Blue,
+                Level::Info => Color::Green,
+                Level::Warn => Color::Yellow,
+                Level::Error => Color::Red,
+                Level::Unknown => Color::Reset,
+            };
+            ListItem::new(Span::styled(
+                record.summary.as_str(),
+                Style::default().fg(color),
+            ))
+        })
+        .collect();
+    let mut state = ListState::default();
+    state.select(Some(log.cursor));
+    let list = List::new(items)
+        .block(block)
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+    f.render_stateful_widget(list, v[0], &mut state);
+
+    let footer = match &log.editing {
+        Some(input) => {
+            let cursor = "filter: ".width() + input.cursor_width();
+            f.set_cursor(v[1].x + cursor as u16, v[1].y);
+            format!("filter: {input}")
+        }
+        None => format!(
+            "filter: {} ({}/{} records)  f: filter  p: pretty-print",
+            log.filter,
+            log.visible.len(),
+            log.records.len()
+        ),
+    };
+    f.render_widget(Paragraph::new(footer), v[1]);
+
+    if let (true, Some(record)) = (log.expanded, log.current()) {
+        let popup = centered_rect(80, 80, area);
+        let block = Block::default().title("Record").borders(Borders::ALL);
+        let paragraph = Paragraph::new(record.pretty()).block(block);
+        f.render_widget(Clear, popup);
+        f.render_widget(paragraph, popup);
     }
 }
 
-fn draw_pane<B: Backend>(f: &mut Frame<B>, area: Rect, pane: &Pane, active: bool) {
-    let title = format!(" {} ", pane.current_dir.display());
-    let block = Block::default().borders(Borders::ALL).title(Span::styled(
-        title,
-        Style::default()
-            .fg(if active { Color::Yellow } else { Color::White })
-            .add_modifier(Modifier::BOLD),
+fn draw_tree<B: Backend>(f: &mut Frame<B>, area: Rect, tree: &TreeView) {
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title(format!("{}  za: fold  zR/zM: open/close all ", tree.title));
+    let items: Vec<ListItem> = tree
+        .visible
+        .iter()
+        .map(|&i| {
+            let node = &tree.nodes[i];
+            let mut spans = vec![Span::raw("  ".repeat(node.depth))];
+            if let Some(key) = &node.key {
+                spans.push(Span::styled(
+                    Value::String(key.clone()).to_string(),
+                    Style::default().fg(Color::Cyan),
+                ));
+                spans.push(Span::raw(": "));
+            }
+            let comma = if node.comma { "," } else { "" };
+            match &node.kind {
+                NodeKind::Open {
+                    bracket,
+                    close,
+                    len,
+                    ..
+                } if tree.folded.contains(&i) => {
+                    let NodeKind::Close(closing) = tree.nodes[*close].kind else {
+                        unreachable!("an open node points at its closing node");
+                    };
+                    spans.push(Span::raw(format!("{bracket}…{closing}{comma}")));
+                    spans.push(Span::styled(
+                        format!("  {len} item(s)"),
+                        Style::default().fg(Color::DarkGray),
+                    ));
+                }
+                NodeKind::Open { bracket, .. } => spans.push(Span::raw(bracket.to_string())),
+                NodeKind::Close(bracket) => spans.push(Span::raw(format!("{bracket}{comma}"))),
+                NodeKind::Scalar(text, kind) => {
+                    let color = match kind {
+                        ScalarKind::String => Color::Green,
+                        ScalarKind::Number => Color::Yellow,
+                        ScalarKind::Literal => Color::Magenta,
+                    };
+                    spans.push(Span::styled(text.as_str(), Style::default().fg(color)));
+                    spans.push(Span::raw(comma));
+                }
+            }
+            ListItem::new(Spans::from(spans))
+        })
+        .collect();
+    let mut state = ListState::default();
+    state.select(Some(tree.cursor));
+    let list = List::new(items)
+        .block(block)
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+    f.render_stateful_widget(list, area, &mut state);
+}
+
+fn draw_table<B: Backend>(f: &mut Frame<B>, area: Rect, table: &TableView) {
+    let delimiter = match table.delimiter {
+        '\t' => "tab".to_string(),
+        d => format!("'{d}'"),
+    };
+    let block = Block::default().borders(Borders::ALL).title(format!(
+        "{} ({} rows, {} columns, delimiter {delimiter}) ",
+        table.title,
+        table.rows.len(),
+        table.widths.len()
     ));
-    let items: Vec<ListItem> = pane
-        .items
+    // columns that fit, starting from the scrolled-to one
+    let inner_width = area.width.saturating_sub(2) as usize;
+    let mut columns = Vec::new();
+    let mut used = 0;
+    for (c, &w) in table.widths.iter().enumerate().skip(table.column_offset) {
+        if !columns.is_empty() && used + w > inner_width {
+            break;
+        }
+        columns.push(c);
+        used += w + 3;
+    }
+    let separator = Span::styled(" │ ", Style::default().fg(Color::DarkGray));
+    let line = |cells: &[String], style: Style| {
+        let mut spans = Vec::new();
+        for (n, &c) in columns.iter().enumerate() {
+            if n > 0 {
+                spans.push(separator.clone());
+            }
+            let cell = cells.get(c).map_or("", String::as_str);
+            spans.push(Span::styled(fit_cell(cell, table.widths[c]), style));
+        }
+        Spans::from(spans)
+    };
+    let rule = columns
         .iter()
-        .enumerate()
-        .map(|(i, e)| {
-            let name = e.file_name().to_string_lossy().into_owned();
-            let path = e.path();
-            let style = if path.is_dir() {
-                Style::default().fg(Color::Blue)
-            } else if name.starts_with('.') {
-                Style::default().fg(Color::Red)
+        .map(|&c| "─".repeat(table.widths[c]))
+        .collect::<Vec<_>>()
+        .join("─┼─");
+    let mut lines = vec![
+        line(
+            &table.header,
+            Style::default()
+                .fg(Color::Yellow)
+                .add_modifier(Modifier::BOLD),
+        ),
+        Spans::from(Span::styled(rule, Style::default().fg(Color::DarkGray))),
+    ];
+    let body_height = area.height.saturating_sub(4) as usize;
+    lines.extend(
+        table
+            .rows
+            .iter()
+            .skip(table.row_offset)
+            .take(body_height)
+            .map(|row| line(row, Style::default())),
+    );
+    f.render_widget(Paragraph::new(lines).block(block), area);
+}
+
+/// `cell` on one line, cut with `…` or padded to exactly `width` columns.
+fn fit_cell(cell: &str, width: usize) -> String {
+    let flat: String = cell
+        .chars()
+        .map(|c| if c.is_control() { ' ' } else { c })
+        .collect();
+    let mut out = String::new();
+    let mut w = 0;
+    if flat.width() > width {
+        for ch in flat.chars() {
+            let cw = ch.width().unwrap_or(0);
+            if w + cw + 1 > width {
+                break;
+            }
+            out.push(ch);
+            w += cw;
+        }
+        out.push('…');
+        w += 1;
+    } else {
+        out = flat;
+        w = out.width();
+    }
+    out.push_str(&" ".repeat(width.saturating_sub(w)));
+    out
+}
+
+/// `name` on one line within `width` columns: cut in the middle with `…`, keeping the
+/// extension when `keep_extension` and it is short enough, otherwise cut at the end.
+fn fit_name(name: &str, width: usize, keep_extension: bool) -> String {
+    let flat: String = name
+        .chars()
+        .map(|c| if c.is_control() { '?' } else { c })
+        .collect();
+    if flat.width() <= width {
+        return flat;
+    }
+    if width == 0 {
+        return String::new();
+    }
+    // `.bashrc` is all stem
+    let split = flat
+        .rfind('.')
+        .filter(|&dot| keep_extension && dot > 0 && dot + 1 < flat.len());
+    let Some((stem, extension)) = split
+        .map(|dot| flat.split_at(dot))
+        .filter(|(_, extension)| extension.width() <= width / 2)
+    else {
+        let (_, end) = clip_range(&flat, 0, width - 1);
+        return format!("{}…", &flat[..end]);
+    };
+    let room = width - 1 - extension.width();
+    let (_, head) = clip_range(stem, 0, room - room / 2);
+    let mut tail = stem.len();
+    let mut w = stem[..head].width();
+    for (i, ch) in stem.char_indices().rev() {
+        let cw = ch.width().unwrap_or(0);
+        if i < head || w + cw > room {
+            break;
+        }
+        tail = i;
+        w += cw;
+    }
+    format!("{}…{}{extension}", &stem[..head], &stem[tail..])
+}
+
+fn draw_todos<B: Backend>(f: &mut Frame<B>, area: Rect, list: &TodoList) {
+    let title = if list.is_scanning() {
+        format!(" Scanning {} ... ", list.root.display())
+    } else {
+        format!(
+            " {} marker(s) under {}  Enter: open  r: rescan ",
+            list.matches.len(),
+            list.root.display()
+        )
+    };
+    let block = Block::default().borders(Borders::ALL).title(title);
+    let mut rows = Vec::new();
+    let mut selected_row = 0;
+    for (i, m) in list.matches.iter().enumerate() {
+        if list.starts_file(i) {
+            let path = m.path.strip_prefix(&list.root).unwrap_or(&m.path);
+            let count = list.matches[i..]
+                .iter()
+                .take_while(|n| n.path == m.path)
+                .count();
+            rows.push(ListItem::new(Span::styled(
+                format!("{} ({count})", path.display()),
+                Style::default()
+                    .fg(Color::Blue)
+                    .add_modifier(Modifier::BOLD),
+            )));
+        }
+        if i == list.cursor {
+            selected_row = rows.len();
+        }
+        let color = match m.marker {
+            "FIXME" => Color::Red,
+            "HACK" => Color::Magenta,
+            _ => Color::Yellow,
+        };
+        rows.push(ListItem::new(Spans::from(vec![
+            Span::styled(
+                format!("{:>6}: ", m.line),
+                Style::default().fg(Color::DarkGray),
+            ),
+            Span::styled(m.text.as_str(), Style::default().fg(color)),
+        ])));
+    }
+    let mut state = ListState::default();
+    if !list.matches.is_empty() {
+        state.select(Some(selected_row));
+    }
+    let list = List::new(rows)
+        .block(block)
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+    f.render_stateful_widget(list, area, &mut state);
+}
+
+/// The pieces of a pane's path in its title: each directory on the way to it, with its path,
+/// and the separators between them, without one.
+pub fn breadcrumbs(pane: &Pane) -> Vec<(String, Option<PathBuf>)> {
+    let mut pieces: Vec<(String, Option<PathBuf>)> = Vec::new();
+    let mut dir = PathBuf::new();
+    // a Windows drive goes with the root after it, `C:\`
+    let mut prefix = String::new();
+    for component in pane.current_dir.components() {
+        dir.push(component);
+        let text = component.as_os_str().to_string_lossy();
+        match component {
+            Component::Prefix(_) => {
+                prefix = text.into_owned();
+                continue;
+            }
+            Component::Normal(_)
+                if pieces
+                    .last()
+                    .is_some_and(|(last, _)| !last.ends_with(MAIN_SEPARATOR)) =>
+            {
+                pieces.push((MAIN_SEPARATOR.to_string(), None));
+            }
+            _ => {}
+        }
+        pieces.push((
+            format!("{}{text}", std::mem::take(&mut prefix)),
+            Some(dir.clone()),
+        ));
+    }
+    pieces
+}
+
+/// The directory whose breadcrumb is at `column`, `row` of the screen, in the title of `pane`
+/// as it was last drawn.
+pub fn breadcrumb_at(pane: &Pane, column: u16, row: u16) -> Option<PathBuf> {
+    let (x, y, width) = pane.title_area.get()?;
+    if row != y || column < x || column >= x + width {
+        return None;
+    }
+    // after the space and the file system label
+    let mut start = x as usize + 1 + pane.vfs.label().width();
+    for (text, dir) in breadcrumbs(pane) {
+        let end = start + text.width();
+        if (start..end).contains(&(column as usize)) {
+            return dir;
+        }
+        start = end;
+    }
+    None
+}
+
+/// A row of a pane's listing, by index into its entries.
+enum Row {
+    Entry(usize),
+    /// The header of the date group that starts at the entry
+    Header(usize),
+}
+
+fn draw_pane<B: Backend>(f: &mut Frame<B>, area: Rect, app: &App, pane: &Pane, active: bool) {
+    let git_colors = &app.config.git_colors;
+    let ls_colors = app.ls_colors.as_ref();
+    let basket = &app.basket;
+    let style = Style::default()
+        .fg(if active { Color::Yellow } else { Color::White })
+        .add_modifier(Modifier::BOLD);
+    // the breadcrumb picked with `gb`
+    let picked = match &app.mode {
+        Mode::Breadcrumbs { selected } if active => Some(*selected),
+        _ => None,
+    };
+    let mut spans = vec![Span::styled(format!(" {}", pane.vfs.label()), style)];
+    let mut dirs = 0;
+    for (text, dir) in breadcrumbs(pane) {
+        let mut crumb = style;
+        if dir.is_some() {
+            if picked == Some(dirs) {
+                crumb = crumb.add_modifier(Modifier::REVERSED);
+            }
+            dirs += 1;
+        }
+        spans.push(Span::styled(text, crumb));
+    }
+    pane.title_area
+        .set(Some((area.x + 1, area.y, area.width.saturating_sub(2))));
+    let mut title = match &pane.git {
+        Some(git) => format!(" ({}) ", git.branch),
+        None => " ".to_string(),
+    };
+    title.push_str(&pane.state_label());
+    title.push(' ');
+    if let Some(total) = pane.paged_total {
+        title.push_str(&format!(
+            "[page {}/{} of {} entries] ",
+            pane.page + 1,
+            pane.page_count(),
+            thousands(total as u64)
+        ));
+    }
+    if let Some(root) = &pane.root {
+        title.push_str(&format!("[root: {}] ", root.display()));
+    }
+    spans.push(Span::styled(title, style));
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title(Spans::from(spans));
+    let now = SystemTime::now();
+    let heat_colors = &app.config.heat_colors;
+    let largest = match pane.heat {
+        Heat::Size => pane
+            .items
+            .iter()
+            .filter(|e| !e.is_dir())
+            .map(Entry::len)
+            .max()
+            .unwrap_or(0),
+        _ => 0,
+    };
+    // the highlight symbol's room is kept on every row
+    let row_width = (area.width.saturating_sub(2) as usize).saturating_sub(">> ".width());
+    let entry_item = |e: &Entry| {
+        let name = e.file_name().to_string_lossy().into_owned();
+        let path = e.path();
+        // LS_COLORS rules need local metadata
+        let ls_style = ls_colors
+            .filter(|_| pane.is_local())
+            .and_then(|c| e.symlink_metadata().and_then(|meta| c.style(&path, meta)));
+        let style = if let Some(style) = ls_style {
+            style
+        } else if e.is_broken_link() {
+            Style::default()
+                .fg(Color::Magenta)
+                .add_modifier(Modifier::CROSSED_OUT)
+        } else if e.is_dir() {
+            Style::default().fg(Color::Blue)
+        } else if name.starts_with('.') {
+            Style::default().fg(Color::Red)
+        } else if e.is_executable() {
+            Style::default().fg(Color::Green)
+        } else {
+            Style::default()
+        };
+        let style = match pane.heat.level(e, largest, now) {
+            Some(level) => style.fg(blend(heat_colors.cold, heat_colors.hot, level)),
+            None => style,
+        };
+        let marker = if pane.marked.contains(&path) {
+            Span::raw("* ")
+        } else if pane.is_local() && basket.contains(&path) {
+            Span::styled("• ", Style::default().fg(Color::Cyan))
+        } else {
+            Span::raw("  ")
+        };
+        let mut spans = vec![marker];
+        if !pane.comparison.is_empty() {
+            let (indicator, color) = match pane.comparison.get(&e.file_name()) {
+                Some(CompareStatus::Only) => ("+", Color::Green),
+                Some(CompareStatus::Newer) => (">", Color::Yellow),
+                Some(CompareStatus::Older) => ("<", Color::DarkGray),
+                Some(CompareStatus::Differs) => ("!", Color::Red),
+                Some(CompareStatus::Same) | None => (" ", Color::Reset),
+            };
+            spans.push(Span::styled(
+                format!("{indicator} "),
+                Style::default().fg(color),
+            ));
+        }
+        if let Some(git) = &pane.git {
+            let status = git.statuses.get(&e.file_name());
+            let color = match status {
+                Some(GitStatus::Modified) => git_colors.modified,
+                Some(GitStatus::Staged) => git_colors.staged,
+                Some(GitStatus::Untracked) => git_colors.untracked,
+                Some(GitStatus::Ignored) => git_colors.ignored,
+                Some(GitStatus::Conflicted) => git_colors.conflicted,
+                None => Color::Reset,
+            };
+            spans.push(Span::styled(
+                format!("{} ", status.map_or(" ", |s| s.symbol())),
+                Style::default().fg(color),
+            ));
+        }
+        if pane.size_display != SizeDisplay::Hidden {
+            let size = if e.is_dir() {
+                String::new()
             } else {
-                let is_executable = {
-                    #[cfg(unix)]
-                    {
-                        use std::os::unix::fs::PermissionsExt;
-                        e.metadata()
-                            .map(|m| m.permissions().mode() & 0o111 != 0)
-                            .unwrap_or(false)
-                    }
-                    #[cfg(windows)]
-                    {
-                        path.extension().map_or(false, |ext| ext == "exe")
+                pane.size_display.format(e.len())
+            };
+            let width = if pane.size_display == SizeDisplay::Exact {
+                17
+            } else {
+                10
+            };
+            spans.push(Span::styled(
+                format!("{size:>width$} "),
+                Style::default().fg(Color::Cyan),
+            ));
+        }
+        let mut trailing = Vec::new();
+        if pane.show_counts && e.is_dir() {
+            if let Some(stats) = pane.dir_stats(&path) {
+                let counts = match (stats.entries, stats.size) {
+                    (Some(1), None) => "1 item".to_string(),
+                    (Some(n), None) => format!("{} items", thousands(n as u64)),
+                    (Some(1), Some(size)) => format!("1 item, {}", human_size(size)),
+                    (Some(n), Some(size)) => {
+                        format!("{} items, {}", thousands(n as u64), human_size(size))
                     }
+                    (None, _) => "…".to_string(),
                 };
-                if is_executable {
-                    Style::default().fg(Color::Green)
+                trailing.push(Span::styled(
+                    format!("  {counts}"),
+                    Style::default().fg(Color::DarkGray),
+                ));
+            }
+        }
+        if let Some(commit) = pane.commits.get(&e.file_name()) {
+            trailing.push(Span::styled(
+                format!("  {} · {}", commit.summary, commit.age(now)),
+                Style::default().fg(Color::DarkGray),
+            ));
+        }
+        // the name leaves room for what follows it, but keeps at least half the row
+        let room = row_width.saturating_sub(spans.iter().map(|s| s.content.width()).sum());
+        let after: usize = trailing.iter().map(|s| s.content.width()).sum();
+        let name = fit_name(&name, room.saturating_sub(after).max(room / 2), !e.is_dir());
+        spans.push(Span::styled(name, style));
+        spans.extend(trailing);
+        ListItem::new(Spans::from(spans))
+    };
+    let mut selected_row = pane.selected;
+    let rows: Vec<Row> = if pane.groups.is_empty() {
+        (0..pane.items.len()).map(Row::Entry).collect()
+    } else {
+        // date-grouped listing: a header row per group, entries hidden while folded
+        let mut rows = Vec::new();
+        for i in 0..pane.items.len() {
+            let folded = pane.collapsed.contains(&pane.groups[i]);
+            if pane.starts_group(i) {
+                if folded && i == pane.selected {
+                    selected_row = rows.len();
+                }
+                rows.push(Row::Header(i));
+            }
+            if !folded {
+                if i == pane.selected {
+                    selected_row = rows.len();
+                }
+                rows.push(Row::Entry(i));
+            }
+        }
+        rows
+    };
+    // keep the previous view unless the cursor has left it
+    let height = area.height.saturating_sub(2) as usize;
+    let total = rows.len();
+    let mut scroll = pane.scroll.get().min(total.saturating_sub(height));
+    if selected_row < scroll {
+        scroll = selected_row;
+    } else if selected_row >= scroll + height {
+        scroll = selected_row + 1 - height.max(1);
+    }
+    pane.scroll.set(scroll);
+    pane.view_height.set(height);
+    let mut state = ListState::default();
+    state.select(Some(selected_row - scroll));
+    // only the rows in view are drawn
+    let items: Vec<ListItem> = rows
+        .iter()
+        .skip(scroll)
+        .take(height)
+        .map(|row| match *row {
+            Row::Entry(i) => entry_item(&pane.items[i]),
+            Row::Header(i) => {
+                let group = pane.groups[i];
+                let count = pane.groups.iter().filter(|&&g| g == group).count();
+                let arrow = if pane.collapsed.contains(&group) {
+                    "▸"
                 } else {
+                    "▾"
+                };
+                ListItem::new(Span::styled(
+                    format!("{arrow} {} ({count})", group.label()),
                     Style::default()
-                }
-            };
-            let marker = if pane.marked.contains(&i) { "*" } else { " " };
-            ListItem::new(Spans::from(vec![
-                Span::raw(format!("{marker} ")),
-                Span::styled(name, style),
-            ]))
+                        .fg(Color::Magenta)
+                        .add_modifier(Modifier::BOLD),
+                ))
+            }
         })
         .collect();
-    let mut state = ListState::default();
-    state.select(Some(pane.selected));
     let list = List::new(items)
         .block(block)
         .highlight_style(Style::default().add_modifier(Modifier::REVERSED))
         .highlight_symbol(">> ");
     f.render_stateful_widget(list, area, &mut state);
+    draw_scrollbar(f, area, scroll, height, total);
+    if !pane.items.is_empty() {
+        // entries of earlier pages count towards the position in a paged listing
+        let before = pane.page * PAGE_SIZE;
+        let count = pane.paged_total.unwrap_or(pane.items.len());
+        draw_bottom_label(
+            f,
+            area,
+            &format!(
+                " item {}/{} ",
+                thousands((before + pane.selected + 1) as u64),
+                thousands(count as u64)
+            ),
+        );
+    }
+}
+
+/// The parent directory of the active pane in the column layout, with its directory highlighted.
+fn draw_parent_column<B: Backend>(f: &mut Frame<B>, area: Rect, app: &App) {
+    let dir = &app.current_pane().current_dir;
+    let title = dir
+        .parent()
+        .map(|p| p.display().to_string())
+        .unwrap_or_default();
+    let block = Block::default().borders(Borders::ALL).title(title);
+    let Some((entries, row)) = &app.columns.parent else {
+        f.render_widget(block, area);
+        return;
+    };
+    let items: Vec<ListItem> = entries.iter().map(column_item).collect();
+    let mut state = ListState::default();
+    state.select(*row);
+    let list = List::new(items)
+        .block(block)
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+    f.render_stateful_widget(list, area, &mut state);
+}
+
+/// The entry under the active pane's cursor in the column layout: a directory's entries, the
+/// start of a text file, or what kind of file it is.
+fn draw_preview_column<B: Backend>(f: &mut Frame<B>, area: Rect, app: &App) {
+    let pane = app.current_pane();
+    let title = pane
+        .items
+        .get(pane.selected)
+        .map(|e| e.file_name().to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let block = Block::default().borders(Borders::ALL).title(title);
+    match &app.columns.preview {
+        Some(Preview::Dir(entries)) => {
+            let items: Vec<ListItem> = entries.iter().map(column_item).collect();
+            f.render_widget(List::new(items).block(block), area);
+        }
+        Some(Preview::Text(lines)) => {
+            let text: Vec<Spans> = lines.iter().map(|l| Spans::from(l.as_str())).collect();
+            f.render_widget(Paragraph::new(text).block(block), area);
+        }
+        Some(Preview::Info(info)) => {
+            let text = Span::styled(info.as_str(), Style::default().fg(Color::DarkGray));
+            f.render_widget(Paragraph::new(text).block(block), area);
+        }
+        None => f.render_widget(block, area),
+    }
+}
+
+/// An entry in a column beside the active pane: its name, blue for directories.
+fn column_item(entry: &Entry) -> ListItem<'static> {
+    let name = entry.file_name().to_string_lossy().into_owned();
+    if entry.is_dir() {
+        ListItem::new(Span::styled(name, Style::default().fg(Color::Blue)))
+    } else {
+        ListItem::new(name)
+    }
+}
+
+/// Warning after a name being typed, if it has a problem; `overwrite` once the user was
+/// asked to confirm replacing an existing entry.
+fn name_warning(problem: Option<NameProblem>, overwrite: bool) -> Option<Span<'static>> {
+    let problem = problem?;
+    let (text, color) = if let (NameProblem::MissingDirs(n), true) = (&problem, overwrite) {
+        (
+            format!(
+                "create {n} director{}? press Enter to confirm",
+                if *n == 1 { "y" } else { "ies" }
+            ),
+            Color::Yellow,
+        )
+    } else if overwrite {
+        ("overwrite? press Enter to confirm".to_string(), Color::Red)
+    } else if problem.is_overridable() {
+        (problem.message(), Color::Yellow)
+    } else {
+        (problem.message(), Color::Red)
+    };
+    Some(Span::styled(
+        format!("  ⚠ {text}"),
+        Style::default().fg(color),
+    ))
+}
+
+fn severity_color(severity: Severity) -> Color {
+    match severity {
+        Severity::Info => Color::Cyan,
+        Severity::Warning => Color::Yellow,
+        Severity::Error => Color::Red,
+    }
+}
+
+/// Mark the part of a `total` rows long list shown from row `scroll` on the right border of
+/// `area`.
+fn draw_scrollbar<B: Backend>(
+    f: &mut Frame<B>,
+    area: Rect,
+    scroll: usize,
+    height: usize,
+    total: usize,
+) {
+    if total <= height || height == 0 || area.width < 2 {
+        return;
+    }
+    let thumb = (height * height / total).max(1);
+    let top = scroll * (height - thumb) / (total - height);
+    let bar = Rect {
+        x: area.x + area.width - 1,
+        y: area.y + 1 + top as u16,
+        width: 1,
+        height: thumb as u16,
+    };
+    let lines: Vec<Spans> = (0..thumb).map(|_| Spans::from("┃")).collect();
+    f.render_widget(Paragraph::new(lines), bar);
+}
+
+/// The last lines of a confirmation popup: `(y/N)`, or the protected directory's name to type.
+fn confirm_prompt(protected: &Option<TypeToConfirm>) -> Vec<Spans<'static>> {
+    match protected {
+        None => vec![Spans::from("(y/N)")],
+        Some(protected) => vec![
+            Spans::from(Span::styled(
+                format!("{} is protected", protected.name),
+                Style::default().fg(Color::Red),
+            )),
+            Spans::from(format!("type its name: {}_", &*protected.buffer)),
+        ],
+    }
 }
 
 /// Helper to create a centered rect using the given percentage width and height of the available rect
@@ -0,0 +1,319 @@
+use std::{
+    ffi::OsString,
+    fs, io,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use crate::fs_utils::long_path;
+
+/// An entry listed by a `VfsProvider`.
+pub enum Entry {
+    Local(LocalEntry),
+    Remote(RemoteEntry),
+}
+
+/// A local entry with its metadata, read once when the directory is listed.
+pub struct LocalEntry {
+    entry: fs::DirEntry,
+    /// The entry itself, not following symlinks
+    meta: Option<fs::Metadata>,
+    /// Directory, or symlink to one
+    is_dir: bool,
+    /// Symlink to nothing
+    is_broken: bool,
+}
+
+impl LocalEntry {
+    fn new(entry: fs::DirEntry) -> Self {
+        let meta = entry.metadata().ok();
+        let (is_dir, is_broken) = match &meta {
+            Some(m) if m.is_symlink() => match entry.path().metadata() {
+                Ok(target) => (target.is_dir(), false),
+                Err(_) => (false, true),
+            },
+            Some(m) => (m.is_dir(), false),
+            None => (false, false),
+        };
+        LocalEntry {
+            entry,
+            meta,
+            is_dir,
+            is_broken,
+        }
+    }
+}
+
+/// Attributes of a remote entry, fetched along with the listing.
+pub struct RemoteEntry {
+    pub path: PathBuf,
+    /// Directory, or symlink to one
+    pub is_dir: bool,
+    /// Symbolic link, whatever it points to
+    pub is_symlink: bool,
+    pub len: u64,
+    pub modified: Option<SystemTime>,
+    /// Unix permission bits, when the server reports them
+    pub mode: Option<u32>,
+}
+
+// `len` is a file size, like `Metadata::len`
+#[allow(clippy::len_without_is_empty)]
+impl Entry {
+    pub fn path(&self) -> PathBuf {
+        match self {
+            Entry::Local(e) => e.entry.path(),
+            Entry::Remote(e) => e.path.clone(),
+        }
+    }
+
+    pub fn file_name(&self) -> OsString {
+        match self {
+            Entry::Local(e) => e.entry.file_name(),
+            Entry::Remote(e) => e.path.file_name().unwrap_or_default().to_os_string(),
+        }
+    }
+
+    /// Whether the entry is a directory, following symlinks.
+    pub fn is_dir(&self) -> bool {
+        match self {
+            Entry::Local(e) => e.is_dir,
+            Entry::Remote(e) => e.is_dir,
+        }
+    }
+
+    /// Whether the entry is a symbolic link itself, not following it.
+    pub fn is_symlink(&self) -> bool {
+        match self {
+            Entry::Local(e) => e.meta.as_ref().is_some_and(|m| m.is_symlink()),
+            Entry::Remote(e) => e.is_symlink,
+        }
+    }
+
+    /// Metadata of a local entry itself, not following symlinks.
+    pub fn symlink_metadata(&self) -> Option<&fs::Metadata> {
+        match self {
+            Entry::Local(e) => e.meta.as_ref(),
+            Entry::Remote(_) => None,
+        }
+    }
+
+    /// Whether the entry is a symbolic link to nothing; remote links are followed by the server.
+    pub fn is_broken_link(&self) -> bool {
+        match self {
+            Entry::Local(e) => e.is_broken,
+            Entry::Remote(_) => false,
+        }
+    }
+
+    /// Size in bytes.
+    pub fn len(&self) -> u64 {
+        match self {
+            Entry::Local(e) => e.meta.as_ref().map_or(0, |m| m.len()),
+            Entry::Remote(e) => e.len,
+        }
+    }
+
+    pub fn modified(&self) -> Option<SystemTime> {
+        match self {
+            Entry::Local(e) => e.meta.as_ref().and_then(|m| m.modified().ok()),
+            Entry::Remote(e) => e.modified,
+        }
+    }
+
+    /// Creation time; unknown for remote entries.
+    pub fn created(&self) -> Option<SystemTime> {
+        match self {
+            Entry::Local(e) => e.meta.as_ref().and_then(|m| m.created().ok()),
+            Entry::Remote(_) => None,
+        }
+    }
+
+    /// What the entry looks like now, to tell later whether it was replaced.
+    pub fn stamp(&self) -> EntryStamp {
+        EntryStamp {
+            is_dir: self.is_dir(),
+            len: self.len(),
+            modified: self.modified(),
+        }
+    }
+
+    pub fn is_executable(&self) -> bool {
+        match self {
+            #[cfg(unix)]
+            Entry::Local(e) => {
+                use std::os::unix::fs::PermissionsExt;
+                e.meta
+                    .as_ref()
+                    .is_some_and(|m| m.permissions().mode() & 0o111 != 0)
+            }
+            #[cfg(windows)]
+            Entry::Local(e) => e.entry.path().extension().is_some_and(|ext| {
+                ["exe", "com", "bat", "cmd", "ps1"]
+                    .iter()
+                    .any(|x| ext.eq_ignore_ascii_case(x))
+            }),
+            Entry::Remote(e) => !e.is_dir && e.mode.is_some_and(|m| m & 0o111 != 0),
+        }
+    }
+}
+
+/// Attributes of an entry that change when it is replaced by another one.
+#[derive(Clone, Copy, PartialEq)]
+pub struct EntryStamp {
+    is_dir: bool,
+    len: u64,
+    modified: Option<SystemTime>,
+}
+
+/// How an entry changed behind kura's back.
+#[derive(Clone, Copy, PartialEq)]
+pub enum EntryChange {
+    Removed,
+    /// Another entry took its name, or it was modified
+    Replaced,
+}
+
+/// Where a pane's entries live: the local file system or a remote server.
+///
+/// Paths handed to a provider are paths on its own file system.
+pub trait VfsProvider {
+    /// Shown before the path in the pane title; empty for the local file system
+    fn label(&self) -> String;
+
+    fn is_local(&self) -> bool {
+        false
+    }
+
+    fn read_dir(&self, dir: &Path) -> io::Result<Box<dyn Iterator<Item = io::Result<Entry>> + '_>>;
+
+    fn rename(&self, from: &Path, to: &Path) -> io::Result<()>;
+
+    fn create_dir(&self, path: &Path) -> io::Result<()>;
+
+    fn remove_file(&self, path: &Path) -> io::Result<()>;
+
+    /// Remove the directory `path`, which must be empty.
+    fn remove_dir(&self, path: &Path) -> io::Result<()>;
+
+    /// Copy the file `path` to the local file `dest`.
+    fn download(&self, path: &Path, dest: &Path) -> io::Result<()>;
+
+    /// Copy the local file `source` to `path`.
+    fn upload(&self, source: &Path, path: &Path) -> io::Result<()>;
+
+    /// The local disk image the file system is read from, if it is one
+    fn image(&self) -> Option<PathBuf> {
+        None
+    }
+}
+
+pub struct LocalFs;
+
+impl VfsProvider for LocalFs {
+    fn label(&self) -> String {
+        String::new()
+    }
+
+    fn is_local(&self) -> bool {
+        true
+    }
+
+    fn read_dir(&self, dir: &Path) -> io::Result<Box<dyn Iterator<Item = io::Result<Entry>> + '_>> {
+        Ok(Box::new(
+            fs::read_dir(dir)?.map(|e| e.map(|e| Entry::Local(LocalEntry::new(e)))),
+        ))
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> io::Result<()> {
+        fs::rename(long_path(from), long_path(to))
+    }
+
+    fn create_dir(&self, path: &Path) -> io::Result<()> {
+        fs::create_dir(long_path(path))
+    }
+
+    fn remove_file(&self, path: &Path) -> io::Result<()> {
+        fs::remove_file(long_path(path))
+    }
+
+    fn remove_dir(&self, path: &Path) -> io::Result<()> {
+        fs::remove_dir(long_path(path))
+    }
+
+    fn download(&self, path: &Path, dest: &Path) -> io::Result<()> {
+        fs::copy(long_path(path), long_path(dest)).map(|_| ())
+    }
+
+    fn upload(&self, source: &Path, path: &Path) -> io::Result<()> {
+        fs::copy(long_path(source), long_path(path)).map(|_| ())
+    }
+}
+
+/// Number of files under the directory `dir` on `vfs` and their total size. Symlinks count
+/// as files, not followed, so a link cycle cannot loop.
+pub fn dir_size(vfs: &dyn VfsProvider, dir: &Path) -> (usize, u64) {
+    let Ok(children) = vfs.read_dir(dir) else {
+        return (0, 0);
+    };
+    children
+        .flatten()
+        .map(|child| {
+            if child.is_dir() && !child.is_symlink() {
+                dir_size(vfs, &child.path())
+            } else {
+                (1, child.len())
+            }
+        })
+        .fold((0, 0), |(files, bytes), (f, b)| (files + f, bytes + b))
+}
+
+/// Delete `path` on `vfs`, with everything in it if it is a directory (`is_dir`) rather than
+/// a symlink to one. Links are removed, never followed.
+pub fn remove_tree(vfs: &dyn VfsProvider, path: &Path, is_dir: bool) -> io::Result<()> {
+    if !is_dir {
+        return vfs.remove_file(path);
+    }
+    let children: Vec<Entry> = vfs.read_dir(path)?.collect::<io::Result<_>>()?;
+    for child in children {
+        remove_tree(vfs, &child.path(), child.is_dir() && !child.is_symlink())?;
+    }
+    vfs.remove_dir(path)
+}
+
+/// Copy `source` (a directory if `is_dir`, recursively) from one provider to `dest` on another.
+pub fn copy_across(
+    from: &dyn VfsProvider,
+    source: &Path,
+    is_dir: bool,
+    to: &dyn VfsProvider,
+    dest: &Path,
+) -> io::Result<()> {
+    if is_dir {
+        to.create_dir(dest)?;
+        let children: Vec<Entry> = from.read_dir(source)?.collect::<io::Result<_>>()?;
+        for child in children {
+            copy_across(
+                from,
+                &child.path(),
+                child.is_dir(),
+                to,
+                &dest.join(child.file_name()),
+            )?;
+        }
+        return Ok(());
+    }
+    if from.is_local() {
+        to.upload(source, dest)
+    } else if to.is_local() {
+        from.download(source, dest)
+    } else {
+        // between two servers: stage the file locally
+        let staged = std::env::temp_dir().join(format!("kura-transfer-{}", std::process::id()));
+        let result = from
+            .download(source, &staged)
+            .and_then(|()| to.upload(&staged, dest));
+        let _ = fs::remove_file(&staged);
+        result
+    }
+}
@@ -0,0 +1,356 @@
+use std::{
+    fs::{self, File},
+    io::{self, BufRead, BufReader, Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+};
+
+use crate::line_edit::LineEditor;
+
+/// A byte offset is remembered for every this many lines, so seeking to a line reads at most
+/// this many lines past a known position.
+const CHECKPOINT_EVERY: usize = 1024;
+
+/// Lines held in memory around the viewed position.
+const WINDOW_LINES: usize = 4096;
+
+/// Longer lines are cut when loaded into the window.
+const MAX_LINE_BYTES: usize = 16 * 1024;
+
+/// A text file opened for reading, with its scroll position.
+///
+/// The file is not read into memory: only a window of lines around the viewed position is
+/// loaded, and lines are located through byte offsets recorded every `CHECKPOINT_EVERY` lines,
+/// so memory use stays bounded however large the file is.
+pub struct Viewer {
+    pub title: String,
+    path: PathBuf,
+    reader: BufReader<File>,
+    /// First line shown
+    offset: usize,
+    /// Byte offset of every `CHECKPOINT_EVERY`th line
+    checkpoints: Vec<u64>,
+    /// Lines counted so far, and the byte offset where counting stopped
+    indexed_lines: usize,
+    indexed_bytes: u64,
+    /// Number of lines once the whole file has been counted
+    total_lines: Option<usize>,
+    window_start: usize,
+    window: Vec<String>,
+    /// Active search pattern; empty when not searching
+    pub search: String,
+    /// Search text being edited, if the search prompt is open
+    pub editing: Option<LineEditor>,
+    /// Lines containing `search`, ascending
+    pub matches: Vec<usize>,
+    /// Index into `matches` of the match last jumped to
+    pub current_match: Option<usize>,
+    /// Keep the view on the end of the file as it grows
+    pub follow: bool,
+    /// Wrap long lines; when off, lines are cut and scrolled horizontally
+    pub wrap: bool,
+    /// First display column shown when not wrapping
+    pub column: usize,
+    /// File size at the last follow check
+    file_len: u64,
+}
+
+impl Viewer {
+    /// Open `path` as text; returns `None` if the file is unreadable or does not look like text.
+    pub fn open(path: &Path) -> Option<Self> {
+        let mut file = File::open(path).ok()?;
+        let mut head = Vec::new();
+        file.by_ref().take(64 * 1024).read_to_end(&mut head).ok()?;
+        if !looks_like_text(&head) {
+            return None;
+        }
+        file.rewind().ok()?;
+        let file_len = file.metadata().ok()?.len();
+        let title = path
+            .file_name()
+            .map(|os_str| os_str.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let mut viewer = Self {
+            title,
+            path: path.to_path_buf(),
+            reader: BufReader::new(file),
+            offset: 0,
+            checkpoints: vec![0],
+            indexed_lines: 0,
+            indexed_bytes: 0,
+            total_lines: None,
+            window_start: 0,
+            window: Vec::new(),
+            search: String::new(),
+            editing: None,
+            matches: Vec::new(),
+            current_match: None,
+            follow: false,
+            wrap: true,
+            column: 0,
+            file_len,
+        };
+        viewer.load_window(0).ok()?;
+        Some(viewer)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// First line shown.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Number of lines, once the whole file has been counted.
+    pub fn total_lines(&self) -> Option<usize> {
+        self.total_lines
+    }
+
+    /// Percentage of the file above the first line shown; estimated from the byte offset of
+    /// the nearest checkpoint while the lines have not all been counted.
+    pub fn percent(&self) -> usize {
+        match self.total_lines {
+            Some(total) => self.offset * 100 / total.max(1),
+            None => {
+                let checkpoint = (self.offset / CHECKPOINT_EVERY).min(self.checkpoints.len() - 1);
+                (self.checkpoints[checkpoint] * 100 / self.file_len.max(1)) as usize
+            }
+        }
+    }
+
+    /// Loaded lines, starting at line `window_start()`.
+    pub fn window(&self) -> &[String] {
+        &self.window
+    }
+
+    pub fn window_start(&self) -> usize {
+        self.window_start
+    }
+
+    /// Whether the loaded window extends to the end of the file.
+    pub fn window_at_end(&self) -> bool {
+        self.total_lines == Some(self.window_start + self.window.len())
+    }
+
+    /// Show line `line` (0-based) at the top, clamped to the last line of the file.
+    pub fn goto_line(&mut self, line: usize) {
+        let _ = self.index_to(line);
+        self.offset = match self.total_lines {
+            Some(total) => line.min(total.saturating_sub(1)),
+            None => line,
+        };
+        let end = self.window_start + self.window.len();
+        let margin = WINDOW_LINES / 4;
+        let covered = self.offset >= self.window_start
+            && (self.offset + margin <= end || self.window_at_end());
+        if !covered {
+            let _ = self.load_window(self.offset.saturating_sub(margin));
+        }
+    }
+
+    // scrolling by hand ends follow mode
+
+    pub fn scroll_down(&mut self, count: usize) {
+        self.follow = false;
+        self.goto_line(self.offset.saturating_add(count));
+    }
+
+    pub fn scroll_up(&mut self, count: usize) {
+        self.follow = false;
+        self.goto_line(self.offset.saturating_sub(count));
+    }
+
+    pub fn go_top(&mut self) {
+        self.follow = false;
+        self.goto_line(0);
+    }
+
+    pub fn go_bottom(&mut self) {
+        self.follow = false;
+        self.goto_line(usize::MAX);
+    }
+
+    /// Turn follow mode on or off; turning it on jumps to the end of the file.
+    pub fn toggle_follow(&mut self) {
+        self.follow = !self.follow;
+        if self.follow {
+            self.reload_tail();
+        }
+    }
+
+    /// In follow mode, pick up lines appended since the last check and stay on the last line.
+    ///
+    /// A file that shrank (truncated or rotated) is reopened and counted from the start.
+    pub fn follow_tick(&mut self) {
+        let Ok(len) = fs::metadata(&self.path).map(|m| m.len()) else {
+            return;
+        };
+        if len == self.file_len {
+            return;
+        }
+        if len < self.file_len {
+            let Ok(file) = File::open(&self.path) else {
+                return;
+            };
+            self.reader = BufReader::new(file);
+            self.checkpoints = vec![0];
+            self.indexed_lines = 0;
+            self.indexed_bytes = 0;
+        }
+        self.file_len = len;
+        self.reload_tail();
+    }
+
+    fn reload_tail(&mut self) {
+        self.total_lines = None;
+        self.goto_line(usize::MAX);
+        let _ = self.load_window(self.offset.saturating_sub(WINDOW_LINES / 4));
+    }
+
+    pub fn toggle_wrap(&mut self) {
+        self.wrap = !self.wrap;
+        self.column = 0;
+    }
+
+    /// Scroll horizontally by `columns` (negative to the left) when not wrapping.
+    pub fn scroll_columns(&mut self, columns: isize) {
+        if !self.wrap {
+            self.column = self.column.saturating_add_signed(columns);
+        }
+    }
+
+    /// Search for `pattern` and jump to the first match at or below the current line.
+    ///
+    /// The file is streamed from disk, so searching a huge file takes a while but not memory.
+    pub fn set_search(&mut self, pattern: String) {
+        self.matches = if pattern.is_empty() {
+            Vec::new()
+        } else {
+            self.find_lines(&pattern).unwrap_or_default()
+        };
+        self.search = pattern;
+        self.current_match = None;
+        let from = self.offset;
+        self.jump(self.matches.iter().position(|&l| l >= from).or(
+            // wrap around to the first match
+            (!self.matches.is_empty()).then_some(0),
+        ));
+    }
+
+    /// Jump to the next match below the current line, wrapping around at the end.
+    pub fn next_match(&mut self) {
+        let from = self.offset;
+        let next = self.matches.iter().position(|&l| l > from);
+        self.jump(next.or((!self.matches.is_empty()).then_some(0)));
+    }
+
+    /// Jump to the previous match above the current line, wrapping around at the top.
+    pub fn prev_match(&mut self) {
+        let from = self.offset;
+        let prev = self.matches.iter().rposition(|&l| l < from);
+        self.jump(prev.or(self.matches.len().checked_sub(1)));
+    }
+
+    fn jump(&mut self, index: Option<usize>) {
+        if let Some(i) = index {
+            self.follow = false;
+            self.current_match = Some(i);
+            self.goto_line(self.matches[i]);
+        }
+    }
+
+    fn find_lines(&self, pattern: &str) -> io::Result<Vec<usize>> {
+        let mut reader = BufReader::new(File::open(&self.path)?);
+        let mut buf = Vec::new();
+        let mut found = Vec::new();
+        let mut line = 0;
+        while reader.read_until(b'\n', &mut buf)? > 0 {
+            if String::from_utf8_lossy(&buf).contains(pattern) {
+                found.push(line);
+            }
+            buf.clear();
+            line += 1;
+        }
+        Ok(found)
+    }
+
+    /// Count lines until line `line` is reached or the file ends, recording checkpoints.
+    fn index_to(&mut self, line: usize) -> io::Result<()> {
+        if self.total_lines.is_some() || self.indexed_lines > line {
+            return Ok(());
+        }
+        self.reader.seek(SeekFrom::Start(self.indexed_bytes))?;
+        // byte offset where the line being counted starts
+        let mut line_start = self.indexed_bytes;
+        while self.indexed_lines <= line {
+            let buf = self.reader.fill_buf()?;
+            if buf.is_empty() {
+                // an unterminated last line counts, but is counted again if the file grows
+                let partial = self.indexed_bytes > line_start;
+                self.indexed_bytes = line_start;
+                self.total_lines = Some(self.indexed_lines + usize::from(partial));
+                return Ok(());
+            }
+            let mut consumed = buf.len();
+            for (i, _) in buf.iter().enumerate().filter(|(_, &b)| b == b'\n') {
+                self.indexed_lines += 1;
+                line_start = self.indexed_bytes + i as u64 + 1;
+                if self.indexed_lines.is_multiple_of(CHECKPOINT_EVERY) {
+                    self.checkpoints.push(line_start);
+                }
+                if self.indexed_lines > line {
+                    consumed = i + 1;
+                    break;
+                }
+            }
+            self.indexed_bytes += consumed as u64;
+            self.reader.consume(consumed);
+        }
+        Ok(())
+    }
+
+    /// Load up to `WINDOW_LINES` lines starting at line `start`.
+    fn load_window(&mut self, start: usize) -> io::Result<()> {
+        self.index_to(start)?;
+        let start = match self.total_lines {
+            Some(total) => start.min(total),
+            None => start,
+        };
+        let checkpoint = (start / CHECKPOINT_EVERY).min(self.checkpoints.len() - 1);
+        self.reader
+            .seek(SeekFrom::Start(self.checkpoints[checkpoint]))?;
+        let mut buf = Vec::new();
+        for _ in checkpoint * CHECKPOINT_EVERY..start {
+            buf.clear();
+            self.reader.read_until(b'\n', &mut buf)?;
+        }
+        self.window.clear();
+        while self.window.len() < WINDOW_LINES {
+            buf.clear();
+            if self.reader.read_until(b'\n', &mut buf)? == 0 {
+                self.total_lines = Some(start + self.window.len());
+                break;
+            }
+            while matches!(buf.last(), Some(b'\n' | b'\r')) {
+                buf.pop();
+            }
+            buf.truncate(MAX_LINE_BYTES);
+            self.window.push(String::from_utf8_lossy(&buf).into_owned());
+        }
+        self.window_start = start;
+        Ok(())
+    }
+}
+
+/// Text heuristic for the start of a file: no NUL bytes, and valid UTF-8 except possibly for a
+/// character cut off at the end of the sample.
+fn looks_like_text(head: &[u8]) -> bool {
+    if head.contains(&0) {
+        return false;
+    }
+    match std::str::from_utf8(head) {
+        Ok(_) => true,
+        Err(e) => e.error_len().is_none(),
+    }
+}
@@ -0,0 +1,130 @@
+use std::{
+    fs::{File, OpenOptions},
+    io::{self, Write},
+    path::PathBuf,
+    process::{Child, Stdio},
+    sync::mpsc::{self, Receiver},
+    time::{Duration, Instant},
+};
+
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use tracing::debug;
+
+use crate::runner::{fill_template, shell_command};
+
+/// How long changes must stop coming in before the command runs, so saving a file or checking
+/// out a branch runs it once.
+const QUIET: Duration = Duration::from_millis(200);
+
+/// A directory watched for changes, with a command run after each burst of them: a small
+/// `entr`. The command lines, their output and exit status go to the file `log`.
+pub struct Watch {
+    pub dir: PathBuf,
+    /// Filled in (see `fill_template`) with the first path that changed
+    pub template: String,
+    pub log: PathBuf,
+    watcher: RecommendedWatcher,
+    events: Receiver<notify::Result<notify::Event>>,
+    /// The first path that changed since the last run, and when the latest change came
+    changed: Option<(PathBuf, Instant)>,
+    running: Option<Child>,
+    /// When the command last exited: changes it made may still be on their way
+    finished: Option<Instant>,
+}
+
+impl Watch {
+    /// Watch `dir` and everything under it, and run `template` once right away.
+    pub fn start(dir: PathBuf, template: String) -> io::Result<Self> {
+        let (tx, events) = mpsc::channel();
+        let mut watcher = notify::recommended_watcher(move |event| {
+            let _ = tx.send(event);
+        })
+        .map_err(io::Error::other)?;
+        watcher
+            .watch(&dir, RecursiveMode::Recursive)
+            .map_err(io::Error::other)?;
+        let log = std::env::temp_dir().join(format!("kura-watch-{}.txt", std::process::id()));
+        File::create(&log)?;
+        let line = fill_template(&template, &dir);
+        let mut watch = Self {
+            dir,
+            template,
+            log,
+            watcher,
+            events,
+            changed: None,
+            running: None,
+            finished: None,
+        };
+        watch.run(&line)?;
+        Ok(watch)
+    }
+
+    /// Take in changes, run the command once they have settled, and notice when it exits.
+    /// Changes made while the command runs, often by the command itself, are ignored.
+    pub fn poll(&mut self) -> io::Result<()> {
+        while let Ok(event) = self.events.try_recv() {
+            let Ok(event) = event else {
+                continue;
+            };
+            let settling = self.finished.is_some_and(|at| at.elapsed() < QUIET);
+            if self.running.is_some() || settling || matches!(event.kind, EventKind::Access(_)) {
+                continue;
+            }
+            for path in event.paths {
+                if path == self.log || path.components().any(|c| c.as_os_str() == ".git") {
+                    continue;
+                }
+                let first = self.changed.take().map_or(path, |(first, _)| first);
+                self.changed = Some((first, Instant::now()));
+            }
+        }
+        if let Some(child) = &mut self.running {
+            let Some(status) = child.try_wait()? else {
+                return Ok(());
+            };
+            self.running = None;
+            self.finished = Some(Instant::now());
+            let result = match status.code() {
+                Some(code) => format!("exit code {code}"),
+                None => status.to_string(),
+            };
+            writeln!(self.append()?, "[{result}]\n")?;
+        }
+        if let Some((path, at)) = &self.changed {
+            if at.elapsed() >= QUIET {
+                let line = fill_template(&self.template, path);
+                self.changed = None;
+                self.run(&line)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn run(&mut self, line: &str) -> io::Result<()> {
+        debug!(line, "watch");
+        let mut log = self.append()?;
+        writeln!(log, "$ {line}")?;
+        let child = shell_command(line)
+            .current_dir(&self.dir)
+            .stdin(Stdio::null())
+            .stdout(log.try_clone()?)
+            .stderr(log)
+            .spawn()?;
+        self.running = Some(child);
+        Ok(())
+    }
+
+    fn append(&self) -> io::Result<File> {
+        OpenOptions::new().append(true).open(&self.log)
+    }
+
+    /// Stop watching, and stop the command if it is running.
+    pub fn stop(mut self) {
+        let _ = self.watcher.unwatch(&self.dir);
+        if let Some(mut child) = self.running.take() {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+    }
+}
@@ -0,0 +1,285 @@
+use std::{
+    fs::{self, File},
+    io,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use ureq::{Agent, AgentBuilder, Request};
+
+use crate::config::expand_home;
+use crate::fs_utils::{percent_decode, percent_encode};
+use crate::vfs::{Entry, RemoteEntry, VfsProvider};
+
+/// Properties asked of each entry in a listing.
+const PROPFIND_BODY: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<propfind xmlns="DAV:"><prop><resourcetype/><getcontentlength/><getlastmodified/></prop></propfind>"#;
+
+/// A directory tree on a WebDAV server, such as a NAS or Nextcloud, reached over HTTP(S).
+pub struct WebDavFs {
+    label: String,
+    /// `http[s]://host[:port]`, which paths are appended to
+    origin: String,
+    agent: Agent,
+    /// Value of the `Authorization` header, when logging in
+    auth: Option<String>,
+}
+
+impl WebDavFs {
+    /// Connect to `dav://[user[:password]@]host[:port][/path]`, or `davs://` for HTTPS,
+    /// returning the provider and the directory to show (`/` when no path is given).
+    ///
+    /// Without a password in the URL, the login for the host in `~/.netrc` is used, if any.
+    pub fn connect(url: &str) -> io::Result<(Self, PathBuf)> {
+        let invalid = || {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "expected dav[s]://[user[:password]@]host[:port]/path",
+            )
+        };
+        let (dav_scheme, scheme, rest) = match url.split_once("://") {
+            Some(("dav", rest)) => ("dav", "http", rest),
+            Some(("davs", rest)) => ("davs", "https", rest),
+            _ => return Err(invalid()),
+        };
+        let (authority, path) = match rest.find('/') {
+            Some(i) => rest.split_at(i),
+            None => (rest, "/"),
+        };
+        let (login, host) = match authority.rsplit_once('@') {
+            Some((login, host)) => (Some(login), host),
+            None => (None, authority),
+        };
+        if host.is_empty() || host.starts_with(':') {
+            return Err(invalid());
+        }
+        let host_name = host.rsplit_once(':').map_or(host, |(name, _)| name);
+        let credentials = match login.map(|l| l.split_once(':')) {
+            Some(Some((user, password))) => Some((user.to_string(), password.to_string())),
+            Some(None) => netrc_login(host_name)
+                .filter(|(user, _)| Some(user.as_str()) == login)
+                .or(login.map(|user| (user.to_string(), String::new()))),
+            None => netrc_login(host_name),
+        };
+        let auth = credentials.as_ref().map(|(user, password)| {
+            format!("Basic {}", STANDARD.encode(format!("{user}:{password}")))
+        });
+        let label = match &credentials {
+            Some((user, _)) => format!("{dav_scheme}://{user}@{host}"),
+            None => format!("{dav_scheme}://{host}"),
+        };
+        let agent = AgentBuilder::new()
+            .timeout_connect(Duration::from_secs(10))
+            .timeout_read(Duration::from_secs(30))
+            .build();
+        let dav = Self {
+            label,
+            origin: format!("{scheme}://{host}"),
+            agent,
+            auth,
+        };
+        let dir = PathBuf::from(percent_decode(path.trim_end_matches('/')).unwrap_or_default());
+        let dir = if dir.as_os_str().is_empty() {
+            PathBuf::from("/")
+        } else {
+            dir
+        };
+        // fails early on a wrong address, login or path
+        dav.propfind(&dir, 0)?;
+        Ok((dav, dir))
+    }
+
+    fn url(&self, path: &Path, is_dir: bool) -> String {
+        let path = path.to_string_lossy().replace('\\', "/");
+        let mut url = format!("{}{}", self.origin, percent_encode(&path));
+        if is_dir && !url.ends_with('/') {
+            url.push('/');
+        }
+        url
+    }
+
+    fn request(&self, method: &str, url: &str) -> Request {
+        let request = self.agent.request(method, url);
+        match &self.auth {
+            Some(auth) => request.set("Authorization", auth),
+            None => request,
+        }
+    }
+
+    /// The entries the server reports at `dir` for `depth` 0 (`dir` itself) or 1 (also its
+    /// children).
+    fn propfind(&self, dir: &Path, depth: u8) -> io::Result<Vec<RemoteEntry>> {
+        let response = self
+            .request("PROPFIND", &self.url(dir, true))
+            .set("Depth", &depth.to_string())
+            .set("Content-Type", "application/xml; charset=utf-8")
+            .send_string(PROPFIND_BODY)
+            .map_err(http_error)?;
+        parse_multistatus(&response.into_string()?)
+    }
+}
+
+impl VfsProvider for WebDavFs {
+    fn label(&self) -> String {
+        self.label.clone()
+    }
+
+    fn read_dir(&self, dir: &Path) -> io::Result<Box<dyn Iterator<Item = io::Result<Entry>> + '_>> {
+        let entries = self.propfind(dir, 1)?;
+        let dir = dir.to_path_buf();
+        Ok(Box::new(
+            entries
+                .into_iter()
+                // the directory itself is listed too
+                .filter(move |e| e.path != dir)
+                .map(|e| Ok(Entry::Remote(e))),
+        ))
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> io::Result<()> {
+        // without `Overwrite: F`, an existing target would be replaced
+        self.request("MOVE", &self.url(from, false))
+            .set("Destination", &self.url(to, false))
+            .set("Overwrite", "F")
+            .call()
+            .map_err(http_error)?;
+        Ok(())
+    }
+
+    fn create_dir(&self, path: &Path) -> io::Result<()> {
+        self.request("MKCOL", &self.url(path, true))
+            .call()
+            .map_err(http_error)?;
+        Ok(())
+    }
+
+    fn remove_file(&self, path: &Path) -> io::Result<()> {
+        self.request("DELETE", &self.url(path, false))
+            .call()
+            .map_err(http_error)?;
+        Ok(())
+    }
+
+    fn remove_dir(&self, path: &Path) -> io::Result<()> {
+        self.request("DELETE", &self.url(path, true))
+            .call()
+            .map_err(http_error)?;
+        Ok(())
+    }
+
+    fn download(&self, path: &Path, dest: &Path) -> io::Result<()> {
+        let response = self
+            .request("GET", &self.url(path, false))
+            .call()
+            .map_err(http_error)?;
+        io::copy(&mut response.into_reader(), &mut File::create(dest)?).map(|_| ())
+    }
+
+    fn upload(&self, source: &Path, path: &Path) -> io::Result<()> {
+        let file = File::open(source)?;
+        // a known length spares servers that refuse chunked uploads
+        let len = file.metadata()?.len();
+        self.request("PUT", &self.url(path, false))
+            .set("Content-Length", &len.to_string())
+            .send(file)
+            .map_err(http_error)?;
+        Ok(())
+    }
+}
+
+/// An HTTP failure as the `io::Error` of the same meaning.
+fn http_error(e: ureq::Error) -> io::Error {
+    match e {
+        ureq::Error::Status(code, response) => {
+            let kind = match code {
+                401 | 403 => io::ErrorKind::PermissionDenied,
+                404 | 409 => io::ErrorKind::NotFound,
+                // `MKCOL` on an existing path, or `MOVE` onto one
+                405 | 412 => io::ErrorKind::AlreadyExists,
+                _ => io::ErrorKind::Other,
+            };
+            io::Error::new(kind, format!("HTTP {code} {}", response.status_text()))
+        }
+        ureq::Error::Transport(e) => io::Error::other(e.to_string()),
+    }
+}
+
+/// The entries of a `207 Multi-Status` answer to `PROPFIND`.
+fn parse_multistatus(xml: &str) -> io::Result<Vec<RemoteEntry>> {
+    let doc = roxmltree::Document::parse(xml).map_err(io::Error::other)?;
+    let mut entries = Vec::new();
+    for response in doc.descendants().filter(|n| is_dav(n, "response")) {
+        let Some(href) = dav(&response, "href").and_then(|n| n.text()) else {
+            continue;
+        };
+        let Some(path) = href_path(href.trim()) else {
+            continue;
+        };
+        let text = |name| dav(&response, name).and_then(|n| n.text()).map(str::trim);
+        let is_dir =
+            dav(&response, "resourcetype").is_some_and(|t| dav(&t, "collection").is_some());
+        entries.push(RemoteEntry {
+            path,
+            is_dir,
+            is_symlink: false,
+            len: text("getcontentlength")
+                .and_then(|len| len.parse().ok())
+                .unwrap_or(0),
+            modified: text("getlastmodified")
+                .and_then(|date| chrono::DateTime::parse_from_rfc2822(date).ok())
+                .map(SystemTime::from),
+            mode: None,
+        });
+    }
+    Ok(entries)
+}
+
+/// The first element named `name` in the `DAV:` namespace under `node`.
+fn dav<'a, 'input>(
+    node: &roxmltree::Node<'a, 'input>,
+    name: &str,
+) -> Option<roxmltree::Node<'a, 'input>> {
+    node.descendants().find(|n| is_dav(n, name))
+}
+
+fn is_dav(node: &roxmltree::Node, name: &str) -> bool {
+    node.tag_name().name() == name && node.tag_name().namespace() == Some("DAV:")
+}
+
+/// The path an `href` names, which may be a whole URL, without the trailing slash of
+/// directories.
+fn href_path(href: &str) -> Option<PathBuf> {
+    let path = match href.split_once("://") {
+        Some((_, rest)) => &rest[rest.find('/').unwrap_or(rest.len())..],
+        None => href,
+    };
+    let path = percent_decode(path)?;
+    let path = path.trim_end_matches('/');
+    Some(PathBuf::from(if path.is_empty() { "/" } else { path }))
+}
+
+/// The login and password `~/.netrc` has for `host`, as curl and ftp read them.
+fn netrc_login(host: &str) -> Option<(String, String)> {
+    let text = fs::read_to_string(expand_home("~/.netrc")).ok()?;
+    let mut tokens = text.split_whitespace();
+    let (mut login, mut password) = (None, None);
+    let mut matching = false;
+    while let Some(token) = tokens.next() {
+        match token {
+            "machine" | "default" if matching => break,
+            "machine" => matching = tokens.next() == Some(host),
+            "default" => matching = true,
+            "login" | "password" | "account" => {
+                let value = tokens.next();
+                if matching && token == "login" {
+                    login = value;
+                } else if matching && token == "password" {
+                    password = value;
+                }
+            }
+            _ => {}
+        }
+    }
+    Some((login?.to_string(), password.unwrap_or_default().to_string()))
+}
@@ -0,0 +1,246 @@
+use std::{io, path::Path};
+
+/// The attribute macOS sets on downloaded files, which makes Gatekeeper ask before opening them.
+pub const QUARANTINE: &str = "com.apple.quarantine";
+
+/// The extended attributes of `path` (following symlinks) and their values, sorted by name.
+/// Fails with `ErrorKind::Unsupported` on platforms and file systems without them.
+pub fn list(path: &Path) -> io::Result<Vec<(String, Vec<u8>)>> {
+    let mut attributes = imp::names(path)?
+        .into_iter()
+        .map(|name| {
+            let value = imp::get(path, &name)?;
+            Ok((name, value))
+        })
+        .collect::<io::Result<Vec<_>>>()?;
+    attributes.sort();
+    Ok(attributes)
+}
+
+/// Set the attribute `name` of `path` to `value`, creating it if needed. On Linux a name
+/// without a namespace is put in `user.`, the one ordinary users may write.
+pub fn set(path: &Path, name: &str, value: &[u8]) -> io::Result<()> {
+    imp::set(path, &qualified(name), value)
+}
+
+pub fn remove(path: &Path, name: &str) -> io::Result<()> {
+    imp::remove(path, &qualified(name))
+}
+
+fn qualified(name: &str) -> String {
+    const NAMESPACES: [&str; 4] = ["user.", "trusted.", "security.", "system."];
+    if cfg!(target_os = "linux") && !NAMESPACES.iter().any(|ns| name.starts_with(ns)) {
+        format!("user.{name}")
+    } else {
+        name.to_string()
+    }
+}
+
+/// `value` as shown in the properties popup: as text when it is printable, otherwise its first
+/// bytes in hex.
+pub fn display_value(value: &[u8]) -> String {
+    /// Characters or bytes shown before the rest is cut off
+    const SHOWN: usize = 60;
+
+    match std::str::from_utf8(value) {
+        Ok(text) if !text.chars().any(char::is_control) => {
+            if text.chars().count() > SHOWN {
+                format!("{}…", text.chars().take(SHOWN).collect::<String>())
+            } else {
+                text.to_string()
+            }
+        }
+        // C strings often end with a NUL
+        Ok(text) if text.ends_with('\0') && !text[..text.len() - 1].contains(char::is_control) => {
+            display_value(&value[..value.len() - 1])
+        }
+        _ => {
+            let hex: String = value
+                .iter()
+                .take(SHOWN / 3)
+                .map(|b| format!("{b:02x} "))
+                .collect();
+            format!("{}({} bytes)", hex, value.len())
+        }
+    }
+}
+
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+mod imp {
+    use std::{ffi::CString, io, os::unix::ffi::OsStrExt, path::Path};
+
+    fn c_path(path: &Path) -> io::Result<CString> {
+        CString::new(path.as_os_str().as_bytes()).map_err(io::Error::other)
+    }
+
+    fn c_name(name: &str) -> io::Result<CString> {
+        CString::new(name).map_err(io::Error::other)
+    }
+
+    /// Call `read` with a buffer of the size it asks for when given none, again if the data
+    /// grew in between.
+    fn read_sized(read: impl Fn(*mut libc::c_void, usize) -> isize) -> io::Result<Vec<u8>> {
+        loop {
+            let size = read(std::ptr::null_mut(), 0);
+            if size < 0 {
+                return Err(last_error());
+            }
+            let mut buffer = vec![0u8; size as usize];
+            let read = read(buffer.as_mut_ptr().cast(), buffer.len());
+            if read >= 0 {
+                buffer.truncate(read as usize);
+                return Ok(buffer);
+            }
+            let error = io::Error::last_os_error();
+            if error.raw_os_error() != Some(libc::ERANGE) {
+                return Err(unsupported_as_such(error));
+            }
+        }
+    }
+
+    fn last_error() -> io::Error {
+        unsupported_as_such(io::Error::last_os_error())
+    }
+
+    /// `ENOTSUP` with the error kind callers check for.
+    fn unsupported_as_such(error: io::Error) -> io::Error {
+        if error.raw_os_error() == Some(libc::ENOTSUP) {
+            io::Error::new(
+                io::ErrorKind::Unsupported,
+                "the file system does not support extended attributes",
+            )
+        } else {
+            error
+        }
+    }
+
+    fn check(result: libc::c_int) -> io::Result<()> {
+        if result == 0 {
+            Ok(())
+        } else {
+            Err(last_error())
+        }
+    }
+
+    pub fn names(path: &Path) -> io::Result<Vec<String>> {
+        let path = c_path(path)?;
+        let list = read_sized(|buffer, size| unsafe { sys::list(&path, buffer.cast(), size) })?;
+        Ok(list
+            .split(|&b| b == 0)
+            .filter(|name| !name.is_empty())
+            .map(|name| String::from_utf8_lossy(name).into_owned())
+            .collect())
+    }
+
+    pub fn get(path: &Path, name: &str) -> io::Result<Vec<u8>> {
+        let (path, name) = (c_path(path)?, c_name(name)?);
+        read_sized(|buffer, size| unsafe { sys::get(&path, &name, buffer, size) })
+    }
+
+    pub fn set(path: &Path, name: &str, value: &[u8]) -> io::Result<()> {
+        let (path, name) = (c_path(path)?, c_name(name)?);
+        check(unsafe { sys::set(&path, &name, value) })
+    }
+
+    pub fn remove(path: &Path, name: &str) -> io::Result<()> {
+        let (path, name) = (c_path(path)?, c_name(name)?);
+        check(unsafe { sys::remove(&path, &name) })
+    }
+
+    /// The calls take extra arguments on macOS: a position, for resource forks, and options.
+    mod sys {
+        use std::ffi::CStr;
+
+        #[cfg(target_os = "linux")]
+        pub unsafe fn list(path: &CStr, buffer: *mut libc::c_char, size: usize) -> isize {
+            libc::listxattr(path.as_ptr(), buffer, size)
+        }
+
+        #[cfg(target_os = "macos")]
+        pub unsafe fn list(path: &CStr, buffer: *mut libc::c_char, size: usize) -> isize {
+            libc::listxattr(path.as_ptr(), buffer, size, 0)
+        }
+
+        #[cfg(target_os = "linux")]
+        pub unsafe fn get(
+            path: &CStr,
+            name: &CStr,
+            buffer: *mut libc::c_void,
+            size: usize,
+        ) -> isize {
+            libc::getxattr(path.as_ptr(), name.as_ptr(), buffer, size)
+        }
+
+        #[cfg(target_os = "macos")]
+        pub unsafe fn get(
+            path: &CStr,
+            name: &CStr,
+            buffer: *mut libc::c_void,
+            size: usize,
+        ) -> isize {
+            libc::getxattr(path.as_ptr(), name.as_ptr(), buffer, size, 0, 0)
+        }
+
+        #[cfg(target_os = "linux")]
+        pub unsafe fn set(path: &CStr, name: &CStr, value: &[u8]) -> libc::c_int {
+            libc::setxattr(
+                path.as_ptr(),
+                name.as_ptr(),
+                value.as_ptr().cast(),
+                value.len(),
+                0,
+            )
+        }
+
+        #[cfg(target_os = "macos")]
+        pub unsafe fn set(path: &CStr, name: &CStr, value: &[u8]) -> libc::c_int {
+            libc::setxattr(
+                path.as_ptr(),
+                name.as_ptr(),
+                value.as_ptr().cast(),
+                value.len(),
+                0,
+                0,
+            )
+        }
+
+        #[cfg(target_os = "linux")]
+        pub unsafe fn remove(path: &CStr, name: &CStr) -> libc::c_int {
+            libc::removexattr(path.as_ptr(), name.as_ptr())
+        }
+
+        #[cfg(target_os = "macos")]
+        pub unsafe fn remove(path: &CStr, name: &CStr) -> libc::c_int {
+            libc::removexattr(path.as_ptr(), name.as_ptr(), 0)
+        }
+    }
+}
+
+/// Windows keeps alternate data streams instead, and the BSDs another API.
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+mod imp {
+    use std::{io, path::Path};
+
+    fn unsupported() -> io::Error {
+        io::Error::new(
+            io::ErrorKind::Unsupported,
+            "extended attributes are not supported on this platform",
+        )
+    }
+
+    pub fn names(_path: &Path) -> io::Result<Vec<String>> {
+        Err(unsupported())
+    }
+
+    pub fn get(_path: &Path, _name: &str) -> io::Result<Vec<u8>> {
+        Err(unsupported())
+    }
+
+    pub fn set(_path: &Path, _name: &str, _value: &[u8]) -> io::Result<()> {
+        Err(unsupported())
+    }
+
+    pub fn remove(_path: &Path, _name: &str) -> io::Result<()> {
+        Err(unsupported())
+    }
+}